@@ -0,0 +1,61 @@
+/*
+ * umber: syntax highlighting with tree-sitter grammars, as a C library.
+ *
+ * Build the shared library with
+ *
+ *     cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * and link against target/release/libumber.so (libumber.dylib, umber.dll).
+ */
+
+#ifndef UMBER_H
+#define UMBER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* UmberOptions.format */
+#define UMBER_FORMAT_ANSI 0      /* terminal escape sequences */
+#define UMBER_FORMAT_HTML 1      /* a <pre> block styled inline */
+#define UMBER_FORMAT_HTML_FULL 2 /* a standalone HTML page */
+
+/* UmberOptions.color_depth, for UMBER_FORMAT_ANSI */
+#define UMBER_COLORS_TRUECOLOR 0
+#define UMBER_COLORS_256 1
+#define UMBER_COLORS_16 2
+
+typedef struct UmberOptions {
+  uint32_t format;
+  uint32_t line_numbers; /* non-zero to number the lines */
+  uint32_t color_depth;
+} UmberOptions;
+
+/*
+ * Highlight `len` bytes of UTF-8 at `source` as `language` (such as "rust" or
+ * "xml") in `theme` (such as "catppuccin::mocha" or "ansi"). `options` may be
+ * NULL for truecolor ANSI output without line numbers.
+ *
+ * Returns a NUL-terminated string to release with umber_string_free, or NULL
+ * on error; umber_last_error then says why.
+ */
+char *umber_highlight(const uint8_t *source, size_t len, const char *language,
+                      const char *theme, const UmberOptions *options);
+
+/*
+ * Why the last umber_highlight on this thread failed, or NULL if it did not.
+ * Valid until the next call on the same thread; do not free it.
+ */
+const char *umber_last_error(void);
+
+/* Release a string returned by umber_highlight. NULL is ignored. */
+void umber_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* UMBER_H */
@@ -0,0 +1,161 @@
+//! Escape sequences already in the input, such as the colors of another tool
+//! piped into umber. `--strip-ansi` removes them before the input is detected
+//! and highlighted; input shown as it is keeps them, and line numbers and
+//! wrapping work around them.
+
+use clap::ValueEnum;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StripAnsi {
+  /// Remove them when the input is highlighted in a known language
+  #[default]
+  Auto,
+  /// Always remove them, even from plain text and uncolored output
+  Always,
+  /// Keep them
+  Never,
+}
+
+pub fn has_escapes(bytes: &[u8]) -> bool {
+  bytes.contains(&ESC)
+}
+
+/// Length of the escape sequence at the start of `bytes`, which starts with
+/// ESC: a CSI sequence such as `ESC [ 1 ; 31 m`, an OSC such as a hyperlink,
+/// another string sequence, or a short escape like `ESC ( B`. A sequence cut
+/// off at the end of the input runs to its end.
+pub fn sequence_len(bytes: &[u8]) -> usize {
+  debug_assert_eq!(bytes.first(), Some(&ESC));
+  let Some(&kind) = bytes.get(1) else {
+    return bytes.len();
+  };
+  match kind {
+    b'[' => bytes[2..]
+      .iter()
+      .position(|byte| (0x40..=0x7e).contains(byte))
+      .map_or(bytes.len(), |end| end + 3),
+    // OSC ends at BEL or ST; DCS, SOS, PM and APC only at ST.
+    b']' | b'P' | b'X' | b'^' | b'_' => {
+      let mut index = 2;
+      while index < bytes.len() {
+        match bytes[index] {
+          BEL if kind == b']' => return index + 1,
+          ESC if bytes.get(index + 1) == Some(&b'\\') => return index + 2,
+          _ => index += 1,
+        }
+      }
+      bytes.len()
+    }
+    0x20..=0x2f => bytes[2..]
+      .iter()
+      .position(|byte| !(0x20..=0x2f).contains(byte))
+      .map_or(bytes.len(), |end| end + 3),
+    _ => 2,
+  }
+}
+
+/// `bytes` without escape sequences.
+pub fn strip(bytes: &[u8]) -> Vec<u8> {
+  let mut stripped = Vec::with_capacity(bytes.len());
+  let mut rest = bytes;
+  while let Some(start) = rest.iter().position(|byte| *byte == ESC) {
+    stripped.extend_from_slice(&rest[..start]);
+    rest = &rest[start..];
+    rest = &rest[sequence_len(rest)..];
+  }
+  stripped.extend_from_slice(rest);
+  stripped
+}
+
+/// The colors and attributes that SGR sequences in the input have set so far,
+/// so that umber's own output in between, such as line numbers, can be drawn
+/// without them and the text can continue with them afterwards.
+#[derive(Debug, Default)]
+pub struct SgrState {
+  /// Every SGR sequence since the last reset.
+  active: String,
+}
+
+impl SgrState {
+  /// Follow the SGR sequences in `text`.
+  pub fn update(&mut self, text: &str) {
+    let bytes = text.as_bytes();
+    let mut index = 0;
+    while let Some(start) = bytes[index..].iter().position(|byte| *byte == ESC) {
+      let start = index + start;
+      let sequence = &bytes[start..start + sequence_len(&bytes[start..])];
+      index = start + sequence.len();
+      let Some(params) = sequence
+        .strip_prefix(b"\x1b[")
+        .and_then(|sequence| sequence.strip_suffix(b"m"))
+      else {
+        continue;
+      };
+      if params.is_empty() || params == b"0" || params.starts_with(b"0;") {
+        self.active.clear();
+      }
+      if !params.is_empty() && params != b"0" {
+        self.active.push_str(&String::from_utf8_lossy(sequence));
+      }
+    }
+  }
+
+  /// What to write before umber's own output: a reset when anything is set.
+  pub fn suspend(&self) -> &'static str {
+    if self.active.is_empty() {
+      ""
+    } else {
+      "\x1b[0m"
+    }
+  }
+
+  /// What to write after it to continue the text as it was.
+  pub fn resume(&self) -> &str {
+    &self.active
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_strip() {
+    assert_eq!(
+      strip(b"\x1b[1;31merror\x1b[0m: \x1b[38;2;1;2;3mx\x1b[m"),
+      b"error: x"
+    );
+    // A hyperlink, a character set switch and a cursor save.
+    assert_eq!(
+      strip(b"\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07 \x1b(Bok\x1b7"),
+      b"link ok"
+    );
+    assert_eq!(strip(b"cut off \x1b[1;3"), b"cut off ");
+    assert_eq!(strip(b"plain"), b"plain");
+  }
+
+  #[test]
+  fn test_sgr_state() {
+    let mut state = SgrState::default();
+    state.update("\x1b[1mbold \x1b[31mred");
+    assert_eq!(state.suspend(), "\x1b[0m");
+    assert_eq!(state.resume(), "\x1b[1m\x1b[31m");
+    state.update("\x1b]8;;link\x07 still red");
+    assert_eq!(state.resume(), "\x1b[1m\x1b[31m");
+    state.update("\x1b[0;32mgreen");
+    assert_eq!(state.resume(), "\x1b[0;32m");
+    state.update("\x1b[m");
+    assert_eq!(state.suspend(), "");
+    assert_eq!(state.resume(), "");
+  }
+
+  #[test]
+  fn test_sequence_len() {
+    assert_eq!(sequence_len(b"\x1b[0mtext"), 4);
+    assert_eq!(sequence_len(b"\x1b]0;title\x07text"), 10);
+    assert_eq!(sequence_len(b"\x1b"), 1);
+  }
+}
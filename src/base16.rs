@@ -0,0 +1,155 @@
+//! base16 and base24 color schemes: 16 palette slots, from which every theme
+//! key is styled following the base16 styling guidelines.
+//!
+//! Both the classic flat files (`base00: "181818"`) and the tinted-theming
+//! ones with a `palette:` mapping are read; base24's extra slots are unused.
+
+use std::collections::BTreeMap;
+
+use eyre::{Result, eyre};
+use syntastica::style::{Color, Style};
+use syntastica::theme::{ResolvedTheme, THEME_KEYS};
+
+use crate::decorations::parse_color;
+
+/// The palette slot for each theme key, and whether it is italic.
+const SLOTS: &[(&str, usize, bool)] = &[
+  ("attribute", 0x9, false),
+  ("boolean", 0x9, false),
+  ("character", 0x8, false),
+  ("comment", 0x3, true),
+  ("constant", 0x9, false),
+  ("constant.builtin", 0x9, false),
+  ("constructor", 0xA, false),
+  ("diff.delta", 0xE, false),
+  ("diff.minus", 0x8, false),
+  ("diff.plus", 0xB, false),
+  ("error", 0x8, false),
+  ("function", 0xD, false),
+  ("function.builtin", 0xC, false),
+  ("function.macro", 0x8, false),
+  ("keyword", 0xE, false),
+  ("label", 0xA, false),
+  ("markup.heading", 0xD, false),
+  ("markup.italic", 0xE, true),
+  ("markup.link", 0x8, false),
+  ("markup.quote", 0xC, false),
+  ("markup.raw", 0xB, false),
+  ("markup.strong", 0xA, false),
+  ("module", 0xA, false),
+  ("namespace", 0xA, false),
+  ("number", 0x9, false),
+  ("operator", 0x5, false),
+  ("property", 0x8, false),
+  ("punctuation", 0x5, false),
+  ("punctuation.special", 0xF, false),
+  ("string", 0xB, false),
+  ("string.escape", 0xC, false),
+  ("string.regex", 0xC, false),
+  ("string.regexp", 0xC, false),
+  ("string.special", 0xC, false),
+  ("tag", 0x8, false),
+  ("tag.attribute", 0xD, false),
+  ("type", 0xA, false),
+  ("type.builtin", 0xA, false),
+  ("variable", 0x8, false),
+  ("variable.builtin", 0x9, false),
+  ("variable.member", 0x8, false),
+  ("variable.parameter", 0x8, false),
+];
+
+pub fn parse(raw: &str) -> Result<ResolvedTheme> {
+  let mut palette: [Option<Color>; 16] = [None; 16];
+  for line in raw.lines() {
+    let Some((key, value)) = line.split_once(':') else {
+      continue;
+    };
+    let Some(slot) = key
+      .trim()
+      .strip_prefix("base0")
+      .and_then(|digit| usize::from_str_radix(digit, 16).ok())
+    else {
+      continue;
+    };
+    palette[slot] = Some(
+      parse_color(unquote(value))
+        .ok_or_else(|| eyre!("Invalid color for base0{:X}: {}", slot, value.trim()))?,
+    );
+  }
+  let mut colors = [Color::new(0, 0, 0); 16];
+  for (slot, color) in palette.into_iter().enumerate() {
+    colors[slot] = color.ok_or_else(|| eyre!("Missing base0{:X}", slot))?;
+  }
+
+  let mut styles = BTreeMap::new();
+  styles.insert(
+    "_normal".to_string(),
+    Style::new(colors[0x5], Some(colors[0x0]), false, false, false, false),
+  );
+  for &(key, slot, italic) in SLOTS {
+    if THEME_KEYS.contains(&key) {
+      styles.insert(
+        key.to_string(),
+        Style::new(colors[slot], None, false, false, italic, false),
+      );
+    }
+  }
+  Ok(ResolvedTheme::new(styles))
+}
+
+/// A YAML scalar without its quotes or a trailing comment.
+fn unquote(value: &str) -> &str {
+  let value = value.trim();
+  for quote in ['"', '\''] {
+    if let Some(quoted) = value.strip_prefix(quote) {
+      return quoted.split(quote).next().unwrap_or(quoted);
+    }
+  }
+  value.split(" #").next().unwrap_or(value).trim()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn scheme(palette_prefix: &str) -> String {
+    (0..16)
+      .map(|slot| {
+        format!(
+          "{palette_prefix}base0{slot:X}: \"{:02x}{:02x}{:02x}\"\n",
+          slot * 16,
+          0,
+          0
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_parse_base16() {
+    let flat = format!("scheme: \"Test\"\nauthor: \"me\"\n{}", scheme(""));
+    let theme = parse(&flat).unwrap();
+    assert_eq!(
+      theme.find_style("comment").unwrap().color(),
+      Color::new(0x30, 0, 0)
+    );
+    assert!(theme.find_style("comment").unwrap().italic());
+    assert_eq!(
+      theme.get("_normal").unwrap().bg(),
+      Some(Color::new(0, 0, 0))
+    );
+
+    let tinted = format!(
+      "system: \"base16\"\nname: \"Test\"\npalette:\n{}",
+      scheme("  ").replace(": \"", ": \"#")
+    );
+    assert_eq!(parse(&tinted).unwrap().into_inner(), theme.into_inner());
+  }
+
+  #[test]
+  fn test_parse_base16_errors() {
+    let missing = scheme("").replace("base0F", "# base0F");
+    assert!(parse(&missing).unwrap_err().to_string().contains("base0F"));
+    assert!(parse(&scheme("").replace("\"e00000\"", "\"nope\"")).is_err());
+  }
+}
@@ -0,0 +1,223 @@
+//! Migration from bat.
+//! Reads bat's config file (`$BAT_CONFIG_PATH` or `~/.config/bat/config`) and
+//! translates the options umber has an equivalent for: the theme and
+//! `--map-syntax` patterns. Everything else is reported as skipped.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use eyre::{Result, eyre};
+
+use crate::pager::split_command;
+
+/// The parts of a bat configuration that translate to umber.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BatConfig {
+  pub theme: Option<String>,
+  /// (pattern, language) pairs from `--map-syntax`
+  pub syntax_map: Vec<(String, String)>,
+  /// Options and assets that have no umber equivalent
+  pub skipped: Vec<String>,
+}
+
+/// Location of bat's config file, following bat's own lookup.
+pub fn config_path() -> Option<PathBuf> {
+  if let Some(path) = std::env::var_os("BAT_CONFIG_PATH").filter(|value| !value.is_empty()) {
+    return Some(PathBuf::from(path));
+  }
+  config_dir().map(|dir| dir.join("config"))
+}
+
+fn config_dir() -> Option<PathBuf> {
+  if let Some(dir) = std::env::var_os("BAT_CONFIG_DIR").filter(|value| !value.is_empty()) {
+    return Some(PathBuf::from(dir));
+  }
+  if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME").filter(|value| !value.is_empty()) {
+    return Some(PathBuf::from(xdg).join("bat"));
+  }
+  let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+  Some(PathBuf::from(home).join(".config").join("bat"))
+}
+
+/// Load and translate bat's configuration. Returns `None` when bat has none.
+pub fn load() -> Result<Option<BatConfig>> {
+  let Some(path) = config_path() else {
+    return Ok(None);
+  };
+  let raw = match fs::read_to_string(&path) {
+    Ok(raw) => raw,
+    Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+    Err(err) => return Err(eyre!("{}: {err}", path.display())),
+  };
+  let mut config = parse(&raw).map_err(|err| eyre!("{}: {err}", path.display()))?;
+  if let Some(dir) = path.parent() {
+    config.skipped.extend(custom_assets(dir));
+  }
+  Ok(Some(config))
+}
+
+/// Translate the contents of a bat config file: command-line options, one or
+/// more per line, with `#` comments.
+pub fn parse(raw: &str) -> Result<BatConfig> {
+  let mut config = BatConfig::default();
+  for line in raw.lines().map(str::trim) {
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let mut words = split_command(line)?.into_iter();
+    while let Some(word) = words.next() {
+      let (option, value) = match word.split_once('=') {
+        Some((option, value)) => (option.to_string(), Some(value.to_string())),
+        None => (word, None),
+      };
+      match option.as_str() {
+        "--theme" => {
+          let Some(theme) = value.or_else(|| words.next()) else {
+            continue;
+          };
+          match translate_theme(&theme) {
+            Some(theme) => config.theme = Some(theme),
+            None => config.skipped.push(format!("--theme={theme}")),
+          }
+        }
+        "--map-syntax" | "-m" => {
+          let Some(mapping) = value.or_else(|| words.next()) else {
+            continue;
+          };
+          match mapping.rsplit_once(':') {
+            Some((pattern, syntax)) => config
+              .syntax_map
+              .push((pattern.to_string(), translate_syntax(syntax))),
+            None => config.skipped.push(format!("--map-syntax={mapping}")),
+          }
+        }
+        _ => config.skipped.push(match value {
+          Some(value) => format!("{option}={value}"),
+          None => option,
+        }),
+      }
+    }
+  }
+  Ok(config)
+}
+
+/// Map a bat theme name to the closest umber theme.
+fn translate_theme(name: &str) -> Option<String> {
+  let theme = match name {
+    "Dracula" => "dracula",
+    "Nord" => "nord",
+    "OneHalfDark" | "TwoDark" => "one-dark",
+    "OneHalfLight" => "one-light",
+    "gruvbox-dark" => "gruvbox-dark",
+    "gruvbox-light" => "gruvbox-light",
+    "Solarized (dark)" => "solarized-dark",
+    "Solarized (light)" => "solarized-light",
+    "Catppuccin Mocha" | "Catppuccin-mocha" => "catppuccin-mocha",
+    "Catppuccin Latte" | "Catppuccin-latte" => "catppuccin-latte",
+    "Catppuccin Frappe" | "Catppuccin-frappe" => "catppuccin-frappe",
+    "Catppuccin Macchiato" | "Catppuccin-macchiato" => "catppuccin-macchiato",
+    "tokyonight_night" | "Tokyo Night" => "tokyo-night",
+    _ => return None,
+  };
+  Some(theme.to_string())
+}
+
+/// Map a bat (Sublime Text) syntax name to an umber language name.
+fn translate_syntax(syntax: &str) -> String {
+  let language = match syntax {
+    "Bourne Again Shell (bash)" | "Shell-Unix-Generic" => "bash",
+    "C++" => "cpp",
+    "C#" => "c_sharp",
+    "JavaScript (Babel)" => "javascript",
+    "TypeScriptReact" => "tsx",
+    "Makefile" => "make",
+    "Git Config" => "git_config",
+    "Git Ignore" => "gitignore",
+    "Git Attributes" => "gitattributes",
+    "Git Commit" => "gitcommit",
+    "SSH Config" => "ssh_config",
+    "Vim Script" | "VimL" => "vim",
+    _ => {
+      // "Ruby on Rails (erb)" -> "ruby_on_rails"; most names just need lowercasing.
+      let name = syntax.split(" (").next().unwrap_or(syntax);
+      return name.trim().to_ascii_lowercase().replace([' ', '-'], "_");
+    }
+  };
+  language.to_string()
+}
+
+/// Custom `.tmTheme` and `.sublime-syntax` files, which umber cannot load.
+fn custom_assets(dir: &Path) -> Vec<String> {
+  let mut assets = Vec::new();
+  for subdir in ["themes", "syntaxes"] {
+    let Ok(entries) = fs::read_dir(dir.join(subdir)) else {
+      continue;
+    };
+    let mut names: Vec<String> = entries
+      .filter_map(|entry| entry.ok())
+      .map(|entry| format!("{subdir}/{}", entry.file_name().to_string_lossy()))
+      .collect();
+    names.sort();
+    assets.extend(names);
+  }
+  assets
+}
+
+/// Render the translated settings as an umber config file.
+pub fn to_toml(config: &BatConfig, source: &Path) -> String {
+  let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+  let mut out = format!("# Imported from {}\n", source.display());
+  if let Some(theme) = &config.theme {
+    out.push_str(&format!("\n[theme]\nname = {}\n", quote(theme)));
+  }
+  if !config.syntax_map.is_empty() {
+    out.push_str("\n[syntax-map]\n");
+    for (pattern, language) in &config.syntax_map {
+      out.push_str(&format!("{} = {}\n", quote(pattern), quote(language)));
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_bat_config() {
+    let config = parse(
+      "# Set the theme\n\
+       --theme=\"Solarized (dark)\"\n\
+       --italic-text=always\n\
+       --map-syntax '*.conf:INI' -m=.ignore:'Git Ignore'\n\
+       --map-syntax \"*.jenkinsfile:Groovy (Jenkinsfile)\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.theme.as_deref(), Some("solarized-dark"));
+    assert_eq!(
+      config.syntax_map,
+      vec![
+        ("*.conf".to_string(), "ini".to_string()),
+        (".ignore".to_string(), "gitignore".to_string()),
+        ("*.jenkinsfile".to_string(), "groovy".to_string()),
+      ]
+    );
+    assert_eq!(config.skipped, vec!["--italic-text=always"]);
+  }
+
+  #[test]
+  fn test_to_toml_quotes_values() {
+    let config = BatConfig {
+      theme: Some("nord".to_string()),
+      syntax_map: vec![("*.conf".to_string(), "ini".to_string())],
+      skipped: Vec::new(),
+    };
+    assert_eq!(
+      to_toml(&config, Path::new("/home/me/.config/bat/config")),
+      "# Imported from /home/me/.config/bat/config\n\n\
+       [theme]\nname = \"nord\"\n\n\
+       [syntax-map]\n\"*.conf\" = \"ini\"\n"
+    );
+  }
+}
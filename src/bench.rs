@@ -0,0 +1,174 @@
+//! `--bench`: how long each phase of showing a file takes, for tracking
+//! performance across releases and grammars.
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+use clap::ValueEnum;
+
+/// How `--bench` reports its timings.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BenchFormat {
+  /// A table for reading
+  #[default]
+  Human,
+  /// One JSON array, for scripts
+  Json,
+}
+
+/// Time spent on one file in each phase.
+#[derive(Debug, Default)]
+pub struct Timings {
+  pub path: String,
+  /// Detected language; `None` when the file is shown without highlighting
+  pub language: Option<String>,
+  pub bytes: u64,
+  /// Reading and decoding the file
+  pub read: Duration,
+  /// Language detection
+  pub detect: Duration,
+  /// Compiling the highlight queries, unless an earlier file already did
+  pub queries: Duration,
+  /// Parsing, done when the highlighter starts
+  pub parse: Duration,
+  /// Running the highlight queries over the tree
+  pub highlight: Duration,
+  /// Styling and decorating the output
+  pub render: Duration,
+}
+
+impl Timings {
+  fn phases(&self) -> [(&'static str, Duration); 6] {
+    [
+      ("read", self.read),
+      ("detect", self.detect),
+      ("queries", self.queries),
+      ("parse", self.parse),
+      ("highlight", self.highlight),
+      ("render", self.render),
+    ]
+  }
+
+  pub fn total(&self) -> Duration {
+    self.phases().iter().map(|(_, duration)| *duration).sum()
+  }
+}
+
+pub fn write(out: &mut impl Write, timings: &[Timings], format: BenchFormat) -> io::Result<()> {
+  match format {
+    BenchFormat::Human => write_human(out, timings),
+    BenchFormat::Json => write_json(out, timings),
+  }
+}
+
+/// A table with a row per file and a column per phase, in milliseconds.
+fn write_human(out: &mut impl Write, timings: &[Timings]) -> io::Result<()> {
+  let path_width = timings
+    .iter()
+    .map(|timing| timing.path.chars().count())
+    .chain([4])
+    .max()
+    .unwrap_or_default();
+  let language_width = timings
+    .iter()
+    .filter_map(|timing| timing.language.as_deref())
+    .map(str::len)
+    .chain([8])
+    .max()
+    .unwrap_or_default();
+  write!(
+    out,
+    "{:<path_width$}  {:<language_width$}  {:>10}",
+    "FILE", "LANGUAGE", "BYTES"
+  )?;
+  for (name, _) in Timings::default().phases() {
+    write!(out, "  {:>9}", name.to_uppercase())?;
+  }
+  writeln!(out, "  {:>9}", "TOTAL")?;
+  for timing in timings {
+    write!(
+      out,
+      "{:<path_width$}  {:<language_width$}  {:>10}",
+      timing.path,
+      timing.language.as_deref().unwrap_or("-"),
+      timing.bytes
+    )?;
+    for (_, duration) in timing.phases() {
+      write!(out, "  {:>9}", format_millis(duration))?;
+    }
+    writeln!(out, "  {:>9}", format_millis(timing.total()))?;
+  }
+  Ok(())
+}
+
+/// An array with an object per file; durations are in milliseconds.
+fn write_json(out: &mut impl Write, timings: &[Timings]) -> io::Result<()> {
+  let files: Vec<_> = timings
+    .iter()
+    .map(|timing| {
+      let mut file = serde_json::Map::new();
+      file.insert("path".into(), timing.path.clone().into());
+      file.insert("language".into(), timing.language.clone().into());
+      file.insert("bytes".into(), timing.bytes.into());
+      for (name, duration) in timing.phases() {
+        file.insert(format!("{name}_ms"), millis(duration).into());
+      }
+      file.insert("total_ms".into(), millis(timing.total()).into());
+      serde_json::Value::Object(file)
+    })
+    .collect();
+  serde_json::to_writer_pretty(&mut *out, &files)?;
+  writeln!(out)
+}
+
+fn millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
+
+fn format_millis(duration: Duration) -> String {
+  format!("{:.2}ms", millis(duration))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sample() -> Timings {
+    Timings {
+      path: "src/main.rs".into(),
+      language: Some("rust".into()),
+      bytes: 1234,
+      read: Duration::from_micros(250),
+      parse: Duration::from_millis(12),
+      highlight: Duration::from_millis(30),
+      ..Timings::default()
+    }
+  }
+
+  #[test]
+  fn test_write_human() {
+    let mut out = Vec::new();
+    write_human(&mut out, &[sample()]).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let mut lines = out.lines();
+    assert!(
+      lines
+        .next()
+        .unwrap()
+        .starts_with("FILE         LANGUAGE       BYTES       READ")
+    );
+    let row = lines.next().unwrap();
+    assert!(row.starts_with("src/main.rs  rust            1234     0.25ms"));
+    assert!(row.ends_with("42.25ms"));
+  }
+
+  #[test]
+  fn test_write_json() {
+    let mut out = Vec::new();
+    write_json(&mut out, &[sample()]).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(json[0]["language"], "rust");
+    assert_eq!(json[0]["parse_ms"], 12.0);
+    assert_eq!(json[0]["total_ms"], 42.25);
+  }
+}
@@ -0,0 +1,134 @@
+//! On-disk cache location and maintenance.
+//! Resolves the cache directory (`--cache-dir`, `XDG_CACHE_HOME`, platform default)
+//! and implements `umber cache --clear` / `umber cache --size`, and where
+//! `umber cache build` keeps preprocessed highlight queries.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use eyre::{Result, eyre};
+
+/// Name of the cache subdirectory inside the platform cache location.
+const CACHE_DIR_NAME: &str = "umber";
+
+/// Bumped when the format of cached queries changes. umber's own version is
+/// part of the path too, as the queries ship with the binary.
+const QUERY_CACHE_VERSION: u32 = 1;
+
+/// Resolve the cache directory.
+///
+/// Precedence: explicit override, `$XDG_CACHE_HOME/umber`, then the platform default
+/// (`~/Library/Caches/umber` on macOS, `%LOCALAPPDATA%\umber` on Windows,
+/// `~/.cache/umber` elsewhere).
+pub fn cache_dir(override_dir: Option<&Path>) -> Result<PathBuf> {
+  if let Some(dir) = override_dir {
+    return Ok(dir.to_path_buf());
+  }
+  if let Some(xdg) = non_empty_env("XDG_CACHE_HOME") {
+    return Ok(PathBuf::from(xdg).join(CACHE_DIR_NAME));
+  }
+  platform_cache_dir()
+    .map(|dir| dir.join(CACHE_DIR_NAME))
+    .ok_or_else(|| eyre!("could not determine a cache directory; use --cache-dir"))
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+  std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+#[cfg(target_os = "macos")]
+fn platform_cache_dir() -> Option<PathBuf> {
+  non_empty_env("HOME").map(|home| PathBuf::from(home).join("Library").join("Caches"))
+}
+
+#[cfg(windows)]
+fn platform_cache_dir() -> Option<PathBuf> {
+  non_empty_env("LOCALAPPDATA").map(PathBuf::from)
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn platform_cache_dir() -> Option<PathBuf> {
+  non_empty_env("HOME").map(|home| PathBuf::from(home).join(".cache"))
+}
+
+/// Summary of the cache contents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheUsage {
+  pub files: u64,
+  pub bytes: u64,
+}
+
+/// Compute the total size of the cache directory. A missing directory is empty.
+pub fn cache_usage(dir: &Path) -> io::Result<CacheUsage> {
+  let mut usage = CacheUsage::default();
+  if !dir.exists() {
+    return Ok(usage);
+  }
+  let mut pending = vec![dir.to_path_buf()];
+  while let Some(current) = pending.pop() {
+    for entry in fs::read_dir(&current)? {
+      let entry = entry?;
+      let file_type = entry.file_type()?;
+      if file_type.is_dir() {
+        pending.push(entry.path());
+      } else {
+        usage.files += 1;
+        usage.bytes += entry.metadata()?.len();
+      }
+    }
+  }
+  Ok(usage)
+}
+
+/// Remove everything in the cache directory.
+pub fn clear_cache(dir: &Path) -> io::Result<()> {
+  match fs::remove_dir_all(dir) {
+    Ok(()) => Ok(()),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+    Err(err) => Err(err),
+  }
+}
+
+/// Directory of the preprocessed highlight queries written by `umber cache build`.
+pub fn query_dir(dir: &Path) -> PathBuf {
+  dir.join("queries").join(format!(
+    "v{QUERY_CACHE_VERSION}-{}",
+    env!("CARGO_PKG_VERSION")
+  ))
+}
+
+/// Cache file for the preprocessed form of `source`, the highlight query of
+/// language `name`. A changed query gets a new file rather than a stale one.
+pub fn query_path(dir: &Path, name: &str, source: &str) -> PathBuf {
+  let mut hasher = DefaultHasher::new();
+  source.hash(&mut hasher);
+  query_dir(dir).join(format!("{name}-{:016x}.scm", hasher.finish()))
+}
+
+/// Write `contents` to `path` through a temporary file, so that a concurrent
+/// run never reads half of it.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let partial = path.with_extension(format!("partial-{}", std::process::id()));
+  fs::write(&partial, contents)?;
+  fs::rename(&partial, path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_query_path_changes_with_source() {
+    let dir = Path::new("/cache");
+    let first = query_path(dir, "hcl", "(comment) @comment");
+    assert!(first.starts_with(query_dir(dir)));
+    assert_eq!(first, query_path(dir, "hcl", "(comment) @comment"));
+    assert_ne!(first, query_path(dir, "hcl", "(string) @string"));
+  }
+}
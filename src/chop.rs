@@ -0,0 +1,118 @@
+//! `--max-line-length` and `--chop-long-lines`: lines longer than a limit are
+//! cut short with a marker before they are highlighted, so minified assets and
+//! base64 blobs neither stall the highlighter nor fill the screen.
+
+use std::borrow::Cow;
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::ansi;
+use crate::unprintable::CharStyle;
+
+/// Where lines are cut.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxLineLength {
+  /// After this many columns
+  Columns(usize),
+  /// At the edge of the terminal, after the gutter
+  Terminal,
+}
+
+/// What a cut line ends with.
+pub fn marker(style: CharStyle) -> char {
+  match style {
+    CharStyle::Unicode => '…',
+    CharStyle::Caret => '>',
+  }
+}
+
+/// `text` with every line wider than `columns` cut to `columns - 1` columns
+/// and `marker`. Escape sequences take no columns; those in the part cut off
+/// are kept after the marker so colors are still reset. Text without such
+/// lines is returned as is.
+pub fn chop_lines(text: &str, columns: usize, marker: char) -> Cow<'_, str> {
+  let columns = columns.max(1);
+  // Cheap test first: no line can be too wide without having that many bytes.
+  if text.split('\n').all(|line| line.len() <= columns) {
+    return Cow::Borrowed(text);
+  }
+  let mut out = String::with_capacity(text.len().min(64 * 1024));
+  for line in text.split_inclusive('\n') {
+    let (body, ending) = match line.strip_suffix('\n') {
+      Some(body) => match body.strip_suffix('\r') {
+        Some(body) => (body, "\r\n"),
+        None => (body, "\n"),
+      },
+      None => (line, ""),
+    };
+    match cut_point(body, columns) {
+      Some(end) => {
+        out.push_str(&body[..end]);
+        out.push(marker);
+        push_sequences(&mut out, &body[end..]);
+      }
+      None => out.push_str(body),
+    }
+    out.push_str(ending);
+  }
+  Cow::Owned(out)
+}
+
+/// Byte offset to cut `line` at so that the marker still fits in `columns`,
+/// or `None` when the whole line fits.
+fn cut_point(line: &str, columns: usize) -> Option<usize> {
+  let mut width = 0;
+  // End of the text that fits with the marker after it.
+  let mut cut = 0;
+  let mut index = 0;
+  while index < line.len() {
+    if line.as_bytes()[index] == 0x1b {
+      index += ansi::sequence_len(&line.as_bytes()[index..]);
+      if width < columns {
+        cut = index;
+      }
+      continue;
+    }
+    let c = line[index..].chars().next()?;
+    width += c.width().unwrap_or(0);
+    if width > columns {
+      return Some(cut);
+    }
+    index += c.len_utf8();
+    if width < columns {
+      cut = index;
+    }
+  }
+  None
+}
+
+/// Append the escape sequences in `rest` and nothing else.
+fn push_sequences(out: &mut String, rest: &str) {
+  let mut index = 0;
+  while let Some(start) = rest[index..].find('\x1b') {
+    let start = index + start;
+    index = start + ansi::sequence_len(&rest.as_bytes()[start..]).max(1);
+    out.push_str(&rest[start..index]);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_chop_lines() {
+    assert_eq!(chop_lines("short\nlines\n", 5, '…'), "short\nlines\n");
+    assert_eq!(
+      chop_lines("abcdefgh\r\nok\nxyzxyz", 5, '…'),
+      "abcd…\r\nok\nxyzx…"
+    );
+    // Wide characters are not split: 世 would end past the fourth column.
+    assert_eq!(chop_lines("ab世界cd", 5, '>'), "ab世>");
+    // Escape sequences take no room.
+    assert_eq!(
+      chop_lines("\x1b[31mabcdef\x1b[0m", 4, '…'),
+      "\x1b[31mabc…\x1b[0m"
+    );
+  }
+}
@@ -0,0 +1,287 @@
+//! `--color-depth`: terminals without truecolor get theme colors quantized to
+//! the 256-color or 16-color palette instead of 24-bit escape sequences.
+//! The `ansi` theme goes further and styles each theme key with one of the
+//! 16 colors, so that the output follows the terminal's own palette.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use syntastica::renderer::{Renderer, TerminalRenderer};
+use syntastica::style::{Color, Style};
+use syntastica::theme::{ResolvedTheme, THEME_KEYS};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorDepth {
+  /// Detect from COLORTERM and TERM
+  #[default]
+  Auto,
+  /// 24-bit colors
+  Truecolor,
+  /// The 256-color palette
+  #[value(name = "256")]
+  Ansi256,
+  /// The 16 basic colors
+  #[value(name = "16")]
+  Ansi16,
+  /// The 16 basic colors with the `ansi` theme
+  Ansi,
+}
+
+/// xterm's defaults for the 8 normal and 8 bright colors.
+const ANSI_PALETTE: [(u8, u8, u8); 16] = [
+  (0, 0, 0),
+  (205, 0, 0),
+  (0, 205, 0),
+  (205, 205, 0),
+  (0, 0, 238),
+  (205, 0, 205),
+  (0, 205, 205),
+  (229, 229, 229),
+  (127, 127, 127),
+  (255, 0, 0),
+  (0, 255, 0),
+  (255, 255, 0),
+  (92, 92, 255),
+  (255, 0, 255),
+  (0, 255, 255),
+  (255, 255, 255),
+];
+
+const RED: usize = 1;
+const GREEN: usize = 2;
+const YELLOW: usize = 3;
+const BLUE: usize = 4;
+const MAGENTA: usize = 5;
+const CYAN: usize = 6;
+const WHITE: usize = 7;
+const BRIGHT_BLACK: usize = 8;
+
+/// The ANSI color for each theme key in the `ansi` theme. Keys left out,
+/// such as punctuation, keep the terminal's default color.
+const ANSI_SLOTS: &[(&str, usize)] = &[
+  ("attribute", CYAN),
+  ("boolean", RED),
+  ("character", RED),
+  ("comment", BRIGHT_BLACK),
+  ("constant", RED),
+  ("constant.builtin", RED),
+  ("constructor", YELLOW),
+  ("diff.delta", YELLOW),
+  ("diff.minus", RED),
+  ("diff.plus", GREEN),
+  ("error", RED),
+  ("function", BLUE),
+  ("keyword", MAGENTA),
+  ("label", CYAN),
+  ("markup.heading", BLUE),
+  ("markup.link", CYAN),
+  ("markup.raw", GREEN),
+  ("module", YELLOW),
+  ("namespace", YELLOW),
+  ("number", RED),
+  ("string", GREEN),
+  ("string.escape", CYAN),
+  ("string.regex", CYAN),
+  ("string.regexp", CYAN),
+  ("string.special", CYAN),
+  ("tag", BLUE),
+  ("tag.attribute", CYAN),
+  ("type", YELLOW),
+  ("variable.builtin", RED),
+];
+
+fn ansi_color(index: usize) -> Color {
+  let (r, g, b) = ANSI_PALETTE[index];
+  Color::new(r, g, b)
+}
+
+/// The `ansi` theme. Its colors are the entries of the 16-color palette, so
+/// rendering at `Ansi16` depth turns each back into its own slot.
+pub fn ansi_theme() -> ResolvedTheme {
+  let mut styles = BTreeMap::new();
+  styles.insert(
+    "_normal".to_string(),
+    Style::new(ansi_color(WHITE), None, false, false, false, false),
+  );
+  for &(key, slot) in ANSI_SLOTS {
+    if THEME_KEYS.contains(&key) {
+      styles.insert(
+        key.to_string(),
+        Style::new(ansi_color(slot), None, false, false, false, false),
+      );
+    }
+  }
+  ResolvedTheme::new(styles)
+}
+
+/// The depth a terminal supports, from the `COLORTERM` and `TERM` variables.
+/// Terminals that set neither, such as those on Windows, get truecolor.
+pub fn detect(colorterm: Option<&str>, term: Option<&str>) -> ColorDepth {
+  if colorterm.is_some_and(|value| matches!(value, "truecolor" | "24bit")) {
+    return ColorDepth::Truecolor;
+  }
+  let Some(term) = term.filter(|term| !term.is_empty()) else {
+    return ColorDepth::Truecolor;
+  };
+  if term.ends_with("-direct") {
+    ColorDepth::Truecolor
+  } else if term.contains("256color") {
+    ColorDepth::Ansi256
+  } else if [
+    "linux", "vt", "ansi", "xterm", "screen", "tmux", "rxvt", "cygwin",
+  ]
+  .iter()
+  .any(|prefix| term.starts_with(prefix))
+  {
+    ColorDepth::Ansi16
+  } else {
+    ColorDepth::Truecolor
+  }
+}
+
+/// A terminal renderer that writes colors at a fixed depth. Truecolor is left
+/// to syntastica's renderer; the palettes get their own escape sequences.
+pub struct DepthRenderer {
+  inner: TerminalRenderer,
+  depth: ColorDepth,
+}
+
+impl DepthRenderer {
+  /// `depth` must already be resolved; `Auto` is treated as truecolor.
+  pub fn new(depth: ColorDepth) -> Self {
+    Self {
+      inner: TerminalRenderer::new(None),
+      depth,
+    }
+  }
+
+  /// The SGR parameters selecting `color` from the palette.
+  fn color_code(&self, color: Color, background: bool) -> String {
+    match (self.depth, background) {
+      (ColorDepth::Ansi16 | ColorDepth::Ansi, false) => ansi16_code(color, 30).to_string(),
+      (ColorDepth::Ansi16 | ColorDepth::Ansi, true) => ansi16_code(color, 40).to_string(),
+      (_, false) => format!("38;5;{}", ansi256(color)),
+      (_, true) => format!("48;5;{}", ansi256(color)),
+    }
+  }
+}
+
+impl Renderer for DepthRenderer {
+  fn head(&mut self) -> Cow<'static, str> {
+    self.inner.head()
+  }
+
+  fn tail(&mut self) -> Cow<'static, str> {
+    self.inner.tail()
+  }
+
+  fn newline(&mut self) -> Cow<'static, str> {
+    self.inner.newline()
+  }
+
+  fn escape<'a>(&mut self, text: &'a str) -> Cow<'a, str> {
+    self.inner.escape(text)
+  }
+
+  fn unstyled<'a>(&mut self, text: &'a str) -> Cow<'a, str> {
+    self.inner.unstyled(text)
+  }
+
+  fn styled<'a>(&mut self, text: &'a str, style: Style) -> Cow<'a, str> {
+    if matches!(self.depth, ColorDepth::Auto | ColorDepth::Truecolor) {
+      return self.inner.styled(text, style);
+    }
+    let mut codes = Vec::with_capacity(6);
+    for (enabled, code) in [
+      (style.bold(), "1"),
+      (style.italic(), "3"),
+      (style.underline(), "4"),
+      (style.strikethrough(), "9"),
+    ] {
+      if enabled {
+        codes.push(code.to_string());
+      }
+    }
+    codes.push(self.color_code(style.color(), false));
+    if let Some(bg) = style.bg() {
+      codes.push(self.color_code(bg, true));
+    }
+    Cow::Owned(format!("\x1b[{}m{text}\x1b[0m", codes.join(";")))
+  }
+}
+
+/// The closest entry of the 256-color palette: the 6×6×6 cube or the
+/// grayscale ramp, whichever is nearer.
+pub fn ansi256(color: Color) -> u8 {
+  const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+  let level = |channel: u8| match channel {
+    0..48 => 0,
+    48..115 => 1,
+    _ => (channel - 35) / 40,
+  };
+  let (r, g, b) = (level(color.red), level(color.green), level(color.blue));
+  let cube = Color::new(CUBE[r as usize], CUBE[g as usize], CUBE[b as usize]);
+
+  let average = (u16::from(color.red) + u16::from(color.green) + u16::from(color.blue)) / 3;
+  let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+  let gray_value = 8 + 10 * gray_step;
+  let gray = Color::new(gray_value, gray_value, gray_value);
+
+  if distance(color, gray) < distance(color, cube) {
+    232 + gray_step
+  } else {
+    16 + 36 * r + 6 * g + b
+  }
+}
+
+/// The SGR code of the closest of the 16 basic colors, counting from `base`
+/// (30 for the foreground, 40 for the background).
+fn ansi16_code(color: Color, base: u8) -> u8 {
+  let index = (0..ANSI_PALETTE.len())
+    .min_by_key(|&index| distance(color, ansi_color(index)))
+    .unwrap_or(7) as u8;
+  if index < 8 {
+    base + index
+  } else {
+    base + 60 + index - 8
+  }
+}
+
+/// Squared distance between two colors, weighted for how the eye sees them.
+fn distance(a: Color, b: Color) -> u32 {
+  let channel = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+  2 * channel(a.red, b.red) + 4 * channel(a.green, b.green) + 3 * channel(a.blue, b.blue)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_detect() {
+    assert_eq!(
+      detect(Some("truecolor"), Some("xterm")),
+      ColorDepth::Truecolor
+    );
+    assert_eq!(detect(None, Some("xterm-256color")), ColorDepth::Ansi256);
+    assert_eq!(detect(None, Some("linux")), ColorDepth::Ansi16);
+    assert_eq!(detect(None, None), ColorDepth::Truecolor);
+  }
+
+  #[test]
+  fn test_quantize() {
+    assert_eq!(ansi256(Color::new(255, 0, 0)), 196);
+    assert_eq!(ansi256(Color::new(128, 128, 128)), 244);
+    assert_eq!(ansi16_code(Color::new(250, 10, 10), 30), 91);
+    assert_eq!(ansi16_code(Color::new(20, 20, 20), 40), 40);
+  }
+
+  #[test]
+  fn test_ansi_theme() {
+    let theme = ansi_theme();
+    let comment = theme.find_style("comment").unwrap().color();
+    assert_eq!(ansi16_code(comment, 30), 90);
+    assert_eq!(ansi16_code(ansi_color(MAGENTA), 30), 35);
+  }
+}
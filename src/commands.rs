@@ -0,0 +1,97 @@
+//! Subcommand dispatch.
+//! `umber FILE` keeps working: a subcommand is only recognized as the first
+//! argument, and only when no file of that name exists in the current directory.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+/// The subcommands umber understands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subcommand {
+  /// Display files; the same as running umber without a subcommand
+  View,
+  /// Render `git diff` output
+  Diff,
+  /// Display a file with the commit and author of each line
+  Blame,
+  /// Display a file as of a revision, `REV:PATH`
+  Show,
+  /// List the available themes
+  Themes,
+  /// List the supported languages
+  Langs,
+  /// Inspect or reset the on-disk cache
+  Cache,
+}
+
+impl Subcommand {
+  pub const ALL: [Self; 7] = [
+    Self::View,
+    Self::Diff,
+    Self::Blame,
+    Self::Show,
+    Self::Themes,
+    Self::Langs,
+    Self::Cache,
+  ];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::View => "view",
+      Self::Diff => "diff",
+      Self::Blame => "blame",
+      Self::Show => "show",
+      Self::Themes => "themes",
+      Self::Langs => "langs",
+      Self::Cache => "cache",
+    }
+  }
+
+  /// The subcommand named by the first argument, if any.
+  pub fn detect(args: &[OsString]) -> Option<Self> {
+    let first = args.get(1)?;
+    Self::ALL
+      .into_iter()
+      .find(|command| first == command.name())
+      .filter(|command| !Path::new(command.name()).exists())
+  }
+
+  /// Arguments for parsing the rest of the command line, with the subcommand
+  /// folded into the program name so usage and errors read `umber diff ...`.
+  pub fn strip(self, args: &[OsString]) -> Vec<OsString> {
+    std::iter::once(OsString::from(format!("umber {}", self.name())))
+      .chain(args.iter().skip(2).cloned())
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+  }
+
+  #[test]
+  fn test_detect_subcommand() {
+    assert_eq!(
+      Subcommand::detect(&args(&["umber", "show", "HEAD:src/main.rs"])),
+      Some(Subcommand::Show)
+    );
+    assert_eq!(Subcommand::detect(&args(&["umber", "main.rs"])), None);
+    assert_eq!(Subcommand::detect(&args(&["umber"])), None);
+    assert_eq!(
+      Subcommand::detect(&args(&["umber", "langs"])),
+      Some(Subcommand::Langs)
+    );
+  }
+
+  #[test]
+  fn test_strip_folds_subcommand_into_program_name() {
+    assert_eq!(
+      Subcommand::Diff.strip(&args(&["umber", "diff", "--style=numbers", "HEAD~1"])),
+      args(&["umber diff", "--style=numbers", "HEAD~1"])
+    );
+  }
+}
@@ -0,0 +1,169 @@
+//! User configuration file support.
+//! Loads `config.toml` from `$UMBER_CONFIG_PATH`, `$XDG_CONFIG_HOME/umber/`,
+//! or `~/.config/umber/`.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use eyre::{Result, eyre};
+use serde::Deserialize;
+
+use crate::pager::split_command;
+
+/// Parsed contents of the configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub theme: ThemeConfig,
+  /// Per-language rendering settings, keyed by language name (`[language.make]`).
+  #[serde(rename = "language")]
+  pub languages: HashMap<String, LanguageConfig>,
+  /// Languages for file name patterns (`[syntax-map]`), e.g. `"*.conf" = "ini"`.
+  #[serde(rename = "syntax-map")]
+  pub syntax_map: HashMap<String, String>,
+  /// Commands that convert matching files to text before display (`[preprocess]`).
+  pub preprocess: HashMap<String, PreprocessConfig>,
+}
+
+/// The `[theme]` table.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+  /// Theme used when `--theme` is not given.
+  pub name: Option<String>,
+  /// Theme `auto` picks in light mode, when `--theme-light` is not given.
+  pub light: Option<String>,
+  /// Theme `auto` picks in dark mode, when `--theme-dark` is not given.
+  pub dark: Option<String>,
+  /// Theme overrides keyed by language name, e.g. `markdown = "one-light"`.
+  #[serde(rename = "per-language")]
+  pub per_language: HashMap<String, String>,
+  /// Background for `--highlight-line`, as `#rrggbb`.
+  #[serde(rename = "highlight-line")]
+  pub highlight_line: Option<String>,
+}
+
+/// A `[language.<name>]` table, applied after the language has been detected.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LanguageConfig {
+  /// Style components added on top of `--style`, e.g. `"numbers,changes"`.
+  pub style: Option<String>,
+  /// Enable or disable language injections (the `rich` style component).
+  pub injections: Option<bool>,
+  /// Show unprintable characters, like `--show-all`.
+  pub show_all: Option<bool>,
+  /// Shade alternate lines, like `--zebra`.
+  pub zebra: Option<bool>,
+}
+
+/// A `[preprocess]` entry: either just the command, e.g.
+/// `"*.pdf" = "pdftotext {} -"`, or a table that also names the language of
+/// the output, e.g. `"*.sqlite" = { command = "sqlite3 {} .dump", language = "sql" }`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PreprocessConfig {
+  Command(String),
+  Full {
+    command: String,
+    language: Option<String>,
+  },
+}
+
+impl Config {
+  /// Load the configuration file, returning defaults when it does not exist.
+  pub fn load() -> Result<Self> {
+    let Some(path) = config_path() else {
+      return Ok(Self::default());
+    };
+    let raw = match fs::read_to_string(&path) {
+      Ok(raw) => raw,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+      Err(err) => return Err(eyre!("{}: {err}", path.display())),
+    };
+    toml::from_str(&raw).map_err(|err| eyre!("{}: {err}", path.display()))
+  }
+}
+
+/// Directory holding the configuration file and other user assets.
+pub fn config_dir() -> Option<PathBuf> {
+  if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME").filter(|value| !value.is_empty()) {
+    return Some(PathBuf::from(xdg).join("umber"));
+  }
+  let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+  Some(PathBuf::from(home).join(".config").join("umber"))
+}
+
+/// Location of the configuration file.
+pub fn config_path() -> Option<PathBuf> {
+  if let Some(path) = std::env::var_os("UMBER_CONFIG_PATH").filter(|value| !value.is_empty()) {
+    return Some(PathBuf::from(path));
+  }
+  config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Default arguments from `UMBER_OPTS` (split like a shell would),
+/// `UMBER_STYLE` and `UMBER_THEME`, to be parsed before the real command line
+/// so that explicit arguments win.
+pub fn env_args() -> Result<Vec<OsString>> {
+  let var = |name| {
+    std::env::var(name)
+      .ok()
+      .filter(|value| !value.trim().is_empty())
+  };
+  default_args(
+    var("UMBER_OPTS").as_deref(),
+    var("UMBER_STYLE").as_deref(),
+    var("UMBER_THEME").as_deref(),
+  )
+}
+
+fn default_args(
+  opts: Option<&str>,
+  style: Option<&str>,
+  theme: Option<&str>,
+) -> Result<Vec<OsString>> {
+  let mut args: Vec<OsString> = match opts {
+    Some(opts) => split_command(opts)
+      .map_err(|err| eyre!("UMBER_OPTS: {err}"))?
+      .into_iter()
+      .map(OsString::from)
+      .collect(),
+    None => Vec::new(),
+  };
+  if let Some(style) = style {
+    args.push(format!("--style={style}").into());
+  }
+  if let Some(theme) = theme {
+    args.push(format!("--theme={theme}").into());
+  }
+  Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_default_args_from_env_values() {
+    assert_eq!(
+      default_args(
+        Some("--color=always --pager 'less -R'"),
+        Some("numbers"),
+        Some("nord")
+      )
+      .unwrap(),
+      vec![
+        OsString::from("--color=always"),
+        OsString::from("--pager"),
+        OsString::from("less -R"),
+        OsString::from("--style=numbers"),
+        OsString::from("--theme=nord"),
+      ]
+    );
+    assert!(default_args(Some("--pager 'less"), None, None).is_err());
+  }
+}
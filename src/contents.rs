@@ -0,0 +1,204 @@
+//! The bytes of a file to display. Large regular files are memory-mapped
+//! rather than copied onto the heap, so a log of several hundred megabytes
+//! only costs the pages that are looked at and the lines that are shown.
+//!
+//! A map faults when its file is truncated under it, so files that were
+//! modified moments ago, and may still be written to, are read instead.
+
+use std::fs::{File, Metadata};
+use std::io::{self, Cursor, Read};
+use std::ops::Deref;
+use std::path::Path;
+use std::time::Duration;
+
+use memmap2::Mmap;
+
+/// Regular files at least this large are mapped; smaller ones are read,
+/// which is cheaper than setting up a map.
+pub const MAP_MIN_BYTES: u64 = 1024 * 1024;
+
+/// Files modified less than this long ago are taken to be still changing.
+pub const SETTLED_AFTER: Duration = Duration::from_secs(10);
+
+pub enum Contents {
+  Read(Vec<u8>),
+  /// The first `len` bytes of a map
+  Mapped {
+    map: Mmap,
+    len: usize,
+  },
+}
+
+impl Contents {
+  /// The bytes on the heap, copied out of the map if need be.
+  pub fn into_vec(self) -> Vec<u8> {
+    match self {
+      Self::Read(bytes) => bytes,
+      Self::Mapped { map, len } => map[..len].to_vec(),
+    }
+  }
+
+  /// Keep only the first `len` bytes. The rest of a map is never touched.
+  pub fn truncate(&mut self, len: usize) {
+    match self {
+      Self::Read(bytes) => bytes.truncate(len),
+      Self::Mapped { len: mapped, .. } => *mapped = len.min(*mapped),
+    }
+  }
+
+  /// The contents with `edit` applied when `applies`, so that a map is only
+  /// copied onto the heap when it is about to be changed.
+  pub fn edit_if(self, applies: bool, edit: impl FnOnce(Vec<u8>) -> Vec<u8>) -> Self {
+    if applies {
+      Self::Read(edit(self.into_vec()))
+    } else {
+      self
+    }
+  }
+}
+
+impl Deref for Contents {
+  type Target = [u8];
+
+  fn deref(&self) -> &[u8] {
+    match self {
+      Self::Read(bytes) => bytes,
+      Self::Mapped { map, len } => &map[..*len],
+    }
+  }
+}
+
+impl From<Vec<u8>> for Contents {
+  fn from(bytes: Vec<u8>) -> Self {
+    Self::Read(bytes)
+  }
+}
+
+/// A file read window by window, through a map when it can be mapped.
+pub enum Reader {
+  File(File),
+  Mapped(Cursor<Mmap>),
+}
+
+impl Read for Reader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    match self {
+      Self::File(file) => file.read(buf),
+      Self::Mapped(map) => map.read(buf),
+    }
+  }
+}
+
+/// The contents of `path`: mapped when it is a settled regular file of at
+/// least `MAP_MIN_BYTES`, read otherwise or when it cannot be mapped.
+pub fn read(path: &Path) -> io::Result<Contents> {
+  let mut file = File::open(path)?;
+  let metadata = file.metadata()?;
+  if metadata.len() >= MAP_MIN_BYTES
+    && let Some(map) = map(&file, &metadata)
+  {
+    let len = map.len();
+    return Ok(Contents::Mapped { map, len });
+  }
+  let mut bytes = Vec::with_capacity(usize::try_from(metadata.len()).unwrap_or(0));
+  file.read_to_end(&mut bytes)?;
+  Ok(Contents::Read(bytes))
+}
+
+/// `file` to be read in windows: mapped when it is settled, so that a huge
+/// file is read out of the page cache, and as it is otherwise.
+pub fn reader(file: File) -> Reader {
+  match file
+    .metadata()
+    .ok()
+    .and_then(|metadata| map(&file, &metadata))
+  {
+    Some(map) => Reader::Mapped(Cursor::new(map)),
+    None => Reader::File(file),
+  }
+}
+
+/// True for a regular file that nothing has written to for `SETTLED_AFTER`.
+/// Logs being appended to or rotated, and files with a modification time in
+/// the future, are not.
+pub fn is_settled(metadata: &Metadata) -> bool {
+  metadata.is_file()
+    && metadata
+      .modified()
+      .ok()
+      .and_then(|modified| modified.elapsed().ok())
+      .is_some_and(|age| age >= SETTLED_AFTER)
+}
+
+fn map(file: &File, metadata: &Metadata) -> Option<Mmap> {
+  if !is_settled(metadata) {
+    return None;
+  }
+  // SAFETY: the map is only read, and only for files that have not changed
+  // for a while. One truncated by another process while it is shown still
+  // makes reads past its new end fault, the risk every reader mapping files
+  // takes.
+  unsafe { Mmap::map(file) }.ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::time::SystemTime;
+
+  fn settle(path: &Path) {
+    let file = File::options().write(true).open(path).unwrap();
+    file
+      .set_modified(SystemTime::now() - SETTLED_AFTER * 2)
+      .unwrap();
+  }
+
+  #[test]
+  fn test_read_maps_large_files() {
+    let dir = std::env::temp_dir();
+    let small = dir.join(format!("umber-contents-small-{}", std::process::id()));
+    let large = dir.join(format!("umber-contents-large-{}", std::process::id()));
+    fs::write(&small, b"tiny\n").unwrap();
+    fs::write(&large, vec![b'x'; MAP_MIN_BYTES as usize]).unwrap();
+
+    let contents = read(&small).unwrap();
+    assert!(matches!(contents, Contents::Read(_)));
+    assert_eq!(&*contents, b"tiny\n");
+    // Just written, so it may still be changing.
+    assert!(matches!(read(&large).unwrap(), Contents::Read(_)));
+    settle(&large);
+    let mut contents = read(&large).unwrap();
+    assert!(matches!(contents, Contents::Mapped { .. }));
+    assert_eq!(contents.len(), MAP_MIN_BYTES as usize);
+    contents.truncate(3);
+    assert_eq!(&*contents, b"xxx");
+    let edited = contents.edit_if(true, |mut bytes| {
+      bytes.truncate(1);
+      bytes
+    });
+    assert_eq!(edited.into_vec(), b"x");
+
+    fs::remove_file(&small).unwrap();
+    fs::remove_file(&large).unwrap();
+  }
+
+  #[test]
+  fn test_reader_maps_settled_files() {
+    let path = std::env::temp_dir().join(format!("umber-contents-reader-{}", std::process::id()));
+    fs::write(&path, b"one\ntwo\n").unwrap();
+
+    assert!(matches!(
+      reader(File::open(&path).unwrap()),
+      Reader::File(_)
+    ));
+    settle(&path);
+    let mut reader = reader(File::open(&path).unwrap());
+    assert!(matches!(reader, Reader::Mapped(_)));
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).unwrap();
+    assert_eq!(bytes, b"one\ntwo\n");
+
+    fs::remove_file(&path).unwrap();
+  }
+}
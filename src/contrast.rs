@@ -0,0 +1,136 @@
+//! `--contrast high`: foreground colors are brightened or darkened until they
+//! reach a minimum WCAG contrast ratio against their background.
+
+use clap::ValueEnum;
+use syntastica::style::{Color, Style};
+use syntastica::theme::ResolvedTheme;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Contrast {
+  /// The theme's own colors
+  #[default]
+  Normal,
+  /// At least 7:1 against the background (WCAG AAA)
+  High,
+}
+
+impl Contrast {
+  /// The contrast ratio every foreground must reach, if any.
+  fn min_ratio(self) -> Option<f64> {
+    match self {
+      Contrast::Normal => None,
+      Contrast::High => Some(7.0),
+    }
+  }
+
+  /// `theme` with its foreground colors adjusted. Styles without a background
+  /// of their own are measured against `background`.
+  pub fn apply(self, theme: ResolvedTheme, background: Color) -> ResolvedTheme {
+    let Some(min_ratio) = self.min_ratio() else {
+      return theme;
+    };
+    let styles = theme
+      .into_inner()
+      .into_iter()
+      .map(|(key, style)| {
+        let color = ensure_contrast(style.color(), style.bg().unwrap_or(background), min_ratio);
+        let style = Style::new(
+          color,
+          style.bg(),
+          style.underline(),
+          style.strikethrough(),
+          style.italic(),
+          style.bold(),
+        );
+        (key, style)
+      })
+      .collect();
+    ResolvedTheme::new(styles)
+  }
+}
+
+/// WCAG relative luminance, between 0 and 1.
+fn relative_luminance(color: Color) -> f64 {
+  let linear = |channel: u8| {
+    let value = f64::from(channel) / 255.0;
+    if value <= 0.039_28 {
+      value / 12.92
+    } else {
+      ((value + 0.055) / 1.055).powf(2.4)
+    }
+  };
+  0.2126 * linear(color.red) + 0.7152 * linear(color.green) + 0.0722 * linear(color.blue)
+}
+
+/// WCAG contrast ratio, from 1 (none) to 21 (black on white).
+pub fn contrast_ratio(a: Color, b: Color) -> f64 {
+  let (a, b) = (relative_luminance(a), relative_luminance(b));
+  (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// `color`, moved towards white on dark backgrounds and towards black on
+/// light ones just far enough to reach `min_ratio` against `background`.
+fn ensure_contrast(color: Color, background: Color, min_ratio: f64) -> Color {
+  if contrast_ratio(color, background) >= min_ratio {
+    return color;
+  }
+  let white = Color::new(255, 255, 255);
+  let black = Color::new(0, 0, 0);
+  let target = if contrast_ratio(white, background) >= contrast_ratio(black, background) {
+    white
+  } else {
+    black
+  };
+  let mix = |amount: f64| {
+    let channel = |from: u8, to: u8| {
+      (f64::from(from) + (f64::from(to) - f64::from(from)) * amount).round() as u8
+    };
+    Color::new(
+      channel(color.red, target.red),
+      channel(color.green, target.green),
+      channel(color.blue, target.blue),
+    )
+  };
+  // The ratio grows with the amount mixed in, so bisect for the smallest.
+  let (mut low, mut high) = (0.0, 1.0);
+  for _ in 0..16 {
+    let middle = (low + high) / 2.0;
+    if contrast_ratio(mix(middle), background) >= min_ratio {
+      high = middle;
+    } else {
+      low = middle;
+    }
+  }
+  mix(high)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_contrast_ratio() {
+    let white = Color::new(255, 255, 255);
+    let black = Color::new(0, 0, 0);
+    assert!((contrast_ratio(white, black) - 21.0).abs() < 0.01);
+    assert!((contrast_ratio(white, white) - 1.0).abs() < 0.01);
+  }
+
+  #[test]
+  fn test_ensure_contrast() {
+    let dark = Color::new(30, 30, 46);
+    let dim = Color::new(69, 71, 90);
+    let adjusted = ensure_contrast(dim, dark, 7.0);
+    assert!(contrast_ratio(adjusted, dark) >= 7.0);
+    assert!(adjusted.red > dim.red);
+
+    let light = Color::new(239, 241, 245);
+    let pale = Color::new(220, 138, 120);
+    let adjusted = ensure_contrast(pale, light, 7.0);
+    assert!(contrast_ratio(adjusted, light) >= 7.0);
+    assert!(adjusted.red < pale.red);
+
+    let readable = Color::new(205, 214, 244);
+    assert_eq!(ensure_contrast(readable, dark, 7.0), readable);
+  }
+}
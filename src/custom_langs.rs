@@ -1,19 +1,239 @@
-//! Custom language support for languages not in syntastica-parsers-git.
-//! Currently adds support for Terraform and HCL.
+//! Custom language support for languages not in syntastica-parsers-git:
+//! those bundled in `CustomLang::ALL`, such as Terraform and HCL, and the
+//! grammars loaded at runtime from the grammars directory.
 
 use once_cell::sync::OnceCell;
 use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use syntastica::{
   language_set::{FileType, HighlightConfiguration, LanguageSet, SupportedLanguage},
   theme::THEME_KEYS,
 };
-use tree_sitter_language::LanguageFn;
+use syntastica_highlight::Language;
+use syntastica_parsers_git::Lang;
+
+use crate::queries::{self, QueryKind};
+use crate::{cache, grammars, log_tokens};
 
 /// Custom languages that we provide ourselves.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CustomLang {
   Hcl,
   Terraform,
+  Jsonnet,
+  Rego,
+  Bicep,
+  Just,
+  Caddyfile,
+  Nginx,
+  /// systemd units, on syntastica's INI grammar
+  Systemd,
+  /// `.env` files, on syntastica's bash grammar
+  Dotenv,
+  Earthfile,
+  /// Log files, highlighted by `log_tokens` rather than a grammar
+  Log,
+  /// A grammar loaded at runtime, by its index in `grammars::all()`
+  Dynamic(usize),
+}
+
+impl CustomLang {
+  /// The languages built into umber.
+  pub const ALL: [Self; 12] = [
+    Self::Hcl,
+    Self::Terraform,
+    Self::Jsonnet,
+    Self::Rego,
+    Self::Bicep,
+    Self::Just,
+    Self::Caddyfile,
+    Self::Nginx,
+    Self::Systemd,
+    Self::Dotenv,
+    Self::Earthfile,
+    Self::Log,
+  ];
+
+  /// The grammar and queries umber ships; `None` for runtime grammars, which
+  /// bring their own, and for `log`, which has none.
+  fn bundled(self) -> Option<Bundled> {
+    let (language, highlights, injections) = match self {
+      Self::Hcl => (tree_sitter_hcl::LANGUAGE.into(), HCL_HIGHLIGHT_QUERY, ""),
+      Self::Terraform => (
+        tree_sitter_hcl::LANGUAGE.into(),
+        TERRAFORM_HIGHLIGHT_QUERY,
+        "",
+      ),
+      Self::Jsonnet => (
+        tree_sitter_jsonnet::LANGUAGE.into(),
+        JSONNET_HIGHLIGHT_QUERY,
+        "",
+      ),
+      Self::Rego => (tree_sitter_rego::LANGUAGE.into(), REGO_HIGHLIGHT_QUERY, ""),
+      Self::Bicep => (
+        tree_sitter_bicep::LANGUAGE.into(),
+        BICEP_HIGHLIGHT_QUERY,
+        "",
+      ),
+      Self::Just => (
+        tree_sitter_just::LANGUAGE.into(),
+        JUST_HIGHLIGHT_QUERY,
+        JUST_INJECTION_QUERY,
+      ),
+      Self::Caddyfile => (
+        tree_sitter_caddyfile::LANGUAGE.into(),
+        CADDYFILE_HIGHLIGHT_QUERY,
+        "",
+      ),
+      Self::Nginx => (
+        tree_sitter_nginx::LANGUAGE.into(),
+        NGINX_HIGHLIGHT_QUERY,
+        "",
+      ),
+      Self::Systemd => (
+        Lang::Ini.get(),
+        SYSTEMD_HIGHLIGHT_QUERY,
+        SYSTEMD_INJECTION_QUERY,
+      ),
+      Self::Dotenv => (Lang::Bash.get(), DOTENV_HIGHLIGHT_QUERY, ""),
+      Self::Earthfile => (
+        tree_sitter_earthfile::LANGUAGE.into(),
+        EARTHFILE_HIGHLIGHT_QUERY,
+        EARTHFILE_INJECTION_QUERY,
+      ),
+      Self::Log | Self::Dynamic(_) => return None,
+    };
+    Some(Bundled {
+      language,
+      highlights,
+      injections,
+    })
+  }
+
+  /// The built-in language for files named like `path`, for those the file
+  /// type detection does not know.
+  pub fn for_path(path: &Path) -> Option<Self> {
+    match path.file_name()?.to_str()? {
+      "justfile" | "Justfile" | ".justfile" => return Some(Self::Just),
+      "Caddyfile" => return Some(Self::Caddyfile),
+      "nginx.conf" => return Some(Self::Nginx),
+      "Earthfile" => return Some(Self::Earthfile),
+      // Rotated logs such as `app.log.1`
+      name
+        if name
+          .rsplit_once('.')
+          .is_some_and(|(stem, number)| stem.ends_with(".log") && digits_only(number)) =>
+      {
+        return Some(Self::Log);
+      }
+      // .env.local, .env.production
+      name if name == ".env" || name.starts_with(".env.") => return Some(Self::Dotenv),
+      _ if is_nginx_include(path) => return Some(Self::Nginx),
+      _ => {}
+    }
+    match path.extension()?.to_str()? {
+      "jsonnet" | "libsonnet" => Some(Self::Jsonnet),
+      "rego" => Some(Self::Rego),
+      // Parameter files share the grammar; it parses their `using` line.
+      "bicep" | "bicepparam" => Some(Self::Bicep),
+      // Modules imported with `mod`
+      "just" => Some(Self::Just),
+      "caddyfile" => Some(Self::Caddyfile),
+      "nginx" => Some(Self::Nginx),
+      "env" => Some(Self::Dotenv),
+      "log" => Some(Self::Log),
+      // `.path` or `.target` files are only units in a systemd directory;
+      // `for_content` looks at the others.
+      extension if is_systemd_unit(extension) => {
+        (!is_shared_unit_extension(extension) || in_systemd_dir(path)).then_some(Self::Systemd)
+      }
+      // Drop-ins such as `foo.service.d/override.conf`
+      "conf" => {
+        let dir = path.parent()?.file_name()?.to_str()?.strip_suffix(".d")?;
+        let (_, unit) = dir.rsplit_once('.')?;
+        is_systemd_unit(unit).then_some(Self::Systemd)
+      }
+      _ => None,
+    }
+  }
+
+  /// The built-in language of `path` that only its content tells: units
+  /// with an extension other files have too, such as `.path` or `.target`,
+  /// outside a systemd directory, when they have a `[Unit]` section.
+  pub fn for_content(path: &Path, content: &str) -> Option<Self> {
+    let extension = path.extension()?.to_str()?;
+    let unit = is_shared_unit_extension(extension)
+      && content.lines().take(50).any(|line| line.trim() == "[Unit]");
+    unit.then_some(Self::Systemd)
+  }
+}
+
+fn digits_only(text: &str) -> bool {
+  !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn is_systemd_unit(extension: &str) -> bool {
+  matches!(
+    extension,
+    "service"
+      | "timer"
+      | "socket"
+      | "mount"
+      | "automount"
+      | "path"
+      | "slice"
+      | "target"
+      | "network"
+      | "netdev"
+      | "link"
+  )
+}
+
+/// Unit extensions that other files use too.
+fn is_shared_unit_extension(extension: &str) -> bool {
+  matches!(
+    extension,
+    "automount" | "path" | "slice" | "target" | "link"
+  )
+}
+
+/// Whether `path` is under a `systemd` directory, such as `/etc/systemd/`,
+/// `/usr/lib/systemd/` or `~/.config/systemd/`.
+fn in_systemd_dir(path: &Path) -> bool {
+  path.parent().is_some_and(|dir| {
+    dir
+      .components()
+      .any(|component| component.as_os_str() == "systemd")
+  })
+}
+
+/// Whether `path` is in a directory nginx includes server blocks from, such
+/// as `/etc/nginx/sites-enabled/`. The directory has to be in an `nginx`
+/// one, as Apache on Debian has `sites-available` too and other programs
+/// have a `conf.d`, such as fontconfig with XML files.
+fn is_nginx_include(path: &Path) -> bool {
+  let Some(dir) = path.parent() else {
+    return false;
+  };
+  let in_nginx = dir
+    .parent()
+    .and_then(Path::file_name)
+    .is_some_and(|name| name == "nginx");
+  in_nginx
+    && dir
+      .file_name()
+      .and_then(|name| name.to_str())
+      .is_some_and(|name| matches!(name, "sites-available" | "sites-enabled" | "conf.d"))
+}
+
+/// A grammar compiled into umber and its queries.
+struct Bundled {
+  language: Language,
+  highlights: &'static str,
+  /// Used when injections are highlighted, as with `--style=rich`
+  injections: &'static str,
 }
 
 impl AsRef<str> for CustomLang {
@@ -21,6 +241,17 @@ impl AsRef<str> for CustomLang {
     match self {
       Self::Hcl => "hcl",
       Self::Terraform => "terraform",
+      Self::Jsonnet => "jsonnet",
+      Self::Rego => "rego",
+      Self::Bicep => "bicep",
+      Self::Just => "just",
+      Self::Caddyfile => "caddyfile",
+      Self::Nginx => "nginx",
+      Self::Systemd => "systemd",
+      Self::Dotenv => "dotenv",
+      Self::Earthfile => "earthfile",
+      Self::Log => "log",
+      Self::Dynamic(index) => &grammars::all()[*index].name,
     }
   }
 }
@@ -31,9 +262,23 @@ impl<'set, T> SupportedLanguage<'set, T> for CustomLang {
   }
 
   fn for_name(name: impl AsRef<str>, _set: &'set T) -> syntastica::Result<Self> {
+    // Runtime grammars come first, so one can replace a bundled grammar.
+    if let Some(index) = grammars::find(name.as_ref()) {
+      return Ok(CustomLang::Dynamic(index));
+    }
     match name.as_ref() {
       "hcl" => Ok(CustomLang::Hcl),
       "terraform" | "tf" => Ok(CustomLang::Terraform),
+      "jsonnet" | "libsonnet" => Ok(CustomLang::Jsonnet),
+      "rego" => Ok(CustomLang::Rego),
+      "bicep" | "bicepparam" => Ok(CustomLang::Bicep),
+      "just" | "justfile" => Ok(CustomLang::Just),
+      "caddyfile" | "caddy" => Ok(CustomLang::Caddyfile),
+      "nginx" => Ok(CustomLang::Nginx),
+      "systemd" => Ok(CustomLang::Systemd),
+      "dotenv" | "env" => Ok(CustomLang::Dotenv),
+      "earthfile" | "earthly" => Ok(CustomLang::Earthfile),
+      "log" => Ok(CustomLang::Log),
       name => Err(syntastica::Error::UnsupportedLanguage(name.to_string())),
     }
   }
@@ -47,16 +292,22 @@ impl<'set, T> SupportedLanguage<'set, T> for CustomLang {
   }
 }
 
-/// Custom language set with HCL and Terraform support.
+/// Custom language set with the built-in languages and runtime grammars.
 #[derive(Default)]
 pub struct CustomLanguageSet {
-  hcl_lang: OnceCell<HighlightConfiguration>,
-  terraform_lang: OnceCell<HighlightConfiguration>,
+  /// Configurations of `CustomLang::ALL`, in that order
+  bundled: [OnceCell<HighlightConfiguration>; CustomLang::ALL.len()],
+  /// Where `umber cache build` put preprocessed queries; `None` always
+  /// preprocesses them.
+  cache_dir: Option<PathBuf>,
 }
 
 impl CustomLanguageSet {
-  pub fn new() -> Self {
-    Self::default()
+  pub fn new(cache_dir: Option<PathBuf>) -> Self {
+    Self {
+      cache_dir,
+      ..Self::default()
+    }
   }
 }
 
@@ -65,36 +316,83 @@ impl LanguageSet<'_> for CustomLanguageSet {
 
   fn get_language(&self, language: Self::Language) -> syntastica::Result<&HighlightConfiguration> {
     match language {
-      CustomLang::Hcl => init_lang(
-        language.as_ref(),
-        &self.hcl_lang,
-        tree_sitter_hcl::LANGUAGE,
-        HCL_HIGHLIGHT_QUERY,
-      ),
-      CustomLang::Terraform => init_lang(
-        language.as_ref(),
-        &self.terraform_lang,
-        tree_sitter_hcl::LANGUAGE,
-        TERRAFORM_HIGHLIGHT_QUERY,
-      ),
+      CustomLang::Dynamic(index) => {
+        return grammars::all()[index].config(self.cache_dir.as_deref());
+      }
+      CustomLang::Log => return log_tokens::config(),
+      _ => {}
     }
+    let index = CustomLang::ALL
+      .iter()
+      .position(|bundled| *bundled == language)
+      .expect("every other language is bundled");
+    init_lang(language, &self.bundled[index], self.cache_dir.as_deref())
   }
 }
 
+/// Preprocess the queries of every custom language, built in or runtime,
+/// into `cache_dir`, for `umber cache build`. Returns how many were written.
+pub fn build_query_cache(cache_dir: &Path) -> io::Result<usize> {
+  for language in CustomLang::ALL {
+    if let Some(bundled) = language.bundled() {
+      cache_query(cache_dir, language.as_ref(), bundled.highlights)?;
+    }
+  }
+  let mut count = CustomLang::ALL.len();
+  for grammar in grammars::all() {
+    if let Some(source) = grammar.highlights_source() {
+      cache_query(cache_dir, &grammar.name, &source)?;
+      count += 1;
+    }
+  }
+  Ok(count)
+}
+
+/// Preprocess `source`, the highlight query of the language `name`, into
+/// `cache_dir`.
+pub fn cache_query(cache_dir: &Path, name: &str, source: &str) -> io::Result<()> {
+  cache::write_atomically(
+    &cache::query_path(cache_dir, name, source),
+    process_highlights(source).as_bytes(),
+  )
+}
+
+/// Rewrite nvim-treesitter queries for syntastica's capture names and predicates.
+fn process_highlights(source: &str) -> String {
+  syntastica_query_preprocessor::process_highlights("", true, source)
+}
+
+/// `source`, the highlight query of the language `name`, preprocessed, or
+/// read from the cache when `umber cache build` has put it there, and then
+/// overridden or extended by the user's query.
+pub(crate) fn preprocessed_highlights(
+  name: &str,
+  source: &str,
+  cache_dir: Option<&Path>,
+) -> String {
+  let query = cache_dir
+    .and_then(|dir| fs::read_to_string(cache::query_path(dir, name, source)).ok())
+    .unwrap_or_else(|| process_highlights(source));
+  queries::apply(name, QueryKind::Highlights, &query).into_owned()
+}
+
 /// Helper function for initializing a language configuration.
 fn init_lang<'a>(
-  name: &str,
+  language: CustomLang,
   cell: &'a OnceCell<HighlightConfiguration>,
-  get_lang: LanguageFn,
-  queries: &str,
+  cache_dir: Option<&Path>,
 ) -> syntastica::Result<&'a HighlightConfiguration> {
   cell.get_or_try_init(|| {
+    let bundled = language
+      .bundled()
+      .ok_or_else(|| syntastica::Error::UnsupportedLanguage(language.as_ref().to_string()))?;
+    let injections =
+      syntastica_query_preprocessor::process_injections("", true, bundled.injections);
     let mut conf = HighlightConfiguration::new(
-      get_lang.into(),
-      name,
-      // Preprocess queries for syntastica compatibility
-      &syntastica_query_preprocessor::process_highlights("", true, queries),
-      "",
+      bundled.language,
+      language.as_ref(),
+      &preprocessed_highlights(language.as_ref(), bundled.highlights, cache_dir),
+      &queries::apply(language.as_ref(), QueryKind::Injections, &injections),
       "",
     )?;
     // Configure with syntastica's theme keys
@@ -388,3 +686,1012 @@ const TERRAFORM_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
       (identifier) @type.builtin
       (#any-of? @type.builtin "bool" "string" "number" "object" "tuple" "list" "map" "set" "any"))))
 "#;
+
+// Highlight queries from nvim-treesitter:
+// https://github.com/nvim-treesitter/nvim-treesitter/tree/master/queries/jsonnet
+
+const JSONNET_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+[
+  (true)
+  (false)
+] @boolean
+
+(comment) @comment @spell
+
+(id) @variable
+
+(import) @keyword.import
+
+(null) @constant.builtin
+
+(number) @number
+
+(string) @string
+
+(fieldname
+  (id) @variable.member)
+
+[
+  "["
+  "]"
+  "{"
+  "}"
+  "("
+  ")"
+] @punctuation.bracket
+
+[
+  "."
+  ","
+  ";"
+  ":"
+] @punctuation.delimiter
+
+[
+  (dollar)
+  (self)
+] @variable.builtin
+
+((id) @variable.builtin
+  (#eq? @variable.builtin "std"))
+
+; Function declaration
+(bind
+  function: (id) @function
+  params: (params
+    (param
+      identifier: (id) @variable.parameter)))
+
+; Function call
+(expr
+  (expr
+    (id) @function.call)
+  "("
+  (args
+    (named_argument
+      (id) @variable.parameter))?
+  ")")
+
+; Field access
+(expr
+  (expr
+    (id) @variable.builtin)
+  "."
+  (id) @function
+  (#eq? @variable.builtin "std"))
+
+[
+  (local)
+  "assert"
+] @keyword
+
+[
+  "for"
+  "in"
+] @keyword.repeat
+
+"function" @keyword.function
+
+[
+  "if"
+  "then"
+  "else"
+] @keyword.conditional
+
+[
+  (additive)
+  (multiplicative)
+  (equality)
+  (bitand)
+  (bitor)
+  (bitxor)
+  (comparison)
+  (implyop)
+  (unaryop)
+  "="
+] @operator
+
+(ERROR) @error
+"#;
+
+// Highlight queries from nvim-treesitter:
+// https://github.com/nvim-treesitter/nvim-treesitter/tree/master/queries/rego
+
+const REGO_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+[
+  (import)
+  (package)
+] @keyword.import
+
+[
+  (with)
+  (as)
+  (every)
+  (some)
+  (in)
+  (not)
+  (if)
+  (contains)
+  (else)
+  (default)
+  "null"
+] @keyword
+
+[
+  "true"
+  "false"
+] @boolean
+
+[
+  (assignment_operator)
+  (bool_operator)
+  (arith_operator)
+  (bin_operator)
+] @operator
+
+[
+  (string)
+  (raw_string)
+] @string
+
+(term
+  (ref
+    (var))) @variable
+
+(comment) @comment @spell
+
+(number) @number
+
+(expr_call
+  func_name: (fn_name
+    (var) @function.call))
+
+(expr_call
+  func_arguments: (fn_args
+    (expr) @variable.parameter))
+
+[
+  ","
+  "."
+] @punctuation.delimiter
+
+[
+  "{"
+  "}"
+  "["
+  "]"
+  "("
+  ")"
+] @punctuation.bracket
+
+(rule
+  (rule_head
+    (var) @function))
+
+(rule
+  (rule_head
+    (term
+      (ref
+        (var) @variable.member))))
+"#;
+
+// Highlight queries from nvim-treesitter:
+// https://github.com/nvim-treesitter/nvim-treesitter/tree/master/queries/bicep
+
+const BICEP_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+; Includes
+(import_statement
+  "import" @keyword.import)
+
+; Namespaces
+(module_declaration
+  (identifier) @module)
+
+; Builtins
+(primitive_type) @type.builtin
+
+((member_expression
+  object: (identifier) @type.builtin)
+  (#eq? @type.builtin "sys"))
+
+; Functions
+(call_expression
+  function: (identifier) @function.call)
+
+(user_defined_function
+  name: (identifier) @function)
+
+; Properties
+(object_property
+  (identifier) @property
+  ":" @punctuation.delimiter
+  (_))
+
+(property_identifier) @property
+
+; Attributes
+(decorator
+  "@" @attribute)
+
+(decorator
+  (call_expression
+    (identifier) @attribute))
+
+; Types
+(type_declaration
+  (identifier) @type)
+
+(type
+  (identifier) @type)
+
+(resource_declaration
+  (identifier) @type)
+
+; Parameters
+(parameter_declaration
+  (identifier) @variable.parameter
+  (_))
+
+(parameter
+  (identifier) @variable.parameter)
+
+; Variables
+(variable_declaration
+  (identifier) @variable
+  (_))
+
+(metadata_declaration
+  (identifier) @variable
+  (_))
+
+(output_declaration
+  (identifier) @variable
+  (_))
+
+; Conditionals
+"if" @keyword.conditional
+
+(ternary_expression
+  "?" @keyword.conditional.ternary
+  ":" @keyword.conditional.ternary)
+
+; Loops
+(for_statement
+  "for" @keyword.repeat
+  "in"
+  ":" @punctuation.delimiter)
+
+; Keywords
+[
+  "module"
+  "metadata"
+  "output"
+  "param"
+  "resource"
+  "existing"
+  "targetScope"
+  "type"
+  "var"
+  "using"
+] @keyword
+
+"func" @keyword.function
+
+; Operators
+[
+  "+"
+  "-"
+  "*"
+  "/"
+  "%"
+  "||"
+  "&&"
+  "=="
+  "!="
+  "=~"
+  "!~"
+  ">"
+  ">="
+  "<="
+  "<"
+  "??"
+  "="
+  "!"
+] @operator
+
+"in" @keyword.operator
+
+; Literals
+(string) @string
+
+(escape_sequence) @string.escape
+
+(number) @number
+
+(boolean) @boolean
+
+(null) @constant.builtin
+
+; Misc
+[
+  "{"
+  "}"
+  "["
+  "]"
+  "("
+  ")"
+] @punctuation.bracket
+
+[
+  "."
+  ":"
+  "=>"
+] @punctuation.delimiter
+
+; Interpolation
+(interpolation
+  "${" @punctuation.special
+  "}" @punctuation.special)
+
+(interpolation
+  (identifier) @variable)
+
+; Comments
+[
+  (comment)
+  (diagnostic_comment)
+] @comment @spell
+"#;
+
+// Highlight and injection queries from nvim-treesitter:
+// https://github.com/nvim-treesitter/nvim-treesitter/tree/master/queries/just
+
+const JUST_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+[
+  "true"
+  "false"
+] @boolean
+
+[
+  "if"
+  "else"
+] @keyword.conditional
+
+[
+  "alias"
+  "set"
+  "export"
+] @keyword
+
+[
+  "import"
+  "mod"
+] @keyword.import
+
+[
+  "@"
+  "=="
+  "!="
+  "=~"
+  "+"
+  "/"
+  ":="
+  "&&"
+  "||"
+] @operator
+
+[
+  "("
+  ")"
+  "["
+  "]"
+  "{{"
+  "}}"
+] @punctuation.bracket
+
+[
+  ":"
+  ","
+] @punctuation.delimiter
+
+(alias
+  left: (identifier) @variable)
+
+(assignment
+  left: (identifier) @variable)
+
+(module
+  name: (identifier) @module)
+
+(setting
+  left: (identifier) @variable.member)
+
+(recipe_header
+  name: (identifier) @function)
+
+(dependency
+  name: (identifier) @function.call)
+
+(dependency_expression
+  name: (identifier) @function.call)
+
+(parameter
+  name: (identifier) @variable.parameter)
+
+(function_call
+  name: (identifier) @function.call)
+
+(value
+  (identifier) @variable)
+
+(attribute
+  (identifier) @attribute)
+
+(shebang) @keyword.directive
+
+(string) @string
+
+(escape_sequence) @string.escape
+
+(comment) @comment @spell
+"#;
+
+const JUST_INJECTION_QUERY: &str = r#"; injections.scm
+((comment) @injection.content
+  (#set! injection.language "comment"))
+
+; Recipes without a shebang run in the shell
+(recipe_body
+  !shebang
+  (#set! injection.language "bash")
+  (#set! injection.include-children)) @injection.content
+
+(recipe_body
+  (shebang
+    (language) @injection.language)
+  (#set! injection.include-children)) @injection.content
+
+(external_command
+  (command_body) @injection.content
+  (#set! injection.language "bash"))
+"#;
+
+// Curated for tree-sitter-caddyfile; nvim-treesitter has no Caddyfile queries.
+
+const CADDYFILE_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+(comment) @comment @spell
+
+; example.com, :8080, http://localhost
+(address) @label
+
+; reverse_proxy, file_server, tls
+(directive
+  name: (directive_name) @function)
+
+(subdirective
+  name: (directive_name) @variable.member)
+
+; Global options and snippets: (common), import
+(snippet_name) @module
+
+(import
+  "import" @keyword.import)
+
+; @api path /api/*
+(matcher) @attribute
+
+; {http.request.host}, {$ENV}
+(placeholder) @variable.builtin
+
+(environment_variable) @constant
+
+(string) @string
+
+(number) @number
+
+[
+  "{"
+  "}"
+] @punctuation.bracket
+"#;
+
+// Highlight queries from nvim-treesitter:
+// https://github.com/nvim-treesitter/nvim-treesitter/tree/master/queries/nginx
+
+const NGINX_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+(comment) @comment @spell
+
+(block_directive
+  (directive) @type)
+
+(simple_directive
+  (directive) @function)
+
+[
+  "{"
+  "}"
+  "("
+  ")"
+  "["
+  "]"
+] @punctuation.bracket
+
+";" @punctuation.delimiter
+
+((generic) @number
+  (#lua-match? @number "^%d+$"))
+
+((generic) @number
+  (#lua-match? @number "^%d+[kKmMgG]$"))
+
+((generic) @constant.builtin
+  (#any-of? @constant.builtin "on" "off"))
+
+(location_modifier) @operator
+
+(keyword) @keyword
+
+(boolean) @boolean
+
+(auto) @variable.builtin
+
+(file) @string.special.path
+
+(string_literal) @string
+
+(var) @variable
+
+(condition) @keyword.conditional
+"#;
+
+// nvim-treesitter's INI highlights, with unit sections, directives and
+// values told apart.
+
+const SYSTEMD_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+(comment) @comment @spell
+
+[
+  "["
+  "]"
+] @punctuation.bracket
+
+"=" @operator
+
+(section_name
+  (text) @type)
+
+((section_name
+  (text) @module.builtin)
+  (#any-of? @module.builtin
+    "Unit" "Install" "Service" "Socket" "Timer" "Mount" "Automount" "Path"
+    "Slice" "Scope" "Match" "Link" "Network" "NetDev" "Address" "Route"
+    "DHCP" "DHCPv4" "DHCPv6" "IPv6AcceptRA"))
+
+(setting
+  (setting_name) @property)
+
+((setting
+  (setting_name) @function.builtin)
+  (#lua-match? @function.builtin "^Exec"))
+
+((setting
+  (setting_name) @keyword)
+  (#any-of? @keyword
+    "After" "Before" "Requires" "Wants" "BindsTo" "PartOf" "Conflicts"
+    "WantedBy" "RequiredBy" "Also" "Alias"))
+
+(setting_value) @string
+
+((setting_value) @boolean
+  (#any-of? @boolean "yes" "no" "true" "false" "on" "off"))
+
+((setting_value) @number
+  (#lua-match? @number "^%s*%d+[a-z]*%s*$"))
+"#;
+
+// Commands run by Exec* directives, highlighted as shell.
+const SYSTEMD_INJECTION_QUERY: &str = r#"; injections.scm
+((comment) @injection.content
+  (#set! injection.language "comment"))
+
+((setting
+  (setting_name) @_name
+  (setting_value) @injection.content)
+  (#lua-match? @_name "^Exec")
+  (#set! injection.language "bash"))
+"#;
+
+// `KEY=value` lines as the bash grammar parses them, with keys, quoting and
+// `${VAR}` interpolation told apart.
+
+const DOTENV_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+(comment) @comment @spell
+
+(declaration_command
+  "export" @keyword)
+
+(variable_assignment
+  name: (variable_name) @property)
+
+(variable_assignment
+  "=" @operator)
+
+(variable_assignment
+  value: (word) @string)
+
+[
+  (string)
+  (raw_string)
+  (ansi_c_string)
+] @string
+
+(number) @number
+
+(expansion
+  [
+    "${"
+    "}"
+  ] @punctuation.special)
+
+(simple_expansion
+  "$" @punctuation.special)
+
+(expansion
+  (variable_name) @variable)
+
+(simple_expansion
+  (variable_name) @variable)
+
+(command_substitution) @string.special
+"#;
+
+// Highlight and injection queries from nvim-treesitter:
+// https://github.com/nvim-treesitter/nvim-treesitter/tree/master/queries/earthfile
+
+const EARTHFILE_HIGHLIGHT_QUERY: &str = r#"; highlights.scm
+(string_array
+  "," @punctuation.delimiter)
+
+(string_array
+  [
+    "["
+    "]"
+  ] @punctuation.bracket)
+
+[
+  "ARG"
+  "AS LOCAL"
+  "BUILD"
+  "CACHE"
+  "CMD"
+  "COPY"
+  "DO"
+  "ENTRYPOINT"
+  "ENV"
+  "EXPOSE"
+  "FROM DOCKERFILE"
+  "FROM"
+  "FUNCTION"
+  "GIT CLONE"
+  "HEALTHCHECK"
+  "HOST"
+  "LABEL"
+  "LET"
+  "PROJECT"
+  "RUN"
+  "SAVE ARTIFACT"
+  "SAVE IMAGE"
+  "SET"
+  "SHELL"
+  "USER"
+  "VERSION"
+  "VOLUME"
+  "WORKDIR"
+] @keyword
+
+(for_command
+  [
+    "FOR"
+    "IN"
+    "END"
+  ] @keyword.repeat)
+
+(if_command
+  [
+    "IF"
+    "END"
+  ] @keyword.conditional)
+
+(elif_block
+  "ELSE IF" @keyword.conditional)
+
+(else_block
+  "ELSE" @keyword.conditional)
+
+(import_command
+  [
+    "IMPORT"
+    "AS"
+  ] @keyword.import)
+
+(try_command
+  [
+    "TRY"
+    "FINALLY"
+    "END"
+  ] @keyword.exception)
+
+(wait_command
+  [
+    "WAIT"
+    "END"
+  ] @keyword)
+
+(with_docker_command
+  [
+    "WITH DOCKER"
+    "END"
+  ] @keyword)
+
+[
+  (comment)
+  (line_continuation_comment)
+] @comment @spell
+
+[
+  (target_ref)
+  (target_artifact)
+  (function_ref)
+] @function
+
+(target
+  (identifier) @function)
+
+[
+  (double_quoted_string)
+  (single_quoted_string)
+] @string
+
+(unquoted_string) @string.special
+
+(escape_sequence) @string.escape
+
+(variable) @variable
+
+(expansion
+  [
+    "$"
+    "{"
+    "}"
+    "("
+    ")"
+  ] @punctuation.special)
+
+(build_arg) @variable
+
+(options
+  (_) @property)
+
+"=" @operator
+"#;
+
+// RUN commands and the like are shell.
+const EARTHFILE_INJECTION_QUERY: &str = r#"; injections.scm
+((comment) @injection.content
+  (#set! injection.language "comment"))
+
+((shell_fragment) @injection.content
+  (#set! injection.language "bash")
+  (#set! injection.include-children))
+"#;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_for_path() {
+    assert_eq!(
+      CustomLang::for_path(Path::new("config/prod.jsonnet")),
+      Some(CustomLang::Jsonnet)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("lib/k.libsonnet")),
+      Some(CustomLang::Jsonnet)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("policy/authz.rego")),
+      Some(CustomLang::Rego)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("infra/main.bicepparam")),
+      Some(CustomLang::Bicep)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("Justfile")),
+      Some(CustomLang::Just)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("/src/app/.justfile")),
+      Some(CustomLang::Just)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("/etc/caddy/Caddyfile")),
+      Some(CustomLang::Caddyfile)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("sites/blog.caddyfile")),
+      Some(CustomLang::Caddyfile)
+    );
+    for nginx in [
+      "/etc/nginx/nginx.conf",
+      "/etc/nginx/sites-available/default",
+      "/etc/nginx/conf.d/upstreams.conf",
+      "deploy/proxy.nginx",
+    ] {
+      assert_eq!(
+        CustomLang::for_path(Path::new(nginx)),
+        Some(CustomLang::Nginx)
+      );
+    }
+    for other in [
+      "/etc/fonts/conf.d/10-hinting.conf",
+      "/etc/apache2/sites-available/000-default.conf",
+      "/srv/nginx-backup/conf.d/site.conf",
+    ] {
+      assert_eq!(CustomLang::for_path(Path::new(other)), None);
+    }
+    for unit in [
+      "/etc/systemd/system/backup.service",
+      "backup.timer",
+      "10-wired.network",
+      "/etc/systemd/system/nginx.service.d/override.conf",
+    ] {
+      assert_eq!(
+        CustomLang::for_path(Path::new(unit)),
+        Some(CustomLang::Systemd)
+      );
+    }
+    assert_eq!(
+      CustomLang::for_path(Path::new("/usr/lib/systemd/system/multi-user.target")),
+      Some(CustomLang::Systemd)
+    );
+    for other in ["build.target", "docs/search.path", "net/eth0.link"] {
+      assert_eq!(CustomLang::for_path(Path::new(other)), None);
+    }
+    assert_eq!(
+      CustomLang::for_path(Path::new("/etc/modprobe.d/blacklist.conf")),
+      None
+    );
+    for dotenv in [".env", "app/.env.local", ".env.production", "docker/db.env"] {
+      assert_eq!(
+        CustomLang::for_path(Path::new(dotenv)),
+        Some(CustomLang::Dotenv)
+      );
+    }
+    assert_eq!(CustomLang::for_path(Path::new(".envrc")), None);
+    assert_eq!(
+      CustomLang::for_path(Path::new("/var/log/nginx/access.log.1")),
+      Some(CustomLang::Log)
+    );
+    assert_eq!(
+      CustomLang::for_path(Path::new("services/api/Earthfile")),
+      Some(CustomLang::Earthfile)
+    );
+    assert_eq!(CustomLang::for_path(Path::new("main.rs")), None);
+    assert_eq!(CustomLang::for_path(Path::new("jsonnet")), None);
+  }
+
+  #[test]
+  fn test_for_content() {
+    let unit = "# Backups\n[Unit]\nDescription=Backups\n";
+    assert_eq!(
+      CustomLang::for_content(Path::new("backup.target"), unit),
+      Some(CustomLang::Systemd)
+    );
+    assert_eq!(
+      CustomLang::for_content(Path::new("build.target"), "x86_64-linux\n"),
+      None
+    );
+    assert_eq!(CustomLang::for_content(Path::new("notes.txt"), unit), None);
+  }
+
+  /// Highlight `sample` as `language`, which fails unless its grammar loads
+  /// and has every node its queries name, and check that each of `expected`,
+  /// a piece of the sample with its theme key, is highlighted so.
+  fn assert_highlights(language: CustomLang, sample: &str, expected: &[(&str, &str)]) {
+    let set = CustomLanguageSet::new(None);
+    let highlights = syntastica::Processor::new(&set)
+      .process(sample, language)
+      .unwrap_or_else(|err| panic!("{}: {err}", language.as_ref()));
+    for &(text, key) in expected {
+      assert!(
+        highlights
+          .iter()
+          .flatten()
+          .any(|&(piece, style)| piece == text && style == Some(key)),
+        "{}: {text:?} is not highlighted as {key}",
+        language.as_ref()
+      );
+    }
+  }
+
+  #[test]
+  fn test_highlights_jsonnet() {
+    assert_highlights(
+      CustomLang::Jsonnet,
+      "// Ports\nlocal port = 8080;\n{ service: { port: port, tls: true } }\n",
+      &[
+        ("// Ports", "comment"),
+        ("8080", "number"),
+        ("true", "boolean"),
+      ],
+    );
+  }
+
+  #[test]
+  fn test_highlights_rego() {
+    assert_highlights(
+      CustomLang::Rego,
+      "# Admins only\npackage authz\n\nallow if input.user == \"admin\"\n",
+      &[("# Admins only", "comment"), ("\"admin\"", "string")],
+    );
+  }
+
+  #[test]
+  fn test_highlights_bicep() {
+    assert_highlights(
+      CustomLang::Bicep,
+      "// Storage\nparam location string = 'westeurope'\nvar replicas = 3\n",
+      &[
+        ("// Storage", "comment"),
+        ("param", "keyword"),
+        ("3", "number"),
+      ],
+    );
+  }
+
+  #[test]
+  fn test_highlights_just() {
+    assert_highlights(
+      CustomLang::Just,
+      "# Build everything\nbuild:\n    cargo build --release\n",
+      &[("# Build everything", "comment"), ("build", "function")],
+    );
+  }
+
+  #[test]
+  fn test_highlights_nginx() {
+    assert_highlights(
+      CustomLang::Nginx,
+      "# Proxy\nserver {\n    listen 80;\n}\n",
+      &[
+        ("# Proxy", "comment"),
+        ("server", "type"),
+        ("listen", "function"),
+      ],
+    );
+  }
+
+  #[test]
+  fn test_highlights_earthfile() {
+    assert_highlights(
+      CustomLang::Earthfile,
+      "# CI\nVERSION 0.8\nbuild:\n    FROM rust:1\n    RUN cargo build\n",
+      &[("# CI", "comment"), ("FROM", "keyword"), ("RUN", "keyword")],
+    );
+  }
+
+  #[test]
+  fn test_highlights_caddyfile() {
+    assert_highlights(
+      CustomLang::Caddyfile,
+      "# Blog\nexample.com {\n\treverse_proxy localhost:8080\n\timport common\n}\n",
+      &[
+        ("# Blog", "comment"),
+        ("example.com", "label"),
+        ("reverse_proxy", "function"),
+      ],
+    );
+  }
+}
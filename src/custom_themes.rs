@@ -0,0 +1,272 @@
+//! Themes from files: `--theme ./mytheme.toml`, or a name looked up in
+//! `~/.config/umber/themes/`. Besides umber's own TOML format, TextMate
+//! `.tmTheme` files and base16/base24 `.yaml` schemes are converted on load.
+//!
+//! ```toml
+//! # Start from a built-in theme and override some of its keys
+//! extends = "catppuccin-mocha"
+//!
+//! [palette]
+//! pink = "#f5c2e7"
+//!
+//! [highlights]
+//! _normal = { fg = "#cdd6f4", bg = "#1e1e2e" }
+//! comment = { fg = "#6c7086", italic = true }
+//! keyword = "pink"
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use eyre::{Result, WrapErr, bail, eyre};
+use serde::Deserialize;
+use syntastica::style::{Color, Style};
+use syntastica::theme::{ResolvedTheme, THEME_KEYS};
+
+use crate::base16;
+use crate::config;
+use crate::decorations::parse_color;
+use crate::report::closest_name;
+use crate::tmtheme;
+
+/// Key for the default text color and the background.
+const NORMAL_KEY: &str = "_normal";
+
+/// File extensions recognized as theme files.
+const EXTENSIONS: &[&str] = &["toml", "tmTheme", "yaml", "yml"];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+  /// A built-in theme providing every key not set here.
+  extends: Option<String>,
+  /// Named colors, usable wherever a color is expected.
+  palette: HashMap<String, String>,
+  /// Styles keyed by theme key: a color, or a table with `fg`, `bg` and
+  /// `bold`/`italic`/`underline`/`strikethrough`.
+  highlights: BTreeMap<String, toml::Value>,
+}
+
+/// The theme file `name` refers to: a path with a theme extension, or a theme
+/// in the themes directory. `base16-<scheme>` and `base24-<scheme>` are also
+/// looked up in its `base16/` and `base24/` subdirectories, the layout of the
+/// tinted-theming schemes repository. `None` when it is none of these, e.g. a
+/// built-in name.
+pub fn find(name: &str) -> Option<PathBuf> {
+  let path = Path::new(name);
+  if path
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| {
+      EXTENSIONS
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(ext))
+    })
+  {
+    return Some(path.to_path_buf());
+  }
+  if name.contains(['/', '\\']) {
+    return None;
+  }
+  let dir = config::config_dir()?.join("themes");
+  let scheme = ["base16", "base24"].into_iter().find_map(|system| {
+    let scheme = name.strip_prefix(system)?.strip_prefix('-')?;
+    Some(dir.join(system).join(scheme))
+  });
+  let candidates = EXTENSIONS
+    .iter()
+    .map(|ext| dir.join(format!("{name}.{ext}")));
+  let scheme_candidates = scheme
+    .into_iter()
+    .flat_map(|scheme| ["yaml", "yml"].map(|ext| scheme.with_extension(ext)));
+  candidates
+    .chain(scheme_candidates)
+    .find(|candidate| candidate.is_file())
+}
+
+/// Read and resolve a theme file.
+pub fn load(path: &Path) -> Result<ResolvedTheme> {
+  let raw = std::fs::read_to_string(path)
+    .wrap_err_with(|| format!("Failed to read theme {}", path.display()))?;
+  let extension = path
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .unwrap_or("")
+    .to_ascii_lowercase();
+  let theme = match extension.as_str() {
+    "tmtheme" => tmtheme::parse(&raw),
+    "yaml" | "yml" => base16::parse(&raw),
+    _ => parse(&raw),
+  };
+  theme.wrap_err_with(|| format!("Invalid theme {}", path.display()))
+}
+
+fn parse(raw: &str) -> Result<ResolvedTheme> {
+  let file: ThemeFile = toml::from_str(raw)?;
+  let mut styles = match &file.extends {
+    Some(name) => syntastica_themes::from_str(name)
+      .ok_or_else(|| eyre!("Unknown theme to extend '{}'", name))?
+      .into_inner(),
+    None => BTreeMap::new(),
+  };
+  let color = |value: &str, key: &str| -> Result<Color> {
+    let resolved = file.palette.get(value).map_or(value, String::as_str);
+    parse_color(resolved).ok_or_else(|| {
+      eyre!(
+        "Invalid color '{}' for '{}': expected #rrggbb or a palette name",
+        value,
+        key
+      )
+    })
+  };
+
+  // `_normal` goes first: other keys default to its foreground.
+  let keys = file
+    .highlights
+    .keys()
+    .filter(|key| *key == NORMAL_KEY)
+    .chain(file.highlights.keys().filter(|key| *key != NORMAL_KEY));
+  for key in keys {
+    if key != NORMAL_KEY && !THEME_KEYS.contains(&key.as_str()) {
+      let hint = closest_name(key, THEME_KEYS.iter().map(|key| key.to_string()))
+        .map(|name| format!(" (did you mean '{name}'?)"))
+        .unwrap_or_default();
+      bail!("Unknown theme key '{}'{}", key, hint);
+    }
+    let style = match &file.highlights[key] {
+      toml::Value::String(value) => {
+        Style::new(color(value, key)?, None, false, false, false, false)
+      }
+      toml::Value::Table(table) => {
+        let mut fg = None;
+        let mut bg = None;
+        let mut flags = [false; 4];
+        for (field, value) in table {
+          let flag = ["underline", "strikethrough", "italic", "bold"]
+            .iter()
+            .position(|name| name == field);
+          match (field.as_str(), value, flag) {
+            ("fg", toml::Value::String(value), _) => fg = Some(color(value, key)?),
+            ("bg", toml::Value::String(value), _) => bg = Some(color(value, key)?),
+            (_, toml::Value::Boolean(value), Some(index)) => flags[index] = *value,
+            ("fg" | "bg", _, _) => bail!("'{}.{}' must be a color", key, field),
+            (_, _, Some(_)) => bail!("'{}.{}' must be true or false", key, field),
+            _ => bail!(
+              "Unknown field '{}' for '{}': expected fg, bg, bold, italic, underline or strikethrough",
+              field,
+              key
+            ),
+          }
+        }
+        let fg = match fg.or_else(|| styles.get(NORMAL_KEY).map(|normal| normal.color())) {
+          Some(fg) => fg,
+          None => bail!("'{}' needs an fg color, or one from '{}'", key, NORMAL_KEY),
+        };
+        let [underline, strikethrough, italic, bold] = flags;
+        Style::new(fg, bg, underline, strikethrough, italic, bold)
+      }
+      _ => bail!("'{}' must be a color or a table", key),
+    };
+    styles.insert(key.clone(), style);
+  }
+  Ok(ResolvedTheme::new(styles))
+}
+
+/// A theme in the TOML format read by `load`, with a style for every theme
+/// key so that it can be edited without knowing the fallbacks between keys.
+pub fn to_toml(theme: &ResolvedTheme) -> String {
+  let mut out = String::from("[highlights]\n");
+  let keys = std::iter::once(NORMAL_KEY).chain(THEME_KEYS.iter().copied());
+  for key in keys.filter(|key| *key != "none") {
+    let style = if key == NORMAL_KEY {
+      theme.get(key).copied()
+    } else {
+      theme.find_style(key)
+    };
+    let Some(style) = style else {
+      continue;
+    };
+    let hex = |color: Color| {
+      format!(
+        "\"#{:02x}{:02x}{:02x}\"",
+        color.red, color.green, color.blue
+      )
+    };
+    let mut fields = vec![format!("fg = {}", hex(style.color()))];
+    fields.extend(style.bg().map(|bg| format!("bg = {}", hex(bg))));
+    for (name, set) in [
+      ("bold", style.bold()),
+      ("italic", style.italic()),
+      ("underline", style.underline()),
+      ("strikethrough", style.strikethrough()),
+    ] {
+      if set {
+        fields.push(format!("{name} = true"));
+      }
+    }
+    let bare = key
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let key = if bare {
+      key.to_string()
+    } else {
+      format!("\"{key}\"")
+    };
+    out.push_str(&format!("{key} = {{ {} }}\n", fields.join(", ")));
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_theme() {
+    let theme = parse(
+      r##"
+        [palette]
+        gray = "#6c7086"
+
+        [highlights]
+        _normal = { fg = "#cdd6f4", bg = "#1e1e2e" }
+        comment = { fg = "gray", italic = true }
+        attribute = { bold = true }
+      "##,
+    )
+    .unwrap();
+    let comment = theme.find_style("comment").unwrap();
+    assert_eq!(comment.color(), Color::new(0x6c, 0x70, 0x86));
+    assert!(comment.italic());
+    let attribute = theme.find_style("attribute").unwrap();
+    assert_eq!(attribute.color(), Color::new(0xcd, 0xd6, 0xf4));
+    assert!(attribute.bold());
+  }
+
+  #[test]
+  fn test_to_toml_round_trip() {
+    let theme = parse(
+      r##"
+        [highlights]
+        _normal = { fg = "#cdd6f4", bg = "#1e1e2e" }
+        comment = { fg = "#6c7086", italic = true, bold = true }
+      "##,
+    )
+    .unwrap();
+    let exported = to_toml(&theme);
+    assert!(exported.contains("comment = { fg = \"#6c7086\", bold = true, italic = true }"));
+    let reparsed = parse(&exported).unwrap();
+    for key in [NORMAL_KEY, "comment", "attribute"] {
+      assert_eq!(reparsed.find_style(key), theme.find_style(key));
+    }
+  }
+
+  #[test]
+  fn test_parse_theme_errors() {
+    let error = parse("[highlights]\ncomnent = \"#ffffff\"").unwrap_err();
+    assert!(error.to_string().contains("did you mean 'comment'"));
+    assert!(parse("[highlights]\ncomment = \"blue\"").is_err());
+    assert!(parse("[highlights]\ncomment = { italic = true }").is_err());
+    assert!(parse("[highlights]\ncomment = { fg = \"#ffffff\", color = 1 }").is_err());
+  }
+}
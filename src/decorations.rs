@@ -1,13 +1,16 @@
 //! Decoration rendering for line numbers, git changes, and grid separators.
 //! Provides styled output similar to bat's decorations.
 
-use std::borrow::Cow;
+use std::ops::Range;
 
-use syntastica::renderer::{Renderer, TerminalRenderer};
+use syntastica::renderer::Renderer;
 use syntastica::style::{Color, Style};
 use syntastica::theme::ResolvedTheme;
+use unicode_width::UnicodeWidthChar;
 
-use crate::git::LineChange;
+use crate::ansi;
+use crate::color_depth::DepthRenderer;
+use crate::git::{BlameLine, LineChange, LineStatus};
 
 /// Configuration for which decorations to show.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,6 +22,10 @@ pub struct DecorationConfig {
   pub show_changes: bool,
   /// Show file headers between multiple files
   pub show_headers: bool,
+  /// Draw a vertical separator after the gutter and rules around each file
+  pub show_grid: bool,
+  /// Show the commit, author and age of each line in a margin
+  pub show_blame: bool,
 }
 
 impl DecorationConfig {
@@ -26,11 +33,45 @@ impl DecorationConfig {
   pub fn has_decorations(&self) -> bool {
     self.show_numbers || self.show_changes
   }
+
+  /// Combine two configurations, enabling every decoration enabled in either.
+  pub fn merge(self, other: Self) -> Self {
+    Self {
+      show_numbers: self.show_numbers || other.show_numbers,
+      show_changes: self.show_changes || other.show_changes,
+      show_headers: self.show_headers || other.show_headers,
+      show_grid: self.show_grid || other.show_grid,
+      show_blame: self.show_blame || other.show_blame,
+    }
+  }
+}
+
+/// Columns between tab stops when measuring tabs for wrapping.
+pub const TAB_STOP: usize = 8;
+
+/// How long lines are broken into rows in decorated output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WrapMode {
+  /// Let lines run past the edge of the terminal
+  #[default]
+  Never,
+  /// Break at the last character that fits
+  Character,
+  /// Break after the last whitespace that fits, or mid-word for long words
+  Word,
+}
+
+/// Line wrapping settings for decorated output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Wrap {
+  pub mode: WrapMode,
+  /// Columns available for a whole row, gutter included
+  pub width: usize,
 }
 
 /// Get a dim style from the theme for line numbers and decorations.
 /// Returns the first available theme style or creates a fallback.
-fn get_dim_style_or_create(theme: &ResolvedTheme) -> Style {
+pub fn get_dim_style_or_create(theme: &ResolvedTheme) -> Style {
   theme
     .find_style("comment")
     .or_else(|| theme.find_style("punctuation"))
@@ -38,45 +79,382 @@ fn get_dim_style_or_create(theme: &ResolvedTheme) -> Style {
     .unwrap_or_else(|| Style::new(Color::new(100, 100, 100), None, false, false, false, false))
 }
 
-/// Get git change style with appropriate colors.
-fn get_git_change_style(line_change: LineChange) -> Style {
-  match line_change {
-    LineChange::Removed => Style::new(Color::new(255, 100, 100), None, false, false, false, false), // Red
-    LineChange::Modified => Style::new(Color::new(255, 200, 100), None, false, false, false, false), // Yellow
-    LineChange::Added => Style::new(Color::new(150, 255, 150), None, false, false, false, false), // Green
+/// Get git change style from the theme's diff keys.
+/// Tries `diff.plus`/`diff.delta`/`diff.minus` first, then the closest semantic key,
+/// and only falls back to fixed colors when the theme defines none of them.
+pub fn get_git_change_style(line_change: LineChange, theme: &ResolvedTheme) -> Style {
+  let (keys, fallback): (&[&str], Color) = match line_change {
+    LineChange::Removed | LineChange::RemovedAbove => (
+      &["diff.minus", "comment.error", "keyword.exception"],
+      Color::new(255, 100, 100), // Red
+    ),
+    LineChange::Modified => (
+      &["diff.delta", "comment.warning", "constant"],
+      Color::new(255, 200, 100), // Yellow
+    ),
+    LineChange::Added => (&["diff.plus", "string"], Color::new(150, 255, 150)), // Green
+  };
+  let color = keys
+    .iter()
+    .copied()
+    .find_map(|key| theme.get(key))
+    .map(|style| style.color())
+    .unwrap_or(fallback);
+  Style::new(color, None, false, false, false, false)
+}
+
+/// The symbol and style of a line's change marker. Staged changes are drawn
+/// halfway to the dim color, so what `git diff` would still show stands out.
+fn change_marker(status: LineStatus, theme: &ResolvedTheme) -> (&'static str, Style) {
+  let symbol = match status.change {
+    LineChange::Added => "+",
+    LineChange::Modified => "~",
+    LineChange::Removed => "_",
+    LineChange::RemovedAbove => "‾",
+  };
+  let style = get_git_change_style(status.change, theme);
+  if !status.staged {
+    return (symbol, style);
+  }
+  let color = mix_colors(style.color(), get_dim_style_or_create(theme).color(), 0.5);
+  (symbol, Style::new(color, None, false, false, false, false))
+}
+
+/// Blend from `from` to `to` by `amount`, between 0 and 1.
+fn mix_colors(from: Color, to: Color, amount: f32) -> Color {
+  let mix = |from: u8, to: u8| (f32::from(from) + (f32::from(to) - f32::from(from)) * amount) as u8;
+  Color::new(
+    mix(from.red, to.red),
+    mix(from.green, to.green),
+    mix(from.blue, to.blue),
+  )
+}
+
+/// Get the theme's default foreground color (the `_normal` key).
+pub fn theme_foreground(theme: &ResolvedTheme) -> Option<Color> {
+  theme.get("_normal").map(|style| style.color())
+}
+
+/// Get the theme's default background color (the `_normal` key).
+pub fn theme_background(theme: &ResolvedTheme) -> Option<Color> {
+  theme.get("_normal").and_then(|style| style.bg())
+}
+
+/// Returns true if the color is perceived as dark.
+pub fn is_dark(color: Color) -> bool {
+  let luma =
+    0.299 * f32::from(color.red) + 0.587 * f32::from(color.green) + 0.114 * f32::from(color.blue);
+  luma < 128.0
+}
+
+/// Move each channel of a color by `amount`, clamping to the valid range.
+fn shift_color(color: Color, amount: i16) -> Color {
+  let shift = |channel: u8| (i16::from(channel) + amount).clamp(0, 255) as u8;
+  Color::new(shift(color.red), shift(color.green), shift(color.blue))
+}
+
+/// Background for the alternate rows in zebra mode.
+/// Slightly lighter than the theme background on dark themes, slightly darker on light ones.
+pub fn zebra_background(theme: &ResolvedTheme) -> Color {
+  let bg = theme_background(theme).unwrap_or(Color::new(30, 30, 46));
+  if is_dark(bg) {
+    shift_color(bg, 14)
+  } else {
+    shift_color(bg, -14)
+  }
+}
+
+/// Background for lines that should stand out, such as the lines selected
+/// within their `--context`. A stronger shift than the zebra stripe.
+pub fn emphasis_background(theme: &ResolvedTheme) -> Color {
+  let bg = theme_background(theme).unwrap_or(Color::new(30, 30, 46));
+  if is_dark(bg) {
+    shift_color(bg, 36)
+  } else {
+    shift_color(bg, -30)
+  }
+}
+
+/// Parse a `#rrggbb` color; the `#` is optional.
+pub fn parse_color(raw: &str) -> Option<Color> {
+  let hex = raw.trim().strip_prefix('#').unwrap_or(raw.trim());
+  if hex.len() != 6 || !hex.is_ascii() {
+    return None;
+  }
+  let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+  Some(Color::new(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Render a content piece, applying an optional line background on top of its style.
+pub fn render_content_piece(
+  output: &mut String,
+  text: &str,
+  style_key: Option<&'static str>,
+  background: Option<Color>,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+) {
+  let escaped = renderer.escape(text);
+  let style = style_key.and_then(|key| theme.find_style(key));
+  match (style, background) {
+    (Some(style), Some(bg)) => {
+      output.push_str(&renderer.styled(&escaped, with_background(style, bg)))
+    }
+    (Some(style), None) => output.push_str(&renderer.styled(&escaped, style)),
+    (None, Some(bg)) => {
+      let fg = theme_foreground(theme).unwrap_or_else(|| default_foreground(bg));
+      let style = Style::new(fg, Some(bg), false, false, false, false);
+      output.push_str(&renderer.styled(&escaped, style));
+    }
+    (None, None) => output.push_str(&renderer.unstyled(&escaped)),
+  }
+}
+
+/// Header shown by `--follow` when output switches to another file, e.g. `==> app.log <==`.
+/// Each file gets its own color from the theme so interleaved output stays readable.
+pub fn follow_header(
+  name: &str,
+  index: usize,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+) -> String {
+  let style = accent_style(index, theme);
+  let style = Style::new(style.color(), None, false, false, false, true);
+  let header = format!("==> {name} <==");
+  let escaped = renderer.escape(&header);
+  renderer.styled(&escaped, style).into_owned()
+}
+
+/// One of several distinct theme colors, picked by `index`, for telling apart
+/// items such as followed files or languages in `--stat`.
+pub fn accent_style(index: usize, theme: &ResolvedTheme) -> Style {
+  const KEYS: [&str; 6] = ["function", "string", "keyword", "type", "constant", "label"];
+  let color = theme
+    .find_style(KEYS[index % KEYS.len()])
+    .map(|style| style.color())
+    .or_else(|| theme_foreground(theme))
+    .unwrap_or(Color::new(137, 180, 250));
+  Style::new(color, None, false, false, false, false)
+}
+
+/// Replace the background of a style, keeping every other attribute.
+pub fn with_background(style: Style, bg: Color) -> Style {
+  Style::new(
+    style.color(),
+    Some(bg),
+    style.underline(),
+    style.strikethrough(),
+    style.italic(),
+    style.bold(),
+  )
+}
+
+fn default_foreground(bg: Color) -> Color {
+  if is_dark(bg) {
+    Color::new(205, 214, 244)
+  } else {
+    Color::new(76, 79, 105)
+  }
+}
+
+/// Per-line information for the decoration gutter and content.
+#[derive(Debug, Clone, Copy)]
+pub struct LineDecoration {
+  /// The line number (1-based)
+  pub line_no: usize,
+  /// Optional git change for this line
+  pub line_change: Option<LineStatus>,
+  /// Optional background applied to the line content (zebra striping)
+  pub background: Option<Color>,
+}
+
+/// The highlighted pieces of one line, stored in a single text buffer so the
+/// same allocation is reused from line to line.
+#[derive(Debug, Default)]
+pub struct LineContent {
+  text: String,
+  spans: Vec<(Range<usize>, Option<&'static str>)>,
+}
+
+impl LineContent {
+  /// Append a piece of text with its style key.
+  pub fn push(&mut self, text: &str, style_key: Option<&'static str>) {
+    self.push_with(style_key, |buf| buf.push_str(text));
+  }
+
+  /// Append a piece written by `write`, e.g. a transformed version of the source.
+  pub fn push_with(&mut self, style_key: Option<&'static str>, write: impl FnOnce(&mut String)) {
+    let start = self.text.len();
+    write(&mut self.text);
+    self.spans.push((start..self.text.len(), style_key));
+  }
+
+  /// Forget the pieces, keeping the allocated capacity.
+  pub fn clear(&mut self) {
+    self.text.clear();
+    self.spans.clear();
   }
 }
 
 /// Render a single line with all decorations.
 ///
 /// Layout: {line_number}{space}{git_symbol}{space}{border}{content}
-/// The space before git_symbol only appears when git decorations are enabled.
+/// The space before git_symbol only appears when git decorations are enabled,
+/// the border only with the grid.
+/// Wrapped rows repeat the gutter with the line number and git symbol left blank.
 ///
 /// # Arguments
-/// * `content` - The highlighted line content as (text, style_key) pairs
-/// * `line_no` - The line number (1-based)
+/// * `output` - Buffer the rendered line is appended to
+/// * `content` - The highlighted line content
+/// * `line` - Line number, git change, and background for this line
 /// * `config` - Decoration configuration
-/// * `line_change` - Optional git change for this line
 /// * `renderer` - The terminal renderer
 /// * `theme` - The color theme
 /// * `line_number_width` - Width of line number column
+/// * `wrap` - How to break the content into rows that fit the terminal
+#[allow(clippy::too_many_arguments)]
 pub fn render_decorated_line(
-  content: &[(Cow<'_, str>, Option<&'static str>)],
-  line_no: usize,
+  output: &mut String,
+  content: &LineContent,
+  line: &LineDecoration,
   config: &DecorationConfig,
-  line_change: Option<LineChange>,
-  renderer: &mut TerminalRenderer,
+  renderer: &mut DepthRenderer,
   theme: &ResolvedTheme,
   line_number_width: usize,
-) -> String {
-  let mut output = String::new();
+  wrap: Wrap,
+) {
+  push_gutter(
+    output,
+    Some(line),
+    config,
+    renderer,
+    theme,
+    line_number_width,
+  );
+
+  let gutter = gutter_width(config, line_number_width);
+  let mut breaks = wrap_points(
+    &content.text,
+    wrap.width.saturating_sub(gutter),
+    gutter,
+    wrap.mode,
+  )
+  .into_iter()
+  .peekable();
+  // Rows with a background are filled to the edge of the terminal.
+  let fill = |output: &mut String, row: &str, renderer: &mut DepthRenderer| {
+    if let Some(bg) = line.background {
+      let used = gutter + display_width(row, gutter);
+      push_fill(output, wrap.width.saturating_sub(used), bg, renderer, theme);
+    }
+  };
+  let mut row_start = 0;
+  for (range, style_key) in &content.spans {
+    let mut start = range.start;
+    while let Some(at) = breaks.next_if(|at| *at < range.end) {
+      if at > start {
+        let text = &content.text[start..at];
+        render_content_piece(output, text, *style_key, line.background, renderer, theme);
+      }
+      fill(output, &content.text[row_start..at], renderer);
+      output.push_str(&renderer.newline());
+      push_gutter(output, None, config, renderer, theme, line_number_width);
+      start = at;
+      row_start = at;
+    }
+    let text = &content.text[start..range.end];
+    render_content_piece(output, text, *style_key, line.background, renderer, theme);
+  }
+  fill(output, &content.text[row_start..], renderer);
+}
+
+/// Spaces in `background` that carry a line's background to the right edge.
+pub fn push_fill(
+  output: &mut String,
+  columns: usize,
+  background: Color,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+) {
+  if columns > 0 {
+    render_content_piece(
+      output,
+      &" ".repeat(columns),
+      None,
+      Some(background),
+      renderer,
+      theme,
+    );
+  }
+}
+
+/// Columns `text` takes when it starts at column `indent`, which tab stops
+/// depend on.
+pub fn display_width(text: &str, indent: usize) -> usize {
+  visible_chars(text).fold(0, |column, (_, c)| {
+    column
+      + if c == '\t' {
+        TAB_STOP - (indent + column) % TAB_STOP
+      } else {
+        c.width().unwrap_or(0)
+      }
+  })
+}
+
+/// `text.char_indices()` without escape sequences already in the text, which
+/// take no columns.
+fn visible_chars(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+  let mut sequence_end = 0;
+  text.char_indices().filter(move |&(index, c)| {
+    if index < sequence_end {
+      return false;
+    }
+    if c == '\x1b' {
+      sequence_end = index + ansi::sequence_len(&text.as_bytes()[index..]);
+      return false;
+    }
+    true
+  })
+}
+
+/// Columns taken by the gutter that `render_decorated_line` draws.
+pub fn gutter_width(config: &DecorationConfig, line_number_width: usize) -> usize {
+  let mut width = 0;
+  if config.show_numbers {
+    width += line_number_width;
+  }
+  if config.show_changes {
+    width += 2;
+  }
+  if config.show_numbers || config.show_changes {
+    width += 1;
+  }
+  if config.show_grid && config.has_decorations() {
+    width += 2;
+  }
+  width
+}
+
+/// Draw the gutter for `line`, or a blank one for a wrapped continuation row.
+fn push_gutter(
+  output: &mut String,
+  line: Option<&LineDecoration>,
+  config: &DecorationConfig,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+  line_number_width: usize,
+) {
   let dim_style = get_dim_style_or_create(theme);
 
   // Line numbers (right-aligned) - use dim style
   if config.show_numbers {
-    let prefix = format!("{line_no:>width$}", width = line_number_width);
-    let escaped = renderer.escape(&prefix);
-    output.push_str(&renderer.styled(&escaped, dim_style));
+    push_line_number(
+      output,
+      line.map(|line| line.line_no),
+      line_number_width,
+      renderer,
+      dim_style,
+    );
   }
 
   // Git symbol (1 character) - comes after line number with a space
@@ -86,15 +464,12 @@ pub fn render_decorated_line(
     let escaped = renderer.escape(space);
     output.push_str(&renderer.styled(&escaped, dim_style));
 
-    let (symbol, style) = match line_change {
-      Some(LineChange::Added) => ('+', get_git_change_style(LineChange::Added)),
-      Some(LineChange::Modified) => ('~', get_git_change_style(LineChange::Modified)),
-      Some(LineChange::Removed) => ('-', get_git_change_style(LineChange::Removed)),
-      None => (' ', dim_style),
+    let (symbol, style) = match line.and_then(|line| line.line_change) {
+      Some(status) => change_marker(status, theme),
+      None => (" ", dim_style),
     };
 
-    let symbol_str = format!("{symbol}");
-    let escaped = renderer.escape(&symbol_str);
+    let escaped = renderer.escape(symbol);
     output.push_str(&renderer.styled(&escaped, style));
   }
 
@@ -105,21 +480,325 @@ pub fn render_decorated_line(
     output.push_str(&renderer.styled(&escaped, dim_style));
   }
 
-  // Grid separator - shown when there are any decorations
-  if config.has_decorations() {
+  // Grid separator - between the gutter and the content
+  if config.show_grid && config.has_decorations() {
     let grid = "│ ";
     let escaped = renderer.escape(grid);
     output.push_str(&renderer.styled(&escaped, dim_style));
   }
+}
 
-  // Content
-  for (text, style_key) in content {
-    let escaped = renderer.escape(text);
-    match style_key.and_then(|key| theme.find_style(key)) {
-      Some(style) => output.push_str(&renderer.styled(&escaped, style)),
-      None => output.push_str(&renderer.unstyled(&escaped)),
+/// Byte offsets in `text` where a new row starts when it is wrapped to `width`
+/// columns. `indent` is the column the text starts at, which tab stops depend on.
+pub fn wrap_points(text: &str, width: usize, indent: usize, mode: WrapMode) -> Vec<usize> {
+  let mut points = Vec::new();
+  if mode == WrapMode::Never || width == 0 {
+    return points;
+  }
+  let mut column = 0;
+  // Offset just after the last whitespace in the current row, and its column.
+  let mut word_start: Option<(usize, usize)> = None;
+  for (index, c) in visible_chars(text) {
+    let char_width = if c == '\t' {
+      TAB_STOP - (indent + column) % TAB_STOP
+    } else {
+      c.width().unwrap_or(0)
+    };
+    while column > 0 && column + char_width > width {
+      match word_start.take().filter(|_| mode == WrapMode::Word) {
+        Some((start, start_column)) => {
+          points.push(start);
+          column -= start_column;
+        }
+        None => {
+          points.push(index);
+          column = 0;
+        }
+      }
+    }
+    column += char_width;
+    if c.is_whitespace() {
+      word_start = Some((index + c.len_utf8(), column));
     }
   }
+  points
+}
 
+/// Right-align a line number in a column of `width`; `None` leaves the column blank.
+fn push_line_number(
+  output: &mut String,
+  line_no: Option<usize>,
+  width: usize,
+  renderer: &mut DepthRenderer,
+  style: Style,
+) {
+  let prefix = match line_no {
+    Some(line_no) => format!("{line_no:>width$}"),
+    None => " ".repeat(width),
+  };
+  let escaped = renderer.escape(&prefix);
+  output.push_str(&renderer.styled(&escaped, style));
+}
+
+/// Render the gutter for a diff line: old and new line numbers, either of which
+/// may be blank, followed by the grid separator.
+///
+/// Layout: {old_line_number}{space}{new_line_number}{space}{border}
+pub fn render_diff_gutter(
+  old_line: Option<usize>,
+  new_line: Option<usize>,
+  line_number_width: usize,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+) -> String {
+  let mut output = String::new();
+  let dim_style = get_dim_style_or_create(theme);
+  push_line_number(
+    &mut output,
+    old_line,
+    line_number_width,
+    renderer,
+    dim_style,
+  );
+  let escaped = renderer.escape(" ");
+  output.push_str(&renderer.styled(&escaped, dim_style));
+  push_line_number(
+    &mut output,
+    new_line,
+    line_number_width,
+    renderer,
+    dim_style,
+  );
+  let escaped = renderer.escape(" │ ");
+  output.push_str(&renderer.styled(&escaped, dim_style));
   output
 }
+
+/// Age at which a blamed line has faded all the way to the dim color.
+const BLAME_FADE_SECS: f32 = 2.0 * 365.0 * 24.0 * 3600.0;
+
+/// Render the blame margin: abbreviated commit, author and age. Recent lines
+/// get the color of added lines and fade, on a log scale, to the dim color of
+/// line numbers as they age; lines without an entry are dimmed.
+pub fn render_blame_margin(
+  margin: &str,
+  age_secs: Option<u64>,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+) -> String {
+  let Some(age) = age_secs else {
+    return render_dimmed(margin, renderer, theme);
+  };
+  let fresh = get_git_change_style(LineChange::Added, theme).color();
+  let old = get_dim_style_or_create(theme).color();
+  let fade = ((age as f32).ln_1p() / BLAME_FADE_SECS.ln_1p()).min(1.0);
+  let color = mix_colors(fresh, old, fade);
+  let escaped = renderer.escape(margin);
+  renderer
+    .styled(
+      &escaped,
+      Style::new(color, None, false, false, false, false),
+    )
+    .into_owned()
+}
+
+/// What the blame margin tells the time of a line by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlameClock {
+  /// Ages from this time, in seconds since the Unix epoch, such as `3d`
+  Age(u64),
+  /// Commit dates, for `--deterministic`; colors fade from this time, that
+  /// of the newest line, rather than from the clock
+  Date(u64),
+}
+
+impl BlameClock {
+  /// Ages from now, or dates when the output must not depend on the clock.
+  pub fn for_blame(blame: &[BlameLine], deterministic: bool) -> Self {
+    if deterministic {
+      let newest = blame.iter().map(|line| line.time).max().unwrap_or(0);
+      Self::Date(u64::try_from(newest).unwrap_or(0))
+    } else {
+      let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+      Self::Age(now)
+    }
+  }
+}
+
+/// The blame margin of a line: abbreviated commit, author and age or date,
+/// and the age its color fades by. Lines without an entry get a blank one.
+pub fn blame_margin(
+  entry: Option<&BlameLine>,
+  author_width: usize,
+  clock: BlameClock,
+) -> (String, Option<u64>) {
+  let (now, when_width) = match clock {
+    BlameClock::Age(now) => (now, 3),
+    BlameClock::Date(newest) => (newest, 10),
+  };
+  let Some(entry) = entry else {
+    return (" ".repeat(author_width + when_width + 11), None);
+  };
+  let age = now.saturating_sub(u64::try_from(entry.time).unwrap_or(0));
+  let when = match clock {
+    BlameClock::Age(_) => format_age(age),
+    BlameClock::Date(_) => format_date(entry.time),
+  };
+  let author: String = entry.author.chars().take(author_width).collect();
+  let margin = format!(
+    "{:<8} {author:<author_width$} {when:>when_width$} ",
+    entry.commit
+  );
+  (margin, Some(age))
+}
+
+/// A short age for the blame margin, e.g. `5m`, `3d` or `2y`.
+pub fn format_age(seconds: u64) -> String {
+  const UNITS: [(u64, &str); 6] = [
+    (365 * 24 * 3600, "y"),
+    (30 * 24 * 3600, "mo"),
+    (7 * 24 * 3600, "w"),
+    (24 * 3600, "d"),
+    (3600, "h"),
+    (60, "m"),
+  ];
+  UNITS
+    .iter()
+    .find(|(unit, _)| seconds >= *unit)
+    .map(|(unit, suffix)| format!("{}{suffix}", seconds / unit))
+    .unwrap_or_else(|| "now".to_string())
+}
+
+/// The day of `seconds` since the Unix epoch, such as `2024-05-01`.
+pub fn format_date(seconds: i64) -> String {
+  let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+  format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// The calendar date `days` after 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+  let month = if shifted_month < 10 {
+    shifted_month + 3
+  } else {
+    shifted_month - 9
+  } as u32;
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  (year, month, day)
+}
+
+/// Render `text` in the theme's dim (comment) color, e.g. a log line's prefix.
+pub fn render_dimmed(text: &str, renderer: &mut DepthRenderer, theme: &ResolvedTheme) -> String {
+  let escaped = renderer.escape(text);
+  renderer
+    .styled(&escaped, get_dim_style_or_create(theme))
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_format_age() {
+    assert_eq!(format_age(30), "now");
+    assert_eq!(format_age(5 * 60), "5m");
+    assert_eq!(format_age(3 * 24 * 3600 + 7), "3d");
+    assert_eq!(format_age(400 * 24 * 3600), "1y");
+  }
+
+  #[test]
+  fn test_blame_margin() {
+    let blame = [
+      BlameLine {
+        commit: "1a2b3c4d".to_string(),
+        author: "Ada Lovelace".to_string(),
+        time: 1_714_564_800,
+      },
+      BlameLine {
+        commit: "5e6f7a8b".to_string(),
+        author: "Bob".to_string(),
+        time: 1_714_564_800 + 3 * 24 * 3600,
+      },
+    ];
+    let now = BlameClock::Age(1_714_564_800 + 10 * 24 * 3600);
+    assert_eq!(
+      blame_margin(Some(&blame[0]), 5, now),
+      ("1a2b3c4d Ada L  1w ".to_string(), Some(10 * 24 * 3600))
+    );
+    assert_eq!(blame_margin(None, 5, now).0.len(), 19);
+    // Rendered twice, deterministic margins are the same bytes whatever the
+    // clock says.
+    let render = || {
+      let clock = BlameClock::for_blame(&blame, true);
+      let mut margins: Vec<_> = blame
+        .iter()
+        .map(|line| blame_margin(Some(line), 5, clock))
+        .collect();
+      margins.push(blame_margin(None, 5, clock));
+      margins
+    };
+    let margins = render();
+    assert_eq!(margins, render());
+    assert_eq!(
+      margins[0],
+      (
+        "1a2b3c4d Ada L 2024-05-01 ".to_string(),
+        Some(3 * 24 * 3600)
+      )
+    );
+    assert_eq!(margins[1].1, Some(0));
+    assert_eq!(margins[2].0.len(), margins[0].0.len());
+  }
+
+  #[test]
+  fn test_wrap_points() {
+    assert_eq!(
+      wrap_points("abcdefgh", 3, 0, WrapMode::Character),
+      vec![3, 6]
+    );
+    assert_eq!(wrap_points("let x = 1;", 6, 0, WrapMode::Word), vec![6]);
+    // A word longer than the row still has to be split.
+    assert_eq!(
+      wrap_points("a verylongword", 5, 0, WrapMode::Word),
+      vec![2, 7, 12]
+    );
+    // Wide characters take two columns.
+    assert_eq!(wrap_points("日本語", 4, 0, WrapMode::Character), vec![6]);
+    assert!(wrap_points("abcdefgh", 3, 0, WrapMode::Never).is_empty());
+    // Escape sequences take no columns and are never split.
+    assert_eq!(
+      wrap_points("\x1b[31mabc\x1b[0mdef", 3, 0, WrapMode::Character),
+      vec![12]
+    );
+  }
+
+  #[test]
+  fn test_display_width() {
+    assert_eq!(display_width("abc", 0), 3);
+    assert_eq!(display_width("日本", 0), 4);
+    // A tab reaches the next stop, which depends on where the text starts.
+    assert_eq!(display_width("\tx", 0), 9);
+    assert_eq!(display_width("\tx", 6), 3);
+    assert_eq!(display_width("\x1b[1;31mab\x1b[0m", 0), 2);
+  }
+
+  #[test]
+  fn test_parse_color() {
+    let color = parse_color("#3b4261").unwrap();
+    assert_eq!((color.red, color.green, color.blue), (0x3b, 0x42, 0x61));
+    assert!(parse_color("3B4261").is_some());
+    assert!(parse_color("#3b426").is_none());
+    assert!(parse_color("#zz4261").is_none());
+  }
+}
@@ -0,0 +1,235 @@
+//! Language detection from a file's name and contents, shared by the command
+//! and the library's builder.
+
+use std::path::{Path, PathBuf};
+
+use crate::custom_langs::CustomLang;
+use crate::log_tokens;
+
+/// Backup and packaging suffixes that hide a file's real extension.
+const IGNORED_SUFFIXES: &[&str] = &[
+  "~",
+  ".bak",
+  ".old",
+  ".orig",
+  ".dist",
+  ".in",
+  ".dpkg-dist",
+  ".dpkg-old",
+  ".rpmnew",
+  ".rpmorig",
+  ".rpmsave",
+];
+
+/// `path` without any ignored suffixes, e.g. `main.rs` for `main.rs.orig`.
+/// `None` when no suffix matches.
+fn strip_ignored_suffixes(path: &Path, extra: &[String]) -> Option<PathBuf> {
+  let mut name = path.file_name()?.to_str()?;
+  let suffixes = || {
+    IGNORED_SUFFIXES
+      .iter()
+      .copied()
+      .chain(extra.iter().map(String::as_str))
+  };
+  let original_len = name.len();
+  while let Some(stripped) = suffixes()
+    .filter(|suffix| !suffix.is_empty())
+    .find_map(|suffix| name.strip_suffix(suffix))
+    .filter(|stripped| !stripped.is_empty())
+  {
+    name = stripped;
+  }
+  (name.len() < original_len).then(|| path.with_file_name(name))
+}
+
+/// Name of the detected file type, resolved with `resolve_language_union`.
+/// Owned rather than leaked, as `--follow` and long runs detect many times.
+pub fn detect_language_name(
+  path: Option<&Path>,
+  content: &str,
+  ignored_suffixes: &[String],
+) -> Option<String> {
+  // Languages umber bundles beyond palate's file types
+  if let Some(path) = path {
+    let custom = CustomLang::for_path(path)
+      .or_else(|| {
+        let stripped = strip_ignored_suffixes(path, ignored_suffixes)?;
+        CustomLang::for_path(&stripped)
+      })
+      .or_else(|| CustomLang::for_content(path, content));
+    if let Some(language) = custom {
+      return Some(language.as_ref().to_string());
+    }
+  }
+
+  match detect_file_type(path, content, ignored_suffixes) {
+    // FileType::Text means no specific language detected
+    Some(palate::FileType::Text) | None => sniff_language(content).map(str::to_string),
+    Some(other) => Some(other.to_string()),
+  }
+}
+
+fn detect_file_type(
+  path: Option<&Path>,
+  content: &str,
+  ignored_suffixes: &[String],
+) -> Option<palate::FileType> {
+  // Use the new palate API which handles all detection internally
+  if let Some(path) = path {
+    // Names such as `Makefile.in` are known as they are; otherwise retry
+    // without backup suffixes like `.orig`.
+    match palate::try_detect(path, content) {
+      Some(palate::FileType::Text) | None => {
+        let stripped = strip_ignored_suffixes(path, ignored_suffixes)?;
+        palate::try_detect(stripped.as_path(), content)
+      }
+      Some(file_type) => Some(file_type),
+    }
+  } else {
+    // No path, try to detect from content only
+    // palate requires a path, so use a dummy path
+    palate::try_detect("", content)
+  }
+}
+
+/// How much of the content the heuristics look at.
+const SNIFF_BYTES: usize = 1024;
+
+/// The language the start of `content` suggests, for input whose name gives
+/// no hint: the `#!` line, the shape of a log, or markers such as `<?xml` or
+/// `diff --git`. Logs come before the markers, as `[2024-05-01 12:00:00]`
+/// would otherwise pass for a JSON array.
+fn sniff_language(content: &str) -> Option<&'static str> {
+  let mut end = content.len().min(SNIFF_BYTES);
+  while !content.is_char_boundary(end) {
+    end -= 1;
+  }
+  let start = &content[..end];
+  // PDF is mostly compressed streams; there is nothing to highlight.
+  if start.starts_with("%PDF-") {
+    return None;
+  }
+  shebang_language(start)
+    .or_else(|| log_tokens::looks_like_log(start).then(|| CustomLang::Log.as_ref()))
+    .or_else(|| marker_language(start))
+}
+
+/// The language of a document announced by how it starts.
+fn marker_language(start: &str) -> Option<&'static str> {
+  let text = start.trim_start_matches('\u{feff}').trim_start();
+  let mut lines = text.lines();
+  let first = lines.next()?.trim_end();
+  let lowercase = first.to_ascii_lowercase();
+  if first.starts_with("<?xml") || lowercase.starts_with("<svg") {
+    Some("xml")
+  } else if lowercase.starts_with("<!doctype html") || lowercase.starts_with("<html") {
+    Some("html")
+  } else if first.starts_with("<?php") {
+    Some("php")
+  } else if first.starts_with("diff --git ")
+    || (first.starts_with("From ") && first.ends_with(" Mon Sep 17 00:00:00 2001"))
+    || (first.starts_with("--- ") && lines.next().is_some_and(|line| line.starts_with("+++ ")))
+  {
+    Some("diff")
+  } else if first == "---" || first.starts_with("--- ") || first.starts_with("%YAML") {
+    // A YAML document start; `--- a/file` was ruled out above
+    Some("yaml")
+  } else if looks_like_json(text) {
+    Some("json")
+  } else {
+    None
+  }
+}
+
+/// An object with a quoted key or an empty one, or an array of values.
+fn looks_like_json(text: &str) -> bool {
+  let mut chars = text.chars();
+  let open = chars.next();
+  let next = chars.as_str().trim_start().chars().next();
+  match (open, next) {
+    (Some('{'), Some('"' | '}')) => true,
+    (Some('['), Some(next)) => matches!(next, '{' | '[' | '"' | ']' | '-') || next.is_ascii_digit(),
+    _ => false,
+  }
+}
+
+/// The language of the interpreter on a `#!` first line, for scripts
+/// without an extension. `#!/usr/bin/env` is looked through, along with its
+/// options and variable assignments, as in `#!/usr/bin/env -S python3 -u`.
+fn shebang_language(content: &str) -> Option<&'static str> {
+  let line = content.lines().next()?.strip_prefix("#!")?;
+  let mut words = line.split_whitespace();
+  let mut program = words.next()?.rsplit('/').next()?;
+  if program == "env" {
+    program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    program = program.rsplit('/').next()?;
+  }
+  // python3.12, lua5.4
+  let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+  Some(match program {
+    "sh" | "bash" | "dash" | "ash" | "ksh" | "mksh" | "zsh" => "bash",
+    "python" | "pypy" | "uv" => "python",
+    "ruby" => "ruby",
+    "node" | "nodejs" | "bun" => "javascript",
+    "deno" | "ts-node" | "tsx" => "typescript",
+    "perl" => "perl",
+    "php" => "php",
+    "lua" | "luajit" => "lua",
+    "fish" => "fish",
+    "awk" | "gawk" | "mawk" => "awk",
+    "tclsh" | "wish" => "tcl",
+    "Rscript" => "r",
+    "julia" => "julia",
+    "elixir" => "elixir",
+    "nu" => "nu",
+    "make" => "make",
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_shebang_language() {
+    assert_eq!(shebang_language("#!/bin/sh\nset -e\n"), Some("bash"));
+    assert_eq!(shebang_language("#! /usr/bin/python3.12\n"), Some("python"));
+    assert_eq!(shebang_language("#!/usr/bin/env node"), Some("javascript"));
+    assert_eq!(
+      shebang_language("#!/usr/bin/env -S PYTHONUNBUFFERED=1 python3 -u\n"),
+      Some("python")
+    );
+    assert_eq!(
+      shebang_language("#!/usr/bin/env -S deno run\n"),
+      Some("typescript")
+    );
+    assert_eq!(shebang_language("#!/opt/custom/interpreter\n"), None);
+    assert_eq!(shebang_language("echo hi\n#!/bin/sh\n"), None);
+  }
+
+  #[test]
+  fn test_sniff_language() {
+    assert_eq!(sniff_language("<?xml version=\"1.0\"?>\n<a/>"), Some("xml"));
+    assert_eq!(sniff_language("\u{feff}<!DOCTYPE html>\n"), Some("html"));
+    assert_eq!(sniff_language("  {\n  \"name\": 1\n}"), Some("json"));
+    assert_eq!(sniff_language("[{\"a\": 1}]"), Some("json"));
+    assert_eq!(sniff_language("[section]\nkey = 1\n"), None);
+    assert_eq!(sniff_language("---\nname: umber\n"), Some("yaml"));
+    assert_eq!(
+      sniff_language("--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1 +1 @@\n"),
+      Some("diff")
+    );
+    assert_eq!(
+      sniff_language("diff --git a/x b/x\nindex 1..2\n"),
+      Some("diff")
+    );
+    assert_eq!(
+      sniff_language("[2024-05-01 12:00:00] INFO started\n[2024-05-01 12:00:01] WARN slow\n"),
+      Some("log")
+    );
+    assert_eq!(sniff_language("[1, 2, 3]\n"), Some("json"));
+    assert_eq!(sniff_language("%PDF-1.7\n2024-05-01 12:00:00\n"), None);
+    assert_eq!(sniff_language("just some text\n"), None);
+  }
+}
@@ -0,0 +1,674 @@
+//! Rendering for unified diffs and patches (`git diff | umber`).
+//! Colors removed and added lines and, for paired `-`/`+` lines, emphasizes
+//! only the words that changed, the way delta and GitHub do. Code inside hunks
+//! is syntax highlighted in the language of the file being patched.
+
+use std::io::{self, Write};
+use std::ops::Range;
+
+use similar::{Algorithm, DiffTag};
+use syntastica::renderer::Renderer;
+use syntastica::style::{Color, Style};
+use syntastica::theme::ResolvedTheme;
+
+use crate::color_depth::DepthRenderer;
+use crate::decorations::{self, with_background};
+use crate::git::LineChange;
+
+/// Paired lines where more than this share of the text changed are shown
+/// without word emphasis; highlighting nearly everything is just noise.
+const MAX_CHANGED_RATIO: f32 = 0.6;
+
+/// How strongly the emphasis background leans towards the change color.
+const EMPHASIS_MIX: f32 = 0.3;
+
+/// Background tint for whole removed/added lines when their code is highlighted.
+const LINE_MIX: f32 = 0.12;
+
+/// One highlighted line as `(text, theme style key)` pieces.
+pub type HighlightedLine = Vec<(String, Option<&'static str>)>;
+
+/// The role of one line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+  /// Text outside hunks: commit messages, `diff --git`, `index`, ...
+  Meta,
+  /// `--- a/path` or `+++ b/path`
+  FileHeader,
+  /// `@@ -a,b +c,d @@`
+  HunkHeader,
+  Context,
+  Removed,
+  Added,
+  /// `\ No newline at end of file`
+  NoNewline,
+}
+
+/// A classified diff line with its position in the old and new file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffLine {
+  pub kind: LineKind,
+  /// Line number in the old file, for context and removed lines.
+  pub old_line: Option<usize>,
+  /// Line number in the new file, for context and added lines.
+  pub new_line: Option<usize>,
+}
+
+/// Classify every line of a unified diff and number it on both sides.
+/// Hunk line counts are tracked so that removed lines starting with `--` are
+/// not mistaken for file headers.
+pub fn classify_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<DiffLine> {
+  let mut classified = Vec::new();
+  let mut old_remaining = 0usize;
+  let mut new_remaining = 0usize;
+  let mut old_line = 0usize;
+  let mut new_line = 0usize;
+
+  for line in lines {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let in_hunk = old_remaining > 0 || new_remaining > 0;
+    let mut numbered = DiffLine {
+      kind: LineKind::Meta,
+      old_line: None,
+      new_line: None,
+    };
+    if in_hunk {
+      match line.as_bytes().first() {
+        Some(b'-') => {
+          old_remaining = old_remaining.saturating_sub(1);
+          numbered.kind = LineKind::Removed;
+          numbered.old_line = Some(old_line);
+          old_line += 1;
+        }
+        Some(b'+') => {
+          new_remaining = new_remaining.saturating_sub(1);
+          numbered.kind = LineKind::Added;
+          numbered.new_line = Some(new_line);
+          new_line += 1;
+        }
+        Some(b'\\') => numbered.kind = LineKind::NoNewline,
+        _ => {
+          old_remaining = old_remaining.saturating_sub(1);
+          new_remaining = new_remaining.saturating_sub(1);
+          numbered.kind = LineKind::Context;
+          numbered.old_line = Some(old_line);
+          numbered.new_line = Some(new_line);
+          old_line += 1;
+          new_line += 1;
+        }
+      }
+    } else if let Some(hunk) = parse_hunk_header(line) {
+      old_remaining = hunk.old_count;
+      new_remaining = hunk.new_count;
+      old_line = hunk.old_start;
+      new_line = hunk.new_start;
+      numbered.kind = LineKind::HunkHeader;
+    } else if line.starts_with("--- ") || line.starts_with("+++ ") {
+      numbered.kind = LineKind::FileHeader;
+    } else if line.starts_with('\\') {
+      numbered.kind = LineKind::NoNewline;
+    }
+    classified.push(numbered);
+  }
+  classified
+}
+
+/// Line ranges from a hunk header like `@@ -1,3 +1,4 @@ fn main()`.
+struct HunkHeader {
+  old_start: usize,
+  old_count: usize,
+  new_start: usize,
+  new_count: usize,
+}
+
+fn parse_hunk_header(line: &str) -> Option<HunkHeader> {
+  let ranges = line.strip_prefix("@@ ")?.split(" @@").next()?;
+  let (old, new) = ranges.split_once(' ')?;
+  let range = |range: &str| -> Option<(usize, usize)> {
+    match range.split_once(',') {
+      Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+      None => Some((range.parse().ok()?, 1)),
+    }
+  };
+  let (old_start, old_count) = range(old.strip_prefix('-')?)?;
+  let (new_start, new_count) = range(new.strip_prefix('+')?)?;
+  Some(HunkHeader {
+    old_start,
+    old_count,
+    new_start,
+    new_count,
+  })
+}
+
+/// Syntax highlighting for the code in hunk lines, indexed like the lines of the diff.
+#[derive(Default)]
+pub struct HunkHighlights {
+  lines: Vec<Option<HighlightedLine>>,
+}
+
+impl HunkHighlights {
+  fn line(&self, index: usize) -> Option<&HighlightedLine> {
+    self.lines.get(index).and_then(Option::as_ref)
+  }
+}
+
+/// Lines of one patched file, split into its old and new version.
+#[derive(Default)]
+struct FileSection {
+  old_path: Option<String>,
+  new_path: Option<String>,
+  old: Vec<usize>,
+  new: Vec<usize>,
+}
+
+impl FileSection {
+  /// Highlight both versions and store the result for each of their lines.
+  fn finish(
+    self,
+    lines: &[&str],
+    highlights: &mut [Option<HighlightedLine>],
+    highlight: &mut impl FnMut(&str, &str) -> Option<Vec<HighlightedLine>>,
+  ) {
+    let Some(path) = self.new_path.or(self.old_path) else {
+      return;
+    };
+    for side in [self.old, self.new] {
+      if side.is_empty() {
+        continue;
+      }
+      let code = side
+        .iter()
+        .map(|index| content(lines[*index]))
+        .collect::<Vec<_>>()
+        .join("\n");
+      let Some(highlighted) = highlight(&path, &code) else {
+        continue;
+      };
+      for (index, line) in side.into_iter().zip(highlighted) {
+        highlights[index] = Some(line);
+      }
+    }
+  }
+}
+
+/// Highlight the code inside hunks. The old and new version of each file are
+/// highlighted separately, as if they were files of their own, in the language
+/// detected from the `+++`/`---` paths. `highlight` receives a path and the code
+/// and returns its highlighted lines, or `None` when it can't be highlighted.
+pub fn highlight_hunks(
+  text: &str,
+  mut highlight: impl FnMut(&str, &str) -> Option<Vec<HighlightedLine>>,
+) -> HunkHighlights {
+  let lines: Vec<&str> = text.split_inclusive('\n').collect();
+  let mut highlights = vec![None; lines.len()];
+  let mut section = FileSection::default();
+
+  for (index, numbered) in classify_lines(lines.iter().copied()).iter().enumerate() {
+    match numbered.kind {
+      LineKind::FileHeader if lines[index].starts_with("--- ") => {
+        std::mem::take(&mut section).finish(&lines, &mut highlights, &mut highlight);
+        section.old_path = header_path(lines[index]);
+      }
+      LineKind::FileHeader => section.new_path = header_path(lines[index]),
+      LineKind::Context => {
+        section.old.push(index);
+        section.new.push(index);
+      }
+      LineKind::Removed => section.old.push(index),
+      LineKind::Added => section.new.push(index),
+      _ => {}
+    }
+  }
+  section.finish(&lines, &mut highlights, &mut highlight);
+  HunkHighlights { lines: highlights }
+}
+
+/// The path in a `--- a/path` or `+++ b/path` header, or `None` for `/dev/null`.
+fn header_path(line: &str) -> Option<String> {
+  let path = line.get(4..)?.trim_end_matches(['\n', '\r']);
+  // Some tools append a tab and a timestamp after the path.
+  let path = path.split('\t').next().unwrap_or(path);
+  if path == "/dev/null" {
+    return None;
+  }
+  let path = path
+    .strip_prefix("a/")
+    .or_else(|| path.strip_prefix("b/"))
+    .unwrap_or(path);
+  Some(path.to_string())
+}
+
+/// Byte ranges that differ between a removed line and the added line replacing it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChangedSpans {
+  pub old: Vec<Range<usize>>,
+  pub new: Vec<Range<usize>>,
+}
+
+/// Find the words that changed between a removed line and its replacement.
+/// Returns `None` when the lines are too different for word emphasis to help.
+pub fn changed_spans(old: &str, new: &str) -> Option<ChangedSpans> {
+  let old_tokens = tokenize(old);
+  let new_tokens = tokenize(new);
+  let old_words: Vec<&str> = old_tokens.iter().map(|range| &old[range.clone()]).collect();
+  let new_words: Vec<&str> = new_tokens.iter().map(|range| &new[range.clone()]).collect();
+
+  let mut old_spans = Vec::new();
+  let mut new_spans = Vec::new();
+  for op in similar::capture_diff_slices(Algorithm::Myers, &old_words, &new_words) {
+    let (tag, old_range, new_range) = op.as_tag_tuple();
+    if tag == DiffTag::Equal {
+      continue;
+    }
+    push_span(&mut old_spans, &old_tokens, old_range);
+    push_span(&mut new_spans, &new_tokens, new_range);
+  }
+
+  let changed = |spans: &[Range<usize>]| spans.iter().map(|span| span.len()).sum::<usize>();
+  let total = old.trim().len() + new.trim().len();
+  if total > 0
+    && (changed(&old_spans) + changed(&new_spans)) as f32 / total as f32 > MAX_CHANGED_RATIO
+  {
+    return None;
+  }
+  Some(ChangedSpans {
+    old: old_spans,
+    new: new_spans,
+  })
+}
+
+/// Record the bytes covered by `tokens[range]`, merging with the previous span when adjacent.
+fn push_span(spans: &mut Vec<Range<usize>>, tokens: &[Range<usize>], range: Range<usize>) {
+  if range.is_empty() {
+    return;
+  }
+  let span = tokens[range.start].start..tokens[range.end - 1].end;
+  match spans.last_mut() {
+    Some(last) if last.end == span.start => last.end = span.end,
+    _ => spans.push(span),
+  }
+}
+
+/// Split a line into words, runs of whitespace, and single punctuation characters.
+fn tokenize(text: &str) -> Vec<Range<usize>> {
+  #[derive(PartialEq)]
+  enum Class {
+    Word,
+    Space,
+    Other,
+  }
+  let class = |c: char| {
+    if c.is_alphanumeric() || c == '_' {
+      Class::Word
+    } else if c.is_whitespace() {
+      Class::Space
+    } else {
+      Class::Other
+    }
+  };
+
+  let mut tokens: Vec<Range<usize>> = Vec::new();
+  let mut previous = None;
+  for (index, c) in text.char_indices() {
+    let current = class(c);
+    let extends = current != Class::Other && previous.as_ref() == Some(&current);
+    match tokens.last_mut() {
+      Some(last) if extends => last.end = index + c.len_utf8(),
+      _ => tokens.push(index..index + c.len_utf8()),
+    }
+    previous = Some(current);
+  }
+  tokens
+}
+
+/// Colors used for diff output, derived from the theme.
+struct DiffStyles {
+  meta: Style,
+  file_header: Style,
+  hunk_header: Style,
+  context: Option<Style>,
+  removed: Style,
+  added: Style,
+  removed_emphasis: Style,
+  added_emphasis: Style,
+  removed_background: Color,
+  added_background: Color,
+}
+
+impl DiffStyles {
+  fn new(theme: &ResolvedTheme) -> Self {
+    let removed = decorations::get_git_change_style(LineChange::Removed, theme);
+    let added = decorations::get_git_change_style(LineChange::Added, theme);
+    let background = decorations::theme_background(theme).unwrap_or(Color::new(30, 30, 46));
+    let emphasis = |style: Style| {
+      Style::new(
+        style.color(),
+        Some(mix(background, style.color(), EMPHASIS_MIX)),
+        false,
+        false,
+        false,
+        true,
+      )
+    };
+    let plain = |key: &str, bold: bool| {
+      let color = theme
+        .find_style(key)
+        .map(|style| style.color())
+        .or_else(|| decorations::theme_foreground(theme))
+        .unwrap_or(Color::new(205, 214, 244));
+      Style::new(color, None, false, false, false, bold)
+    };
+    Self {
+      meta: plain("comment", false),
+      file_header: plain("keyword", true),
+      hunk_header: plain("function", false),
+      context: decorations::theme_foreground(theme)
+        .map(|color| Style::new(color, None, false, false, false, false)),
+      removed_emphasis: emphasis(removed),
+      added_emphasis: emphasis(added),
+      removed_background: mix(background, removed.color(), LINE_MIX),
+      added_background: mix(background, added.color(), LINE_MIX),
+      removed,
+      added,
+    }
+  }
+}
+
+/// Blend `from` towards `to` by `amount` (0.0 keeps `from`, 1.0 gives `to`).
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+  let channel =
+    |a: u8, b: u8| (f32::from(a) + (f32::from(b) - f32::from(a)) * amount).round() as u8;
+  Color::new(
+    channel(from.red, to.red),
+    channel(from.green, to.green),
+    channel(from.blue, to.blue),
+  )
+}
+
+/// Render a unified diff with colored lines and word-level emphasis.
+/// With `show_numbers`, a gutter shows each line's number in the old and new file.
+/// Lines with an entry in `highlights` keep their syntax colors, with the change
+/// shown as a background tint.
+pub fn write_diff(
+  out: &mut impl Write,
+  text: &str,
+  highlights: &HunkHighlights,
+  show_numbers: bool,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+) -> io::Result<()> {
+  let styles = DiffStyles::new(theme);
+  let lines: Vec<&str> = text.split_inclusive('\n').collect();
+  let classified = classify_lines(lines.iter().copied());
+  let kinds: Vec<LineKind> = classified.iter().map(|line| line.kind).collect();
+  let number_width = classified
+    .iter()
+    .flat_map(|line| [line.old_line, line.new_line])
+    .flatten()
+    .max()
+    .map_or(1, |max| max.to_string().len());
+  let mut emphasis: Vec<Vec<Range<usize>>> = vec![Vec::new(); lines.len()];
+
+  // Pair each run of removed lines with the run of added lines that follows it.
+  let mut index = 0;
+  while index < lines.len() {
+    if kinds[index] != LineKind::Removed {
+      index += 1;
+      continue;
+    }
+    let removed_start = index;
+    while index < lines.len() && kinds[index] == LineKind::Removed {
+      index += 1;
+    }
+    let added_start = index;
+    while index < lines.len() && kinds[index] == LineKind::Added {
+      index += 1;
+    }
+    let pairs = (added_start - removed_start).min(index - added_start);
+    for offset in 0..pairs {
+      let (old_line, new_line) = (removed_start + offset, added_start + offset);
+      if let Some(spans) = changed_spans(content(lines[old_line]), content(lines[new_line])) {
+        emphasis[old_line] = spans.old;
+        emphasis[new_line] = spans.new;
+      }
+    }
+  }
+
+  let mut rendered = String::new();
+  for (index, ((line, numbered), spans)) in lines.iter().zip(&classified).zip(&emphasis).enumerate()
+  {
+    let (body, ending) = split_line_ending(line);
+    let kind = &numbered.kind;
+    if show_numbers {
+      rendered.push_str(&decorations::render_diff_gutter(
+        numbered.old_line,
+        numbered.new_line,
+        number_width,
+        renderer,
+        theme,
+      ));
+    }
+    let highlighted = highlights.line(index).filter(|pieces| {
+      pieces.iter().map(|(text, _)| text.len()).sum::<usize>() == content(line).len()
+    });
+    match kind {
+      LineKind::Removed | LineKind::Added if highlighted.is_some() => {
+        let (marker, background, emphasized) = if *kind == LineKind::Removed {
+          (
+            styles.removed,
+            styles.removed_background,
+            styles.removed_emphasis,
+          )
+        } else {
+          (styles.added, styles.added_background, styles.added_emphasis)
+        };
+        let marker = with_background(marker, background);
+        push_styled(&mut rendered, &body[..1], Some(marker), renderer);
+        let emphasis_background = emphasized.bg().unwrap_or(background);
+        push_highlighted(
+          &mut rendered,
+          highlighted.into_iter().flatten(),
+          spans,
+          background,
+          emphasis_background,
+          renderer,
+          theme,
+        );
+      }
+      LineKind::Context if highlighted.is_some() => {
+        push_styled(
+          &mut rendered,
+          &body[..1.min(body.len())],
+          styles.context,
+          renderer,
+        );
+        for (text, style_key) in highlighted.into_iter().flatten() {
+          decorations::render_content_piece(&mut rendered, text, *style_key, None, renderer, theme);
+        }
+      }
+      LineKind::Removed | LineKind::Added => {
+        let (base, emphasized) = if *kind == LineKind::Removed {
+          (styles.removed, styles.removed_emphasis)
+        } else {
+          (styles.added, styles.added_emphasis)
+        };
+        push_styled(&mut rendered, &body[..1], Some(base), renderer);
+        let text = &body[1..];
+        let mut position = 0;
+        for span in spans {
+          push_styled(
+            &mut rendered,
+            &text[position..span.start],
+            Some(base),
+            renderer,
+          );
+          push_styled(
+            &mut rendered,
+            &text[span.clone()],
+            Some(emphasized),
+            renderer,
+          );
+          position = span.end;
+        }
+        push_styled(&mut rendered, &text[position..], Some(base), renderer);
+      }
+      LineKind::Context => push_styled(&mut rendered, body, styles.context, renderer),
+      LineKind::Meta | LineKind::NoNewline => {
+        push_styled(&mut rendered, body, Some(styles.meta), renderer)
+      }
+      LineKind::FileHeader => push_styled(&mut rendered, body, Some(styles.file_header), renderer),
+      LineKind::HunkHeader => push_styled(&mut rendered, body, Some(styles.hunk_header), renderer),
+    }
+    rendered.push_str(ending);
+    out.write_all(rendered.as_bytes())?;
+    rendered.clear();
+  }
+  Ok(())
+}
+
+/// Render highlighted pieces on a line background, switching to the emphasis
+/// background inside the changed `spans`.
+fn push_highlighted<'p>(
+  output: &mut String,
+  pieces: impl IntoIterator<Item = &'p (String, Option<&'static str>)>,
+  spans: &[Range<usize>],
+  background: Color,
+  emphasis_background: Color,
+  renderer: &mut DepthRenderer,
+  theme: &ResolvedTheme,
+) {
+  let mut offset = 0;
+  for (text, style_key) in pieces {
+    let piece = offset..offset + text.len();
+    let mut position = piece.start;
+    while position < piece.end {
+      let in_span = spans
+        .iter()
+        .find(|span| span.start <= position && position < span.end);
+      let (end, bg) = match in_span {
+        Some(span) => (span.end.min(piece.end), emphasis_background),
+        None => {
+          let next_span = spans
+            .iter()
+            .map(|span| span.start)
+            .filter(|start| *start > position)
+            .min()
+            .unwrap_or(piece.end);
+          (next_span.min(piece.end), background)
+        }
+      };
+      let segment = &text[position - piece.start..end - piece.start];
+      decorations::render_content_piece(output, segment, *style_key, Some(bg), renderer, theme);
+      position = end;
+    }
+    offset = piece.end;
+  }
+}
+
+/// The text of a `-`/`+` line after its marker, without the line ending.
+fn content(line: &str) -> &str {
+  split_line_ending(line).0.get(1..).unwrap_or("")
+}
+
+fn split_line_ending(line: &str) -> (&str, &str) {
+  let body = line.trim_end_matches(['\n', '\r']);
+  (body, &line[body.len()..])
+}
+
+fn push_styled(
+  output: &mut String,
+  text: &str,
+  style: Option<Style>,
+  renderer: &mut DepthRenderer,
+) {
+  if text.is_empty() {
+    return;
+  }
+  let escaped = renderer.escape(text);
+  match style {
+    Some(style) => output.push_str(&renderer.styled(&escaped, style)),
+    None => output.push_str(&renderer.unstyled(&escaped)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_classify_lines() {
+    let diff = "diff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n--- old\n+++ new\n same\n";
+    let kinds: Vec<LineKind> = classify_lines(diff.lines())
+      .iter()
+      .map(|line| line.kind)
+      .collect();
+    assert_eq!(
+      kinds,
+      vec![
+        LineKind::Meta,
+        LineKind::FileHeader,
+        LineKind::FileHeader,
+        LineKind::HunkHeader,
+        LineKind::Removed,
+        LineKind::Added,
+        LineKind::Context,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_line_numbers_on_both_sides() {
+    let diff = "@@ -10,3 +20,3 @@\n a\n-b\n+c\n d\n";
+    let numbers: Vec<(Option<usize>, Option<usize>)> = classify_lines(diff.lines())
+      .iter()
+      .map(|line| (line.old_line, line.new_line))
+      .collect();
+    assert_eq!(
+      numbers,
+      vec![
+        (None, None),
+        (Some(10), Some(20)),
+        (Some(11), None),
+        (None, Some(21)),
+        (Some(12), Some(22)),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_highlight_hunks_per_file_version() {
+    let diff = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n ctx\n-old\n+new\n";
+    let mut calls = Vec::new();
+    let highlights = highlight_hunks(diff, |path, code| {
+      calls.push((path.to_string(), code.to_string()));
+      Some(
+        code
+          .lines()
+          .map(|line| vec![(line.to_string(), None)])
+          .collect(),
+      )
+    });
+    assert_eq!(
+      calls,
+      vec![
+        ("src/lib.rs".to_string(), "ctx\nold".to_string()),
+        ("src/lib.rs".to_string(), "ctx\nnew".to_string()),
+      ]
+    );
+    assert!(highlights.line(2).is_none());
+    assert_eq!(highlights.line(4), Some(&vec![("old".to_string(), None)]));
+    assert_eq!(highlights.line(5), Some(&vec![("new".to_string(), None)]));
+  }
+
+  #[test]
+  fn test_changed_spans_marks_only_changed_words() {
+    let spans = changed_spans("let x = compute(a, b);", "let x = compute(a, c);").unwrap();
+    assert_eq!(spans.old, vec![19..20]);
+    assert_eq!(spans.new, vec![19..20]);
+  }
+
+  #[test]
+  fn test_changed_spans_skips_rewritten_lines() {
+    assert_eq!(changed_spans("fn main() {}", "struct Config;"), None);
+  }
+}
@@ -0,0 +1,86 @@
+//! Byte order marks. A UTF-8 BOM is dropped before detection and highlighting,
+//! where it would stick to the first token; UTF-16 input, recognized by its
+//! BOM, is transcoded to UTF-8.
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const UTF16_LE_BOM: &[u8] = b"\xff\xfe";
+const UTF16_BE_BOM: &[u8] = b"\xfe\xff";
+
+/// True if `bytes` start with a UTF-16 BOM, so the input can only be decoded
+/// as a whole.
+pub fn has_utf16_bom(bytes: &[u8]) -> bool {
+  bytes.starts_with(UTF16_LE_BOM) || bytes.starts_with(UTF16_BE_BOM)
+}
+
+/// True if `bytes` start with any BOM, so that `decode` may change them.
+pub fn has_bom(bytes: &[u8]) -> bool {
+  bytes.starts_with(UTF8_BOM) || has_utf16_bom(bytes)
+}
+
+/// `bytes` without a leading UTF-8 BOM.
+pub fn strip_utf8_bom(mut bytes: Vec<u8>) -> Vec<u8> {
+  if bytes.starts_with(UTF8_BOM) {
+    bytes.drain(..UTF8_BOM.len());
+  }
+  bytes
+}
+
+/// `bytes` as UTF-8 without a BOM. With `keep_bom`, for `--show-all`, the BOM
+/// stays as U+FEFF so that it can be shown. Unpaired surrogates and an odd
+/// trailing byte in UTF-16 become U+FFFD.
+pub fn decode(bytes: Vec<u8>, keep_bom: bool) -> Vec<u8> {
+  let from_bytes: fn([u8; 2]) -> u16 = if bytes.starts_with(UTF16_LE_BOM) {
+    u16::from_le_bytes
+  } else if has_utf16_bom(&bytes) {
+    u16::from_be_bytes
+  } else if keep_bom {
+    return bytes;
+  } else {
+    return strip_utf8_bom(bytes);
+  };
+  let body = &bytes[UTF16_LE_BOM.len()..];
+  let units = body
+    .chunks_exact(2)
+    .map(|pair| from_bytes([pair[0], pair[1]]));
+  let mut text = String::with_capacity(body.len());
+  if keep_bom {
+    text.push('\u{feff}');
+  }
+  text.extend(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)));
+  if body.len() % 2 == 1 {
+    text.push(char::REPLACEMENT_CHARACTER);
+  }
+  text.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_decode_utf8() {
+    assert_eq!(
+      decode(b"\xef\xbb\xbf#!/bin/sh\n".to_vec(), false),
+      b"#!/bin/sh\n"
+    );
+    assert_eq!(decode(b"\xef\xbb\xbfx".to_vec(), true), b"\xef\xbb\xbfx");
+    assert_eq!(decode(b"no bom".to_vec(), false), b"no bom");
+  }
+
+  #[test]
+  fn test_decode_utf16() {
+    assert_eq!(
+      decode(b"\xff\xfeh\0\xe9\0\n\0".to_vec(), false),
+      "hé\n".as_bytes()
+    );
+    assert_eq!(
+      decode(b"\xfe\xff\0h\xd8\x3d\xde\x00".to_vec(), true),
+      "\u{feff}h😀".as_bytes()
+    );
+    // An unpaired surrogate and a stray byte.
+    assert_eq!(
+      decode(b"\xff\xfe\x00\xd8a".to_vec(), false),
+      "\u{fffd}\u{fffd}".as_bytes()
+    );
+  }
+}
@@ -0,0 +1,134 @@
+//! `--show-line-endings` and `--normalize-eol`: CRLF and LF line endings made
+//! visible, or made the same, for tracking down cross-platform whitespace.
+
+use std::borrow::Cow;
+
+use crate::unprintable::CharStyle;
+
+/// Line endings counted in an input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counts {
+  pub lf: usize,
+  pub crlf: usize,
+}
+
+impl Counts {
+  pub fn of(bytes: &[u8]) -> Self {
+    let mut counts = Self::default();
+    for (index, byte) in bytes.iter().enumerate() {
+      if *byte == b'\n' {
+        if index > 0 && bytes[index - 1] == b'\r' {
+          counts.crlf += 1;
+        } else {
+          counts.lf += 1;
+        }
+      }
+    }
+    counts
+  }
+
+  pub fn is_mixed(self) -> bool {
+    self.lf > 0 && self.crlf > 0
+  }
+
+  /// For the `{eol}` header field: `LF`, `CRLF` or `mixed (3 CRLF, 40 LF)`;
+  /// `None` for input without line breaks.
+  pub fn describe(self) -> Option<String> {
+    match (self.crlf, self.lf) {
+      (0, 0) => None,
+      (0, _) => Some("LF".to_string()),
+      (_, 0) => Some("CRLF".to_string()),
+      (crlf, lf) => Some(format!("mixed ({crlf} CRLF, {lf} LF)")),
+    }
+  }
+}
+
+/// The marker at the end of a line, drawn as `--show-all` draws those
+/// characters: `␊` after LF and `↵␊` after CRLF.
+pub fn marker(crlf: bool, style: CharStyle) -> &'static str {
+  match (crlf, style) {
+    (false, CharStyle::Unicode) => "␊",
+    (true, CharStyle::Unicode) => "↵␊",
+    (false, CharStyle::Caret) => "$",
+    (true, CharStyle::Caret) => "^M$",
+  }
+}
+
+/// `text` without the CR of its CRLF line endings, and for each line whether
+/// it ended in CRLF.
+pub fn split_crlf(text: &str) -> (Cow<'_, str>, Vec<bool>) {
+  let crlf: Vec<bool> = text.split('\n').map(|line| line.ends_with('\r')).collect();
+  if !crlf.iter().any(|crlf| *crlf) {
+    return (Cow::Borrowed(text), crlf);
+  }
+  (Cow::Owned(text.replace("\r\n", "\n")), crlf)
+}
+
+/// `bytes` with each line ending marked, for input shown without highlighting.
+pub fn mark(bytes: &[u8], style: CharStyle) -> Vec<u8> {
+  let mut marked = Vec::with_capacity(bytes.len() + bytes.len() / 16);
+  for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+    let Some(body) = line.strip_suffix(b"\n") else {
+      marked.extend_from_slice(line);
+      continue;
+    };
+    let (body, crlf) = match body.strip_suffix(b"\r") {
+      Some(body) => (body, true),
+      None => (body, false),
+    };
+    marked.extend_from_slice(body);
+    marked.extend_from_slice(marker(crlf, style).as_bytes());
+    marked.push(b'\n');
+  }
+  marked
+}
+
+pub fn has_crlf(bytes: &[u8]) -> bool {
+  bytes.windows(2).any(|pair| pair == b"\r\n")
+}
+
+/// `bytes` with CRLF line endings turned into LF.
+pub fn normalize(bytes: Vec<u8>) -> Vec<u8> {
+  if !has_crlf(&bytes) {
+    return bytes;
+  }
+  let mut normalized = Vec::with_capacity(bytes.len());
+  for (index, byte) in bytes.iter().enumerate() {
+    if *byte != b'\r' || bytes.get(index + 1) != Some(&b'\n') {
+      normalized.push(*byte);
+    }
+  }
+  normalized
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_counts() {
+    let counts = Counts::of(b"a\r\nb\nc\r\n\rd");
+    assert_eq!(counts, Counts { lf: 1, crlf: 2 });
+    assert!(counts.is_mixed());
+    assert_eq!(counts.describe().as_deref(), Some("mixed (2 CRLF, 1 LF)"));
+    assert_eq!(Counts::of(b"a\r\n").describe().as_deref(), Some("CRLF"));
+    assert_eq!(Counts::of(b"no break").describe(), None);
+  }
+
+  #[test]
+  fn test_split_crlf() {
+    let (text, crlf) = split_crlf("a\r\nb\nc");
+    assert_eq!(text, "a\nb\nc");
+    assert_eq!(crlf, [true, false, false]);
+  }
+
+  #[test]
+  fn test_mark_and_normalize() {
+    assert_eq!(
+      mark(b"a\r\nb\nc", CharStyle::Unicode),
+      "a↵␊\nb␊\nc".as_bytes()
+    );
+    assert_eq!(mark(b"a\r\n\n", CharStyle::Caret), b"a^M$\n$\n");
+    assert_eq!(normalize(b"a\r\nb\r\rc\n".to_vec()), b"a\nb\r\rc\n");
+  }
+}
@@ -0,0 +1,171 @@
+//! The C interface declared in `include/umber.h`, built as a shared library
+//! with `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+//!
+//! Strings returned by umber are owned by the caller and released with
+//! `umber_string_free`. On failure `umber_highlight` returns NULL and
+//! `umber_last_error` says why.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::ptr;
+
+use eyre::{Result, eyre};
+
+use crate::color_depth::ColorDepth;
+use crate::highlight::{Format, Options, highlight};
+
+/// `UmberOptions` in the header.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct UmberOptions {
+  /// `UMBER_FORMAT_ANSI`, `UMBER_FORMAT_HTML` or `UMBER_FORMAT_HTML_FULL`
+  pub format: u32,
+  /// Non-zero to number the lines
+  pub line_numbers: u32,
+  /// `UMBER_COLORS_TRUECOLOR`, `UMBER_COLORS_256` or `UMBER_COLORS_16`
+  pub color_depth: u32,
+}
+
+thread_local! {
+  static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Highlight `len` bytes of UTF-8 at `source` as `language` in `theme`, both
+/// NUL-terminated names. `options` may be NULL for ANSI output without line
+/// numbers. Returns a NUL-terminated string to release with
+/// `umber_string_free`, or NULL on error.
+///
+/// # Safety
+///
+/// `source` must point to `len` readable bytes, `language` and `theme` to
+/// NUL-terminated strings, and `options`, unless NULL, to an `UmberOptions`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn umber_highlight(
+  source: *const u8,
+  len: usize,
+  language: *const c_char,
+  theme: *const c_char,
+  options: *const UmberOptions,
+) -> *mut c_char {
+  // SAFETY: the caller upholds the contract documented above.
+  let result = unsafe { highlight_raw(source, len, language, theme, options.as_ref()) };
+  match result.and_then(|out| CString::new(out).map_err(|_| eyre!("output contains a NUL byte"))) {
+    Ok(out) => {
+      set_last_error(None);
+      out.into_raw()
+    }
+    Err(err) => {
+      set_last_error(Some(&format!("{err:#}")));
+      ptr::null_mut()
+    }
+  }
+}
+
+/// Why the last `umber_highlight` on this thread failed, or NULL after a
+/// success. Valid until the next call on the same thread; not to be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn umber_last_error() -> *const c_char {
+  LAST_ERROR.with_borrow(|error| error.as_ref().map_or(ptr::null(), |error| error.as_ptr()))
+}
+
+/// Release a string returned by `umber_highlight`. NULL is ignored.
+///
+/// # Safety
+///
+/// `string` must be NULL or a pointer `umber_highlight` returned that has not
+/// been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn umber_string_free(string: *mut c_char) {
+  if !string.is_null() {
+    // SAFETY: `string` came from `CString::into_raw` in `umber_highlight`.
+    drop(unsafe { CString::from_raw(string) });
+  }
+}
+
+/// # Safety
+///
+/// As for `umber_highlight`.
+unsafe fn highlight_raw(
+  source: *const u8,
+  len: usize,
+  language: *const c_char,
+  theme: *const c_char,
+  options: Option<&UmberOptions>,
+) -> Result<String> {
+  if source.is_null() && len > 0 || language.is_null() || theme.is_null() {
+    return Err(eyre!("source, language and theme must not be NULL"));
+  }
+  let source = match len {
+    0 => &[][..],
+    // SAFETY: `source` points to `len` readable bytes.
+    _ => unsafe { std::slice::from_raw_parts(source, len) },
+  };
+  let source = std::str::from_utf8(source).map_err(|err| eyre!("source is not UTF-8: {err}"))?;
+  // SAFETY: both are NUL-terminated strings.
+  let (language, theme) = unsafe { (CStr::from_ptr(language), CStr::from_ptr(theme)) };
+  let language = language
+    .to_str()
+    .map_err(|_| eyre!("language is not UTF-8"))?;
+  let theme = theme.to_str().map_err(|_| eyre!("theme is not UTF-8"))?;
+  let options = options.map_or_else(|| Ok(Options::default()), options_from_c)?;
+  highlight(source, language, theme, &options)
+}
+
+fn options_from_c(options: &UmberOptions) -> Result<Options> {
+  Ok(Options {
+    format: match options.format {
+      0 => Format::Ansi,
+      1 => Format::Html,
+      2 => Format::HtmlFull,
+      other => return Err(eyre!("unknown format {other}")),
+    },
+    line_numbers: options.line_numbers != 0,
+    color_depth: match options.color_depth {
+      0 => ColorDepth::Truecolor,
+      1 => ColorDepth::Ansi256,
+      2 => ColorDepth::Ansi16,
+      other => return Err(eyre!("unknown color depth {other}")),
+    },
+  })
+}
+
+fn set_last_error(message: Option<&str>) {
+  let message = message
+    .map(|message| CString::new(message.replace('\0', " ")).expect("NUL bytes were replaced"));
+  LAST_ERROR.with_borrow_mut(|error| *error = message);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_errors_are_reported() {
+    let language = c"no-such-language";
+    let theme = c"catppuccin::mocha";
+    // SAFETY: valid pointers, as the contract asks.
+    let out = unsafe {
+      umber_highlight(
+        b"x".as_ptr(),
+        1,
+        language.as_ptr(),
+        theme.as_ptr(),
+        ptr::null(),
+      )
+    };
+    assert!(out.is_null());
+    // SAFETY: umber_last_error returned a live NUL-terminated string.
+    let error = unsafe { CStr::from_ptr(umber_last_error()) };
+    assert_eq!(
+      error.to_str().unwrap(),
+      "unknown language 'no-such-language'"
+    );
+
+    let options = UmberOptions {
+      format: 7,
+      line_numbers: 0,
+      color_depth: 0,
+    };
+    assert!(options_from_c(&options).is_err());
+  }
+}
@@ -0,0 +1,61 @@
+//! `--files-from`: paths read from a file or stdin, one per line or, with
+//! `-0`, separated by NUL bytes as `fd -0` and `git ls-files -z` write them.
+//! Long lists get past the limit on command line length this way.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// The paths listed in `list`, or on stdin when it is `-`.
+pub fn read(list: &Path, nul_separated: bool) -> io::Result<Vec<PathBuf>> {
+  let mut bytes = Vec::new();
+  if list == Path::new("-") {
+    io::stdin().lock().read_to_end(&mut bytes)?;
+  } else {
+    bytes = fs::read(list)?;
+  }
+  Ok(parse(&bytes, nul_separated))
+}
+
+/// Empty entries are skipped; lines may end in CRLF.
+fn parse(bytes: &[u8], nul_separated: bool) -> Vec<PathBuf> {
+  let separator = if nul_separated { b'\0' } else { b'\n' };
+  bytes
+    .split(|byte| *byte == separator)
+    .map(|entry| match nul_separated {
+      true => entry,
+      false => entry.strip_suffix(b"\r").unwrap_or(entry),
+    })
+    .filter(|entry| !entry.is_empty())
+    .map(path_from_bytes)
+    .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+  use std::os::unix::ffi::OsStrExt;
+  PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+  PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(
+      parse(b"src/main.rs\r\n\nREADME.md\n", false),
+      [PathBuf::from("src/main.rs"), PathBuf::from("README.md")]
+    );
+    assert_eq!(
+      parse(b"with\nnewline\0b.rs\0", true),
+      [PathBuf::from("with\nnewline"), PathBuf::from("b.rs")]
+    );
+    assert!(parse(b"", false).is_empty());
+  }
+}
@@ -0,0 +1,231 @@
+//! `--follow`: keep watching files for appended data, like `tail -f`. Files
+//! are followed by name, like `tail -F`, so a log that is truncated or
+//! replaced by rotation is picked up again from its first line. Changes are
+//! noticed through inotify, kqueue or their Windows counterpart, with polling
+//! for filesystems that deliver no events, such as NFS.
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+
+/// How long to wait between checks when no file has grown and change events
+/// are not available.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long to wait for a change event before checking anyway, in case the
+/// filesystem does not deliver them.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often `auto` themes are re-resolved against the desktop's dark/light mode.
+pub const RECHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Wakes `--follow` when something changes in the directories of the
+/// followed files. The directories are watched rather than the files so that
+/// a file created again after rotation is noticed too.
+pub struct ChangeEvents {
+  /// Kept alive for as long as events are wanted; `None` when watching failed
+  watcher: Option<notify::RecommendedWatcher>,
+  events: Receiver<()>,
+}
+
+impl ChangeEvents {
+  pub fn new<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Self {
+    let (sender, events) = mpsc::channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+      if event.is_ok() {
+        let _ = sender.send(());
+      }
+    });
+    let watcher = watcher.ok().and_then(|mut watcher| {
+      for path in paths {
+        let dir = match path.parent() {
+          Some(dir) if !dir.as_os_str().is_empty() => dir,
+          _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive).ok()?;
+      }
+      Some(watcher)
+    });
+    Self { watcher, events }
+  }
+
+  /// Wait until a change is reported, or for as long as it is worth waiting
+  /// before checking the files anyway. At most `limit`.
+  pub fn wait(&self, limit: Duration) {
+    if self.watcher.is_none() {
+      std::thread::sleep(POLL_INTERVAL.min(limit));
+      return;
+    }
+    if self.events.recv_timeout(EVENT_TIMEOUT.min(limit)).is_ok() {
+      // One check covers the events that came in a burst.
+      while self.events.try_recv().is_ok() {}
+    }
+  }
+}
+
+/// Why a followed file is being read from the top again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+  /// It became shorter than what was already read
+  Truncated,
+  /// Another file now has its name, as after log rotation
+  Replaced,
+}
+
+/// Identifies a file independently of its name: device and inode on Unix.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+  use std::os::unix::fs::MetadataExt;
+  Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+  None
+}
+
+/// A file being followed, with its read position and line numbering.
+pub struct FollowedFile {
+  pub path: PathBuf,
+  id: Option<(u64, u64)>,
+  position: u64,
+  pending: Vec<u8>,
+  next_line: usize,
+  restart: Option<Restart>,
+}
+
+impl FollowedFile {
+  /// Start following `path` after `shown`, the content that was already displayed.
+  pub fn new(path: PathBuf, shown: &[u8]) -> Self {
+    Self {
+      id: fs::metadata(&path).ok().as_ref().and_then(file_id),
+      path,
+      position: shown.len() as u64,
+      pending: Vec::new(),
+      next_line: count_newlines(shown) + 1,
+      restart: None,
+    }
+  }
+
+  /// Why the lines of the last poll start from the top, if they do.
+  pub fn take_restart(&mut self) -> Option<Restart> {
+    self.restart.take()
+  }
+
+  /// Read data appended since the last poll.
+  /// Returns the line number of the first new line and the complete lines read;
+  /// a trailing partial line is held back until it is finished.
+  /// A file that is missing, as between rotation and the creation of its
+  /// successor, has nothing new yet.
+  pub fn poll(&mut self) -> io::Result<Option<(usize, Vec<u8>)>> {
+    let mut file = match File::open(&self.path) {
+      Ok(file) => file,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(err) => return Err(err),
+    };
+    let metadata = file.metadata()?;
+    let id = file_id(&metadata);
+    let len = metadata.len();
+    if id != self.id {
+      self.start_over(Restart::Replaced);
+      self.id = id;
+    } else if len < self.position {
+      self.start_over(Restart::Truncated);
+    }
+    if len == self.position {
+      return Ok(None);
+    }
+
+    file.seek(SeekFrom::Start(self.position))?;
+    let read = file
+      .take(len - self.position)
+      .read_to_end(&mut self.pending)?;
+    self.position += read as u64;
+
+    let Some(last_newline) = self.pending.iter().rposition(|byte| *byte == b'\n') else {
+      return Ok(None);
+    };
+    let rest = self.pending.split_off(last_newline + 1);
+    let lines = std::mem::replace(&mut self.pending, rest);
+    let line_number_start = self.next_line;
+    self.next_line += count_newlines(&lines);
+    Ok(Some((line_number_start, lines)))
+  }
+
+  fn start_over(&mut self, restart: Restart) {
+    self.position = 0;
+    self.pending.clear();
+    self.next_line = 1;
+    self.restart = Some(restart);
+  }
+}
+
+fn count_newlines(bytes: &[u8]) -> usize {
+  bytes.iter().filter(|byte| **byte == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+  use std::time::Instant;
+
+  #[test]
+  fn test_change_events_wake_on_append() {
+    let path = std::env::temp_dir().join(format!("umber-events-{}.log", std::process::id()));
+    fs::write(&path, "one\n").unwrap();
+    let events = ChangeEvents::new([path.as_path()]);
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"two\n").unwrap();
+    let started = Instant::now();
+    events.wait(Duration::from_secs(10));
+    // Woken by the event, or by the poll when the platform has none
+    assert!(started.elapsed() < Duration::from_secs(5));
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_poll_returns_appended_lines() {
+    let path = std::env::temp_dir().join(format!("umber-follow-{}.log", std::process::id()));
+    fs::write(&path, "one\ntwo\n").unwrap();
+    let mut followed = FollowedFile::new(path.clone(), b"one\ntwo\n");
+    assert_eq!(followed.poll().unwrap(), None);
+
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"three\nfo").unwrap();
+    assert_eq!(followed.poll().unwrap(), Some((3, b"three\n".to_vec())));
+    file.write_all(b"ur\n").unwrap();
+    assert_eq!(followed.poll().unwrap(), Some((4, b"four\n".to_vec())));
+
+    fs::write(&path, "new\n").unwrap();
+    assert_eq!(followed.poll().unwrap(), Some((1, b"new\n".to_vec())));
+    assert_eq!(followed.take_restart(), Some(Restart::Truncated));
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_poll_follows_rotation() {
+    let path = std::env::temp_dir().join(format!("umber-rotate-{}.log", std::process::id()));
+    let rotated = path.with_extension("log.1");
+    fs::write(&path, "old\n").unwrap();
+    let mut followed = FollowedFile::new(path.clone(), b"old\n");
+
+    fs::rename(&path, &rotated).unwrap();
+    assert_eq!(followed.poll().unwrap(), None);
+    // The new file is already longer than what was read from the old one.
+    fs::write(&path, "first\nsecond\n").unwrap();
+    assert_eq!(
+      followed.poll().unwrap(),
+      Some((1, b"first\nsecond\n".to_vec()))
+    );
+    assert_eq!(followed.take_restart(), Some(Restart::Replaced));
+    assert_eq!(followed.take_restart(), None);
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&rotated).unwrap();
+  }
+}
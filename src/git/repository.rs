@@ -0,0 +1,486 @@
+//! Reading a repository: changes are read through gix; history commands
+//! still run the git binary.
+
+use eyre::{Result, eyre};
+use gix::bstr::BString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use super::{LineChange, LineStatus};
+
+/// Get git line changes for a file path.
+///
+/// Returns a vector where the index corresponds to the line number (1-based).
+/// Lines with no changes will have `None` in the vector. A line with both
+/// staged and unstaged changes is shown as unstaged. Against a `base`
+/// revision nothing counts as staged.
+pub fn get_git_line_changes(path: &Path, base: Option<&str>) -> Result<Vec<Option<LineStatus>>> {
+  let Some(file) = TrackedFile::open(path)? else {
+    return Ok(Vec::new());
+  };
+  Ok(match base {
+    Some(base) => line_statuses(&[], &file.base_hunks(base)?),
+    None => line_statuses(&file.staged_hunks()?, &file.unstaged_hunks()?),
+  })
+}
+
+/// The line ranges of `path` that differ from the index, or from the `base`
+/// revision, as inclusive `(first, last)` pairs in the working copy. A pure
+/// deletion is reported as the line it follows, so it still has something to
+/// show around it.
+///
+/// `None` when `path` is not in a git working tree.
+pub fn changed_hunks(path: &Path, base: Option<&str>) -> Result<Option<Vec<(usize, usize)>>> {
+  let Some(file) = TrackedFile::open(path)? else {
+    return Ok(None);
+  };
+  let hunks = match base {
+    Some(base) => file.base_hunks(base)?,
+    None => file.unstaged_hunks()?,
+  };
+  Ok(Some(
+    hunks
+      .iter()
+      .map(|hunk| {
+        let first = hunk.new_start.max(1);
+        (first, first + hunk.new_lines.max(1) - 1)
+      })
+      .collect(),
+  ))
+}
+
+/// One hunk of a diff without context, like `@@ -3,2 +3,4 @@`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hunk {
+  /// First old line, or the line a pure insertion follows
+  old_start: usize,
+  old_lines: usize,
+  /// First new line, or the line a pure deletion follows
+  new_start: usize,
+  new_lines: usize,
+}
+
+impl Hunk {
+  /// The marker of each new line it touches, by line number. As many lines
+  /// as were removed count as modified and the rest as added; a pure deletion
+  /// marks the line it follows.
+  fn changes(self) -> impl Iterator<Item = (usize, LineChange)> {
+    let removal = (self.new_lines == 0).then_some(match self.new_start {
+      0 => (1, LineChange::RemovedAbove),
+      line => (line, LineChange::Removed),
+    });
+    (0..self.new_lines)
+      .map(move |offset| {
+        let change = if offset < self.old_lines {
+          LineChange::Modified
+        } else {
+          LineChange::Added
+        };
+        (self.new_start + offset, change)
+      })
+      .chain(removal)
+  }
+}
+
+/// A file in a git working tree. gix is used rather than the git binary so
+/// this works without git installed and ignores diff settings such as
+/// external drivers. The repository is the one that owns the file, found from
+/// its own directory, so files in other repositories or in submodules work
+/// whatever the current directory is.
+struct TrackedFile {
+  repo: gix::Repository,
+  index: gix::worktree::Index,
+  /// Path from the root of the working tree
+  relative: PathBuf,
+  /// The same path as git stores it, with forward slashes
+  git_path: BString,
+}
+
+impl TrackedFile {
+  /// The file at `path`, or `None` when it is not in a git working tree.
+  fn open(path: &Path) -> Result<Option<Self>> {
+    let repo = match gix::discover(path.parent().unwrap_or(path)) {
+      Ok(repo) => repo,
+      Err(gix::discover::Error::Discover(_)) => return Ok(None),
+      Err(err) => return Err(eyre!("Failed to open git repository: {}", err)),
+    };
+    let Some(workdir) = repo.workdir() else {
+      return Ok(None);
+    };
+    let workdir = std::fs::canonicalize(workdir).unwrap_or_else(|_| workdir.to_path_buf());
+    let Ok(relative) = path.strip_prefix(&workdir) else {
+      return Ok(None);
+    };
+    let relative = relative.to_path_buf();
+    let git_path =
+      gix::path::to_unix_separators_on_windows(gix::path::into_bstr(&relative)).into_owned();
+    let index = repo.index_or_empty()?;
+    Ok(Some(Self {
+      repo,
+      index,
+      relative,
+      git_path,
+    }))
+  }
+
+  /// The working copy against the index, as `git diff` compares them. An
+  /// untracked file has no changes.
+  fn unstaged_hunks(&self) -> Result<Vec<Hunk>> {
+    let Some(staged) = self.index_blob()? else {
+      return Ok(Vec::new());
+    };
+    Ok(diff_hunks(&staged, &self.worktree_blob()?))
+  }
+
+  /// The working copy against the `base` revision, as `git diff BASE`
+  /// compares them.
+  fn base_hunks(&self, base: &str) -> Result<Vec<Hunk>> {
+    let tree = self
+      .repo
+      .rev_parse_single(base)
+      .map_err(|e| eyre!("Unknown revision '{}': {}", base, e))?
+      .object()?
+      .peel_to_tree()
+      .map_err(|e| eyre!("Unknown revision '{}': {}", base, e))?;
+    let old = self.tree_blob(&tree)?;
+    if old.is_none() && self.index_blob()?.is_none() {
+      return Ok(Vec::new());
+    }
+    Ok(diff_hunks(&old.unwrap_or_default(), &self.worktree_blob()?))
+  }
+
+  /// The index against `HEAD`, as `git diff --cached` compares them. Lines
+  /// are numbered as in the index. Before the first commit everything in the
+  /// index is staged.
+  fn staged_hunks(&self) -> Result<Vec<Hunk>> {
+    let Some(staged) = self.index_blob()? else {
+      return Ok(Vec::new());
+    };
+    let old = match self.repo.head_tree() {
+      Ok(tree) => self.tree_blob(&tree)?,
+      Err(_) => None,
+    };
+    Ok(diff_hunks(&old.unwrap_or_default(), &staged))
+  }
+
+  /// The contents of the file in the index, or `None` when it is not there.
+  fn index_blob(&self) -> Result<Option<Vec<u8>>> {
+    let Some(entry) = self.index.entry_by_path(self.git_path.as_ref()) else {
+      return Ok(None);
+    };
+    Ok(Some(self.repo.find_blob(entry.id)?.take_data()))
+  }
+
+  /// The contents of the file in `tree`, or `None` when it is not there. A
+  /// file that was renamed since is compared with its old name: when it is
+  /// missing, the tree is diffed against the index with rename detection to
+  /// find where it came from.
+  fn tree_blob(&self, tree: &gix::Tree<'_>) -> Result<Option<Vec<u8>>> {
+    let id = match tree.lookup_entry_by_path(&self.relative)? {
+      Some(entry) => Some(entry.object_id()),
+      None => self.renamed_from(tree.id)?,
+    };
+    match id {
+      Some(id) => Ok(Some(self.repo.find_blob(id)?.take_data())),
+      None => Ok(None),
+    }
+  }
+
+  /// The blob the file was renamed or copied from since `tree`.
+  fn renamed_from(&self, tree: gix::ObjectId) -> Result<Option<gix::ObjectId>> {
+    use gix::diff::index::{Action, ChangeRef};
+
+    let mut source = None;
+    self.repo.tree_index_status(
+      &tree,
+      &self.index,
+      None,
+      gix::status::tree_index::TrackRenames::Given(Default::default()),
+      |change, _, _| {
+        if let ChangeRef::Rewrite {
+          location,
+          source_id,
+          ..
+        } = change
+          && location.as_ref() == self.git_path
+        {
+          source = Some(source_id.into_owned());
+          return Ok::<_, std::convert::Infallible>(Action::Cancel);
+        }
+        Ok(Action::Continue)
+      },
+    )?;
+    Ok(source)
+  }
+
+  /// The working copy as git would store it, after filters such as line
+  /// ending conversion.
+  fn worktree_blob(&self) -> Result<Vec<u8>> {
+    let path = self
+      .repo
+      .workdir()
+      .unwrap_or(Path::new("."))
+      .join(&self.relative);
+    let (mut pipeline, index) = self.repo.filter_pipeline(None)?;
+    let mut contents = Vec::new();
+    pipeline
+      .convert_to_git(std::fs::File::open(path)?, &self.relative, &index)?
+      .read_to_end(&mut contents)?;
+    Ok(contents)
+  }
+}
+
+/// The hunks of a line diff from `old` to `new` without context, numbered as
+/// `git diff` numbers them: a side without lines starts at the line it
+/// follows.
+fn diff_hunks(old: &[u8], new: &[u8]) -> Vec<Hunk> {
+  use gix::diff::blob::{Algorithm, diff, intern::InternedInput, sources};
+
+  let input = InternedInput::new(
+    sources::byte_lines_with_terminator(old),
+    sources::byte_lines_with_terminator(new),
+  );
+  let start = |lines: &std::ops::Range<u32>| lines.start as usize + usize::from(!lines.is_empty());
+  let mut hunks = Vec::new();
+  diff(
+    Algorithm::Myers,
+    &input,
+    |before: std::ops::Range<u32>, after: std::ops::Range<u32>| {
+      hunks.push(Hunk {
+        old_start: start(&before),
+        old_lines: before.len(),
+        new_start: start(&after),
+        new_lines: after.len(),
+      });
+    },
+  );
+  hunks
+}
+
+/// Per-line statuses, indexed from 0 for line 1, from the staged hunks (in
+/// index lines) and the unstaged hunks (from index to working copy lines).
+fn line_statuses(staged: &[Hunk], unstaged: &[Hunk]) -> Vec<Option<LineStatus>> {
+  let mut statuses: Vec<Option<LineStatus>> = Vec::new();
+  let mut mark = |line: usize, change: LineChange, staged: bool| {
+    if line > statuses.len() {
+      statuses.resize(line, None);
+    }
+    let slot = &mut statuses[line - 1];
+    // A removal only shows on a line without a change of its own.
+    let removal = matches!(change, LineChange::Removed | LineChange::RemovedAbove);
+    if !(removal && slot.is_some_and(|status| status.staged == staged)) {
+      *slot = Some(LineStatus { change, staged });
+    }
+  };
+  for hunk in staged {
+    for (line, change) in hunk.changes() {
+      if let Some(line) = index_to_workdir(line, unstaged) {
+        mark(line, change, true);
+      }
+    }
+  }
+  for hunk in unstaged {
+    for (line, change) in hunk.changes() {
+      mark(line, change, false);
+    }
+  }
+  statuses
+}
+
+/// Where `line` of the index is in the working copy, or `None` when unstaged
+/// changes replaced or removed it.
+fn index_to_workdir(line: usize, unstaged: &[Hunk]) -> Option<usize> {
+  let mut shift = 0isize;
+  for hunk in unstaged {
+    if hunk.old_lines == 0 {
+      // A pure insertion after `old_start`.
+      if hunk.old_start >= line {
+        break;
+      }
+    } else if line < hunk.old_start {
+      break;
+    } else if line < hunk.old_start + hunk.old_lines {
+      return None;
+    }
+    shift += hunk.new_lines as isize - hunk.old_lines as isize;
+  }
+  line.checked_add_signed(shift)
+}
+
+/// The root of the git repository containing `path`: the closest ancestor
+/// with a `.git` directory, or `.git` file for worktrees and submodules.
+pub fn repository_root(path: &Path) -> Option<PathBuf> {
+  path
+    .ancestors()
+    .skip(1)
+    .find(|dir| dir.join(".git").exists())
+    .map(Path::to_path_buf)
+}
+
+/// The branch checked out in the repository containing `path`, or the short
+/// commit hash when `HEAD` is detached.
+pub fn branch(path: &Path) -> Option<String> {
+  let repo = gix::discover(path.parent().unwrap_or(path)).ok()?;
+  let head = repo.head().ok()?;
+  if head.is_unborn() {
+    return None;
+  }
+  if let Some(name) = head.referent_name() {
+    return Some(name.shorten().to_string());
+  }
+  Some(head.id()?.to_hex_with_len(8).to_string())
+}
+
+/// Run `git diff` with the given revisions and paths and return its output.
+pub fn diff_output(args: &[PathBuf]) -> Result<Vec<u8>> {
+  let output = Command::new("git")
+    .arg("diff")
+    .arg("--no-color")
+    .args(args)
+    .output()
+    .map_err(|e| eyre!("Failed to run git diff: {}", e))?;
+  checked_stdout("git diff", output)
+}
+
+/// The contents of `PATH` at `REV`, given as `REV:PATH` like `git show`.
+pub fn show_file(spec: &str) -> Result<Vec<u8>> {
+  let output = Command::new("git")
+    .arg("show")
+    .arg("--no-color")
+    .arg(spec)
+    .output()
+    .map_err(|e| eyre!("Failed to run git show: {}", e))?;
+  checked_stdout("git show", output)
+}
+
+/// The commit and author that last touched a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+  /// Abbreviated commit hash
+  pub commit: String,
+  pub author: String,
+  /// When the commit was authored, in seconds since the Unix epoch
+  pub time: i64,
+}
+
+/// Blame every line of the working copy of `path`.
+/// The result is indexed by line number, starting at 0 for line 1.
+pub fn blame(path: &Path) -> Result<Vec<BlameLine>> {
+  // Run from the file's directory so the repository that owns it is used.
+  let dir = path
+    .parent()
+    .filter(|dir| !dir.as_os_str().is_empty())
+    .unwrap_or(Path::new("."));
+  let name = path.file_name().map_or(path.as_os_str(), |name| name);
+  let output = Command::new("git")
+    .current_dir(dir)
+    .arg("blame")
+    .arg("--porcelain")
+    .arg("--")
+    .arg(name)
+    .output()
+    .map_err(|e| eyre!("Failed to run git blame: {}", e))?;
+  let stdout = checked_stdout("git blame", output)?;
+  Ok(parse_blame_porcelain(&String::from_utf8_lossy(&stdout)))
+}
+
+fn checked_stdout(command: &str, output: Output) -> Result<Vec<u8>> {
+  if output.status.success() {
+    return Ok(output.stdout);
+  }
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  Err(eyre!("{} failed: {}", command, stderr.trim()))
+}
+
+/// Parse `git blame --porcelain`. Commit details are only printed the first
+/// time a commit appears, so authors and times are remembered by hash.
+fn parse_blame_porcelain(porcelain: &str) -> Vec<BlameLine> {
+  use std::collections::HashMap;
+
+  let mut authors: HashMap<&str, &str> = HashMap::new();
+  let mut times: HashMap<&str, i64> = HashMap::new();
+  let mut lines = Vec::new();
+  let mut commit = "";
+  for line in porcelain.lines() {
+    if line.starts_with('\t') {
+      lines.push(BlameLine {
+        commit: commit.chars().take(8).collect(),
+        author: authors.get(commit).copied().unwrap_or_default().to_string(),
+        time: times.get(commit).copied().unwrap_or_default(),
+      });
+    } else if let Some(author) = line.strip_prefix("author ") {
+      authors.insert(commit, author);
+    } else if let Some(time) = line.strip_prefix("author-time ") {
+      times.insert(commit, time.parse().unwrap_or_default());
+    } else if let Some(hash) = line.split(' ').next()
+      && hash.len() == 40
+      && hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+    {
+      commit = hash;
+    }
+  }
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn hunk(old_start: usize, old_lines: usize, new_start: usize, new_lines: usize) -> Hunk {
+    Hunk {
+      old_start,
+      old_lines,
+      new_start,
+      new_lines,
+    }
+  }
+
+  #[test]
+  fn test_line_statuses() {
+    // Staged: line 2 modified and line 3 added. Unstaged: two lines inserted
+    // after line 1, line 5 removed and a line inserted after line 6.
+    let staged = [hunk(2, 1, 2, 2)];
+    let unstaged = [hunk(1, 0, 2, 2), hunk(5, 1, 6, 0), hunk(6, 0, 8, 1)];
+    let staged_line = |change| {
+      Some(LineStatus {
+        change,
+        staged: true,
+      })
+    };
+    let unstaged_line = |change| {
+      Some(LineStatus {
+        change,
+        staged: false,
+      })
+    };
+    assert_eq!(
+      line_statuses(&staged, &unstaged),
+      vec![
+        None,
+        unstaged_line(LineChange::Added),
+        unstaged_line(LineChange::Added),
+        staged_line(LineChange::Modified),
+        staged_line(LineChange::Added),
+        unstaged_line(LineChange::Removed),
+        None,
+        unstaged_line(LineChange::Added),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_parse_blame_porcelain() {
+    let commit = "a".repeat(40);
+    let porcelain = format!(
+      "{commit} 1 1 2\nauthor Ada\nauthor-time 1700000000\nsummary init\n\tfn main() {{\n\
+       {commit} 2 2\n\t}}\n"
+    );
+    let line = BlameLine {
+      commit: "aaaaaaaa".to_string(),
+      author: "Ada".to_string(),
+      time: 1_700_000_000,
+    };
+    assert_eq!(parse_blame_porcelain(&porcelain), vec![line.clone(), line]);
+  }
+}
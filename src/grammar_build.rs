@@ -0,0 +1,216 @@
+//! `umber cache build --grammar`: compile a tree-sitter grammar from source
+//! and install it where `grammars` looks for runtime grammars. The parser is
+//! built with the C compiler the way nvim-treesitter builds its parsers, from
+//! a local checkout or a shallow clone of a git repository.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use eyre::{Result, eyre};
+
+/// A grammar to build: its language name and where to get it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GrammarSource {
+  pub name: String,
+  location: Location,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Location {
+  Directory(PathBuf),
+  Repository(String),
+}
+
+impl GrammarSource {
+  /// `[NAME=]SOURCE`, where SOURCE is a directory or a git URL. Without a
+  /// name, it is the last part of SOURCE without a `tree-sitter-` prefix or
+  /// `.git` suffix, so `https://github.com/gleam-lang/tree-sitter-gleam`
+  /// builds `gleam`.
+  pub fn parse(spec: &str) -> Result<Self> {
+    let (name, source) = match spec.split_once('=') {
+      Some((name, source)) if is_language_name(name) => (Some(name.to_string()), source),
+      _ => (None, spec),
+    };
+    let location = if is_repository(source) {
+      Location::Repository(source.to_string())
+    } else {
+      Location::Directory(PathBuf::from(source))
+    };
+    let name = match name {
+      Some(name) => name,
+      None => default_name(&location)
+        .ok_or_else(|| eyre!("cannot name the grammar in {source}; give a name as NAME=SOURCE"))?,
+    };
+    Ok(Self { name, location })
+  }
+
+  /// Build the parser and install it with its highlight query into
+  /// `grammars_dir/<name>/`, cloning repositories into `cache_dir` first.
+  /// Returns the highlight query.
+  pub fn install(&self, grammars_dir: &Path, cache_dir: &Path) -> Result<String> {
+    let source = match &self.location {
+      Location::Directory(dir) => dir.clone(),
+      Location::Repository(url) => clone(url, &cache_dir.join("sources").join(&self.name))?,
+    };
+    let highlights = find_highlights(&source, &self.name)
+      .ok_or_else(|| eyre!("{} has no queries/highlights.scm", source.display()))?;
+    let query = fs::read_to_string(&highlights)?;
+    let target = grammars_dir.join(&self.name);
+    fs::create_dir_all(&target)?;
+    // Built beside the old parser and renamed over it, so a running umber
+    // that has it open keeps a consistent file.
+    let library = format!("parser.{}", std::env::consts::DLL_EXTENSION);
+    let partial = target.join(format!("{library}.partial"));
+    compile(&source.join("src"), &partial)?;
+    fs::rename(&partial, target.join(&library))?;
+    fs::write(target.join("highlights.scm"), &query)?;
+    Ok(query)
+  }
+}
+
+/// Names as runtime grammars use them: the `tree_sitter_<name>` symbol needs
+/// letters, digits, `_` or `-`.
+fn is_language_name(name: &str) -> bool {
+  !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+fn is_repository(source: &str) -> bool {
+  source.contains("://") || source.starts_with("git@") || source.ends_with(".git")
+}
+
+fn default_name(location: &Location) -> Option<String> {
+  let last = match location {
+    Location::Repository(url) => url
+      .trim_end_matches('/')
+      .rsplit(['/', ':'])
+      .next()?
+      .to_string(),
+    Location::Directory(dir) => fs::canonicalize(dir)
+      .unwrap_or_else(|_| dir.clone())
+      .file_name()?
+      .to_str()?
+      .to_string(),
+  };
+  let last = last.strip_suffix(".git").unwrap_or(&last);
+  let name = last
+    .strip_prefix("tree-sitter-")
+    .unwrap_or(last)
+    .to_ascii_lowercase();
+  is_language_name(&name).then_some(name)
+}
+
+/// A fresh shallow clone of `url` in `dir`.
+fn clone(url: &str, dir: &Path) -> Result<PathBuf> {
+  if dir.exists() {
+    fs::remove_dir_all(dir)?;
+  }
+  let status = Command::new("git")
+    .args(["clone", "--quiet", "--depth", "1", url])
+    .arg(dir)
+    .status()
+    .map_err(|err| eyre!("Failed to run git clone: {err}"))?;
+  if !status.success() {
+    return Err(eyre!("git clone {url} failed with {status}"));
+  }
+  Ok(dir.to_path_buf())
+}
+
+/// The highlight query in the places grammar repositories keep it. Those
+/// with several grammars, such as tree-sitter-typescript, share the one in
+/// the parent directory.
+fn find_highlights(source: &Path, name: &str) -> Option<PathBuf> {
+  let parent = source
+    .parent()
+    .filter(|parent| !parent.as_os_str().is_empty());
+  [
+    Some(source.join("queries").join("highlights.scm")),
+    Some(source.join("queries").join(name).join("highlights.scm")),
+    parent.map(|parent| parent.join("queries").join("highlights.scm")),
+  ]
+  .into_iter()
+  .flatten()
+  .find(|path| path.is_file())
+}
+
+/// Compile `parser.c` and the external scanner, if any, in `src` into the
+/// shared library `out`. `$CC` picks the compiler; a C++ scanner needs `$CXX`
+/// or `c++`.
+fn compile(src: &Path, out: &Path) -> Result<()> {
+  let parser = src.join("parser.c");
+  if !parser.is_file() {
+    return Err(eyre!("{} not found", parser.display()));
+  }
+  let cpp_scanner = src.join("scanner.cc");
+  let cpp = cpp_scanner.is_file();
+  let (compiler, scanner) = if cpp {
+    (
+      std::env::var("CXX").unwrap_or_else(|_| "c++".to_string()),
+      Some(cpp_scanner),
+    )
+  } else {
+    let scanner = src.join("scanner.c");
+    (
+      std::env::var("CC").unwrap_or_else(|_| "cc".to_string()),
+      scanner.is_file().then_some(scanner),
+    )
+  };
+  let mut command = Command::new(&compiler);
+  command
+    .args(["-shared", "-fPIC", "-Os", "-I"])
+    .arg(src)
+    .arg("-o")
+    .arg(out);
+  if cpp {
+    // parser.c is still C when the driver is a C++ compiler.
+    command.args(["-x", "c"]).arg(&parser).args(["-x", "c++"]);
+  } else {
+    command.arg(&parser);
+  }
+  command.args(scanner);
+  let status = command
+    .status()
+    .map_err(|err| eyre!("Failed to run {compiler}: {err}"))?;
+  if !status.success() {
+    return Err(eyre!("{compiler} failed with {status}"));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_grammar_source() {
+    let source =
+      GrammarSource::parse("https://github.com/gleam-lang/tree-sitter-gleam.git").unwrap();
+    assert_eq!(source.name, "gleam");
+    assert_eq!(
+      source.location,
+      Location::Repository("https://github.com/gleam-lang/tree-sitter-gleam.git".to_string())
+    );
+    assert_eq!(
+      GrammarSource::parse("git@github.com:tree-sitter/tree-sitter-Ruby/")
+        .unwrap()
+        .name,
+      "ruby"
+    );
+    let source = GrammarSource::parse("tsx=vendor/tree-sitter-typescript/tsx").unwrap();
+    assert_eq!(source.name, "tsx");
+    assert_eq!(
+      source.location,
+      Location::Directory(PathBuf::from("vendor/tree-sitter-typescript/tsx"))
+    );
+    // Not a name, so the whole argument is the source.
+    assert_eq!(
+      GrammarSource::parse("https://example.com/x?a=b/tree-sitter-foo")
+        .unwrap()
+        .name,
+      "foo"
+    );
+  }
+}
@@ -0,0 +1,149 @@
+//! Tree-sitter grammars loaded at runtime, so niche languages can be added
+//! without rebuilding umber. Each is a directory of
+//! `~/.config/umber/grammars/` named after the language, holding the compiled
+//! parser as `parser.so` (`parser.dylib` on macOS, `parser.dll` on Windows)
+//! and its `highlights.scm`. The parser must export `tree_sitter_<name>`, with
+//! dashes in the name written as underscores, as `tree-sitter generate` does.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::OnceCell;
+use syntastica::language_set::HighlightConfiguration;
+use syntastica::theme::THEME_KEYS;
+use tree_sitter_language::LanguageFn;
+
+use crate::custom_langs::preprocessed_highlights;
+
+/// A grammar directory. Its library is opened the first time the language is
+/// highlighted, so unused grammars cost a directory listing at startup.
+pub struct Grammar {
+  /// The directory name, lowercased
+  pub name: String,
+  dir: PathBuf,
+  config: OnceCell<HighlightConfiguration>,
+}
+
+static GRAMMARS: OnceCell<Vec<Grammar>> = OnceCell::new();
+
+/// Make the grammars in `dir` available to every language set. Only the
+/// first call has an effect; a missing directory registers none.
+pub fn register(dir: &Path) {
+  GRAMMARS.get_or_init(|| scan(dir));
+}
+
+/// The registered grammars, sorted by name.
+pub fn all() -> &'static [Grammar] {
+  GRAMMARS.get().map_or(&[], Vec::as_slice)
+}
+
+/// Index in `all()` of the grammar called `name`.
+pub fn find(name: &str) -> Option<usize> {
+  all().iter().position(|grammar| grammar.name == name)
+}
+
+/// Subdirectories of `dir` with a `highlights.scm`.
+fn scan(dir: &Path) -> Vec<Grammar> {
+  let Ok(entries) = fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let mut grammars: Vec<_> = entries
+    .filter_map(|entry| entry.ok())
+    .map(|entry| entry.path())
+    .filter(|dir| dir.join("highlights.scm").is_file())
+    .filter_map(|dir| {
+      let name = dir.file_name()?.to_str()?.to_ascii_lowercase();
+      Some(Grammar {
+        name,
+        dir,
+        config: OnceCell::new(),
+      })
+    })
+    .collect();
+  grammars.sort_by(|a, b| a.name.cmp(&b.name));
+  grammars
+}
+
+impl Grammar {
+  /// The highlight query as installed, before preprocessing.
+  pub fn highlights_source(&self) -> Option<String> {
+    fs::read_to_string(self.dir.join("highlights.scm")).ok()
+  }
+
+  /// The highlight configuration, loading the parser on first use. The
+  /// query comes preprocessed from `cache_dir` when `umber cache build` has
+  /// put it there.
+  pub fn config(&self, cache_dir: Option<&Path>) -> syntastica::Result<&HighlightConfiguration> {
+    self.config.get_or_try_init(|| {
+      let query = self.highlights_source().ok_or_else(|| {
+        failure(format!(
+          "{}: cannot read highlights.scm",
+          self.dir.display()
+        ))
+      })?;
+      let mut conf = HighlightConfiguration::new(
+        self.load()?.into(),
+        &self.name,
+        &preprocessed_highlights(&self.name, &query, cache_dir),
+        "",
+        "",
+      )?;
+      conf.configure(THEME_KEYS);
+      Ok(conf)
+    })
+  }
+
+  /// The language function exported by the parser library.
+  #[cfg(any(unix, windows))]
+  fn load(&self) -> syntastica::Result<LanguageFn> {
+    let path = self
+      .dir
+      .join(format!("parser.{}", std::env::consts::DLL_EXTENSION));
+    let symbol = format!("tree_sitter_{}", self.name.replace('-', "_"));
+    // SAFETY: opening the library runs its initializers and the symbol is
+    // taken to be a tree-sitter language function; both come from a grammar
+    // the user installed, which is trusted like any other program they run.
+    unsafe {
+      let library = libloading::Library::new(&path)
+        .map_err(|err| failure(format!("{}: {err}", path.display())))?;
+      let function = *library
+        .get::<unsafe extern "C" fn() -> *const ()>(symbol.as_bytes())
+        .map_err(|err| failure(format!("{}: {err}", path.display())))?;
+      // The parse tables live in the library, and the configuration using
+      // them lasts as long as the process, so the library is never closed.
+      std::mem::forget(library);
+      Ok(LanguageFn::from_raw(function))
+    }
+  }
+
+  #[cfg(not(any(unix, windows)))]
+  fn load(&self) -> syntastica::Result<LanguageFn> {
+    Err(failure(format!(
+      "{}: grammars cannot be loaded on this platform",
+      self.name
+    )))
+  }
+}
+
+fn failure(message: String) -> syntastica::Error {
+  syntastica::Error::Custom(message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_scan() {
+    let dir = std::env::temp_dir().join(format!("umber-grammars-{}", std::process::id()));
+    fs::create_dir_all(dir.join("Zig")).unwrap();
+    fs::create_dir_all(dir.join("awk")).unwrap();
+    fs::create_dir_all(dir.join("incomplete")).unwrap();
+    fs::write(dir.join("Zig/highlights.scm"), "").unwrap();
+    fs::write(dir.join("awk/highlights.scm"), "").unwrap();
+    let names: Vec<_> = scan(&dir).into_iter().map(|grammar| grammar.name).collect();
+    assert_eq!(names, ["awk", "zig"]);
+    assert!(scan(&dir.join("missing")).is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,170 @@
+//! `--header-format`: what the header between files says. Either a preset or
+//! a template with `{placeholder}` fields such as `{path} ({lang}, {size})`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{decorations, eol};
+
+/// How the header is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+  /// Centered between two rules spanning the terminal
+  Banner,
+  /// A line of its own, exactly as the template says
+  Line,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderFormat {
+  pub layout: Layout,
+  pub template: String,
+}
+
+impl Default for HeaderFormat {
+  fn default() -> Self {
+    Self::parse("banner")
+  }
+}
+
+impl HeaderFormat {
+  /// A preset by name (`banner`, `detailed` or `title`), or else a template
+  /// shown on a line of its own.
+  pub fn parse(raw: &str) -> Self {
+    let (layout, template) = match raw {
+      "banner" => (Layout::Banner, "{path}"),
+      "detailed" => (Layout::Banner, "{path} ({lang}, {size})"),
+      "title" => (Layout::Line, "==> {path} <=="),
+      template => (Layout::Line, template),
+    };
+    Self {
+      layout,
+      template: template.to_string(),
+    }
+  }
+
+  /// True if the template has a `{field}` placeholder, so fields that are
+  /// slow to look up are only looked up when shown.
+  pub fn uses(&self, field: &str) -> bool {
+    self.template.contains(&format!("{{{field}}}"))
+  }
+}
+
+/// Values for the placeholders; `None` is shown as `-`.
+#[derive(Debug, Default)]
+pub struct Fields {
+  pub path: String,
+  pub basename: String,
+  pub lang: Option<String>,
+  pub size: Option<u64>,
+  pub mtime: Option<SystemTime>,
+  pub branch: Option<String>,
+  pub eol: Option<eol::Counts>,
+}
+
+impl Fields {
+  /// The value for a placeholder, or `None` for an unknown one.
+  fn get(&self, name: &str) -> Option<String> {
+    let missing = || "-".to_string();
+    Some(match name {
+      "path" => self.path.clone(),
+      "basename" => self.basename.clone(),
+      "lang" => self.lang.clone().unwrap_or_else(missing),
+      "size" => self.size.map_or_else(missing, format_size),
+      "mtime" => self.mtime.map_or_else(missing, format_time),
+      "branch" => self.branch.clone().unwrap_or_else(missing),
+      "eol" => self
+        .eol
+        .and_then(eol::Counts::describe)
+        .unwrap_or_else(missing),
+      _ => return None,
+    })
+  }
+}
+
+/// Fill in the placeholders of `template`. Unknown ones are left as written.
+pub fn expand(template: &str, fields: &Fields) -> String {
+  let mut out = String::with_capacity(template.len());
+  let mut rest = template;
+  while let Some(open) = rest.find('{') {
+    out.push_str(&rest[..open]);
+    let after = &rest[open + 1..];
+    match after
+      .find('}')
+      .and_then(|close| Some((close, fields.get(&after[..close])?)))
+    {
+      Some((close, value)) => {
+        out.push_str(&value);
+        rest = &after[close + 1..];
+      }
+      None => {
+        out.push('{');
+        rest = after;
+      }
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+/// A size such as `512 B` or `1.5 KiB`.
+pub fn format_size(bytes: u64) -> String {
+  const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+  if bytes < 1024 {
+    return format!("{bytes} B");
+  }
+  let mut size = bytes as f64 / 1024.0;
+  let mut unit = 0;
+  while size >= 1024.0 && unit < UNITS.len() - 1 {
+    size /= 1024.0;
+    unit += 1;
+  }
+  format!("{size:.1} {}", UNITS[unit])
+}
+
+/// A time such as `2024-05-01 12:00 UTC`.
+fn format_time(time: SystemTime) -> String {
+  let seconds = match time.duration_since(UNIX_EPOCH) {
+    Ok(elapsed) => elapsed.as_secs() as i64,
+    Err(err) => -(err.duration().as_secs() as i64),
+  };
+  let minutes = seconds.rem_euclid(86_400) / 60;
+  format!(
+    "{} {:02}:{:02} UTC",
+    decorations::format_date(seconds),
+    minutes / 60,
+    minutes % 60
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  #[test]
+  fn test_expand() {
+    let fields = Fields {
+      path: "src/main.rs".to_string(),
+      lang: Some("rust".to_string()),
+      size: Some(1536),
+      ..Fields::default()
+    };
+    assert_eq!(
+      expand("{path} ({lang}, {size})", &fields),
+      "src/main.rs (rust, 1.5 KiB)"
+    );
+    assert_eq!(expand("{branch} {unknown} {", &fields), "- {unknown} {");
+    let fields = Fields {
+      eol: Some(eol::Counts { lf: 2, crlf: 1 }),
+      ..fields
+    };
+    assert_eq!(expand("{eol}", &fields), "mixed (1 CRLF, 2 LF)");
+  }
+
+  #[test]
+  fn test_format_time() {
+    let time = UNIX_EPOCH + Duration::from_secs(1_714_564_800);
+    assert_eq!(format_time(time), "2024-05-01 12:00 UTC");
+    assert_eq!(format_time(UNIX_EPOCH), "1970-01-01 00:00 UTC");
+  }
+}
@@ -0,0 +1,246 @@
+//! `-x/--hex`: any input as a hex dump, an offset column, the bytes in groups
+//! of eight and the printable characters beside them. Bytes are colored by
+//! class, so runs of zeros, text and binary data stand apart at a glance.
+
+use std::io::{self, Read, Write};
+
+use syntastica::renderer::Renderer;
+use syntastica::style::{Color, Style};
+use syntastica::theme::ResolvedTheme;
+
+use crate::color_depth::DepthRenderer;
+use crate::decorations::get_dim_style_or_create;
+use crate::unprintable::CharStyle;
+
+/// Bytes in a group; groups are separated by an extra space.
+const GROUP: usize = 8;
+/// Columns a row of 16 bytes takes, the width of `hexdump -C`.
+const WIDE_ROW_COLUMNS: usize = 78;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteClass {
+  Null,
+  Printable,
+  Whitespace,
+  Control,
+  High,
+}
+
+impl ByteClass {
+  fn of(byte: u8) -> Self {
+    match byte {
+      0 => Self::Null,
+      b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c => Self::Whitespace,
+      0x21..=0x7e => Self::Printable,
+      0x01..=0x1f | 0x7f => Self::Control,
+      _ => Self::High,
+    }
+  }
+
+  /// The character shown for `byte` in the printable pane.
+  fn symbol(self, byte: u8, char_style: CharStyle) -> char {
+    match (self, char_style) {
+      (Self::Printable, _) => byte as char,
+      (_, _) if byte == b' ' => ' ',
+      (Self::Null, CharStyle::Unicode) => '⋄',
+      (Self::Whitespace, CharStyle::Unicode) => '_',
+      (Self::Control, CharStyle::Unicode) => '•',
+      (Self::High, CharStyle::Unicode) => '×',
+      (_, CharStyle::Caret) => '.',
+    }
+  }
+
+  /// Theme keys tried for the class's color, and the color used when the
+  /// theme has none of them. Zeros take the dim line number style.
+  fn style(self, theme: &ResolvedTheme) -> Style {
+    let (keys, fallback): (&[&str], Color) = match self {
+      Self::Null => return get_dim_style_or_create(theme),
+      Self::Printable => (&["string"], Color::new(86, 182, 194)), // Cyan
+      Self::Whitespace => (&["operator", "punctuation"], Color::new(152, 195, 121)), // Green
+      Self::Control => (&["number", "constant"], Color::new(198, 120, 221)), // Magenta
+      Self::High => (&["keyword"], Color::new(229, 192, 123)),    // Yellow
+    };
+    let color = keys
+      .iter()
+      .copied()
+      .find_map(|key| theme.get(key))
+      .map(|style| style.color())
+      .unwrap_or(fallback);
+    Style::new(color, None, false, false, false, false)
+  }
+}
+
+const CLASSES: [ByteClass; 5] = [
+  ByteClass::Null,
+  ByteClass::Printable,
+  ByteClass::Whitespace,
+  ByteClass::Control,
+  ByteClass::High,
+];
+
+/// How to lay out and color a dump.
+pub struct HexDump {
+  /// Styles of the offset column and of each class, in `CLASSES` order;
+  /// `None` for plain output.
+  styles: Option<(Style, [Style; 5])>,
+  char_style: CharStyle,
+  bytes_per_row: usize,
+}
+
+impl HexDump {
+  /// A dump fitting `width` columns: 16 bytes a row, or 8 when that is too
+  /// wide. Colors come from `theme` when given.
+  pub fn new(theme: Option<&ResolvedTheme>, char_style: CharStyle, width: usize) -> Self {
+    Self {
+      styles: theme.map(|theme| {
+        (
+          get_dim_style_or_create(theme),
+          CLASSES.map(|class| class.style(theme)),
+        )
+      }),
+      char_style,
+      bytes_per_row: if width >= WIDE_ROW_COLUMNS {
+        2 * GROUP
+      } else {
+        GROUP
+      },
+    }
+  }
+
+  fn push(&self, out: &mut String, renderer: &mut DepthRenderer, text: &str, style: Option<Style>) {
+    match style {
+      Some(style) => out.push_str(&renderer.styled(text, style)),
+      None => out.push_str(text),
+    }
+  }
+
+  /// One row of output for `bytes`, which start at `offset`.
+  fn row(&self, offset: u64, bytes: &[u8], renderer: &mut DepthRenderer) -> String {
+    let class_style = |class: ByteClass| self.styles.map(|(_, styles)| styles[class as usize]);
+    let separator = match self.char_style {
+      CharStyle::Unicode => "│",
+      CharStyle::Caret => "|",
+    };
+    let mut out = String::with_capacity(self.bytes_per_row * 8);
+    self.push(
+      &mut out,
+      renderer,
+      &format!("{offset:08x}"),
+      self.styles.map(|(offset, _)| offset),
+    );
+    out.push(' ');
+    for index in 0..self.bytes_per_row {
+      if index % GROUP == 0 {
+        out.push(' ');
+      }
+      match bytes.get(index) {
+        Some(&byte) => {
+          let style = class_style(ByteClass::of(byte));
+          self.push(&mut out, renderer, &format!("{byte:02x}"), style);
+          out.push(' ');
+        }
+        None => out.push_str("   "),
+      }
+    }
+    out.push(' ');
+    out.push_str(separator);
+    for index in 0..self.bytes_per_row {
+      match bytes.get(index) {
+        Some(&byte) => {
+          let class = ByteClass::of(byte);
+          let symbol = class.symbol(byte, self.char_style).to_string();
+          self.push(&mut out, renderer, &symbol, class_style(class));
+        }
+        None => out.push(' '),
+      }
+    }
+    out.push_str(separator);
+    out.push('\n');
+    out
+  }
+
+  /// Dump everything `input` yields, a row at a time, so inputs of any size
+  /// and pipes that are still open are shown as they arrive.
+  pub fn write(
+    &self,
+    out: &mut impl Write,
+    input: &mut impl Read,
+    renderer: &mut DepthRenderer,
+  ) -> io::Result<()> {
+    let mut buf = vec![0; self.bytes_per_row];
+    let mut offset = 0u64;
+    loop {
+      let filled = fill(input, &mut buf)?;
+      if filled == 0 {
+        return Ok(());
+      }
+      out.write_all(self.row(offset, &buf[..filled], renderer).as_bytes())?;
+      offset += filled as u64;
+      if filled < buf.len() {
+        return Ok(());
+      }
+    }
+  }
+}
+
+/// Read until `buf` is full or the input ends; the number of bytes read.
+fn fill(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+  let mut filled = 0;
+  while filled < buf.len() {
+    match input.read(&mut buf[filled..]) {
+      Ok(0) => break,
+      Ok(read) => filled += read,
+      Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+      Err(err) => return Err(err),
+    }
+  }
+  Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::color_depth::ColorDepth;
+
+  fn dump(dump: &HexDump, input: &[u8]) -> String {
+    let mut out = Vec::new();
+    let mut renderer = DepthRenderer::new(ColorDepth::Truecolor);
+    dump
+      .write(&mut out, &mut &input[..], &mut renderer)
+      .unwrap();
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn test_plain_rows() {
+    let hex = HexDump::new(None, CharStyle::Unicode, 80);
+    let input = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0hi there\n\xff";
+    assert_eq!(
+      dump(&hex, input),
+      "00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  │•ELF•••⋄⋄⋄⋄⋄⋄⋄⋄⋄│\n\
+       00000010  68 69 20 74 68 65 72 65  0a ff                    │hi there_×      │\n"
+    );
+    assert_eq!(dump(&hex, b""), "");
+  }
+
+  #[test]
+  fn test_narrow_ascii() {
+    let hex = HexDump::new(None, CharStyle::Caret, 60);
+    assert_eq!(
+      dump(&hex, b"\0a b\t"),
+      "00000000  00 61 20 62 09           |.a b.   |\n"
+    );
+  }
+
+  #[test]
+  fn test_byte_classes() {
+    assert_eq!(ByteClass::of(0), ByteClass::Null);
+    assert_eq!(ByteClass::of(b'~'), ByteClass::Printable);
+    assert_eq!(ByteClass::of(b'\r'), ByteClass::Whitespace);
+    assert_eq!(ByteClass::of(0x1b), ByteClass::Control);
+    assert_eq!(ByteClass::of(0x80), ByteClass::High);
+    for (index, class) in CLASSES.into_iter().enumerate() {
+      assert_eq!(class as usize, index);
+    }
+  }
+}
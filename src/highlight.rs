@@ -0,0 +1,286 @@
+//! Highlighting shared by the command and the library: language lookup,
+//! highlight events and their split into styled lines, and `highlight`, the
+//! library's entry point from source text to ANSI or HTML.
+
+use eyre::{Result, eyre};
+use syntastica::language_set::{
+  EitherLang, HighlightConfiguration, LanguageSet, SupportedLanguage, Union,
+};
+use syntastica::renderer::Renderer;
+use syntastica::theme::{ResolvedTheme, THEME_KEYS};
+use syntastica_highlight::{Highlight, HighlightEvent, Highlighter};
+use syntastica_parsers_git::{LANGUAGE_NAMES, Lang, LanguageSetImpl};
+
+use crate::color_depth::{self, ColorDepth, DepthRenderer};
+use crate::custom_langs::{CustomLang, CustomLanguageSet};
+use crate::decorations::get_dim_style_or_create;
+use crate::diff::HighlightedLine;
+use crate::html;
+use crate::listing::Listing;
+use crate::log_tokens;
+
+/// umber's own grammars, then syntastica's.
+pub type Languages = Union<CustomLanguageSet, LanguageSetImpl>;
+pub type Language = EitherLang<CustomLang, Lang>;
+
+/// What `highlight` produces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+  /// Terminal escape sequences
+  #[default]
+  Ansi,
+  /// A `<pre>` block styled inline, as `--format html` writes
+  Html,
+  /// A standalone page, as `--format html-full` writes
+  HtmlFull,
+}
+
+/// How `highlight` renders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+  pub format: Format,
+  /// Number the lines, as `--style=numbers` does
+  pub line_numbers: bool,
+  /// Palette for `Format::Ansi`; `Auto` is truecolor
+  pub color_depth: ColorDepth,
+}
+
+/// `source` highlighted as `language`, a name such as `rust` or an alias
+/// such as `xml`, in `theme`, one of the built-in themes.
+pub fn highlight(source: &str, language: &str, theme: &str, options: &Options) -> Result<String> {
+  let languages = Languages::new(CustomLanguageSet::new(None), LanguageSetImpl::new());
+  let language = resolve_language_union(language, &languages)
+    .ok_or_else(|| eyre!("unknown language '{language}'"))?;
+  let theme = builtin_theme(theme).ok_or_else(|| eyre!("unknown theme '{theme}'"))?;
+  let lines = highlighted_lines(source, language, &languages)?;
+  Ok(render(vec![(String::new(), lines)], &theme, options))
+}
+
+/// `source` split into lines of highlighted pieces. A final newline ends the
+/// last line rather than starting another.
+pub(crate) fn highlighted_lines(
+  source: &str,
+  language: Language,
+  languages: &Languages,
+) -> Result<Vec<HighlightedLine>> {
+  let config = languages
+    .get_language(language)
+    .map_err(|err| eyre!("failed to load {}: {err}", language_name(&language)))?;
+  let mut highlighter = Highlighter::new();
+  let events = highlight_events(&mut highlighter, config, source, languages, true);
+  let mut lines = split_lines(source, events)
+    .ok_or_else(|| eyre!("failed to highlight {}", language_name(&language)))?;
+  if source.ends_with('\n') {
+    lines.pop();
+  }
+  Ok(lines)
+}
+
+/// `source` split into lines without any styles, for text in no known
+/// language.
+pub(crate) fn plain_lines(source: &str) -> Vec<HighlightedLine> {
+  source
+    .lines()
+    .map(|line| match line {
+      "" => Vec::new(),
+      line => vec![(line.to_string(), None)],
+    })
+    .collect()
+}
+
+/// Named, highlighted sources in `options.format`: one after another for the
+/// terminal, or as listings of one HTML document.
+pub(crate) fn render(
+  sources: Vec<(String, Vec<HighlightedLine>)>,
+  theme: &ResolvedTheme,
+  options: &Options,
+) -> String {
+  match options.format {
+    Format::Ansi => sources
+      .iter()
+      .map(|(_, lines)| render_ansi(lines, theme, options))
+      .collect(),
+    Format::Html | Format::HtmlFull => {
+      let listings: Vec<_> = sources
+        .into_iter()
+        .map(|(name, lines)| Listing {
+          name,
+          lines: (1..).zip(lines).collect(),
+          theme,
+          numbers: options.line_numbers,
+        })
+        .collect();
+      html::render(&listings, options.format == Format::HtmlFull, theme)
+    }
+  }
+}
+
+/// A theme that ships with umber: `ansi` or one of syntastica's, such as
+/// `catppuccin::mocha`.
+pub fn builtin_theme(name: &str) -> Option<ResolvedTheme> {
+  if name == "ansi" {
+    return Some(color_depth::ansi_theme());
+  }
+  syntastica_themes::from_str(name)
+}
+
+/// `lines` as terminal escapes, each ending in a newline.
+fn render_ansi(lines: &[HighlightedLine], theme: &ResolvedTheme, options: &Options) -> String {
+  let mut renderer = DepthRenderer::new(options.color_depth);
+  let dim = get_dim_style_or_create(theme);
+  let width = lines.len().to_string().len();
+  let mut out = String::new();
+  for (line_no, pieces) in (1..).zip(lines) {
+    if options.line_numbers {
+      out.push_str(&renderer.styled(&format!("{line_no:>width$} "), dim));
+    }
+    for (text, style_key) in pieces {
+      match style_key.and_then(|key| theme.find_style(key)) {
+        Some(style) => out.push_str(&renderer.styled(text, style)),
+        None => out.push_str(&renderer.unstyled(text)),
+      }
+    }
+    out.push('\n');
+  }
+  out
+}
+
+/// The language named `name`, an alias such as `xml`, or an injection name
+/// such as `js`, case-insensitively.
+pub fn resolve_language_union(name: impl AsRef<str>, language_set: &Languages) -> Option<Language> {
+  let name = name.as_ref().trim();
+  let normalized = name.to_ascii_lowercase();
+
+  // First check if it's a custom language (HCL or Terraform)
+  if let Ok(custom_lang) =
+    <CustomLang as SupportedLanguage<'_, _>>::for_name(&normalized, language_set)
+  {
+    return Some(EitherLang::Left(custom_lang));
+  }
+
+  // Then try the syntastica parsers with aliases
+  let name = match normalized.as_str() {
+    "xml" | "xhtml" | "svg" | "plist" => "html",
+    _ => normalized.as_str(),
+  };
+
+  // Try as a normal language
+  if let Ok(lang) = <Lang as SupportedLanguage<'_, _>>::for_name(name, language_set) {
+    return Some(EitherLang::Right(lang));
+  }
+
+  // Try as an injection language
+  if let Some(lang) = <Lang as SupportedLanguage<'_, _>>::for_injection(name, language_set) {
+    return Some(EitherLang::Right(lang));
+  }
+
+  // Try with canonical names
+  if let Some(canonical) = LANGUAGE_NAMES
+    .iter()
+    .copied()
+    .find(|candidate| candidate.eq_ignore_ascii_case(name))
+    && let Ok(lang) = <Lang as SupportedLanguage<'_, _>>::for_name(canonical, language_set)
+  {
+    return Some(EitherLang::Right(lang));
+  }
+
+  None
+}
+
+/// Canonical lowercase name of a resolved language.
+pub fn language_name(language: &Language) -> &str {
+  match language {
+    EitherLang::Left(custom) => custom.as_ref(),
+    EitherLang::Right(lang) => lang.as_ref(),
+  }
+}
+
+pub type HighlightItem = std::result::Result<HighlightEvent, syntastica_highlight::Error>;
+
+/// Highlight events for `text`. A highlighter that fails to start yields a
+/// single error so callers can fall back to plain output.
+pub fn highlight_events<'a>(
+  highlighter: &'a mut Highlighter,
+  highlight_config: &'a HighlightConfiguration,
+  text: &'a str,
+  language_set: &'a Languages,
+  highlight_injections: bool,
+) -> impl Iterator<Item = HighlightItem> + 'a {
+  // `log` has no grammar to parse with.
+  let tokens = log_tokens::is_config(highlight_config).then(|| log_tokens::events(text));
+  let events = tokens.is_none().then(|| {
+    highlighter.highlight(
+      highlight_config,
+      text.as_bytes(),
+      None,
+      move |lang_name: &str| {
+        if !highlight_injections {
+          return None;
+        }
+
+        let lang_name = lang_name.to_ascii_lowercase();
+        EitherLang::<CustomLang, Lang>::for_name(&lang_name, language_set)
+          .ok()
+          .or_else(|| EitherLang::<CustomLang, Lang>::for_injection(&lang_name, language_set))
+          .or_else(|| {
+            lang_name.rsplit_once('/').and_then(|(_, name)| {
+              EitherLang::<CustomLang, Lang>::for_name(name, language_set)
+                .ok()
+                .or_else(|| EitherLang::<CustomLang, Lang>::for_injection(name, language_set))
+            })
+          })
+          .and_then(|lang| language_set.get_language(lang).ok())
+      },
+    )
+  });
+  let (events, error) = match events {
+    Some(Ok(events)) => (Some(events), None),
+    Some(Err(err)) => (None, Some(Err(err))),
+    None => (None, None),
+  };
+  tokens
+    .into_iter()
+    .flatten()
+    .chain(events.into_iter().flatten())
+    .chain(error)
+}
+
+/// `events` for `text` split into lines of `(text, style key)` pieces, with
+/// one more line after a final newline. `None` when highlighting failed.
+pub fn split_lines(
+  text: &str,
+  events: impl IntoIterator<Item = HighlightItem>,
+) -> Option<Vec<HighlightedLine>> {
+  let mut lines = vec![Vec::new()];
+  let mut style_stack = Vec::new();
+  for event in events {
+    match event.ok()? {
+      HighlightEvent::HighlightStart(Highlight(highlight)) => style_stack.push(highlight),
+      HighlightEvent::HighlightEnd => {
+        style_stack.pop();
+      }
+      HighlightEvent::Source { start, end } => {
+        let style_key = current_style_key(&style_stack);
+        for (index, part) in text[start..end].split('\n').enumerate() {
+          if index > 0 {
+            lines.push(Vec::new());
+          }
+          if !part.is_empty()
+            && let Some(line) = lines.last_mut()
+          {
+            line.push((part.to_string(), style_key));
+          }
+        }
+      }
+    }
+  }
+  Some(lines)
+}
+
+/// The theme key of the innermost highlight, or `None` for unstyled text.
+pub fn current_style_key(style_stack: &[usize]) -> Option<&'static str> {
+  style_stack
+    .last()
+    .and_then(|idx| THEME_KEYS.get(*idx).copied())
+    .and_then(|key| (key != "none").then_some(key))
+}
@@ -0,0 +1,181 @@
+//! `--format html` and `--format html-full`: highlighted code as HTML, for
+//! static sites and snippets. Pieces are styled inline by syntastica's HTML
+//! renderer, so a fragment looks the same wherever it is pasted; the full page
+//! only adds a stylesheet for the page around it.
+
+use syntastica::renderer::{HtmlRenderer, Renderer};
+use syntastica::style::{Color, Style};
+use syntastica::theme::ResolvedTheme;
+
+use crate::decorations::get_dim_style_or_create;
+use crate::listing::Listing;
+
+/// Monospace fonts used by `html-full`, as a CSS `font-family`.
+const FONT_FAMILY: &str = "ui-monospace, SFMono-Regular, Menlo, Consolas, monospace";
+
+fn css_color(color: Color) -> String {
+  format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+}
+
+/// Text color and background of the theme's `_normal` style as CSS.
+fn normal_css(theme: &ResolvedTheme) -> String {
+  let Some(normal) = theme.get("_normal") else {
+    return String::new();
+  };
+  let mut css = format!("color:{};", css_color(normal.color()));
+  if let Some(bg) = normal.bg() {
+    css.push_str(&format!("background-color:{};", css_color(bg)));
+  }
+  css
+}
+
+fn push_styled(output: &mut String, renderer: &mut HtmlRenderer, text: &str, style: Option<Style>) {
+  let escaped = renderer.escape(text);
+  match style {
+    Some(style) => output.push_str(&renderer.styled(&escaped, style)),
+    None => output.push_str(&escaped),
+  }
+}
+
+/// One file as a `<pre>` block. When line numbers are shown, each number is a `<span>` with the id `{id_prefix}L{number}`, so that
+/// `page.html#L12` links to line 12.
+fn listing(listing: &Listing<'_>, id_prefix: &str) -> String {
+  let theme = listing.theme;
+  let mut renderer = HtmlRenderer::new();
+  let dim_style = get_dim_style_or_create(theme);
+  let width = listing.number_width();
+  let normal = normal_css(theme);
+  let mut output = if normal.is_empty() {
+    "<pre class=\"umber\"><code>".to_string()
+  } else {
+    format!("<pre class=\"umber\" style=\"{normal}\"><code>")
+  };
+  for (line_no, pieces) in &listing.lines {
+    if listing.numbers {
+      output.push_str(&format!(
+        "<span class=\"line-number\" id=\"{id_prefix}L{line_no}\">"
+      ));
+      push_styled(
+        &mut output,
+        &mut renderer,
+        &format!("{line_no:>width$} "),
+        Some(dim_style),
+      );
+      output.push_str("</span>");
+    }
+    for (text, style_key) in pieces {
+      let style = style_key.and_then(|key| theme.find_style(key));
+      push_styled(&mut output, &mut renderer, text, style);
+    }
+    output.push('\n');
+  }
+  output.push_str("</code></pre>\n");
+  output
+}
+
+/// Every listing, as fragments or, with `full`, as a page with a heading above
+/// each file when there are several. Line anchors get a per-file prefix when
+/// there are several files, so that they stay unique.
+pub fn render(listings: &[Listing<'_>], full: bool, theme: &ResolvedTheme) -> String {
+  let multiple = listings.len() > 1;
+  let mut body = String::new();
+  for (index, item) in listings.iter().enumerate() {
+    let id_prefix = if multiple {
+      format!("f{}-", index + 1)
+    } else {
+      String::new()
+    };
+    if full && multiple {
+      body.push_str(&file_heading(&item.name));
+    }
+    body.push_str(&listing(item, &id_prefix));
+  }
+  if !full {
+    return body;
+  }
+  let title = match listings {
+    [listing] => listing.name.as_str(),
+    _ => "umber",
+  };
+  document(title, &body, theme)
+}
+
+/// A standalone page around `body`, one or more `listing`s, with the theme's
+/// background behind them.
+fn document(title: &str, body: &str, theme: &ResolvedTheme) -> String {
+  let mut renderer = HtmlRenderer::new();
+  let title = renderer.escape(title);
+  let normal = normal_css(theme);
+  format!(
+    "<!DOCTYPE html>\n\
+     <html>\n\
+     <head>\n\
+     <meta charset=\"utf-8\">\n\
+     <title>{title}</title>\n\
+     <style>\n\
+     body {{ margin: 0; padding: 1em; {normal} }}\n\
+     h2.umber-file {{ margin: 1em 0 0.5em; font: bold 1em {FONT_FAMILY}; }}\n\
+     pre.umber {{ margin: 0; padding: 1em 0; overflow-x: auto; font-family: {FONT_FAMILY}; line-height: 1.4; }}\n\
+     pre.umber .line-number {{ user-select: none; }}\n\
+     pre.umber .line-number:target {{ font-weight: bold; }}\n\
+     </style>\n\
+     </head>\n\
+     <body>\n\
+     {body}\
+     </body>\n\
+     </html>\n"
+  )
+}
+
+/// The heading `html-full` puts above each file when there are several.
+fn file_heading(name: &str) -> String {
+  let mut renderer = HtmlRenderer::new();
+  format!("<h2 class=\"umber-file\">{}</h2>\n", renderer.escape(name))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::BTreeMap;
+
+  fn lines(theme: &ResolvedTheme, numbers: bool) -> Listing<'_> {
+    Listing {
+      name: "main.rs".to_string(),
+      lines: vec![
+        (9, vec![("nine".to_string(), None)]),
+        (10, vec![("ten".to_string(), None)]),
+      ],
+      theme,
+      numbers,
+    }
+  }
+
+  #[test]
+  fn test_listing_numbers() {
+    let theme = ResolvedTheme::new(BTreeMap::new());
+    let html = listing(&lines(&theme, true), "");
+    assert!(html.starts_with("<pre class=\"umber\""));
+    assert!(html.contains("<span class=\"line-number\" id=\"L9\">"));
+    assert!(html.contains("id=\"L10\""));
+    assert!(html.contains(" 9 "));
+    assert_eq!(html.matches('\n').count(), 3);
+
+    let html = listing(&lines(&theme, false), "f2-");
+    assert!(!html.contains("line-number"));
+    assert!(html.contains("nine\nten\n"));
+  }
+
+  #[test]
+  fn test_render_full() {
+    let theme = ResolvedTheme::new(BTreeMap::new());
+    let page = render(&[lines(&theme, true)], true, &theme);
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("<title>main.rs</title>"));
+    assert!(page.contains("<body>\n<pre class=\"umber\"><code>"));
+    assert!(!page.contains("<h2"));
+
+    let page = render(&[lines(&theme, true), lines(&theme, true)], true, &theme);
+    assert!(page.contains("<h2 class=\"umber-file\">main.rs</h2>"));
+    assert!(page.contains("id=\"f2-L10\""));
+  }
+}
@@ -0,0 +1,197 @@
+//! Localized user-facing messages.
+//! Messages live in Fluent files under `src/i18n/`. The locale is taken from
+//! `LC_ALL`, `LC_MESSAGES` or `LANG`, and anything missing falls back to English.
+
+use clap::Command;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use once_cell::sync::Lazy;
+use unic_langid::LanguageIdentifier;
+
+const ENGLISH: &str = include_str!("i18n/en.ftl");
+
+/// Translations, keyed by language subtag.
+const TRANSLATIONS: &[(&str, &str)] = &[
+  ("de", include_str!("i18n/de.ftl")),
+  ("es", include_str!("i18n/es.ftl")),
+  ("fr", include_str!("i18n/fr.ftl")),
+];
+
+static CATALOG: Lazy<Catalog> = Lazy::new(|| Catalog::new(requested_language()));
+
+/// An argument to a localized message.
+pub enum Arg<'a> {
+  Str(&'a str),
+  Num(u64),
+}
+
+struct Catalog {
+  localized: Option<FluentBundle<FluentResource>>,
+  english: FluentBundle<FluentResource>,
+}
+
+impl Catalog {
+  fn new(language: Option<&'static str>) -> Self {
+    let localized = language.and_then(|language| {
+      TRANSLATIONS
+        .iter()
+        .find(|(tag, _)| *tag == language)
+        .map(|(tag, source)| bundle(tag, source))
+    });
+    Self {
+      localized,
+      english: bundle("en", ENGLISH),
+    }
+  }
+
+  fn format(&self, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+    self
+      .localized
+      .as_ref()
+      .and_then(|bundle| format(bundle, id, args))
+      .or_else(|| format(&self.english, id, args))
+  }
+}
+
+fn bundle(tag: &str, source: &'static str) -> FluentBundle<FluentResource> {
+  let language: LanguageIdentifier = tag.parse().expect("valid language tag");
+  let mut bundle = FluentBundle::new_concurrent(vec![language]);
+  // Unicode isolation marks show up as garbage in most terminals.
+  bundle.set_use_isolating(false);
+  let resource = FluentResource::try_new(source.to_string())
+    .unwrap_or_else(|(_, errors)| panic!("invalid {tag} messages: {errors:?}"));
+  bundle
+    .add_resource(resource)
+    .unwrap_or_else(|errors| panic!("duplicate {tag} messages: {errors:?}"));
+  bundle
+}
+
+fn format(
+  bundle: &FluentBundle<FluentResource>,
+  id: &str,
+  args: Option<&FluentArgs>,
+) -> Option<String> {
+  let pattern = bundle.get_message(id)?.value()?;
+  let mut errors = Vec::new();
+  Some(
+    bundle
+      .format_pattern(pattern, args, &mut errors)
+      .into_owned(),
+  )
+}
+
+/// The translation for the first locale variable that is set, if there is one.
+fn requested_language() -> Option<&'static str> {
+  ["LC_ALL", "LC_MESSAGES", "LANG"]
+    .into_iter()
+    .filter_map(|name| std::env::var(name).ok())
+    .find(|value| !value.is_empty())
+    .and_then(|value| language_for_locale(&value))
+}
+
+/// Map a POSIX locale such as `de_AT.UTF-8@euro` to an available translation.
+fn language_for_locale(locale: &str) -> Option<&'static str> {
+  let language = locale
+    .split(['_', '-', '.', '@'])
+    .next()?
+    .to_ascii_lowercase();
+  TRANSLATIONS
+    .iter()
+    .map(|(tag, _)| *tag)
+    .find(|tag| *tag == language)
+}
+
+/// Look up a message, falling back to English and then to the id itself.
+pub fn tr(id: &str) -> String {
+  CATALOG.format(id, None).unwrap_or_else(|| id.to_string())
+}
+
+/// Look up a message with named arguments.
+pub fn tr_args(id: &str, args: &[(&str, Arg<'_>)]) -> String {
+  let mut fluent_args = FluentArgs::new();
+  for (name, value) in args {
+    match value {
+      Arg::Str(value) => fluent_args.set(*name, FluentValue::from(*value)),
+      Arg::Num(value) => fluent_args.set(*name, FluentValue::from(*value)),
+    }
+  }
+  CATALOG
+    .format(id, Some(&fluent_args))
+    .unwrap_or_else(|| id.to_string())
+}
+
+/// Translate the about text and argument help of a command.
+///
+/// Messages are looked up as `{prefix}about` and `{prefix}help-<argument>`. The
+/// English text in the clap attributes is kept when no translation exists; a
+/// translated argument also drops its English long help so `--help` stays in
+/// one language.
+pub fn localize_command(command: Command, prefix: &str) -> Command {
+  let Some(bundle) = CATALOG.localized.as_ref() else {
+    return command;
+  };
+  let command = match format(bundle, &format!("{prefix}about"), None) {
+    Some(about) => command.about(about).long_about(None),
+    None => command,
+  };
+  command.mut_args(|arg| {
+    let id = format!("{prefix}help-{}", arg.get_id().as_str().replace('_', "-"));
+    match format(bundle, &id, None) {
+      Some(help) => arg.help(help).long_help(None),
+      None => arg,
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_language_for_locale() {
+    assert_eq!(language_for_locale("de_DE.UTF-8"), Some("de"));
+    assert_eq!(language_for_locale("fr_CA@euro"), Some("fr"));
+    assert_eq!(language_for_locale("es"), Some("es"));
+    assert_eq!(language_for_locale("C.UTF-8"), None);
+    assert_eq!(language_for_locale("en_US.UTF-8"), None);
+  }
+
+  #[test]
+  fn test_translations_fall_back_to_english() {
+    let english = Catalog::new(None);
+    let german = Catalog::new(Some("de"));
+    assert_eq!(english.format("label-error", None).unwrap(), "error");
+    assert_eq!(german.format("label-error", None).unwrap(), "Fehler");
+    assert!(german.format("help-files", None).is_some());
+    assert_eq!(german.format("missing-message", None), None);
+  }
+
+  #[test]
+  fn test_plural_arguments() {
+    let catalog = Catalog::new(None);
+    let mut args = FluentArgs::new();
+    args.set("path", "/tmp/umber");
+    args.set("size", "1.0 KiB");
+    args.set("files", 1);
+    assert_eq!(
+      catalog.format("cache-size", Some(&args)).unwrap(),
+      "/tmp/umber: 1.0 KiB in 1 file"
+    );
+  }
+
+  #[test]
+  fn test_translations_cover_all_messages() {
+    let ids = ENGLISH
+      .lines()
+      .filter_map(|line| line.split_once(" = "))
+      .map(|(id, _)| id)
+      .filter(|id| !id.starts_with([' ', '#', '*', '[']));
+    for (tag, _) in TRANSLATIONS {
+      let catalog = Catalog::new(Some(tag));
+      let localized = catalog.localized.as_ref().unwrap();
+      for id in ids.clone() {
+        assert!(localized.has_message(id), "{tag} is missing {id}");
+      }
+    }
+  }
+}
@@ -0,0 +1,138 @@
+label-error = Fehler
+label-warning = Warnung
+label-note = Hinweis
+label-hint = Tipp
+
+note-reading-stdin = lese von der Standardeingabe, Strg-D beendet die Eingabe (siehe --help)
+note-follow-truncated = { $path } wurde gekürzt, wird ab dem Anfang weiterverfolgt
+note-follow-replaced = { $path } wurde ersetzt, die neue Datei wird verfolgt
+warning-recursive-pager = Pager '{ $program }' wird ignoriert (er würde umber erneut starten)
+error-unsupported-language = Nicht unterstützte Sprache: { $name }
+error-pager-start = Pager '{ $program }' konnte nicht gestartet werden: { $error }
+warning-preprocess-failed = Vorverarbeitung von { $path } fehlgeschlagen, die Datei wird unverändert angezeigt: { $error }
+binary-file = <Binärdatei: { $name } ({ $size })>
+binary-input = <binäre Eingabe ({ $size })>
+size-unknown = Größe unbekannt
+warning-invalid-utf8 = ungültiges UTF-8 in { $path } wird als � angezeigt
+note-too-large-to-highlight = { $path } ist größer als --max-highlight-size und wird ohne Hervorhebung angezeigt
+note-minified = { $path } scheint minifiziert und wird ohne Hervorhebung angezeigt (--max-highlight-size=none hebt es hervor)
+header-mixed-eol = (gemischte Zeilenenden)
+
+hint-did-you-mean = meinten Sie '{ $name }'?
+hint-permissions = prüfen Sie die Dateirechte (ls -l { $path })
+hint-directory = '{ $path }' ist ein Verzeichnis; übergeben Sie stattdessen die Dateien darin (z. B. { $path }/*)
+
+error-unknown-theme = Unbekanntes Thema '{ $name }'
+error-show-arguments = umber show erwartet genau ein Argument REV:PATH
+error-show-spec = '{ $spec }' hat nicht die Form REV:PATH
+error-blame-arguments = umber blame erwartet genau ein Argument FILE
+error-bench-files = --bench braucht Dateien als Argumente
+error-highlight-color = ungültige highlight-line-Farbe '{ $color }' in der Konfigurationsdatei (erwartet #rrggbb)
+error-output-format = --output braucht ein anderes --format als terminal
+error-png-terminal = Ein PNG-Bild wird nicht ins Terminal geschrieben; verwenden Sie --output DATEI oder leiten Sie die Ausgabe um
+error-png-feature = umber wurde ohne das Feature `png` gebaut
+error-write-output = { $path } konnte nicht geschrieben werden
+error-read-file-list = Die Dateiliste { $path } konnte nicht gelesen werden
+error-no-config-dir = kein Konfigurationsverzeichnis
+error-range-missing-path = Dateipfad vor dem Zeilenbereich fehlt
+error-range-missing-range = Zeilenbereich nach #L fehlt
+error-range-suffix = ungültiger Zeilenbereich '#L{ $range }' (erwartet #L<start>-<end>, #L<start>:<end> oder #L<start>)
+error-range = ungültiger Zeilenbereich '{ $range }' (erwartet start-end, start:end oder start, durch Kommas getrennt)
+
+bat-imported = { $path } geschrieben
+bat-skipped = nicht aus bat übernommen: { $option }
+bat-config-exists = { $path } existiert bereits; die übernommenen Einstellungen werden stattdessen ausgegeben
+error-no-bat-config = keine bat-Konfiguration gefunden
+
+cache-cleared = { $path } geleert
+cache-failed-clear = { $path } konnte nicht geleert werden: { $error }
+cache-failed-inspect = { $path } konnte nicht untersucht werden: { $error }
+cache-size = { $path }: { $size } in { $files ->
+    [one] { $files } Datei
+   *[other] { $files } Dateien
+}
+cache-built = { $count ->
+    [one] { $count } Hervorhebungsabfrage
+   *[other] { $count } Hervorhebungsabfragen
+} in { $path } vorbereitet
+cache-failed-build = Cache in { $path } konnte nicht erstellt werden: { $error }
+cache-grammar-installed = Grammatik { $name } in { $path } installiert
+cache-failed-grammar = Grammatik { $source } konnte nicht erstellt werden: { $error }
+
+about = cat mit Syntaxhervorhebung
+help-completions = Shell-Vervollständigungen für die angegebene Shell erzeugen
+help-language = Eine bestimmte Programmiersprache erzwingen
+help-theme = Farbschema für die Syntaxhervorhebung
+help-theme-light = Farbschema für „auto“ im hellen Modus
+help-theme-dark = Farbschema für „auto“ im dunklen Modus
+help-lines = Nur ausgewählte Zeilen anzeigen (z. B. 10-20, 10:20, 10, 10,20, 1-10,40-60, -20:)
+help-context = N Zeilen um jeden --lines-Bereich anzeigen
+help-diff = Nur die im git-Arbeitsverzeichnis geänderten Zeilen anzeigen
+help-diff-context = Kontextzeilen um jede Änderung bei --diff
+help-diff-base = Änderungsmarkierungen und --diff mit REF statt mit dem Index vergleichen
+help-highlight-line = Die angegebenen Zeilen mit hervorgehobenem Hintergrund zeichnen (wiederholbar)
+help-file-name = Name, der statt des Pfads angezeigt und zur Spracherkennung verwendet wird
+help-ignored-suffix = Dateien mit Endung SUFFIX anhand des Namens ohne sie erkennen (wiederholbar)
+help-color = Festlegen, wann farbig ausgegeben wird
+help-no-color = Farbige Ausgabe deaktivieren
+help-color-depth = Farbtiefe erzwingen (auto, truecolor, 256, 16)
+help-contrast = Den Kontrast des Farbschemas erhöhen
+help-italic-text = Kursivschrift darstellen
+help-no-bold = Niemals fett darstellen
+help-no-underline = Niemals unterstreichen
+help-list-themes = Verfügbare Farbschemata auflisten
+help-export-theme = Ein Farbschema als TOML ausgeben
+help-squeeze-blank = Aufeinanderfolgende Leerzeilen zu einer zusammenfassen
+help-squeeze-limit = Höchstzahl aufeinanderfolgender Leerzeilen festlegen
+help-style = Stilkomponenten (numbers, changes, headers, grid, blame, full, rich, plain)
+help-zebra = Jede zweite Zeile dezent hinterlegen
+help-padding = Leere Spalten und Zeilen um die Ausgabe
+help-padding-top = Leere Zeilen über der Ausgabe
+help-padding-right = Leere Spalten rechts jeder Zeile
+help-padding-bottom = Leere Zeilen unter der Ausgabe
+help-padding-left = Leere Spalten links jeder Zeile
+help-deterministic = Byte-stabile Ausgabe unabhängig vom Terminal erzeugen
+help-terminal-width = Terminalbreite festlegen statt sie zu ermitteln
+help-pager = Ausgabe im Terminal durch COMMAND anzeigen
+help-stdin = Von einer interaktiven Standardeingabe ohne Hinweis lesen
+help-files-from = Die anzuzeigenden Dateien zeilenweise aus DATEI lesen ('-' für die Standardeingabe)
+help-null = Einträge der --files-from-Liste sind durch NUL-Bytes getrennt
+help-error-format = Format der Fehlermeldungen auf stderr
+help-format = Format der hervorgehobenen Ausgabe
+help-font-family = Schriftart für --format svg
+help-window-chrome = Ausgabe von --format svg und png als Fenster einrahmen
+help-output = --format-Ausgabe in DATEI schreiben
+help-unbuffered = Ohne Wirkung, die Ausgabe ist immer ungepuffert
+help-show-all = Nicht druckbare Zeichen anzeigen (Tabulatoren als →, Wagenrückläufe als ↵ usw.)
+help-man-page = Manpage erzeugen
+help-follow = An die Dateien angehängte Zeilen fortlaufend ausgeben, wie tail -f
+help-chunked = Dateien fensterweise hervorheben, statt sie ganz einzulesen
+help-max-highlight-size = Größere oder minifizierte Eingaben ohne Hervorhebung anzeigen
+help-max-line-length = Zeilen über N Spalten abschneiden und mit … beenden
+help-chop-long-lines = Zeilen an der Terminalbreite abschneiden
+help-stat = Dateien, Zeilen und Bytes je erkannter Sprache ausgeben
+help-bench = Die Dauer jedes Schritts der Anzeige messen, statt die Dateien anzuzeigen
+help-import-bat-config = bats Konfiguration in eine umber-Konfigurationsdatei übernehmen
+help-bat-config = Thema und Syntaxzuordnungen aus bats Konfiguration verwenden
+help-no-preprocess = Keine Vorverarbeitung aus der Konfigurationsdatei ausführen
+help-cache-dir = PATH als Cache-Verzeichnis verwenden
+help-header-path = Wie Dateipfade in Kopfzeilen angezeigt werden
+help-header-format = Was die Kopfzeile zwischen Dateien zeigt: eine Vorlage oder ein Muster
+help-log-prefixes = Präfixe in Logzeilen abdunkeln und jede Nachricht hervorheben
+help-hex = Die Eingabe als farbigen Hexdump anzeigen
+help-binary = Wie binäre Eingaben angezeigt werden: placeholder, as-text oder hex
+help-strip-ansi = Escape-Sequenzen aus der Eingabe entfernen: auto, always oder never
+help-show-line-endings = CRLF- und LF-Zeilenenden markieren
+help-normalize-eol = CRLF-Zeilenenden als LF anzeigen
+help-paging = Wann die Ausgabe durch den Pager geleitet wird
+help-wrap = Wie lange Zeilen umbrochen werden
+help-tabs = Tabulatoren auf N Spalten erweitern
+help-plain = Schlichte Ausgabe ohne Dekorationen; doppelt (-pp) auch ohne Pager
+help-files = Anzuzeigende Dateien ('-' oder keine für die Standardeingabe)
+
+cache-about = umbers Cache auf der Festplatte anzeigen oder zurücksetzen
+cache-help-action = Hervorhebungsabfragen vorbereiten oder alle zwischengespeicherten Daten entfernen
+cache-help-grammar = Eine tree-sitter-Grammatik kompilieren und installieren (mehrfach möglich)
+cache-help-clear = Alle zwischengespeicherten Daten entfernen
+cache-help-size = Anzahl der Dateien und Bytes im Cache anzeigen
+cache-help-cache-dir = PATH als Cache-Verzeichnis verwenden
@@ -0,0 +1,64 @@
+# English messages, also used as the fallback for every other locale.
+# Command-line help is written in src/main.rs; translations override it
+# with `about`, `help-<argument>` and `cache-help-<argument>` messages.
+
+label-error = error
+label-warning = warning
+label-note = note
+label-hint = hint
+
+note-reading-stdin = reading from stdin, press Ctrl-D to end (see --help for usage)
+note-follow-truncated = { $path } was truncated, following it from the top
+note-follow-replaced = { $path } was replaced, following the new file
+warning-recursive-pager = ignoring pager '{ $program }' (it would run umber again)
+error-unsupported-language = Unsupported language: { $name }
+error-pager-start = failed to start pager '{ $program }': { $error }
+warning-preprocess-failed = preprocessor for { $path } failed, showing the file as is: { $error }
+binary-file = <binary file: { $name } ({ $size })>
+binary-input = <binary input ({ $size })>
+size-unknown = unknown size
+warning-invalid-utf8 = invalid UTF-8 in { $path } is shown as �
+note-too-large-to-highlight = { $path } is larger than --max-highlight-size and is shown without highlighting
+note-minified = { $path } looks minified and is shown without highlighting (--max-highlight-size=none highlights it)
+header-mixed-eol = (mixed line endings)
+
+hint-did-you-mean = did you mean '{ $name }'?
+hint-permissions = check the file permissions (ls -l { $path })
+hint-directory = '{ $path }' is a directory; pass the files inside it instead (e.g. { $path }/*)
+
+error-unknown-theme = Unknown theme '{ $name }'
+error-show-arguments = umber show expects a single REV:PATH argument
+error-show-spec = '{ $spec }' is not of the form REV:PATH
+error-blame-arguments = umber blame expects a single FILE argument
+error-bench-files = --bench needs FILE arguments
+error-highlight-color = invalid highlight-line color '{ $color }' in the config file (expected #rrggbb)
+error-output-format = --output needs a --format other than terminal
+error-png-terminal = Not writing a PNG image to the terminal; use --output FILE or redirect stdout
+error-png-feature = umber was built without the `png` feature
+error-write-output = Failed to write { $path }
+error-read-file-list = Failed to read the file list { $path }
+error-no-config-dir = no configuration directory
+error-range-missing-path = missing file path before line range
+error-range-missing-range = missing line range after #L
+error-range-suffix = invalid line range '#L{ $range }' (expected #L<start>-<end>, #L<start>:<end>, or #L<start>)
+error-range = invalid line range '{ $range }' (expected start-end, start:end, or start, separated by commas)
+
+bat-imported = Wrote { $path }
+bat-skipped = not imported from bat: { $option }
+bat-config-exists = { $path } already exists; printing the imported settings instead
+error-no-bat-config = no bat configuration found
+
+cache-cleared = Cleared { $path }
+cache-failed-clear = failed to clear { $path }: { $error }
+cache-failed-inspect = failed to inspect { $path }: { $error }
+cache-size = { $path }: { $size } in { $files ->
+    [one] { $files } file
+   *[other] { $files } files
+}
+cache-built = Prepared { $count ->
+    [one] { $count } highlight query
+   *[other] { $count } highlight queries
+} in { $path }
+cache-failed-build = failed to build the cache in { $path }: { $error }
+cache-grammar-installed = Installed the { $name } grammar in { $path }
+cache-failed-grammar = failed to build the grammar { $source }: { $error }
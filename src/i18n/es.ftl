@@ -0,0 +1,138 @@
+label-error = error
+label-warning = aviso
+label-note = nota
+label-hint = sugerencia
+
+note-reading-stdin = leyendo de la entrada estándar, pulse Ctrl-D para terminar (vea --help)
+note-follow-truncated = { $path } se ha truncado, se sigue desde el principio
+note-follow-replaced = { $path } se ha reemplazado, se sigue el nuevo archivo
+warning-recursive-pager = se ignora el paginador '{ $program }' (volvería a ejecutar umber)
+error-unsupported-language = Lenguaje no admitido: { $name }
+error-pager-start = no se pudo iniciar el paginador '{ $program }': { $error }
+warning-preprocess-failed = falló el preprocesador de { $path }, se muestra el archivo tal cual: { $error }
+binary-file = <archivo binario: { $name } ({ $size })>
+binary-input = <entrada binaria ({ $size })>
+size-unknown = tamaño desconocido
+warning-invalid-utf8 = el UTF-8 no válido en { $path } se muestra como �
+note-too-large-to-highlight = { $path } supera --max-highlight-size y se muestra sin resaltado
+note-minified = { $path } parece minificado y se muestra sin resaltado (--max-highlight-size=none lo resalta)
+header-mixed-eol = (finales de línea mixtos)
+
+hint-did-you-mean = ¿quiso decir '{ $name }'?
+hint-permissions = compruebe los permisos del archivo (ls -l { $path })
+hint-directory = '{ $path }' es un directorio; pase los archivos que contiene (p. ej. { $path }/*)
+
+error-unknown-theme = tema desconocido '{ $name }'
+error-show-arguments = umber show espera un único argumento REV:PATH
+error-show-spec = '{ $spec }' no tiene la forma REV:PATH
+error-blame-arguments = umber blame espera un único argumento FILE
+error-bench-files = --bench necesita archivos como argumentos
+error-highlight-color = color de highlight-line '{ $color }' no válido en el archivo de configuración (se esperaba #rrggbb)
+error-output-format = --output necesita un --format distinto de terminal
+error-png-terminal = no se escribe una imagen PNG en la terminal; use --output ARCHIVO o redirija la salida
+error-png-feature = umber se compiló sin la característica `png`
+error-write-output = no se pudo escribir { $path }
+error-read-file-list = no se pudo leer la lista de archivos { $path }
+error-no-config-dir = no hay directorio de configuración
+error-range-missing-path = falta la ruta del archivo antes del rango de líneas
+error-range-missing-range = falta el rango de líneas después de #L
+error-range-suffix = rango de líneas '#L{ $range }' no válido (se esperaba #L<start>-<end>, #L<start>:<end> o #L<start>)
+error-range = rango de líneas '{ $range }' no válido (se esperaba start-end, start:end o start, separados por comas)
+
+bat-imported = Se escribió { $path }
+bat-skipped = no importado de bat: { $option }
+bat-config-exists = { $path } ya existe; se muestran los ajustes importados en su lugar
+error-no-bat-config = no se encontró ninguna configuración de bat
+
+cache-cleared = Se vació { $path }
+cache-failed-clear = no se pudo vaciar { $path }: { $error }
+cache-failed-inspect = no se pudo examinar { $path }: { $error }
+cache-size = { $path }: { $size } en { $files ->
+    [one] { $files } archivo
+   *[other] { $files } archivos
+}
+cache-built = { $count ->
+    [one] { $count } consulta de resaltado preparada
+   *[other] { $count } consultas de resaltado preparadas
+} en { $path }
+cache-failed-build = no se pudo construir la caché en { $path }: { $error }
+cache-grammar-installed = Gramática { $name } instalada en { $path }
+cache-failed-grammar = no se pudo construir la gramática { $source }: { $error }
+
+about = cat con resaltado de sintaxis
+help-completions = Generar completado para el shell indicado
+help-language = Forzar un lenguaje de programación concreto
+help-theme = Tema de colores para el resaltado de sintaxis
+help-theme-light = Tema que usa «auto» en modo claro
+help-theme-dark = Tema que usa «auto» en modo oscuro
+help-lines = Mostrar solo las líneas indicadas (p. ej. 10-20, 10:20, 10, 10,20, 1-10,40-60, -20:)
+help-context = Mostrar N líneas alrededor de cada rango de --lines
+help-diff = Mostrar solo las líneas modificadas en el árbol de trabajo de git
+help-diff-context = Líneas de contexto alrededor de cada cambio con --diff
+help-diff-base = Comparar los marcadores de cambios y --diff con REF en lugar del índice
+help-highlight-line = Dibujar las líneas indicadas con un fondo resaltado (repetible)
+help-file-name = Nombre que se muestra y se usa para detectar el lenguaje en lugar de la ruta
+help-ignored-suffix = Detectar los archivos que terminan en SUFFIX por el nombre sin él (repetible)
+help-color = Indicar cuándo usar colores
+help-no-color = Desactivar los colores
+help-color-depth = Forzar la profundidad de color (auto, truecolor, 256, 16)
+help-contrast = Aumentar el contraste del tema
+help-italic-text = Mostrar el texto en cursiva
+help-no-bold = No mostrar nunca en negrita
+help-no-underline = No subrayar nunca
+help-list-themes = Listar los temas disponibles
+help-export-theme = Mostrar un tema en formato TOML
+help-squeeze-blank = Reducir las líneas vacías consecutivas a una sola
+help-squeeze-limit = Número máximo de líneas vacías consecutivas
+help-style = Componentes de estilo (numbers, changes, headers, grid, blame, full, rich, plain)
+help-zebra = Sombrear una de cada dos líneas con un fondo sutil
+help-padding = Columnas y líneas vacías alrededor de la salida
+help-padding-top = Líneas vacías encima de la salida
+help-padding-right = Columnas vacías a la derecha de cada línea
+help-padding-bottom = Líneas vacías debajo de la salida
+help-padding-left = Columnas vacías a la izquierda de cada línea
+help-deterministic = Producir una salida estable que no depende del terminal
+help-terminal-width = Fijar el ancho del terminal en lugar de detectarlo
+help-pager = Paginar la salida con COMMAND al escribir en un terminal
+help-stdin = Leer de una entrada estándar interactiva sin mostrar el aviso
+help-files-from = Leer los archivos a mostrar de ARCHIVO, uno por línea ('-' para la entrada estándar)
+help-null = Las entradas de la lista --files-from están separadas por bytes NUL
+help-error-format = Formato de los mensajes de error en stderr
+help-format = Formato de la salida resaltada
+help-font-family = Fuente de --format svg
+help-window-chrome = Enmarcar la salida de --format svg y png como una ventana
+help-output = Escribir la salida de --format en ARCHIVO
+help-unbuffered = Sin efecto, la salida nunca usa búfer
+help-show-all = Mostrar caracteres no imprimibles (tabuladores como →, retornos de carro como ↵, etc.)
+help-man-page = Generar la página de manual
+help-follow = Seguir mostrando las líneas añadidas a los archivos, como tail -f
+help-chunked = Resaltar los archivos por ventanas en lugar de leerlos enteros
+help-max-highlight-size = Mostrar sin resaltado las entradas más grandes o minificadas
+help-max-line-length = Cortar las líneas de más de N columnas, terminadas en …
+help-chop-long-lines = Cortar las líneas al ancho de la terminal
+help-stat = Mostrar archivos, líneas y bytes por lenguaje detectado
+help-bench = Medir cada fase de la visualización de los archivos en lugar de mostrarlos
+help-import-bat-config = Convertir la configuración de bat en un archivo de configuración de umber
+help-bat-config = Usar el tema y las asociaciones de sintaxis de la configuración de bat
+help-no-preprocess = No ejecutar los preprocesadores del archivo de configuración
+help-cache-dir = Usar PATH como directorio de caché
+help-header-path = Cómo mostrar las rutas en los encabezados
+help-header-format = Qué muestra el encabezado entre archivos: un ajuste predefinido o una plantilla
+help-log-prefixes = Atenuar los prefijos de las líneas de registro y resaltar cada mensaje
+help-hex = Mostrar la entrada como un volcado hexadecimal coloreado
+help-binary = Cómo mostrar la entrada binaria: placeholder, as-text o hex
+help-strip-ansi = Quitar las secuencias de escape de la entrada: auto, always o never
+help-show-line-endings = Marcar los finales de línea CRLF y LF
+help-normalize-eol = Mostrar los finales de línea CRLF como LF
+help-paging = Cuándo paginar la salida
+help-wrap = Cómo ajustar las líneas largas
+help-tabs = Expandir tabulaciones a N columnas
+help-plain = Salida simple sin decoraciones; dos veces (-pp) también desactiva el paginador
+help-files = Archivos a mostrar ('-' o ninguno para la entrada estándar)
+
+cache-about = Consultar o vaciar la caché en disco de umber
+cache-help-action = Preparar las consultas de resaltado, o eliminar todos los datos en caché
+cache-help-grammar = Compilar e instalar una gramática de tree-sitter (repetible)
+cache-help-clear = Eliminar todos los datos en caché
+cache-help-size = Mostrar el número de archivos y bytes de la caché
+cache-help-cache-dir = Usar PATH como directorio de caché
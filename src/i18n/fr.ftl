@@ -0,0 +1,138 @@
+label-error = erreur
+label-warning = avertissement
+label-note = remarque
+label-hint = astuce
+
+note-reading-stdin = lecture de l'entrée standard, Ctrl-D pour terminer (voir --help)
+note-follow-truncated = { $path } a été tronqué, suivi depuis le début
+note-follow-replaced = { $path } a été remplacé, suivi du nouveau fichier
+warning-recursive-pager = pager '{ $program }' ignoré (il relancerait umber)
+error-unsupported-language = Langage non pris en charge : { $name }
+error-pager-start = impossible de lancer le pager '{ $program }' : { $error }
+warning-preprocess-failed = échec du préprocesseur pour { $path }, le fichier est affiché tel quel : { $error }
+binary-file = <fichier binaire : { $name } ({ $size })>
+binary-input = <entrée binaire ({ $size })>
+size-unknown = taille inconnue
+warning-invalid-utf8 = l'UTF-8 invalide dans { $path } est affiché comme �
+note-too-large-to-highlight = { $path } dépasse --max-highlight-size et est affiché sans coloration
+note-minified = { $path } semble minifié et est affiché sans coloration (--max-highlight-size=none le colore)
+header-mixed-eol = (fins de ligne mixtes)
+
+hint-did-you-mean = vouliez-vous dire '{ $name }' ?
+hint-permissions = vérifiez les permissions du fichier (ls -l { $path })
+hint-directory = '{ $path }' est un répertoire ; passez plutôt les fichiers qu'il contient (par ex. { $path }/*)
+
+error-unknown-theme = thème inconnu '{ $name }'
+error-show-arguments = umber show attend un seul argument REV:PATH
+error-show-spec = '{ $spec }' n'est pas de la forme REV:PATH
+error-blame-arguments = umber blame attend un seul argument FILE
+error-bench-files = --bench demande des fichiers en argument
+error-highlight-color = couleur highlight-line '{ $color }' invalide dans le fichier de configuration (attendu : #rrggbb)
+error-output-format = --output demande un --format autre que terminal
+error-png-terminal = une image PNG n'est pas écrite dans le terminal ; utilisez --output FICHIER ou redirigez la sortie
+error-png-feature = umber a été compilé sans la fonctionnalité `png`
+error-write-output = impossible d'écrire { $path }
+error-read-file-list = impossible de lire la liste de fichiers { $path }
+error-no-config-dir = aucun répertoire de configuration
+error-range-missing-path = chemin de fichier manquant avant la plage de lignes
+error-range-missing-range = plage de lignes manquante après #L
+error-range-suffix = plage de lignes '#L{ $range }' invalide (attendu : #L<start>-<end>, #L<start>:<end> ou #L<start>)
+error-range = plage de lignes '{ $range }' invalide (attendu : start-end, start:end ou start, séparés par des virgules)
+
+bat-imported = { $path } écrit
+bat-skipped = non importé depuis bat : { $option }
+bat-config-exists = { $path } existe déjà ; les réglages importés sont affichés à la place
+error-no-bat-config = aucune configuration bat trouvée
+
+cache-cleared = { $path } vidé
+cache-failed-clear = impossible de vider { $path } : { $error }
+cache-failed-inspect = impossible d'examiner { $path } : { $error }
+cache-size = { $path } : { $size } dans { $files ->
+    [one] { $files } fichier
+   *[other] { $files } fichiers
+}
+cache-built = { $count ->
+    [one] { $count } requête de coloration préparée
+   *[other] { $count } requêtes de coloration préparées
+} dans { $path }
+cache-failed-build = impossible de construire le cache dans { $path } : { $error }
+cache-grammar-installed = Grammaire { $name } installée dans { $path }
+cache-failed-grammar = impossible de construire la grammaire { $source } : { $error }
+
+about = cat avec coloration syntaxique
+help-completions = Générer les complétions pour le shell indiqué
+help-language = Forcer un langage de programmation précis
+help-theme = Thème de couleurs pour la coloration syntaxique
+help-theme-light = Thème utilisé par « auto » en mode clair
+help-theme-dark = Thème utilisé par « auto » en mode sombre
+help-lines = N'afficher que certaines lignes (par ex. 10-20, 10:20, 10, 10,20, 1-10,40-60, -20:)
+help-context = Afficher N lignes autour de chaque plage de --lines
+help-diff = N'afficher que les lignes modifiées dans l'arbre de travail git
+help-diff-context = Lignes de contexte autour de chaque modification avec --diff
+help-diff-base = Comparer les marqueurs de modification et --diff à REF plutôt qu'à l'index
+help-highlight-line = Dessiner les lignes indiquées avec un fond mis en évidence (répétable)
+help-file-name = Nom à afficher et à utiliser pour détecter le langage à la place du chemin
+help-ignored-suffix = Détecter les fichiers finissant par SUFFIX d'après le nom sans celui-ci (répétable)
+help-color = Indiquer quand utiliser les couleurs
+help-no-color = Désactiver les couleurs
+help-color-depth = Forcer la profondeur de couleur (auto, truecolor, 256, 16)
+help-contrast = Augmenter le contraste du thème
+help-italic-text = Afficher le texte en italique
+help-no-bold = Ne jamais afficher en gras
+help-no-underline = Ne jamais souligner
+help-list-themes = Lister les thèmes disponibles
+help-export-theme = Afficher un thème au format TOML
+help-squeeze-blank = Fusionner les lignes vides consécutives en une seule
+help-squeeze-limit = Nombre maximal de lignes vides consécutives
+help-style = Composants de style (numbers, changes, headers, grid, blame, full, rich, plain)
+help-zebra = Ombrer une ligne sur deux avec un fond discret
+help-padding = Colonnes et lignes vides autour de la sortie
+help-padding-top = Lignes vides au-dessus de la sortie
+help-padding-right = Colonnes vides à droite de chaque ligne
+help-padding-bottom = Lignes vides sous la sortie
+help-padding-left = Colonnes vides à gauche de chaque ligne
+help-deterministic = Produire une sortie stable indépendante du terminal
+help-terminal-width = Fixer la largeur du terminal au lieu de la détecter
+help-pager = Paginer la sortie avec COMMAND dans un terminal
+help-stdin = Lire une entrée standard interactive sans afficher d'indication
+help-files-from = Lire les fichiers à afficher depuis FICHIER, un par ligne ('-' pour l'entrée standard)
+help-null = Les entrées de la liste --files-from sont séparées par des octets NUL
+help-error-format = Format des messages d'erreur sur stderr
+help-format = Format de la sortie colorée
+help-font-family = Police de --format svg
+help-window-chrome = Encadrer la sortie de --format svg et png comme une fenêtre
+help-output = Écrire la sortie de --format dans FICHIER
+help-unbuffered = Sans effet, la sortie n'est jamais mise en tampon
+help-show-all = Afficher les caractères non imprimables (tabulations en →, retours chariot en ↵, etc.)
+help-man-page = Générer la page de manuel
+help-follow = Continuer d'afficher les lignes ajoutées aux fichiers, comme tail -f
+help-chunked = Colorer les fichiers par fenêtres au lieu de les lire en entier
+help-max-highlight-size = Afficher sans coloration les entrées plus grandes ou minifiées
+help-max-line-length = Couper les lignes de plus de N colonnes, terminées par …
+help-chop-long-lines = Couper les lignes à la largeur du terminal
+help-stat = Afficher fichiers, lignes et octets par langage détecté
+help-bench = Mesurer chaque étape de l'affichage des fichiers au lieu de les afficher
+help-import-bat-config = Convertir la configuration de bat en fichier de configuration umber
+help-bat-config = Utiliser le thème et les associations de syntaxe de la configuration de bat
+help-no-preprocess = Ne pas exécuter les préprocesseurs du fichier de configuration
+help-cache-dir = Utiliser PATH comme répertoire de cache
+help-header-path = Comment afficher les chemins dans les en-têtes
+help-header-format = Ce qu'affiche l'en-tête entre les fichiers : un préréglage ou un modèle
+help-log-prefixes = Atténuer les préfixes des lignes de journal et colorer chaque message
+help-hex = Afficher l'entrée sous forme de vidage hexadécimal coloré
+help-binary = Comment afficher une entrée binaire : placeholder, as-text ou hex
+help-strip-ansi = Retirer les séquences d'échappement de l'entrée : auto, always ou never
+help-show-line-endings = Marquer les fins de ligne CRLF et LF
+help-normalize-eol = Afficher les fins de ligne CRLF comme LF
+help-paging = Quand utiliser le pager
+help-wrap = Comment couper les lignes longues
+help-tabs = Remplacer les tabulations par N colonnes
+help-plain = Sortie simple sans décorations ; deux fois (-pp) désactive aussi le pager
+help-files = Fichiers à afficher ('-' ou aucun pour l'entrée standard)
+
+cache-about = Examiner ou réinitialiser le cache disque d'umber
+cache-help-action = Préparer les requêtes de coloration, ou supprimer toutes les données en cache
+cache-help-grammar = Compiler et installer une grammaire tree-sitter (répétable)
+cache-help-clear = Supprimer toutes les données en cache
+cache-help-size = Afficher le nombre de fichiers et d'octets du cache
+cache-help-cache-dir = Utiliser PATH comme répertoire de cache
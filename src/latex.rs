@@ -0,0 +1,180 @@
+//! `--format latex`: highlighted listings for LaTeX documents, as fancyvrb
+//! `Verbatim` environments. Each theme key in use gets an xcolor color and a
+//! macro, e.g. `\umberComment{...}`, so a document can restyle them after the
+//! listing. Needs `\usepackage{xcolor}` and `\usepackage{fancyvrb}`.
+
+use syntastica::style::{Color, Style};
+
+use crate::decorations::get_dim_style_or_create;
+use crate::listing::{Listing, expand_tabs};
+
+/// Written once, before the listings: the characters that would otherwise
+/// start a command inside `Verbatim`.
+const PREAMBLE: &str = "\
+% Highlighted by umber; needs \\usepackage{xcolor} and \\usepackage{fancyvrb}.
+\\def\\umberBS{\\char`\\\\}
+\\def\\umberOB{\\char`\\{}
+\\def\\umberCB{\\char`\\}}
+";
+
+const DIGITS: [&str; 10] = [
+  "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine",
+];
+
+/// `key` in macro and color names: `function.builtin` is `FunctionBuiltin`.
+/// TeX macro names are letters only, so digits are spelled out.
+fn macro_name(key: &str) -> String {
+  let mut name = String::with_capacity(key.len());
+  let mut capitalize = true;
+  for c in key.chars() {
+    match c {
+      'a'..='z' | 'A'..='Z' if capitalize => {
+        name.push(c.to_ascii_uppercase());
+        capitalize = false;
+      }
+      'a'..='z' | 'A'..='Z' => name.push(c),
+      '0'..='9' => {
+        name.push_str(DIGITS[c as usize - '0' as usize]);
+        capitalize = true;
+      }
+      _ => capitalize = true,
+    }
+  }
+  name
+}
+
+fn html_color(color: Color) -> String {
+  format!("{:02X}{:02X}{:02X}", color.red, color.green, color.blue)
+}
+
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '\\' => escaped.push_str("\\umberBS{}"),
+      '{' => escaped.push_str("\\umberOB{}"),
+      '}' => escaped.push_str("\\umberCB{}"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// The color of `style` and a one-argument macro applying it.
+fn define(out: &mut String, name: &str, style: Style) {
+  let mut body = "#1".to_string();
+  if style.underline() {
+    body = format!("\\underline{{{body}}}");
+  }
+  if style.italic() {
+    body = format!("\\textit{{{body}}}");
+  }
+  if style.bold() {
+    body = format!("\\textbf{{{body}}}");
+  }
+  out.push_str(&format!(
+    "\\definecolor{{umber{name}}}{{HTML}}{{{}}}\n\
+     \\def\\umber{name}#1{{\\textcolor{{umber{name}}}{{{body}}}}}\n",
+    html_color(style.color())
+  ));
+}
+
+fn listing(out: &mut String, listing: &Listing<'_>) {
+  let theme = listing.theme;
+  let normal = theme.get("_normal");
+  if let Some(normal) = normal {
+    out.push_str(&format!(
+      "\\definecolor{{umberForeground}}{{HTML}}{{{}}}\n",
+      html_color(normal.color())
+    ));
+    if let Some(bg) = normal.bg() {
+      out.push_str(&format!(
+        "\\definecolor{{umberBackground}}{{HTML}}{{{}}}\n",
+        html_color(bg)
+      ));
+    }
+  }
+  if listing.numbers {
+    define(out, "LineNumber", get_dim_style_or_create(theme));
+  }
+  for key in listing.style_keys() {
+    if let Some(style) = theme.find_style(key) {
+      define(out, &macro_name(key), style);
+    }
+  }
+
+  out.push_str("\\begin{Verbatim}[commandchars=\\\\\\{\\}");
+  if normal.is_some() {
+    out.push_str(",formatcom=\\color{umberForeground}");
+  }
+  out.push_str("]\n");
+  let width = listing.number_width();
+  for (line_no, pieces) in &listing.lines {
+    if listing.numbers {
+      out.push_str(&format!("\\umberLineNumber{{{line_no:>width$} }}"));
+    }
+    for (text, style_key) in expand_tabs(pieces) {
+      match style_key.filter(|key| theme.find_style(key).is_some()) {
+        Some(key) => out.push_str(&format!("\\umber{}{{{}}}", macro_name(key), escape(&text))),
+        None => out.push_str(&escape(&text)),
+      }
+    }
+    out.push('\n');
+  }
+  out.push_str("\\end{Verbatim}\n");
+}
+
+pub fn render(listings: &[Listing<'_>]) -> String {
+  let mut out = PREAMBLE.to_string();
+  for item in listings {
+    listing(&mut out, item);
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::BTreeMap;
+  use syntastica::theme::ResolvedTheme;
+
+  #[test]
+  fn test_macro_name() {
+    assert_eq!(macro_name("comment"), "Comment");
+    assert_eq!(macro_name("function.builtin"), "FunctionBuiltin");
+    assert_eq!(macro_name("markup.heading.1"), "MarkupHeadingOne");
+  }
+
+  #[test]
+  fn test_render() {
+    let mut styles = BTreeMap::new();
+    styles.insert(
+      "comment".to_string(),
+      Style::new(
+        Color::new(0x6c, 0x70, 0x86),
+        None,
+        false,
+        false,
+        true,
+        false,
+      ),
+    );
+    let theme = ResolvedTheme::new(styles);
+    let listings = [Listing {
+      name: "main.rs".to_string(),
+      lines: vec![
+        (1, vec![("// {hi}".to_string(), Some("comment"))]),
+        (2, vec![("a\\b".to_string(), Some("attribute"))]),
+      ],
+      theme: &theme,
+      numbers: false,
+    }];
+    let tex = render(&listings);
+    assert!(tex.contains("\\definecolor{umberComment}{HTML}{6C7086}\n"));
+    assert!(tex.contains("\\def\\umberComment#1{\\textcolor{umberComment}{\\textit{#1}}}\n"));
+    assert!(tex.contains("\\begin{Verbatim}[commandchars=\\\\\\{\\}]\n"));
+    assert!(tex.contains("\\umberComment{// \\umberOB{}hi\\umberCB{}}\n"));
+    // Keys the theme has no style for are left as plain text.
+    assert!(tex.contains("\na\\umberBS{}b\n\\end{Verbatim}"));
+  }
+}
@@ -0,0 +1,59 @@
+//! umber as a library: source text highlighted with the grammars and themes
+//! of the `umber` command, as terminal escapes or HTML.
+//!
+//! ```no_run
+//! let options = umber::Options {
+//!   line_numbers: true,
+//!   ..umber::Options::default()
+//! };
+//! let ansi = umber::highlight("fn main() {}\n", "rust", "catppuccin::mocha", &options)?;
+//! print!("{ansi}");
+//! # Ok::<(), eyre::Report>(())
+//! ```
+//!
+//! With the `ffi` feature the same is available to C; see `include/umber.h`.
+//! With the `wasm` feature and no default features the library builds for
+//! `wasm32-unknown-unknown` and exports `highlight_to_html` to JavaScript.
+
+// Modules shared with the `umber` binary; they are not a stable API.
+#[doc(hidden)]
+pub mod ansi;
+#[doc(hidden)]
+pub mod cache;
+#[doc(hidden)]
+pub mod color_depth;
+#[doc(hidden)]
+pub mod contrast;
+#[doc(hidden)]
+pub mod custom_langs;
+#[doc(hidden)]
+pub mod decorations;
+#[doc(hidden)]
+pub mod detect;
+#[doc(hidden)]
+pub mod diff;
+#[doc(hidden)]
+pub mod git;
+#[doc(hidden)]
+pub mod grammars;
+#[doc(hidden)]
+pub mod highlight;
+#[doc(hidden)]
+pub mod html;
+#[doc(hidden)]
+pub mod listing;
+#[doc(hidden)]
+pub mod log_tokens;
+#[doc(hidden)]
+pub mod printer;
+#[doc(hidden)]
+pub mod queries;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use color_depth::ColorDepth;
+pub use highlight::{Format, Options, builtin_theme, highlight};
+pub use printer::Umber;
@@ -0,0 +1,93 @@
+//! `--max-highlight-size`: inputs tree-sitter would take minutes over, such
+//! as a JSON dump of hundreds of megabytes or a minified `bundle.js` on a
+//! single line, are shown without highlighting instead.
+
+/// Largest input highlighted when `--max-highlight-size` is not given.
+pub const DEFAULT_MAX_HIGHLIGHT_SIZE: &str = "32M";
+
+/// Inputs smaller than this are highlighted however long their lines are.
+const MINIFIED_MIN_BYTES: usize = 64 * 1024;
+/// Average line length above which an input is taken to be minified.
+const MINIFIED_LINE_BYTES: usize = 1000;
+
+/// The `--max-highlight-size` limit; `None` for `none`, which also turns off
+/// the check for minified input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxHighlightSize(pub Option<u64>);
+
+/// Why an input is shown without highlighting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Skip {
+  TooLarge,
+  Minified,
+}
+
+impl MaxHighlightSize {
+  pub fn check(self, bytes: &[u8]) -> Option<Skip> {
+    let max = self.0?;
+    if bytes.len() as u64 > max {
+      return Some(Skip::TooLarge);
+    }
+    if bytes.len() < MINIFIED_MIN_BYTES {
+      return None;
+    }
+    let lines = bytes.iter().filter(|byte| **byte == b'\n').count() + 1;
+    (bytes.len() / lines > MINIFIED_LINE_BYTES).then_some(Skip::Minified)
+  }
+}
+
+/// Parse a size such as `500000`, `512K`, `32M` or `1G` (powers of 1024), or
+/// `none` for no limit.
+pub fn parse_max_highlight_size(raw: &str) -> Result<MaxHighlightSize, String> {
+  let raw = raw.trim();
+  if raw.eq_ignore_ascii_case("none") {
+    return Ok(MaxHighlightSize(None));
+  }
+  let split = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+  let (digits, unit) = raw.split_at(split);
+  let shift = match unit.to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+    "" => 0,
+    "K" => 10,
+    "M" => 20,
+    "G" => 30,
+    _ => return Err(format!("unknown size unit '{unit}', use K, M or G")),
+  };
+  let value: u64 = digits
+    .parse()
+    .map_err(|_| format!("'{raw}' is not a size such as 32M or none"))?;
+  value
+    .checked_mul(1 << shift)
+    .map(|bytes| MaxHighlightSize(Some(bytes)))
+    .ok_or_else(|| format!("'{raw}' is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_max_highlight_size() {
+    let parse = |raw| parse_max_highlight_size(raw).map(|size| size.0);
+    assert_eq!(parse("32M"), Ok(Some(32 * 1024 * 1024)));
+    assert_eq!(parse("512kib"), Ok(Some(512 * 1024)));
+    assert_eq!(parse("1000"), Ok(Some(1000)));
+    assert_eq!(parse("none"), Ok(None));
+    assert!(parse("12X").is_err());
+    assert!(parse("M").is_err());
+  }
+
+  #[test]
+  fn test_check() {
+    let limit = MaxHighlightSize(Some(1024 * 1024));
+    assert_eq!(limit.check(b"fn main() {}\n"), None);
+    assert_eq!(
+      limit.check(&vec![b'x'; 2 * 1024 * 1024]),
+      Some(Skip::TooLarge)
+    );
+    let minified = vec![b'x'; 100 * 1024];
+    assert_eq!(limit.check(&minified), Some(Skip::Minified));
+    assert_eq!(MaxHighlightSize(None).check(&minified), None);
+    let lines = "let x = 1;\n".repeat(10_000);
+    assert_eq!(limit.check(lines.as_bytes()), None);
+  }
+}
@@ -0,0 +1,130 @@
+//! Highlighted files collected for `--format`, shared by the document
+//! renderers.
+
+use std::collections::BTreeSet;
+
+use syntastica::style::{Color, Style};
+use syntastica::theme::ResolvedTheme;
+use unicode_width::UnicodeWidthChar;
+
+use crate::contrast::contrast_ratio;
+use crate::decorations::{TAB_STOP, get_dim_style_or_create};
+use crate::diff::HighlightedLine;
+
+/// A highlighted file: its selected lines with their numbers, and the theme
+/// and line number setting of its language.
+pub struct Listing<'a> {
+  pub name: String,
+  pub lines: Vec<(usize, HighlightedLine)>,
+  pub theme: &'a ResolvedTheme,
+  pub numbers: bool,
+}
+
+impl Listing<'_> {
+  /// Digits in the largest line number.
+  pub fn number_width(&self) -> usize {
+    self
+      .lines
+      .iter()
+      .map(|(line_no, _)| line_no.to_string().len())
+      .max()
+      .unwrap_or(1)
+  }
+
+  /// The style keys of all pieces, for formats that define a macro for each.
+  pub fn style_keys(&self) -> BTreeSet<&'static str> {
+    self
+      .lines
+      .iter()
+      .flat_map(|(_, pieces)| pieces.iter().filter_map(|(_, key)| *key))
+      .collect()
+  }
+}
+
+/// `pieces` with each tab replaced by spaces up to the next tab stop, counted
+/// from the start of the line as with `--tabs`.
+pub fn expand_tabs(pieces: &HighlightedLine) -> HighlightedLine {
+  let mut column = 0;
+  pieces
+    .iter()
+    .map(|(text, style_key)| {
+      let mut expanded = String::with_capacity(text.len());
+      for c in text.chars() {
+        if c == '\t' {
+          let len = TAB_STOP - column % TAB_STOP;
+          expanded.extend(std::iter::repeat_n(' ', len));
+          column += len;
+        } else {
+          expanded.push(c);
+          column += c.width().unwrap_or(0);
+        }
+      }
+      (expanded, *style_key)
+    })
+    .collect()
+}
+
+/// Text runs of one row, with tabs already expanded.
+pub type Row = Vec<(String, Option<Style>)>;
+
+/// The rows of every listing, with a blank row between files.
+pub fn rows(listings: &[Listing<'_>]) -> Vec<Row> {
+  let mut rows = Vec::new();
+  for (index, listing) in listings.iter().enumerate() {
+    if index > 0 {
+      rows.push(Row::new());
+    }
+    let dim_style = get_dim_style_or_create(listing.theme);
+    let width = listing.number_width();
+    for (line_no, pieces) in &listing.lines {
+      let mut row = Row::new();
+      if listing.numbers {
+        row.push((format!("{line_no:>width$} "), Some(dim_style)));
+      }
+      for (text, style_key) in expand_tabs(pieces) {
+        let style = style_key.and_then(|key| listing.theme.find_style(key));
+        row.push((text, style));
+      }
+      rows.push(row);
+    }
+  }
+  rows
+}
+
+/// Text and background colors of an image of the code. Themes without a
+/// background of their own, like ansi, get black or white, whichever their
+/// text reads better on.
+pub fn image_colors(theme: &ResolvedTheme) -> (Color, Color) {
+  let normal = theme.get("_normal").copied();
+  let foreground = normal.map_or(Color::new(0xcc, 0xcc, 0xcc), |normal| normal.color());
+  let background = normal.and_then(|normal| normal.bg()).unwrap_or_else(|| {
+    let black = Color::new(0, 0, 0);
+    let white = Color::new(255, 255, 255);
+    if contrast_ratio(foreground, black) >= contrast_ratio(foreground, white) {
+      black
+    } else {
+      white
+    }
+  });
+  (foreground, background)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_expand_tabs() {
+    let pieces = vec![
+      ("ab\t".to_string(), Some("keyword")),
+      ("\tc".to_string(), None),
+    ];
+    assert_eq!(
+      expand_tabs(&pieces),
+      vec![
+        ("ab      ".to_string(), Some("keyword")),
+        ("        c".to_string(), None),
+      ]
+    );
+  }
+}
@@ -0,0 +1,427 @@
+//! The `log` pseudo-language. Logs have no grammar, so instead of a
+//! tree-sitter parse a token layer picks out timestamps, levels, IPv4
+//! addresses, UUIDs and quoted strings line by line, and turns them into the
+//! highlight events a grammar would give.
+
+use std::ops::Range;
+
+use once_cell::sync::OnceCell;
+use syntastica::language_set::HighlightConfiguration;
+use syntastica::theme::THEME_KEYS;
+use syntastica_highlight::{Highlight, HighlightEvent};
+use syntastica_parsers_git::Lang;
+
+use crate::highlight::HighlightItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+  Timestamp,
+  Error,
+  Warning,
+  Info,
+  Debug,
+  Address,
+  Uuid,
+  String,
+}
+
+impl Token {
+  fn theme_key(self) -> &'static str {
+    match self {
+      Self::Timestamp => "string.special",
+      Self::Error => "comment.error",
+      Self::Warning => "comment.warning",
+      Self::Info => "comment.note",
+      Self::Debug => "comment",
+      Self::Address => "number",
+      Self::Uuid => "constant",
+      Self::String => "string",
+    }
+  }
+
+  fn highlight(self) -> Option<Highlight> {
+    THEME_KEYS
+      .iter()
+      .position(|key| *key == self.theme_key())
+      .map(Highlight)
+  }
+}
+
+static CONFIG: OnceCell<HighlightConfiguration> = OnceCell::new();
+
+/// A stand-in configuration, so `log` can be looked up like the languages
+/// with a grammar. It never parses anything: `highlight_events` recognizes
+/// it and uses `events` instead.
+pub fn config() -> syntastica::Result<&'static HighlightConfiguration> {
+  CONFIG.get_or_try_init(|| {
+    Ok(HighlightConfiguration::new(
+      Lang::Ini.get(),
+      "log",
+      "",
+      "",
+      "",
+    )?)
+  })
+}
+
+/// Whether `config` is the one from `config()`.
+pub fn is_config(config: &HighlightConfiguration) -> bool {
+  CONFIG.get().is_some_and(|log| std::ptr::eq(log, config))
+}
+
+/// Highlight events for `text`, line by line.
+pub fn events(text: &str) -> impl Iterator<Item = HighlightItem> + '_ {
+  let mut offset = 0;
+  text.split_inclusive('\n').flat_map(move |line| {
+    let start = offset;
+    offset += line.len();
+    line_events(line, start)
+  })
+}
+
+fn line_events(line: &str, start: usize) -> Vec<HighlightItem> {
+  let mut events = Vec::new();
+  let mut end = 0;
+  for (range, token) in tokens(line) {
+    let Some(highlight) = token.highlight() else {
+      continue;
+    };
+    if range.start > end {
+      events.push(Ok(HighlightEvent::Source {
+        start: start + end,
+        end: start + range.start,
+      }));
+    }
+    events.push(Ok(HighlightEvent::HighlightStart(highlight)));
+    events.push(Ok(HighlightEvent::Source {
+      start: start + range.start,
+      end: start + range.end,
+    }));
+    events.push(Ok(HighlightEvent::HighlightEnd));
+    end = range.end;
+  }
+  if end < line.len() {
+    events.push(Ok(HighlightEvent::Source {
+      start: start + end,
+      end: start + line.len(),
+    }));
+  }
+  events
+}
+
+/// Whether most of the first lines of `content` start with a timestamp, or
+/// with an address and a bracketed date as in web server access logs.
+pub fn looks_like_log(content: &str) -> bool {
+  let lines: Vec<_> = content
+    .lines()
+    .filter(|line| !line.trim().is_empty())
+    .take(5)
+    .collect();
+  let stamped = lines
+    .iter()
+    .filter(|line| {
+      let line = line.trim_start_matches('[');
+      timestamp(line).is_some()
+        || address(line).is_some_and(|len| {
+          line[len..]
+            .split_once('[')
+            .is_some_and(|(_, date)| clf_date(date).is_some())
+        })
+    })
+    .count();
+  !lines.is_empty() && stamped * 2 > lines.len()
+}
+
+/// The tokens of `line`, in order and not overlapping.
+fn tokens(line: &str) -> Vec<(Range<usize>, Token)> {
+  let mut tokens = Vec::new();
+  let mut index = 0;
+  while index < line.len() {
+    let rest = &line[index..];
+    // Not inside a word or dotted number, such as the `2.3.4.5` of `1.2.3.4.5`
+    let at_word_start = !line[..index].ends_with(|c: char| c.is_ascii_alphanumeric() || c == '.');
+    let found = if rest.starts_with('"') {
+      quoted(rest).map(|len| (len, Token::String))
+    } else if at_word_start {
+      timestamp(rest)
+        .map(|len| (len, Token::Timestamp))
+        .or_else(|| uuid(rest).map(|len| (len, Token::Uuid)))
+        .or_else(|| address(rest).map(|len| (len, Token::Address)))
+        .or_else(|| level(rest, &line[..index]))
+    } else {
+      None
+    };
+    match found {
+      Some((len, token)) => {
+        tokens.push((index..index + len, token));
+        index += len;
+      }
+      None => index += rest.chars().next().map_or(1, char::len_utf8),
+    }
+  }
+  tokens
+}
+
+/// Number of ASCII digits at the start of `text`.
+fn digits(text: &str) -> usize {
+  text.bytes().take_while(u8::is_ascii_digit).count()
+}
+
+/// Whether the `len` bytes at the start of `text` end a word.
+fn ends_word(text: &str, len: usize) -> bool {
+  !text[len..].starts_with(|c: char| c.is_ascii_alphanumeric())
+}
+
+const MONTHS: [&str; 12] = [
+  "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// `2024-05-01T12:00:00.123Z`, `2024/05/01 12:00:00`, `May  1 12:00:00` as
+/// syslog writes it, `01/May/2024:12:00:00 +0000` from access logs, or a
+/// bare `12:00:00`.
+fn timestamp(text: &str) -> Option<usize> {
+  let len = date(text)
+    .map(|date| {
+      let time = text[date..].strip_prefix(['T', ' ']).and_then(time);
+      time.map_or(date, |time| date + 1 + time)
+    })
+    .or_else(|| syslog_date(text))
+    .or_else(|| clf_date(text))
+    .or_else(|| time(text))?;
+  ends_word(text, len).then_some(len)
+}
+
+/// `2024-05-01` or `2024/05/01`.
+fn date(text: &str) -> Option<usize> {
+  let bytes = text.as_bytes();
+  let valid = bytes.len() >= 10
+    && digits(text) == 4
+    && matches!(bytes[4], b'-' | b'/')
+    && digits(&text[5..]) == 2
+    && bytes[7] == bytes[4]
+    && digits(&text[8..]) == 2;
+  valid.then_some(10)
+}
+
+/// `12:00:00`, with optional fractions of a second and time zone.
+fn time(text: &str) -> Option<usize> {
+  let bytes = text.as_bytes();
+  let valid = bytes.len() >= 8
+    && digits(text) == 2
+    && bytes[2] == b':'
+    && digits(&text[3..]) == 2
+    && bytes[5] == b':'
+    && digits(&text[6..]) == 2;
+  if !valid {
+    return None;
+  }
+  let mut len = 8;
+  if let Some(fraction) = text[len..].strip_prefix(['.', ',']) {
+    let fraction = digits(fraction);
+    if fraction > 0 {
+      len += 1 + fraction;
+    }
+  }
+  Some(len + zone(&text[len..]))
+}
+
+/// Length of the time zone at the start of `text`: `Z`, `+02:00`, `-0700`
+/// or `+02`.
+fn zone(text: &str) -> usize {
+  if text.starts_with('Z') {
+    return 1;
+  }
+  let Some(offset) = text.strip_prefix(['+', '-']) else {
+    return 0;
+  };
+  match digits(offset) {
+    2 if offset[2..]
+      .strip_prefix(':')
+      .is_some_and(|minutes| digits(minutes) == 2) =>
+    {
+      6
+    }
+    2 => 3,
+    4 => 5,
+    _ => 0,
+  }
+}
+
+/// `May  1 12:00:00`.
+fn syslog_date(text: &str) -> Option<usize> {
+  if !MONTHS.iter().any(|month| text.starts_with(month)) {
+    return None;
+  }
+  let rest = &text[3..];
+  let spaces = rest.len() - rest.trim_start_matches(' ').len();
+  let rest = &rest[spaces..];
+  let day = digits(rest);
+  if spaces == 0 || !(1..=2).contains(&day) || !rest[day..].starts_with(' ') {
+    return None;
+  }
+  Some(3 + spaces + day + 1 + time(&rest[day + 1..])?)
+}
+
+/// `01/May/2024:12:00:00 +0000`.
+fn clf_date(text: &str) -> Option<usize> {
+  let bytes = text.as_bytes();
+  let valid = bytes.len() >= 12
+    && digits(text) == 2
+    && bytes[2] == b'/'
+    && MONTHS.iter().any(|month| text[3..].starts_with(month))
+    && bytes[6] == b'/'
+    && digits(&text[7..]) == 4
+    && bytes[11] == b':';
+  if !valid {
+    return None;
+  }
+  let len = 12 + time(&text[12..])?;
+  match text[len..].strip_prefix(' ').map(zone) {
+    Some(5) => Some(len + 6),
+    _ => Some(len),
+  }
+}
+
+/// `123e4567-e89b-12d3-a456-426614174000`.
+fn uuid(text: &str) -> Option<usize> {
+  let bytes = text.as_bytes();
+  let mut len = 0;
+  for (index, group) in [8, 4, 4, 4, 12].into_iter().enumerate() {
+    if index > 0 {
+      if bytes.get(len) != Some(&b'-') {
+        return None;
+      }
+      len += 1;
+    }
+    let hex = bytes[len..]
+      .iter()
+      .take_while(|byte| byte.is_ascii_hexdigit())
+      .count();
+    if hex != group {
+      return None;
+    }
+    len += group;
+  }
+  ends_word(text, len).then_some(len)
+}
+
+/// `192.168.0.1`, optionally with a port. Longer dotted numbers, such as
+/// versions, are not addresses.
+fn address(text: &str) -> Option<usize> {
+  let mut len = 0;
+  for index in 0..4 {
+    if index > 0 {
+      if !text[len..].starts_with('.') {
+        return None;
+      }
+      len += 1;
+    }
+    let octet = digits(&text[len..]);
+    if !(1..=3).contains(&octet) || text[len..len + octet].parse::<u8>().is_err() {
+      return None;
+    }
+    len += octet;
+  }
+  if let Some(port) = text[len..].strip_prefix(':') {
+    let port = digits(port);
+    if (1..=5).contains(&port) {
+      len += 1 + port;
+    }
+  }
+  let more = text[len..]
+    .strip_prefix('.')
+    .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+  (!more && ends_word(text, len)).then_some(len)
+}
+
+/// A level such as `ERROR` or `warn`. Lowercase ones only count after
+/// `level=`, as logfmt writes them, since they are common words.
+fn level(text: &str, before: &str) -> Option<(usize, Token)> {
+  let len = text.bytes().take_while(u8::is_ascii_alphabetic).count();
+  let word = &text[..len];
+  let keyed = before.ends_with("level=") || before.ends_with("lvl=");
+  if len == 0 || !(keyed || word.bytes().all(|byte| byte.is_ascii_uppercase())) {
+    return None;
+  }
+  let token = match word.to_ascii_uppercase().as_str() {
+    "FATAL" | "PANIC" | "EMERG" | "ALERT" | "CRIT" | "CRITICAL" | "ERR" | "ERROR" => Token::Error,
+    "WARN" | "WARNING" => Token::Warning,
+    "INFO" | "NOTICE" => Token::Info,
+    "DEBUG" | "TRACE" => Token::Debug,
+    _ => return None,
+  };
+  Some((len, token))
+}
+
+/// Length of the double-quoted string at the start of `text`, which must end
+/// on the same line.
+fn quoted(text: &str) -> Option<usize> {
+  let mut escaped = false;
+  for (index, c) in text.char_indices().skip(1) {
+    match c {
+      '\n' => return None,
+      _ if escaped => escaped = false,
+      '\\' => escaped = true,
+      '"' => return Some(index + 1),
+      _ => {}
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn texts(line: &str) -> Vec<(&str, Token)> {
+    tokens(line)
+      .into_iter()
+      .map(|(range, token)| (&line[range], token))
+      .collect()
+  }
+
+  #[test]
+  fn test_tokens() {
+    assert_eq!(
+      texts(
+        "2024-05-01T12:00:00.123Z ERROR request 123e4567-e89b-12d3-a456-426614174000 from 10.0.0.7:8080 failed: \"timed \\\"out\\\"\""
+      ),
+      [
+        ("2024-05-01T12:00:00.123Z", Token::Timestamp),
+        ("ERROR", Token::Error),
+        ("123e4567-e89b-12d3-a456-426614174000", Token::Uuid),
+        ("10.0.0.7:8080", Token::Address),
+        ("\"timed \\\"out\\\"\"", Token::String),
+      ]
+    );
+    assert_eq!(
+      texts("May  1 12:00:00 host sshd[42]: level=warn msg=Accepted"),
+      [
+        ("May  1 12:00:00", Token::Timestamp),
+        ("warn", Token::Warning)
+      ]
+    );
+    assert_eq!(
+      texts("127.0.0.1 - - [01/May/2024:12:00:00 +0000] \"GET / HTTP/1.1\" 200"),
+      [
+        ("127.0.0.1", Token::Address),
+        ("01/May/2024:12:00:00 +0000", Token::Timestamp),
+        ("\"GET / HTTP/1.1\"", Token::String),
+      ]
+    );
+    // Versions, words containing levels and lowercase levels in prose
+    assert!(texts("upgraded to 1.2.3.4.5 with INFORMATION about an error").is_empty());
+  }
+
+  #[test]
+  fn test_looks_like_log() {
+    assert!(looks_like_log(
+      "[2024-05-01 12:00:00] INFO start\n[2024-05-01 12:00:01] INFO ready\n"
+    ));
+    assert!(looks_like_log(
+      "10.0.0.1 - - [01/May/2024:12:00:00 +0000] \"GET / HTTP/1.1\" 200 512\n"
+    ));
+    assert!(!looks_like_log("fn main() {}\n"));
+    assert!(!looks_like_log(""));
+  }
+}
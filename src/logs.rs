@@ -0,0 +1,138 @@
+//! `--log-prefixes`: logs multiplexed by tools such as `docker compose logs`
+//! or `kubectl logs --prefix`, where every line starts with the name of the
+//! stream it came from and often a timestamp. The prefix is split off so it
+//! can be dimmed and the message highlighted on its own.
+
+/// Split `line` into the tool prefix and the message. Prefixes may be
+/// stacked, e.g. a compose service name followed by a timestamp.
+pub fn split_prefix(line: &str) -> (&str, &str) {
+  let mut end = 0;
+  while let Some(len) = [compose_service, kubectl_pod, timestamp]
+    .iter()
+    .find_map(|matcher| matcher(&line[end..]))
+  {
+    end += len;
+  }
+  line.split_at(end)
+}
+
+/// True when the message is a JSON object or array.
+pub fn is_json(message: &str) -> bool {
+  let message = message.trim();
+  let delimited = (message.starts_with('{') && message.ends_with('}'))
+    || (message.starts_with('[') && message.ends_with(']'));
+  delimited && serde_json::from_str::<serde::de::IgnoredAny>(message).is_ok()
+}
+
+/// `web-1  | ` from docker compose.
+fn compose_service(text: &str) -> Option<usize> {
+  let name = text
+    .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    .filter(|len| *len > 0)?;
+  let rest = &text[name..];
+  let padding = rest.len() - rest.trim_start_matches(' ').len();
+  if padding == 0 || !rest[padding..].starts_with('|') {
+    return None;
+  }
+  let len = name + padding + 1;
+  Some(len + usize::from(text[len..].starts_with(' ')))
+}
+
+/// `[pod/web-6d4cf56db6-x7k2p/app] ` from `kubectl logs --prefix`.
+fn kubectl_pod(text: &str) -> Option<usize> {
+  let inner = text.strip_prefix('[')?;
+  let close = inner.find(']')?;
+  let name = &inner[..close];
+  if !name.contains('/') || name.contains(char::is_whitespace) {
+    return None;
+  }
+  followed_by_space(text, close + 2)
+}
+
+/// An ISO 8601 timestamp such as `2024-05-01T12:00:00.123Z ` or
+/// `2024-05-01 12:00:00,123 `.
+fn timestamp(text: &str) -> Option<usize> {
+  let bytes = text.as_bytes();
+  let digits = |range: std::ops::Range<usize>| {
+    bytes
+      .get(range)
+      .is_some_and(|part| part.iter().all(u8::is_ascii_digit))
+  };
+  let at = |index: usize, expected: &[u8]| bytes.get(index).is_some_and(|b| expected.contains(b));
+  if !(digits(0..4)
+    && at(4, b"-")
+    && digits(5..7)
+    && at(7, b"-")
+    && digits(8..10)
+    && at(10, b"T ")
+    && digits(11..13)
+    && at(13, b":")
+    && digits(14..16)
+    && at(16, b":")
+    && digits(17..19))
+  {
+    return None;
+  }
+  let mut end = 19;
+  if at(end, b".,") {
+    end += 1;
+    while at(end, b"0123456789") {
+      end += 1;
+    }
+  }
+  if at(end, b"Z") {
+    end += 1;
+  } else if at(end, b"+-") && digits(end + 1..end + 3) {
+    end += 3;
+    if at(end, b":") {
+      end += 1;
+    }
+    if digits(end..end + 2) {
+      end += 2;
+    }
+  }
+  followed_by_space(text, end)
+}
+
+/// `len` plus the whitespace after it, or `None` when no whitespace follows.
+fn followed_by_space(text: &str, len: usize) -> Option<usize> {
+  let rest = text.get(len..)?;
+  let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+  (spaces > 0).then_some(len + spaces)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_split_prefix() {
+    assert_eq!(
+      split_prefix("web-1  | 2024-05-01T12:00:00.123456789Z {\"level\":\"info\"}"),
+      (
+        "web-1  | 2024-05-01T12:00:00.123456789Z ",
+        "{\"level\":\"info\"}"
+      )
+    );
+    assert_eq!(
+      split_prefix("[pod/api-6d4cf56db6-x7k2p/app] listening on :8080"),
+      ("[pod/api-6d4cf56db6-x7k2p/app] ", "listening on :8080")
+    );
+    assert_eq!(
+      split_prefix("2024-05-01 12:00:00,123+02:00 ERROR boom"),
+      ("2024-05-01 12:00:00,123+02:00 ", "ERROR boom")
+    );
+    assert_eq!(
+      split_prefix("[INFO] no prefix | here"),
+      ("", "[INFO] no prefix | here")
+    );
+  }
+
+  #[test]
+  fn test_is_json() {
+    assert!(is_json("{\"msg\": \"started\", \"port\": 8080}"));
+    assert!(is_json(" [1, 2] "));
+    assert!(!is_json("{not json}"));
+    assert!(!is_json("started on port 8080"));
+  }
+}
@@ -1,27 +1,87 @@
-mod custom_langs;
-mod decorations;
-mod git;
+mod base16;
+mod bat;
+mod bench;
+mod chop;
+mod commands;
+mod config;
+mod contents;
+mod custom_themes;
+mod encoding;
+mod eol;
+mod file_list;
+mod follow;
+mod grammar_build;
+mod header;
+mod hexdump;
+mod i18n;
+mod latex;
+mod limits;
+mod logs;
+mod padding;
+mod pager;
+mod paths;
+mod pipeline;
+mod preprocess;
+mod ranges;
+#[cfg(feature = "png")]
+mod raster;
+mod remote;
+mod report;
+mod stat;
+mod stream;
+mod style_filter;
+mod svg;
+mod syntax_map;
+mod tabs;
+mod terminal;
+mod tmtheme;
+mod typst;
 mod unprintable;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fmt::Write as _;
 use std::fs;
-use std::io::{self, IsTerminal, Read, Write};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-use clap::{CommandFactory, Parser, ValueEnum};
+use ansi::StripAnsi;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use color_depth::{ColorDepth, DepthRenderer};
+use commands::Subcommand;
+use contents::Contents;
+use contrast::Contrast;
 use dark_light::Mode as DarkLightMode;
 use decorations::DecorationConfig;
-use eyre::{Result, eyre};
-use palate;
+use eyre::{Result, WrapErr, eyre};
+use grammar_build::GrammarSource;
+use i18n::{Arg, tr, tr_args};
+use listing::Listing;
+use padding::{PaddedWriter, Padding};
+use ranges::{LineSelection, RangeSpec};
+use report::{ErrorFormat, Reporter};
+use style_filter::{ItalicText, StyleFilter};
 use syntastica::language_set::{EitherLang, LanguageSet, SupportedLanguage, Union};
-use syntastica::renderer::{Renderer, TerminalRenderer};
+use syntastica::renderer::Renderer;
+use syntastica::style::Color;
 use syntastica::theme::{ResolvedTheme, THEME_KEYS};
 use syntastica_highlight::{Highlight, HighlightConfiguration, HighlightEvent, Highlighter};
 use syntastica_parsers_git::{LANGUAGE_NAMES, Lang, LanguageSetImpl};
+use syntax_map::SyntaxMap;
+use terminal::TerminalQueries;
+use umber::{
+  ansi, cache, color_depth, contrast, custom_langs, decorations, detect, diff, git, grammars,
+  highlight, html, listing, queries,
+};
 
 use custom_langs::{CustomLang, CustomLanguageSet};
+use detect::detect_language_name;
+use highlight::{
+  HighlightItem, current_style_key, highlight_events, language_name, resolve_language_union,
+};
+use queries::QueryKind;
 
 const STREAM_OUTPUT_BUFFER_BYTES: usize = 64 * 1024;
 const STREAM_OUTPUT_FLUSH_BYTES: usize = 8 * 1024;
@@ -33,10 +93,62 @@ enum ColorWhen {
   Always,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum WrapWhen {
+  Auto,
+  Never,
+  Character,
+  Word,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Paging {
+  Auto,
+  Never,
+  Always,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+  /// Colored text for the terminal
+  Terminal,
+  /// An HTML fragment with inline styles
+  Html,
+  /// A standalone HTML page
+  HtmlFull,
+  /// A standalone SVG image
+  Svg,
+  /// A fancyvrb Verbatim environment for LaTeX
+  Latex,
+  /// A block for Typst
+  Typst,
+  /// A PNG image
+  Png,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryMode {
+  /// A one-line notice with the name and size instead of the bytes
+  Placeholder,
+  /// The bytes as they are, like cat
+  AsText,
+  /// A hex dump, as with --hex
+  Hex,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ThemeListing {
+  /// Each name above a sample highlighted in that theme
+  Preview,
+  /// Only the names
+  Plain,
+}
+
 #[derive(Parser, Debug)]
 #[command(
   name = "umber",
   version,
+  args_override_self = true,
   about = "cat with syntax highlighting",
   long_about = "A modern replacement for cat with syntax highlighting powered by tree-sitter.\n\
                 Automatically detects file types and applies appropriate syntax highlighting.\n\
@@ -48,7 +160,24 @@ enum ColorWhen {
     umber --language rust file.txt   Force Rust syntax highlighting\n    \
     umber --theme dracula main.js    Use Dracula color theme\n    \
     cat file.rs | umber              Read from stdin\n    \
-    umber *.py                       Display multiple files\n\n\
+    umber *.py                       Display multiple files\n    \
+    umber diff HEAD~1                Render git diff output\n    \
+    umber show HEAD~1:src/main.rs    Display a file as of a revision\n    \
+    umber blame main.rs              Show the commit and author of each line\n    \
+    umber cache --clear              Remove cached data\n\n\
+    COMMANDS:\n    \
+    view     Display files (the default)\n    \
+    diff     Render git diff output; FILE arguments are passed to git diff\n    \
+    show     Display REV:PATH from git, highlighted by PATH\n    \
+    blame    Display FILE with the commit and author of each line\n    \
+    themes   List available themes\n    \
+    langs    List supported languages\n    \
+    cache    Inspect or reset the on-disk cache\n\n\
+    ENVIRONMENT:\n    \
+    UMBER_OPTS   Default arguments, e.g. '--style=numbers --pager \"less -R\"'\n    \
+    UMBER_STYLE  Default --style\n    \
+    UMBER_THEME  Default --theme\n    \
+    UMBER_PAGER  Pager command, before PAGER\n\n\
     For available themes, see: https://docs.rs/syntastica-themes/latest/syntastica_themes/\n\n\
     To generate shell completions:\n    \
     umber --completions bash > ~/.local/share/bash-completion/completions/umber"
@@ -88,35 +217,156 @@ struct Cli {
   #[arg(
     long,
     value_name = "THEME",
-    default_value = "auto",
     help = "Color theme to use for syntax highlighting",
     long_help = "Specify a color theme for syntax highlighting.\n\n\
                  Use 'auto' (default) to automatically detect light/dark mode:\n  \
-                 - Light mode: catppuccin-latte\n  \
-                 - Dark mode: catppuccin-mocha\n\n\
+                 - Light mode: catppuccin-latte, or --theme-light\n  \
+                 - Dark mode: catppuccin-mocha, or --theme-dark\n\n\
                  Popular themes include:\n  \
                  dracula, nord, one-dark, one-light, gruvbox-dark, gruvbox-light,\n  \
                  solarized-dark, solarized-light, tokyo-night, catppuccin-mocha,\n  \
                  catppuccin-latte, catppuccin-frappe, catppuccin-macchiato\n\n\
+                 'ansi' uses only the 16 colors of the terminal's own palette.\n\n\
+                 A path to a .toml, .tmTheme or base16 .yaml theme file, or the name of\n\
+                 one in ~/.config/umber/themes/, uses that theme.\n\n\
+                 Defaults to the `name` in the [theme] table of the config file, then 'auto'.\n\
+                 Individual languages can use a different theme via [theme.per-language]\n\
+                 in ~/.config/umber/config.toml, e.g. markdown = \"one-light\".\n\n\
                  For a complete list of available themes, see:\n\
                  https://docs.rs/syntastica-themes/latest/syntastica_themes/"
   )]
-  theme: String,
+  theme: Option<String>,
+
+  #[arg(
+    long,
+    value_name = "THEME",
+    help = "Theme used by 'auto' in light mode",
+    long_help = "The theme 'auto' and 'light' pick in light mode.\n\
+                 Defaults to `light` in the [theme] table of the config file, then\n\
+                 catppuccin-latte."
+  )]
+  theme_light: Option<String>,
+
+  #[arg(
+    long,
+    value_name = "THEME",
+    help = "Theme used by 'auto' in dark mode",
+    long_help = "The theme 'auto' and 'dark' pick in dark mode.\n\
+                 Defaults to `dark` in the [theme] table of the config file, then\n\
+                 catppuccin-mocha."
+  )]
+  theme_dark: Option<String>,
 
   #[arg(
     long,
     short = 'n',
     value_name = "RANGE",
-    help = "Show only selected lines (e.g. 10-20, 10:20, 10,20, 10)",
+    allow_hyphen_values = true,
+    help = "Show only selected lines (e.g. 10-20, 10:20, 10, 10,20, 1-10,40-60, -20:)",
     long_help = "Show only selected lines from the file.\n\
-                 Accepted formats: start-end, start:end, start,end, or a single line number.\n\
+                 Accepted formats: start-end, start:end, or a single line number.\n\
+                 Negative numbers count from the end (-1 is the last line), and a missing\n\
+                 start or end means the first or last line.\n\
+                 Several ranges or lines can be separated by commas (10,20 is lines 10\n\
+                 and 20) or given with repeated --lines; overlapping ranges are merged.\n\
                  Examples:\n  \
                  umber --lines 10-20 main.rs\n  \
                  umber --lines 10:20 main.rs\n  \
+                 umber --lines 10 main.rs\n  \
                  umber --lines 10,20 main.rs\n  \
-                 umber --lines 10 main.rs"
+                 umber --lines 1-10,40-60 main.rs\n  \
+                 umber --lines 1-10 --lines 40-60 main.rs\n  \
+                 umber --lines -20: app.log"
+  )]
+  lines: Vec<String>,
+
+  #[arg(
+    long,
+    short = 'C',
+    value_name = "N",
+    help = "Show N lines around each --lines range",
+    long_help = "Show N lines of context around every range selected with --lines or a #L\n\
+                 suffix, stopping at the start and end of the file. In color the selected\n\
+                 lines are shaded so they stand out from their context.\n\
+                 Example:\n  \
+                 umber -C 3 'main.rs#L42'"
+  )]
+  context: Option<usize>,
+
+  #[arg(
+    long,
+    help = "Only show lines changed in the git working tree",
+    long_help = "Only show the lines of each file that differ from the git index, with\n\
+                 --diff-context lines around them and the change markers in the gutter.\n\
+                 Files without changes are skipped; files outside a repository are shown\n\
+                 whole.\n\
+                 Example:\n  \
+                 umber --diff src/*.rs"
+  )]
+  diff: bool,
+
+  #[arg(
+    long,
+    value_name = "N",
+    default_value_t = 2,
+    help = "Lines of context around each change with --diff"
+  )]
+  diff_context: usize,
+
+  #[arg(
+    long,
+    value_name = "REF",
+    help = "Compare change markers and --diff against REF instead of the index",
+    long_help = "Compare the change markers and --diff against a commit, branch or other\n\
+                 revision instead of the git index, so changes since REF are shown whether\n\
+                 they are committed, staged or not.\n\
+                 Examples:\n  \
+                 umber --diff --diff-base origin/main src/main.rs\n  \
+                 umber --style=changes --diff-base @{upstream} src/main.rs"
+  )]
+  diff_base: Option<String>,
+
+  #[arg(
+    long,
+    value_name = "N[:M]",
+    allow_hyphen_values = true,
+    action = ArgAction::Append,
+    help = "Draw the given lines with a highlighted background (repeatable)",
+    long_help = "Draw the given lines with a highlighted background while still showing\n\
+                 the rest of the file. Takes the same ranges as --lines and can be repeated.\n\
+                 The color comes from the theme, or from highlight-line in the [theme]\n\
+                 table of the config file.\n\
+                 Examples:\n  \
+                 umber --highlight-line 42 main.rs\n  \
+                 umber --highlight-line 10:12 --highlight-line 30 main.rs"
+  )]
+  highlight_line: Vec<String>,
+
+  #[arg(
+    long,
+    value_name = "NAME",
+    action = ArgAction::Append,
+    help = "Name to show and detect the language from instead of the input's path",
+    long_help = "Name shown in the header and used to detect the language instead of the\n\
+                 input's path, mostly for stdin. Given several times, each name applies to\n\
+                 the input in the same position.\n\
+                 Example:\n  \
+                 git show HEAD:src/main.rs | umber --file-name src/main.rs"
+  )]
+  file_name: Vec<PathBuf>,
+
+  #[arg(
+    long,
+    value_name = "SUFFIX",
+    action = ArgAction::Append,
+    help = "Detect files ending in SUFFIX by the name without it (repeatable)",
+    long_help = "Detect the language of files ending in SUFFIX from the name without it,\n\
+                 so main.rs.orig is highlighted as Rust. Adds to the built-in list:\n\
+                 ~ .bak .old .orig .dist .in .dpkg-dist .dpkg-old .rpmnew .rpmorig .rpmsave\n\
+                 Example:\n  \
+                 umber --ignored-suffix .backup config.toml.backup"
   )]
-  lines: Option<String>,
+  ignored_suffix: Vec<String>,
 
   #[arg(
     long,
@@ -129,8 +379,84 @@ struct Cli {
   #[arg(long, help = "Disable colored output")]
   no_color: bool,
 
-  #[arg(long, help = "List supported themes")]
-  list_themes: bool,
+  #[arg(
+    long,
+    value_enum,
+    default_value = "auto",
+    value_name = "DEPTH",
+    help = "Colors the terminal supports",
+    long_help = "How many colors the terminal supports. Theme colors are matched to the\n\
+                 closest entry of the 256-color or 16-color palette when truecolor is not\n\
+                 available.\n\
+                 auto: truecolor when COLORTERM says so, otherwise guessed from TERM.\n\
+                 truecolor: 24-bit colors.\n\
+                 256: the 256-color palette.\n\
+                 16: the 16 basic colors.\n\
+                 ansi: the 16 basic colors, with the ansi theme."
+  )]
+  color_depth: ColorDepth,
+
+  #[arg(
+    long,
+    value_enum,
+    value_name = "LEVEL",
+    default_value = "normal",
+    help = "Raise the contrast of the theme",
+    long_help = "Adjust theme colors for readability.\n\
+                 normal: the theme's own colors.\n\
+                 high: brighten or darken text colors until each has a contrast ratio of\n\
+                 at least 7:1 (WCAG AAA) against the theme's background, or the\n\
+                 terminal's when the theme has none."
+  )]
+  contrast: Contrast,
+
+  #[arg(
+    long,
+    value_enum,
+    value_name = "WHEN",
+    default_value = "always",
+    help = "Whether to render italic text",
+    long_help = "Whether theme styles may use italics. Many terminals render italic text\n\
+                 poorly or substitute reverse video.\n\
+                 always: keep the theme's italics.\n\
+                 never: render italic text upright."
+  )]
+  italic_text: ItalicText,
+
+  #[arg(long, help = "Never render text in bold")]
+  no_bold: bool,
+
+  #[arg(long, help = "Never underline text")]
+  no_underline: bool,
+
+  #[arg(
+    long,
+    value_enum,
+    value_name = "MODE",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "preview",
+    help = "List supported themes",
+    long_help = "List the supported themes.\n\
+                 preview (default): each name above a code sample highlighted in that\n\
+                 theme; only the names when the output is not colored.\n\
+                 plain: only the names, for scripts."
+  )]
+  list_themes: Option<ThemeListing>,
+
+  #[arg(
+    long,
+    value_name = "THEME",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "",
+    help = "Print a theme as TOML",
+    long_help = "Print every key of a theme, with its colors and attributes, in the TOML\n\
+                 format --theme reads. Without a name, prints the theme that would be used.\n\
+                 The output is a starting point for a theme of your own:\n\
+                 umber --export-theme=nord > ~/.config/umber/themes/mine.toml"
+  )]
+  export_theme: Option<String>,
 
   #[arg(
     long,
@@ -149,10 +475,236 @@ struct Cli {
   #[arg(
     long,
     value_name = "components",
-    help = "Style components (numbers, changes, headers, rich)"
+    help = "Style components (numbers, changes, headers, grid, blame, full, rich, plain)"
   )]
   style: Option<String>,
 
+  #[arg(
+    long,
+    short = 'p',
+    action = ArgAction::Count,
+    help = "Plain output without decorations; twice (-pp) also disables paging",
+    long_help = "Show only the (highlighted) content: no line numbers, git changes, headers\n\
+                 or zebra stripes, whatever --style or the config file ask for.\n\
+                 Given twice (-pp), also never page, like --paging never."
+  )]
+  plain: u8,
+
+  #[arg(
+    long,
+    help = "Shade every other line with a subtle background",
+    long_help = "Give every other line a slightly different background derived from the\n\
+                 theme background, which helps tracking long lines across wide terminals."
+  )]
+  zebra: bool,
+
+  #[arg(
+    long,
+    value_name = "N",
+    help = "Expand tabs to N columns",
+    long_help = "Replace tabs with spaces up to the next multiple of N columns, counted from\n\
+                 the start of each line so a gutter does not shift the tab stops. Wrapping\n\
+                 and --show-all then line up with what is displayed. 0 (the default) leaves\n\
+                 tabs to the terminal."
+  )]
+  tabs: Option<usize>,
+
+  #[arg(
+    long,
+    value_name = "N",
+    help = "Blank columns and lines to add around the output",
+    long_help = "Insert N blank columns on the left and right of every line and N blank\n\
+                 lines above and below the output. Use the --padding-* flags to set a\n\
+                 single side.\n\n\
+                 Examples:\n  \
+                 umber --padding 2 main.rs\n  \
+                 umber --padding-left 4 --padding-top 1 main.rs"
+  )]
+  padding: Option<usize>,
+
+  #[arg(long, value_name = "N", help = "Blank lines above the output")]
+  padding_top: Option<usize>,
+
+  #[arg(
+    long,
+    value_name = "N",
+    help = "Blank columns to the right of each line"
+  )]
+  padding_right: Option<usize>,
+
+  #[arg(long, value_name = "N", help = "Blank lines below the output")]
+  padding_bottom: Option<usize>,
+
+  #[arg(
+    long,
+    value_name = "N",
+    help = "Blank columns to the left of each line"
+  )]
+  padding_left: Option<usize>,
+
+  #[arg(
+    long,
+    help = "Produce byte-stable output that does not depend on the terminal",
+    long_help = "Never query the terminal or the environment while rendering.\n\
+                 The terminal width is fixed (80 columns unless --terminal-width is given),\n\
+                 'auto' themes resolve to the dark default without asking the desktop, and\n\
+                 --show-all always uses Unicode symbols regardless of the locale.\n\
+                 Blame margins show commit dates instead of ages, and {mtime} in\n\
+                 --header-format is -.\n\
+                 Useful for golden tests and reproducible documentation."
+  )]
+  deterministic: bool,
+
+  #[arg(
+    long,
+    value_name = "WIDTH",
+    help = "Set the terminal width instead of detecting it"
+  )]
+  terminal_width: Option<usize>,
+
+  #[arg(
+    long,
+    value_name = "COMMAND",
+    help = "Page output through COMMAND when writing to a terminal",
+    long_help = "Send output through the given pager command when stdout is a terminal.\n\
+                 The command is split into words like a shell would, so arguments and\n\
+                 quotes are supported. Falls back to the UMBER_PAGER and PAGER environment\n\
+                 variables, then to 'less -RFX', which exits right away when the output fits\n\
+                 on one screen. An empty command disables paging.\n\
+                 A pager that would run umber itself is ignored.\n\n\
+                 Examples:\n  \
+                 umber --pager 'less -RX' main.rs\n  \
+                 umber --pager 'ov -F' main.rs"
+  )]
+  pager: Option<String>,
+
+  #[arg(
+    long,
+    value_enum,
+    default_value = "auto",
+    value_name = "WHEN",
+    help = "When to page output",
+    long_help = "When to send output through the pager.\n\
+                 auto: when stdout is a terminal, except with --follow and --log-prefixes,\n\
+                 whose output never ends.\n\
+                 never: always write to stdout.\n\
+                 always: even when stdout is not a terminal."
+  )]
+  paging: Paging,
+
+  #[arg(
+    long,
+    value_enum,
+    default_value = "auto",
+    value_name = "MODE",
+    help = "How to wrap long lines",
+    long_help = "Wrap lines longer than the terminal in decorated output, keeping the gutter\n\
+                 aligned: continuation rows get a blank line number column.\n\
+                 auto: character wrapping when stdout is a terminal, none otherwise.\n\
+                 never: let lines run past the edge of the terminal.\n\
+                 character: break at the last character that fits.\n\
+                 word: break after the last space that fits when there is one."
+  )]
+  wrap: WrapWhen,
+
+  #[arg(
+    long,
+    help = "Read from an interactive stdin without printing a hint",
+    long_help = "When no files are given and stdin is a terminal, umber prints a short hint\n\
+                 to stderr so it doesn't appear to hang. Pass --stdin to type input\n\
+                 interactively without the hint."
+  )]
+  stdin: bool,
+
+  #[arg(
+    long,
+    value_name = "FILE",
+    help = "Read the files to display from FILE, one per line ('-' for stdin)",
+    long_help = "Read the paths of the files to display from FILE, or from stdin when FILE is\n\
+                 '-', one per line, after any given as arguments. Lists too long for the\n\
+                 command line work this way. Empty lines are skipped.\n\n\
+                 Examples:\n  \
+                 git ls-files '*.rs' | umber --files-from - --style=headers\n  \
+                 fd -0 -e toml | umber --files-from - -0"
+  )]
+  files_from: Option<PathBuf>,
+
+  #[arg(
+    short = '0',
+    long = "null",
+    requires = "files_from",
+    help = "Entries in the --files-from list are separated by NUL bytes",
+    long_help = "Entries in the --files-from list are separated by NUL bytes instead of\n\
+                 newlines, as written by fd -0, find -print0 and git ls-files -z, so paths may\n\
+                 contain newlines."
+  )]
+  null: bool,
+
+  #[arg(
+    long,
+    value_enum,
+    default_value = "human",
+    value_name = "FORMAT",
+    help = "Format of error messages on stderr",
+    long_help = "Format of errors and warnings written to stderr.\n\
+                 human: colored messages with hints (e.g. a similarly named file).\n\
+                 json: one JSON object per line with level, path, kind, message and hint."
+  )]
+  error_format: ErrorFormat,
+
+  #[arg(
+    long,
+    value_enum,
+    default_value = "terminal",
+    value_name = "FORMAT",
+    help = "Format of the highlighted output",
+    long_help = "Format of the highlighted output.\n\
+                 terminal: colored text for the terminal.\n\
+                 html: a <pre> block styled inline, to paste into a page or a blog post.\n\
+                 html-full: a standalone HTML page with the theme's background.\n\
+                 svg: an image of the code on the theme's background, to share as a\n\
+                 screenshot. --padding sets its margin, in columns and lines.\n\
+                 latex: a fancyvrb Verbatim environment with an xcolor color and a macro\n\
+                 per theme key, e.g. \\umberComment; needs the xcolor and fancyvrb packages.\n\
+                 typst: a block with a function per theme key, e.g. umber-comment.\n\
+                 png: an image like svg, drawn with an embedded DejaVu Sans Mono; write it\n\
+                 with --output or redirect stdout.\n\
+                 In HTML with --style=numbers, each line number is a <span id=\"L12\"> that\n\
+                 links can point at. The output does not depend on the terminal or the pager.\n\
+                 Example:\n  \
+                 umber --format html-full --style=numbers main.rs > main.html"
+  )]
+  format: OutputFormat,
+
+  #[arg(
+    long,
+    value_name = "FAMILY",
+    help = "Font of --format svg",
+    long_help = "CSS font-family of the code in --format svg, e.g. \"'JetBrains Mono', monospace\".\n\
+                 The font has to be installed wherever the image is viewed. Defaults to the\n\
+                 system's monospace fonts."
+  )]
+  font_family: Option<String>,
+
+  #[arg(
+    long,
+    help = "Frame --format svg and png output as a window",
+    long_help = "Draw a title bar above --format svg and png output, with the three window buttons\n\
+                 and the file name, like a screenshot of a terminal window."
+  )]
+  window_chrome: bool,
+
+  #[arg(
+    long,
+    short = 'o',
+    value_name = "FILE",
+    help = "Write --format output to FILE",
+    long_help = "Write the output of --format to FILE instead of stdout.\n\
+                 Example:\n  \
+                 umber --format png -o snippet.png -n 10:30 main.rs"
+  )]
+  output: Option<PathBuf>,
+
   #[arg(long, short = 'u', help = "No-op, output is always unbuffered")]
   unbuffered: bool,
 
@@ -163,6 +715,24 @@ struct Cli {
   )]
   show_all: bool,
 
+  #[arg(
+    long,
+    help = "Mark each line's ending as LF (␊) or CRLF (↵␊)",
+    long_help = "Mark the end of every line with its line ending, ␊ for LF and ↵␊ for CRLF\n\
+                 ($ and ^M$ without UTF-8), as --show-all does but without marking spaces and\n\
+                 tabs. Headers of files that mix both say so. Useful when tracking down\n\
+                 cross-platform whitespace problems."
+  )]
+  show_line_endings: bool,
+
+  #[arg(
+    long,
+    help = "Show CRLF line endings as LF",
+    long_help = "Turn CRLF line endings into LF before showing the input, so that carriage\n\
+                 returns never reach the terminal and files from Windows show like the rest."
+  )]
+  normalize_eol: bool,
+
   #[arg(
     long,
     help = "Generate man page",
@@ -175,75 +745,576 @@ struct Cli {
   man_page: bool,
 
   #[arg(
-    value_name = "FILE",
-    help = "Files to display (use '-' or omit for stdin)",
-    long_help = "One or more files to display with syntax highlighting.\n\
-                 If no files are specified, or if '-' is given, reads from stdin.\n\n\
+    long,
+    short = 'f',
+    help = "Keep printing lines appended to the files, like tail -f",
+    long_help = "After displaying the files, keep watching them and print appended lines\n\
+                 as they are written, with line numbers continuing where they left off.\n\
+                 Several files can be followed at once; a header marks which file each\n\
+                 group of new lines belongs to. A file that is truncated or replaced, as\n\
+                 by log rotation, is followed again from its first line. Stdin and pipes\n\
+                 are shown once.\n\n\
                  Examples:\n  \
-                 umber main.rs lib.rs\n  \
-                 umber main.rs#L10-L20\n  \
-                 cat file.rs | umber\n  \
-                 echo 'code' | umber --language rust"
+                 umber -f /var/log/app.log\n  \
+                 umber -f --style=numbers access.log error.log"
   )]
-  files: Vec<PathBuf>,
-}
+  follow: bool,
 
-#[derive(Clone, Copy, Debug)]
-struct LineRange {
-  start: usize,
-  end: usize,
-}
+  #[arg(
+    long,
+    help = "Highlight files in windows instead of reading them whole",
+    long_help = "Read, highlight and print each file a few megabytes at a time, so memory use\n\
+                 stays bounded no matter how large the file is. Constructs that cross a\n\
+                 window boundary, such as long block comments, may be highlighted\n\
+                 incorrectly. Files of 256 MiB and more are always processed this way."
+  )]
+  chunked: bool,
 
-#[derive(Clone, Debug)]
-struct FileSpec {
-  path: PathBuf,
-  line_range: Option<LineRange>,
-}
+  #[arg(
+    long,
+    value_name = "SIZE",
+    default_value = limits::DEFAULT_MAX_HIGHLIGHT_SIZE,
+    value_parser = limits::parse_max_highlight_size,
+    help = "Show larger inputs, or minified ones, without highlighting",
+    long_help = "Show inputs larger than SIZE, such as 512K, 32M or 1G, without highlighting,\n\
+                 with a note on stderr, instead of leaving tree-sitter busy for minutes. Inputs\n\
+                 whose lines average over a thousand bytes, like minified JavaScript, are\n\
+                 treated the same way. `none` highlights everything."
+  )]
+  max_highlight_size: limits::MaxHighlightSize,
 
-#[derive(Clone, Copy, Debug, Default)]
-struct StyleConfig {
-  decoration_config: DecorationConfig,
-  highlight_locals: bool,
-  highlight_injections: bool,
-}
+  #[arg(
+    long,
+    value_name = "N",
+    help = "Cut lines longer than N columns short, ending them with …",
+    long_help = "Cut lines longer than N columns before highlighting them and end them with\n\
+                 … (> outside UTF-8 locales), so a minified bundle or a base64 blob takes one\n\
+                 row and no time instead of screens of wrapped text. Line numbers and other\n\
+                 decorations stay aligned with the lines they belong to."
+  )]
+  max_line_length: Option<usize>,
 
-struct RenderContext<'a> {
-  decoration_config: DecorationConfig,
-  highlight_locals: bool,
-  highlight_injections: bool,
-  use_color: bool,
-  squeeze_blank: bool,
-  squeeze_limit: usize,
-  show_all: bool,
-  language_set: &'a Union<CustomLanguageSet, LanguageSetImpl>,
-  theme: &'a ResolvedTheme,
-}
+  #[arg(
+    long,
+    conflicts_with = "max_line_length",
+    help = "Cut lines short at the terminal width",
+    long_help = "Like --max-line-length with the terminal width, less the line number gutter,\n\
+                 so that every line takes exactly one row."
+  )]
+  chop_long_lines: bool,
 
-struct RenderState {
-  highlighter: Highlighter,
-  highlights_only_configs: HashMap<Lang, HighlightConfiguration>,
-  locals_configs: HashMap<Lang, HighlightConfiguration>,
-  renderer: TerminalRenderer,
-}
+  #[arg(
+    long,
+    help = "Dim tool prefixes on log lines and highlight each message",
+    long_help = "Treat the input as multiplexed logs, such as `docker compose logs` or\n\
+                 `kubectl logs --prefix` output. Service names, [pod/...] prefixes and\n\
+                 timestamps at the start of each line are dimmed, and the rest of the line\n\
+                 is highlighted as JSON when it is a JSON document (or in the --language\n\
+                 given) and left plain otherwise. Lines are shown as they arrive."
+  )]
+  log_prefixes: bool,
 
-impl RenderState {
-  fn new() -> Self {
-    Self {
-      highlighter: Highlighter::new(),
-      highlights_only_configs: HashMap::new(),
-      locals_configs: HashMap::new(),
-      renderer: TerminalRenderer::new(None),
-    }
-  }
-}
+  #[arg(
+    long,
+    short = 'x',
+    conflicts_with = "format",
+    help = "Show the input as a colored hex dump",
+    long_help = "Show every input as a hex dump instead of highlighting it: the byte offset,\n\
+                 the bytes in hex in groups of eight and their printable characters. Bytes are\n\
+                 colored by class (zero, printable, whitespace, other ASCII and non-ASCII), and\n\
+                 in the character pane zeros show as ⋄, whitespace as _, other ASCII as • and\n\
+                 non-ASCII bytes as ×. Rows hold 16 bytes, or 8 on narrow terminals."
+  )]
+  hex: bool,
 
-struct DecorationsStreamSettings<'a> {
-  decoration_config: DecorationConfig,
-  line_number_start: usize,
-  git_changes: &'a [Option<git::LineChange>],
-  theme: &'a ResolvedTheme,
-  show_all: bool,
-}
+  #[arg(
+    long,
+    value_enum,
+    value_name = "MODE",
+    help = "How to show binary input: placeholder, as-text or hex",
+    long_help = "How to show input that looks binary, because its start has a NUL byte or\n\
+                 is largely not UTF-8.\n\
+                 placeholder: a notice such as <binary file: logo.png (12.3 KiB)> instead\n\
+                 of the bytes, so they cannot garble the terminal. The default when\n\
+                 writing to a terminal.\n\
+                 as-text: the bytes as they are. The default when writing to a pipe or\n\
+                 a file, so umber still copies binary files like cat.\n\
+                 hex: a hex dump, as with --hex."
+  )]
+  binary: Option<BinaryMode>,
+
+  #[arg(
+    long,
+    value_enum,
+    value_name = "WHEN",
+    default_value = "auto",
+    help = "Remove escape sequences from the input: auto, always or never",
+    long_help = "Remove escape sequences, such as the colors of another tool, from the input\n\
+                 before detecting its language and highlighting it, so they neither confuse\n\
+                 the parser nor clash with the theme.\n\
+                 auto: remove them when the output is colored and the input is in a known\n\
+                 language; colored plain text keeps its colors.\n\
+                 always: remove them from every input, even into a pipe.\n\
+                 never: keep them."
+  )]
+  strip_ansi: StripAnsi,
+
+  #[arg(
+    long,
+    help = "Print files, lines and bytes per detected language",
+    long_help = "Instead of displaying the files, count files, lines and bytes per detected\n\
+                 language. Directories are searched recursively, skipping hidden entries;\n\
+                 without arguments the current directory is used.\n\n\
+                 Examples:\n  \
+                 umber --stat\n  \
+                 umber --stat src tests"
+  )]
+  stat: bool,
+
+  #[arg(
+    long,
+    value_enum,
+    value_name = "FORMAT",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "human",
+    help = "Time each phase of showing the files instead of showing them",
+    long_help = "Instead of displaying the files, time reading, language detection, query\n\
+                 compilation, parsing, highlighting and rendering for each, with the\n\
+                 colors and decorations the other options ask for. human (default): a\n\
+                 table in milliseconds. json: an array with an object per file, for\n\
+                 tracking regressions in scripts.\n\n\
+                 Examples:\n  \
+                 umber --bench src/main.rs\n  \
+                 umber --bench=json --style=full big.json"
+  )]
+  bench: Option<bench::BenchFormat>,
+
+  #[arg(
+    long,
+    help = "Convert bat's configuration into an umber config file",
+    long_help = "Read bat's config file ($BAT_CONFIG_PATH or ~/.config/bat/config) and write\n\
+                 its theme and --map-syntax patterns to umber's config file. When that file\n\
+                 already exists the converted settings are printed instead, to be merged by\n\
+                 hand. Options without an umber equivalent and custom bat themes or\n\
+                 syntaxes are listed as skipped."
+  )]
+  import_bat_config: bool,
+
+  #[arg(
+    long,
+    help = "Use the theme and syntax mappings from bat's configuration",
+    long_help = "Read bat's config file on every run and use its theme and --map-syntax\n\
+                 patterns where umber's own flags and config file don't set them."
+  )]
+  bat_config: bool,
+
+  #[arg(
+    long,
+    help = "Don't run preprocessors from the config file",
+    long_help = "Display files as they are, even when a [preprocess] entry in the config\n\
+                 file would convert them to text first (e.g. pdftotext for *.pdf)."
+  )]
+  no_preprocess: bool,
+
+  #[arg(
+    long,
+    value_name = "PATH",
+    help = "Use PATH as the cache directory",
+    long_help = "Read cached queries and preprocessor output from PATH instead of\n\
+                 $XDG_CACHE_HOME/umber or the platform default, such as a cache built\n\
+                 with umber cache build --cache-dir PATH."
+  )]
+  cache_dir: Option<PathBuf>,
+
+  #[arg(
+    long,
+    value_enum,
+    default_value = "auto",
+    value_name = "MODE",
+    help = "How to show file paths in headers",
+    long_help = "How file paths are shown in the headers between files.\n\
+                 auto: relative to the current directory for files below it, otherwise as given.\n\
+                 relative: relative to the current directory, using .. when needed.\n\
+                 absolute: the absolute path.\n\
+                 git-relative: relative to the root of the file's git repository."
+  )]
+  header_path: paths::HeaderPath,
+
+  #[arg(
+    long,
+    value_name = "FORMAT",
+    help = "What the header between files shows: a preset or a template",
+    long_help = "What the header between files shows. Presets:\n\
+                 banner: the path centered between two rules (the default).\n\
+                 detailed: like banner, with the language and size.\n\
+                 title: ==> path <== on a line of its own, like head and tail.\n\
+                 Anything else is a template shown on a line of its own, with the\n\
+                 placeholders {path}, {basename}, {lang}, {size}, {mtime} and {branch}.\n\
+                 Example:\n  \
+                 umber --style=headers --header-format '── {path} ({lang}, {branch})' src/*.rs"
+  )]
+  header_format: Option<String>,
+
+  #[arg(
+    value_name = "FILE",
+    help = "Files to display (use '-' or omit for stdin)",
+    long_help = "One or more files to display with syntax highlighting.\n\
+                 If no files are specified, or if '-' is given, reads from stdin.\n\n\
+                 Examples:\n  \
+                 umber main.rs lib.rs\n  \
+                 umber main.rs#L10-L20\n  \
+                 cat file.rs | umber\n  \
+                 echo 'code' | umber --language rust"
+  )]
+  files: Vec<PathBuf>,
+}
+
+/// Arguments for `umber cache`, parsed separately so that `umber FILE` keeps working.
+#[derive(Parser, Debug)]
+#[command(
+  name = "umber cache",
+  about = "Inspect or reset umber's on-disk cache",
+  after_help = "EXAMPLES:\n    \
+    umber cache                      Print the cache directory\n    \
+    umber cache --size               Show how much space the cache uses\n    \
+    umber cache build                Prepare highlight queries ahead of time\n    \
+    umber cache build --grammar https://github.com/gleam-lang/tree-sitter-gleam\n    \
+                                     Compile and install a grammar\n    \
+    umber cache clear                Remove all cached data"
+)]
+struct CacheCli {
+  #[arg(
+    value_enum,
+    help = "Prepare highlight queries, or remove all cached data",
+    long_help = "build: preprocess the highlight queries of the grammars umber bundles\n\
+                 itself (HCL, Terraform, Bicep, nginx and the others it adds to the\n\
+                 standard set) into the cache, so later runs load them instead of\n\
+                 rewriting them on every start. The queries of the standard grammars\n\
+                 need no rewriting and are not cached. Entries are keyed by umber's\n\
+                 version and go stale on upgrade; run it again afterwards.\n\
+                 clear: the same as --clear."
+  )]
+  action: Option<CacheAction>,
+
+  #[arg(
+    long,
+    value_name = "[NAME=]SOURCE",
+    help = "Compile a tree-sitter grammar and install it (repeatable)",
+    long_help = "Compile the tree-sitter grammar in SOURCE, a directory or a git URL to\n\
+                 clone, with $CC (or cc), and install the parser and its\n\
+                 queries/highlights.scm in ~/.config/umber/grammars/NAME/. NAME\n\
+                 defaults to the last part of SOURCE without a tree-sitter- prefix,\n\
+                 and must be the name the grammar exports as tree_sitter_NAME.\n\
+                 Implies build. Can be repeated."
+  )]
+  grammar: Vec<String>,
+
+  #[arg(long, help = "Remove all cached data")]
+  clear: bool,
+
+  #[arg(long, help = "Show the number of files and bytes in the cache")]
+  size: bool,
+
+  #[arg(
+    long,
+    value_name = "PATH",
+    help = "Use PATH as the cache directory",
+    long_help = "Use PATH as the cache directory instead of $XDG_CACHE_HOME/umber\n\
+                 or the platform default."
+  )]
+  cache_dir: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CacheAction {
+  Build,
+  Clear,
+}
+
+/// Content produced by a subcommand rather than read from a file.
+struct GeneratedInput {
+  bytes: Vec<u8>,
+  /// Path used for language detection
+  path: Option<PathBuf>,
+  language: Option<EitherLang<CustomLang, Lang>>,
+}
+
+#[derive(Clone, Debug)]
+struct FileSpec {
+  path: PathBuf,
+  /// Lines to show; empty shows the whole file
+  line_ranges: LineSelection,
+  /// `--file-name` given for this input
+  name: Option<PathBuf>,
+}
+
+impl FileSpec {
+  /// Name for headers: the `--file-name`, or the path shown as `mode` asks.
+  fn display_name(&self, mode: paths::HeaderPath) -> String {
+    match &self.name {
+      Some(name) => name.display().to_string(),
+      None => paths::display_path(&self.path, mode),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct StyleConfig {
+  decoration_config: DecorationConfig,
+  highlight_locals: bool,
+  highlight_injections: bool,
+}
+
+#[derive(Clone, Copy)]
+struct RenderContext<'a> {
+  decoration_config: DecorationConfig,
+  highlight_locals: bool,
+  highlight_injections: bool,
+  use_color: bool,
+  squeeze_blank: bool,
+  squeeze_limit: usize,
+  show_all: bool,
+  /// `--show-line-endings`
+  show_line_endings: bool,
+  /// `--normalize-eol`
+  normalize_eol: bool,
+  char_style: unprintable::CharStyle,
+  zebra: bool,
+  /// `--plain`: per-language styles and zebra stripes are ignored too
+  plain: bool,
+  wrap: decorations::Wrap,
+  /// Tab stop width for `--tabs`; 0 keeps tabs
+  tabs: usize,
+  /// Lines picked with `--highlight-line`
+  highlight_lines: &'a LineSelection,
+  /// Background for highlighted lines from the config file
+  highlight_color: Option<Color>,
+  /// Marked lines of the current input
+  marks: ranges::Marks<'a>,
+  language_set: &'a Union<CustomLanguageSet, LanguageSetImpl>,
+  theme: &'a ResolvedTheme,
+  language_themes: &'a HashMap<String, ResolvedTheme>,
+  language_settings: &'a HashMap<String, config::LanguageConfig>,
+  syntax_map: &'a SyntaxMap,
+  /// `--file-name` of the current input, detected instead of its path
+  file_name: Option<&'a Path>,
+  /// `--ignored-suffix` values, stripped from file names for detection
+  ignored_suffixes: &'a [String],
+  /// Draw a separator where lines are left out between ranges
+  snip: bool,
+  /// `--diff-base` revision the change markers compare against
+  diff_base: Option<&'a str>,
+  /// How input that looks binary is shown
+  binary: BinaryMode,
+  strip_ansi: StripAnsi,
+  max_highlight_size: limits::MaxHighlightSize,
+  /// `--max-line-length` or `--chop-long-lines`
+  max_line_length: Option<chop::MaxLineLength>,
+  /// Least width of the line numbers, so that an input rendered in parts
+  /// keeps one gutter throughout
+  min_line_number_width: usize,
+  /// `--deterministic`: nothing shown depends on the clock
+  deterministic: bool,
+}
+
+impl<'a> RenderContext<'a> {
+  /// Columns `--max-line-length` leaves for the text of lines numbered from
+  /// `line_number_start` in `bytes`.
+  fn chop_columns(&self, bytes: &[u8], line_number_start: usize) -> usize {
+    match self.max_line_length {
+      Some(chop::MaxLineLength::Columns(columns)) => columns,
+      Some(chop::MaxLineLength::Terminal) => {
+        let last = line_number_start + count_lines_bytes(bytes).saturating_sub(1);
+        let width = line_number_width(last).max(self.min_line_number_width);
+        let gutter = if self.use_color {
+          decorations::gutter_width(&self.decoration_config, width)
+        } else if self.decoration_config.show_numbers {
+          width + 2
+        } else {
+          0
+        };
+        self.wrap.width.saturating_sub(gutter)
+      }
+      None => usize::MAX,
+    }
+  }
+
+  /// Detect the language of the input, trying `[syntax-map]` patterns first.
+  fn detect_language(
+    &self,
+    path: Option<&Path>,
+    content: &str,
+  ) -> Option<EitherLang<CustomLang, Lang>> {
+    // `--show-all` keeps the byte order mark, which is not part of the code.
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    detect_mapped_language(
+      path,
+      content,
+      self.language_set,
+      self.syntax_map,
+      self.ignored_suffixes,
+    )
+  }
+
+  /// Detect the language of the current input, preferring its `--file-name`
+  /// over `path`.
+  fn detect_input_language(
+    &self,
+    path: Option<&Path>,
+    content: &str,
+  ) -> Option<EitherLang<CustomLang, Lang>> {
+    self.detect_language(self.file_name.or(path), content)
+  }
+
+  /// Hex dump for `--hex` and `--binary=hex`, in the theme's colors.
+  fn hex_dump(&self) -> hexdump::HexDump {
+    hexdump::HexDump::new(
+      self.use_color.then_some(self.theme),
+      self.char_style,
+      self.wrap.width,
+    )
+  }
+
+  /// Context with the per-language theme and rendering settings for `language` applied.
+  fn for_language(&self, language: Option<&EitherLang<CustomLang, Lang>>) -> RenderContext<'a> {
+    let name = language.map(language_name);
+    let theme = name
+      .and_then(|name| self.language_themes.get(name))
+      .unwrap_or(self.theme);
+    let mut ctx = RenderContext { theme, ..*self };
+    let Some(settings) = name.and_then(|name| self.language_settings.get(name)) else {
+      return ctx;
+    };
+
+    if let Some(style) = settings.style.as_deref() {
+      let extra = parse_style_components(Some(style), self.plain);
+      ctx.decoration_config = ctx.decoration_config.merge(extra.decoration_config);
+      ctx.highlight_injections |= extra.highlight_injections;
+    }
+    if let Some(injections) = settings.injections {
+      ctx.highlight_injections = injections;
+    }
+    if ctx.highlight_injections {
+      ctx.highlight_locals = true;
+    }
+    if let Some(show_all) = settings.show_all {
+      ctx.show_all = show_all;
+    }
+    if let Some(zebra) = settings.zebra.filter(|_| !self.plain) {
+      ctx.zebra = zebra;
+    }
+    ctx
+  }
+}
+
+struct RenderState {
+  highlighter: Highlighter,
+  configs: LangConfigs,
+  renderer: DepthRenderer,
+  /// Set when the input being rendered had invalid UTF-8 replaced
+  invalid_utf8: bool,
+  /// Inputs shown with invalid UTF-8 replaced, to warn about at the end
+  lossy_inputs: Vec<String>,
+  /// Set when the input being rendered, or part of it, is not highlighted
+  /// because of `--max-highlight-size`
+  skipped: Option<limits::Skip>,
+  /// Inputs shown without highlighting, to note at the end
+  unhighlighted: Vec<(String, limits::Skip)>,
+  /// Changed lines of the input being rendered, fetched once for all of its
+  /// parts; empty when none changed
+  git_changes: Option<(PathBuf, Vec<Option<git::LineStatus>>)>,
+}
+
+impl RenderState {
+  fn new(color_depth: ColorDepth) -> Self {
+    Self {
+      highlighter: Highlighter::new(),
+      configs: LangConfigs::default(),
+      renderer: DepthRenderer::new(color_depth),
+      invalid_utf8: false,
+      lossy_inputs: Vec::new(),
+      skipped: None,
+      unhighlighted: Vec::new(),
+      git_changes: None,
+    }
+  }
+
+  /// The changed lines of `path`, taken out to render with and put back
+  /// afterwards. Fetched only for the first part of an input.
+  fn take_git_changes(
+    &mut self,
+    path: Option<&Path>,
+    base: Option<&str>,
+  ) -> (PathBuf, Vec<Option<git::LineStatus>>) {
+    let path = path.unwrap_or(Path::new("-"));
+    match self.git_changes.take() {
+      Some(cached) if cached.0 == path => cached,
+      _ => {
+        let mut changes = fetch_git_changes(Some(path), base);
+        if changes.iter().all(Option::is_none) {
+          changes.clear();
+        }
+        (path.to_path_buf(), changes)
+      }
+    }
+  }
+
+  /// Remember `name` when invalid UTF-8 was replaced while rendering it, or
+  /// when it was not highlighted.
+  fn finish_input(&mut self, name: Option<&Path>) {
+    let name = name.unwrap_or(Path::new("-")).display().to_string();
+    if std::mem::take(&mut self.invalid_utf8) {
+      self.lossy_inputs.push(name.clone());
+    }
+    if let Some(skip) = self.skipped.take() {
+      self.unhighlighted.push((name, skip));
+    }
+    self.git_changes = None;
+  }
+}
+
+struct DecorationsStreamSettings<'a> {
+  decoration_config: DecorationConfig,
+  line_number_start: usize,
+  git_changes: &'a [Option<git::LineStatus>],
+  theme: &'a ResolvedTheme,
+  show_all: bool,
+  show_line_endings: bool,
+  /// Whether each line ended in CRLF, when line endings are marked
+  crlf_lines: Option<&'a [bool]>,
+  char_style: unprintable::CharStyle,
+  zebra: bool,
+  marks: ranges::Marks<'a>,
+  highlight_color: Option<Color>,
+  wrap: decorations::Wrap,
+  min_line_number_width: usize,
+}
+
+impl<'a> DecorationsStreamSettings<'a> {
+  fn new(
+    ctx: &RenderContext<'a>,
+    line_number_start: usize,
+    git_changes: &'a [Option<git::LineStatus>],
+    crlf_lines: Option<&'a [bool]>,
+  ) -> Self {
+    Self {
+      decoration_config: ctx.decoration_config,
+      line_number_start,
+      git_changes,
+      theme: ctx.theme,
+      show_all: ctx.show_all,
+      show_line_endings: ctx.show_line_endings,
+      crlf_lines,
+      char_style: ctx.char_style,
+      zebra: ctx.zebra,
+      marks: ctx.marks,
+      highlight_color: ctx.highlight_color,
+      wrap: ctx.wrap,
+      min_line_number_width: ctx.min_line_number_width,
+    }
+  }
+}
 
 struct StreamBuffer<'a, W> {
   out: &'a mut W,
@@ -290,8 +1361,58 @@ impl<'a, W: Write> StreamBuffer<'a, W> {
   }
 }
 
-fn main() -> Result<()> {
-  let cli = Cli::parse();
+fn main() {
+  let mut reporter = Reporter::default();
+  match run(&mut reporter) {
+    Ok(()) => {}
+    // The reader (pager or pipe) went away early; that is not an error.
+    Err(err) if is_broken_pipe(&err) => {}
+    Err(err) => {
+      reporter.error(&format!("{err:#}"));
+      std::process::exit(1);
+    }
+  }
+}
+
+fn is_broken_pipe(err: &eyre::Report) -> bool {
+  err
+    .downcast_ref::<io::Error>()
+    .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn run(reporter: &mut Reporter) -> Result<()> {
+  if let Some(dir) = config::config_dir() {
+    grammars::register(&dir.join("grammars"));
+    queries::register(&dir.join("queries"));
+  }
+  let args: Vec<OsString> = std::env::args_os().collect();
+  let subcommand = Subcommand::detect(&args);
+  let args = match subcommand {
+    Some(Subcommand::Cache) => {
+      return run_cache_command(parse_localized::<CacheCli>(
+        Subcommand::Cache.strip(&args),
+        "cache-",
+      ));
+    }
+    Some(Subcommand::Themes) => {
+      list_themes();
+      return Ok(());
+    }
+    Some(Subcommand::Langs) => {
+      list_languages();
+      return Ok(());
+    }
+    Some(command) => command.strip(&args),
+    None => args,
+  };
+  // Defaults from the environment go first so the command line overrides them.
+  let mut args = args;
+  args.splice(1..1, config::env_args()?);
+
+  let mut cli: Cli = parse_localized(args, "");
+  let stderr_color =
+    io::stderr().is_terminal() && !cli.no_color && std::env::var("NO_COLOR").is_err();
+  *reporter = Reporter::new(cli.error_format, stderr_color);
   if let Some(shell) = cli.completions {
     write_completions(shell)?;
     return Ok(());
@@ -300,11 +1421,8 @@ fn main() -> Result<()> {
     write_man_page()?;
     return Ok(());
   }
-  if cli.list_themes {
-    for theme in syntastica_themes::THEMES {
-      println!("{theme}");
-    }
-    return Ok(());
+  if cli.import_bat_config {
+    return import_bat_config(reporter);
   }
   let mut use_color = io::stdout().is_terminal();
   // Check --no-color flag and NO_COLOR environment variable (https://no-color.org/)
@@ -317,47 +1435,277 @@ fn main() -> Result<()> {
     ColorWhen::Always => use_color = true,
   }
   // Use Union to combine custom languages (HCL/Terraform) with syntastica-parsers-git
-  let custom_set = CustomLanguageSet::new();
+  let custom_set = CustomLanguageSet::new(cache::cache_dir(cli.cache_dir.as_deref()).ok());
   let parser_set = LanguageSetImpl::new();
   let language_set = Union::new(custom_set, parser_set);
-  let theme = resolve_theme(&cli.theme);
-  let style_config = parse_style_components(cli.style.as_deref());
-  let decoration_config = style_config.decoration_config;
+  let terminal = TerminalQueries {
+    deterministic: cli.deterministic,
+    width: cli.terminal_width,
+    color_depth: cli.color_depth,
+  };
+  match cli.list_themes {
+    Some(ThemeListing::Preview) if use_color => {
+      return preview_themes(&language_set, &terminal);
+    }
+    Some(_) => {
+      list_themes();
+      return Ok(());
+    }
+    None => {}
+  }
+  let config = config::Config::load()?;
+  let bat_config = if cli.bat_config { bat::load()? } else { None };
+  let theme_name = cli
+    .theme
+    .as_deref()
+    .or(config.theme.name.as_deref())
+    .or(bat_config.as_ref().and_then(|bat| bat.theme.as_deref()))
+    .unwrap_or("auto");
+  // `--color-depth ansi` picks the ansi theme, and the ansi theme picks the
+  // 16 colors unless told otherwise.
+  let theme_name = if cli.color_depth == ColorDepth::Ansi {
+    "ansi"
+  } else {
+    theme_name
+  };
+  let terminal = if theme_name.trim() == "ansi" && cli.color_depth == ColorDepth::Auto {
+    TerminalQueries {
+      color_depth: ColorDepth::Ansi,
+      ..terminal
+    }
+  } else {
+    terminal
+  };
+  let auto_themes = AutoThemes {
+    light: cli.theme_light.as_deref().or(config.theme.light.as_deref()),
+    dark: cli.theme_dark.as_deref().or(config.theme.dark.as_deref()),
+  };
+  // Patterns from umber's own config replace identical ones from bat.
+  let mut syntax_patterns: HashMap<String, String> = bat_config
+    .as_ref()
+    .map(|bat| bat.syntax_map.iter().cloned().collect())
+    .unwrap_or_default();
+  syntax_patterns.extend(config.syntax_map.clone());
+  let syntax_map = SyntaxMap::new(syntax_patterns);
+  let preprocessors = if cli.no_preprocess {
+    preprocess::Preprocessors::default()
+  } else {
+    preprocess::Preprocessors::new(
+      &config.preprocess,
+      cache::cache_dir(cli.cache_dir.as_deref()).ok(),
+    )
+  };
+  let theme_adjustments = ThemeAdjustments {
+    contrast: cli.contrast,
+    styles: StyleFilter {
+      italic: cli.italic_text == ItalicText::Always,
+      bold: !cli.no_bold,
+      underline: !cli.no_underline,
+    },
+  };
+  if let Some(name) = cli.export_theme.as_deref() {
+    let name = Some(name)
+      .filter(|name| !name.is_empty())
+      .unwrap_or(theme_name);
+    let theme = match named_theme(name)? {
+      Some(theme) => theme,
+      None if matches!(name.trim(), "" | "auto" | "dark" | "light") => {
+        resolve_theme(name, auto_themes, &terminal)?
+      }
+      None => {
+        return Err(eyre!(tr_args(
+          "error-unknown-theme",
+          &[("name", Arg::Str(name))]
+        )));
+      }
+    };
+    let theme = adjust_theme(theme, theme_adjustments, &terminal);
+    print!("{}", custom_themes::to_toml(&theme));
+    return Ok(());
+  }
+  let theme = adjust_theme(
+    resolve_theme(theme_name, auto_themes, &terminal)?,
+    theme_adjustments,
+    &terminal,
+  );
+  let language_themes: HashMap<String, ResolvedTheme> = config
+    .theme
+    .per_language
+    .iter()
+    .map(|(language, name)| {
+      Ok((
+        language.to_ascii_lowercase(),
+        adjust_theme(
+          resolve_theme(name, auto_themes, &terminal)?,
+          theme_adjustments,
+          &terminal,
+        ),
+      ))
+    })
+    .collect::<Result<_>>()?;
+  let language_settings: HashMap<String, config::LanguageConfig> = config
+    .languages
+    .iter()
+    .map(|(language, settings)| (language.to_ascii_lowercase(), settings.clone()))
+    .collect();
+  let plain = cli.plain > 0;
+  let style_config = parse_style_components(cli.style.as_deref(), plain);
+  let mut decoration_config = style_config.decoration_config;
+  // What changed is the point of `--diff`, so its markers are always shown.
+  decoration_config.show_changes |= cli.diff && !plain;
   let highlight_locals = style_config.highlight_locals;
   let highlight_injections = style_config.highlight_injections;
   let squeeze_limit = cli.squeeze_limit.unwrap_or(1);
   let squeeze_blank = cli.squeeze_blank || cli.squeeze_limit.is_some();
   let language_override = match cli.language.as_deref() {
-    Some(name) => Some(
-      resolve_language_union(name, &language_set)
-        .ok_or_else(|| eyre!("Unsupported language: {name}"))?,
-    ),
+    Some(name) => Some(resolve_language_union(name, &language_set).ok_or_else(|| {
+      eyre!(tr_args(
+        "error-unsupported-language",
+        &[("name", Arg::Str(name))]
+      ))
+    })?),
     None => None,
   };
 
-  let files = if cli.files.is_empty() {
+  if let Some(list) = &cli.files_from {
+    let listed = file_list::read(list, cli.null).wrap_err_with(|| {
+      tr_args(
+        "error-read-file-list",
+        &[("path", Arg::Str(&list.display().to_string()))],
+      )
+    })?;
+    cli.files.extend(listed);
+  }
+
+  // `diff` and `show` render git output instead of reading their arguments as files.
+  let generated = match subcommand {
+    Some(Subcommand::Diff) => Some(GeneratedInput {
+      bytes: git::diff_output(&cli.files)?,
+      path: None,
+      language: resolve_language_union("diff", &language_set),
+    }),
+    Some(Subcommand::Show) => {
+      let [spec] = cli.files.as_slice() else {
+        return Err(eyre!(tr("error-show-arguments")));
+      };
+      let spec = spec.to_string_lossy();
+      let Some((_, path)) = spec.split_once(':') else {
+        return Err(eyre!(tr_args(
+          "error-show-spec",
+          &[("spec", Arg::Str(&spec))]
+        )));
+      };
+      Some(GeneratedInput {
+        bytes: git::show_file(&spec)?,
+        path: Some(PathBuf::from(path)),
+        language: None,
+      })
+    }
+    _ => None,
+  };
+  let blame_command = subcommand == Some(Subcommand::Blame);
+  decoration_config.show_blame |= blame_command;
+  let blame = decoration_config.show_blame;
+  if blame_command && cli.files.len() != 1 {
+    return Err(eyre!(tr("error-blame-arguments")));
+  }
+
+  if cli.stat {
+    let paths = if cli.files.is_empty() && cli.files_from.is_none() {
+      vec![PathBuf::from(".")]
+    } else {
+      cli.files
+    };
+    let failed = write_language_stats(
+      &paths,
+      use_color.then(|| terminal.color_depth()),
+      &theme,
+      &language_set,
+      &syntax_map,
+      &cli.ignored_suffix,
+      reporter,
+    )?;
+    if failed {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+
+  if cli.bench.is_some() && cli.files.is_empty() {
+    return Err(eyre!(tr("error-bench-files")));
+  }
+  let files = if generated.is_some() {
+    Vec::new()
+  } else if cli.files.is_empty() && cli.files_from.is_none() {
+    if !cli.stdin && io::stdin().is_terminal() {
+      reporter.note(&tr("note-reading-stdin"));
+    }
     vec![PathBuf::from("-")]
   } else {
-    cli.files
+    std::mem::take(&mut cli.files)
   };
 
-  let global_line_range = match cli.lines.as_deref() {
-    Some(raw) => Some(parse_line_range_arg(raw)?),
-    None => None,
-  };
+  let mut global_line_ranges = Vec::new();
+  for raw in &cli.lines {
+    global_line_ranges.extend(parse_line_range_arg(raw)?);
+  }
+  let mut highlight_lines = Vec::new();
+  for raw in &cli.highlight_line {
+    highlight_lines.extend(parse_line_range_arg(raw)?);
+  }
+  let highlight_lines = LineSelection::new(highlight_lines);
+  let context = cli.context.unwrap_or(0);
+  let global_line_ranges = LineSelection::new(global_line_ranges).with_context(context);
 
   let mut had_error = false;
   let mut file_specs = Vec::with_capacity(files.len());
-  for path in files {
-    match parse_file_spec(path, global_line_range) {
-      Ok(spec) => file_specs.push(spec),
+  for (index, path) in files.into_iter().enumerate() {
+    match parse_file_spec(path.clone(), &global_line_ranges, context) {
+      Ok(spec) => {
+        let spec = FileSpec {
+          name: cli.file_name.get(index).cloned(),
+          ..spec
+        };
+        if !cli.diff {
+          file_specs.push(spec);
+          continue;
+        }
+        match changed_lines(&spec.path, cli.diff_context, cli.diff_base.as_deref()) {
+          Ok(Some(line_ranges)) if line_ranges.is_empty() => {}
+          Ok(Some(line_ranges)) => file_specs.push(FileSpec {
+            line_ranges,
+            ..spec
+          }),
+          Ok(None) => file_specs.push(spec),
+          Err(err) => {
+            reporter.error_at(&spec.path, &err.to_string());
+            had_error = true;
+          }
+        }
+      }
       Err(err) => {
-        eprintln!("umber: {err}");
+        reporter.error_at(&path, &err.to_string());
         had_error = true;
       }
     }
   }
 
+  let highlight_color = match config.theme.highlight_line.as_deref() {
+    Some(raw) => Some(decorations::parse_color(raw).ok_or_else(|| {
+      eyre!(tr_args(
+        "error-highlight-color",
+        &[("color", Arg::Str(raw))]
+      ))
+    })?),
+    None => None,
+  };
+  let padding = Padding::from_args(
+    cli.padding,
+    cli.padding_top,
+    cli.padding_right,
+    cli.padding_bottom,
+    cli.padding_left,
+  );
   let ctx = RenderContext {
     decoration_config,
     highlight_locals,
@@ -366,143 +1714,1598 @@ fn main() -> Result<()> {
     squeeze_blank,
     squeeze_limit,
     show_all: cli.show_all,
+    show_line_endings: cli.show_line_endings,
+    normalize_eol: cli.normalize_eol,
+    char_style: terminal.char_style(),
+    zebra: cli.zebra && !plain,
+    plain,
+    tabs: cli.tabs.unwrap_or(0),
+    highlight_lines: &highlight_lines,
+    highlight_color,
+    marks: ranges::Marks::default(),
+    wrap: decorations::Wrap {
+      mode: match cli.wrap {
+        WrapWhen::Auto if io::stdout().is_terminal() => decorations::WrapMode::Character,
+        WrapWhen::Auto | WrapWhen::Never => decorations::WrapMode::Never,
+        WrapWhen::Character => decorations::WrapMode::Character,
+        WrapWhen::Word => decorations::WrapMode::Word,
+      },
+      width: terminal.width().saturating_sub(padding.horizontal()),
+    },
     language_set: &language_set,
     theme: &theme,
+    language_themes: &language_themes,
+    language_settings: &language_settings,
+    syntax_map: &syntax_map,
+    file_name: None,
+    ignored_suffixes: &cli.ignored_suffix,
+    snip: cli.diff,
+    diff_base: cli.diff_base.as_deref(),
+    binary: cli.binary.unwrap_or(if io::stdout().is_terminal() {
+      BinaryMode::Placeholder
+    } else {
+      BinaryMode::AsText
+    }),
+    strip_ansi: cli.strip_ansi,
+    max_highlight_size: cli.max_highlight_size,
+    max_line_length: match (cli.max_line_length, cli.chop_long_lines) {
+      (Some(columns), _) => Some(chop::MaxLineLength::Columns(columns)),
+      (None, true) => Some(chop::MaxLineLength::Terminal),
+      (None, false) => None,
+    },
+    min_line_number_width: 0,
+    deterministic: cli.deterministic,
   };
-  let mut state = RenderState::new();
-  let mut stdout = io::stdout().lock();
-  let mut stdin = io::stdin();
+  if let Some(format) = cli.bench {
+    let mut state = RenderState::new(terminal.color_depth());
+    let failed = bench_files(&file_specs, format, &ctx, &mut state, reporter)?;
+    if failed {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+  if cli.output.is_some() && cli.format == OutputFormat::Terminal {
+    return Err(eyre!(tr("error-output-format")));
+  }
+  if cli.format != OutputFormat::Terminal {
+    let failed = export_files(
+      &cli,
+      generated,
+      &file_specs,
+      language_override.as_ref(),
+      padding,
+      &ctx,
+      reporter,
+    )?;
+    if had_error || failed {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+
+  let mut state = RenderState::new(terminal.color_depth());
+  let mut stdout = PaddedWriter::new(open_output(&cli, reporter)?, padding);
   let mut stdin_consumed = false;
   let mut wrote_output = false;
   let multiple_files = file_specs.len() > 1;
+  let mut follow_targets = Vec::new();
+  let options = InputOptions {
+    language_override: language_override.as_ref(),
+    preprocessors: &preprocessors,
+    header_path: cli.header_path,
+    // Output identical to the input: regular files are copied without being read into memory.
+    passthrough: !ctx.use_color
+      && !ctx.decoration_config.has_decorations()
+      && !ctx.decoration_config.show_grid
+      && !ctx.show_all
+      && !ctx.show_line_endings
+      && !ctx.normalize_eol
+      && !ctx.squeeze_blank
+      && ctx.tabs == 0
+      && ctx.max_line_length.is_none()
+      && padding == Padding::default()
+      && !blame
+      && !cli.follow
+      && ctx.binary == BinaryMode::AsText
+      && ctx.strip_ansi != StripAnsi::Always,
+    blame,
+    blame_command,
+    chunked: cli.chunked,
+    follow: cli.follow,
+  };
+
+  let hex_dump = cli.hex.then(|| ctx.hex_dump());
+
+  if let Some(input) = generated {
+    emit_generated(
+      &mut stdout,
+      input,
+      hex_dump.as_ref(),
+      &global_line_ranges,
+      options.language_override,
+      &ctx,
+      &mut state,
+    )?;
+  }
 
+  let header_format = cli
+    .header_format
+    .as_deref()
+    .map(header::HeaderFormat::parse)
+    .unwrap_or_default();
+  let term_width = terminal.width().saturating_sub(padding.horizontal());
+  // Spans the terminal width; frames headers and, with the grid, each file.
+  let border = "─".repeat(term_width);
+  // With the grid, the previous file still needs its closing rule.
+  let mut framed = false;
   for spec in file_specs {
+    let ctx = RenderContext {
+      file_name: spec.name.as_deref(),
+      ..ctx
+    };
+    if framed {
+      close_grid_frame(&mut stdout, &border)?;
+    }
     // Show file header between files when headers are enabled
-    if ctx.decoration_config.show_headers && multiple_files {
-      if wrote_output {
+    let show_header = ctx.decoration_config.show_headers && multiple_files;
+    if show_header {
+      if wrote_output && !framed {
         writeln!(stdout)?;
       }
-      let display_name = display_name_for_spec(&spec);
-      // Get terminal width, default to 80 if unavailable
-      let term_width = crossterm::terminal::size()
-        .map(|(w, _)| w as usize)
-        .unwrap_or(80);
-      // Create a prominent header that spans the terminal width
-      let border = "─".repeat(term_width);
-      writeln!(stdout, "{border}")?;
-      // Center the filename in the header
-      let padding = (term_width.saturating_sub(display_name.len())) / 2;
+      let fields = header_fields(
+        &spec,
+        &header_format,
+        cli.header_path,
+        options.language_override,
+        &ctx,
+      );
+      let mut title = header::expand(&header_format.template, &fields);
+      if ctx.show_line_endings && fields.eol.is_some_and(eol::Counts::is_mixed) {
+        title.push(' ');
+        title.push_str(&tr("header-mixed-eol"));
+      }
+      write_header(
+        &mut stdout,
+        &title,
+        header_format.layout,
+        &border,
+        framed,
+        &ctx,
+      )?;
+    } else if ctx.decoration_config.show_grid && !framed {
+      writeln!(stdout, "{border}")?;
+    }
+    framed = ctx.decoration_config.show_grid;
+
+    // Standard input is only read once, however often `-` is given.
+    if spec.path == Path::new("-") && std::mem::replace(&mut stdin_consumed, true) {
+      continue;
+    }
+    let shown = if let Some(hex_dump) = &hex_dump {
+      dump_input(&mut stdout, &spec, hex_dump, &mut state, reporter)?
+    } else if cli.log_prefixes {
+      emit_log_input(
+        &mut stdout,
+        &spec,
+        options.language_override,
+        &ctx,
+        &mut state,
+        reporter,
+      )?
+    } else if spec.path == Path::new("-") {
+      emit_stdin(
+        &mut stdout,
+        &mut io::stdin().lock(),
+        &spec.line_ranges,
+        options.language_override.map(clone_either_lang),
+        &ctx,
+        &mut state,
+      )?;
+      Shown::Output
+    } else if remote::is_url(&spec.path) {
+      emit_url(
+        &mut stdout,
+        &spec,
+        options.language_override,
+        &ctx,
+        &mut state,
+        reporter,
+      )?
+    } else {
+      emit_file(
+        &mut stdout,
+        &spec,
+        &options,
+        &mut follow_targets,
+        &ctx,
+        &mut state,
+        reporter,
+      )?
+    };
+    match shown {
+      Shown::Output => wrote_output = true,
+      Shown::Failed => had_error = true,
+    }
+  }
+
+  if framed {
+    close_grid_frame(&mut stdout, &border)?;
+  }
+
+  if !follow_targets.is_empty() {
+    stdout.flush()?;
+    let watch = TerminalWatch::new(
+      theme_name,
+      &config.theme.per_language,
+      auto_themes,
+      theme_adjustments,
+      terminal,
+      padding.horizontal(),
+    );
+    had_error |= follow_files(
+      &mut stdout,
+      follow_targets,
+      &ctx,
+      watch,
+      &mut state,
+      reporter,
+    )?;
+  }
+
+  stdout.finish()?;
+  stdout.into_inner().finish()?;
+  for name in &state.lossy_inputs {
+    reporter.warning(&tr_args(
+      "warning-invalid-utf8",
+      &[("path", Arg::Str(name))],
+    ));
+  }
+  for (name, skip) in &state.unhighlighted {
+    let key = match skip {
+      limits::Skip::TooLarge => "note-too-large-to-highlight",
+      limits::Skip::Minified => "note-minified",
+    };
+    reporter.note(&tr_args(key, &[("path", Arg::Str(name))]));
+  }
+  if had_error {
+    std::process::exit(1);
+  }
+  Ok(())
+}
+
+/// Write the inputs as a document or an image in `--format`, to `--output`
+/// or standard output. Returns true when an input could not be read.
+fn export_files(
+  cli: &Cli,
+  generated: Option<GeneratedInput>,
+  file_specs: &[FileSpec],
+  language_override: Option<&EitherLang<CustomLang, Lang>>,
+  padding: Padding,
+  ctx: &RenderContext<'_>,
+  reporter: &Reporter,
+) -> Result<bool> {
+  let (listings, failed) = collect_listings(
+    generated,
+    file_specs,
+    language_override,
+    cli.header_path,
+    ctx,
+    reporter,
+  );
+  // Images look cramped without a margin; --padding still applies as given.
+  let image_padding = if padding == Padding::default() {
+    Padding {
+      top: 1,
+      right: 2,
+      bottom: 1,
+      left: 2,
+    }
+  } else {
+    padding
+  };
+  let image_options = svg::ImageOptions {
+    font_family: cli
+      .font_family
+      .as_deref()
+      .unwrap_or(svg::DEFAULT_FONT_FAMILY),
+    padding: image_padding,
+    window_chrome: cli.window_chrome,
+  };
+  let rendered = render_listings(cli.format, &listings, ctx.theme, &image_options)?;
+  match &cli.output {
+    Some(path) => fs::write(path, rendered).wrap_err_with(|| {
+      tr_args(
+        "error-write-output",
+        &[("path", Arg::Str(&path.display().to_string()))],
+      )
+    })?,
+    None if cli.format == OutputFormat::Png && io::stdout().is_terminal() => {
+      return Err(eyre!(tr("error-png-terminal")));
+    }
+    None => io::stdout().lock().write_all(&rendered)?,
+  }
+  Ok(failed)
+}
+
+/// Where terminal output goes: the pager when `--paging` asks for one and it
+/// starts, standard output otherwise.
+fn open_output(cli: &Cli, reporter: &Reporter) -> Result<pager::Output> {
+  let paging = match cli.paging {
+    _ if cli.plain > 1 => false,
+    Paging::Auto => io::stdout().is_terminal() && !cli.follow && !cli.log_prefixes,
+    Paging::Never => false,
+    Paging::Always => true,
+  };
+  let configured_pager = if paging {
+    pager::configured_pager(cli.pager.as_deref())?
+  } else {
+    None
+  };
+  Ok(match configured_pager {
+    Some(pager) if pager.is_recursive() => {
+      reporter.warning(&tr_args(
+        "warning-recursive-pager",
+        &[("program", Arg::Str(&pager.program))],
+      ));
+      pager::Output::stdout()
+    }
+    Some(pager) => match pager::Output::pager(&pager) {
+      Ok(output) => output,
+      // less is only a default; without it, write straight to the terminal.
+      Err(err) if err.kind() == io::ErrorKind::NotFound && pager.is_default() => {
+        pager::Output::stdout()
+      }
+      Err(err) => {
+        return Err(eyre!(tr_args(
+          "error-pager-start",
+          &[
+            ("program", Arg::Str(&pager.program)),
+            ("error", Arg::Str(&err.to_string())),
+          ],
+        )));
+      }
+    },
+    None => pager::Output::stdout(),
+  })
+}
+
+/// The header naming the next file, as a banner spanning `border` or a
+/// single line. `framed` means the rule closing the previous file is
+/// already there.
+fn write_header(
+  stdout: &mut impl Write,
+  title: &str,
+  layout: header::Layout,
+  border: &str,
+  framed: bool,
+  ctx: &RenderContext<'_>,
+) -> io::Result<()> {
+  match layout {
+    header::Layout::Banner => {
+      // Create a prominent header that spans the terminal width; the rule
+      // closing the previous file already serves as its top border.
+      if !framed {
+        writeln!(stdout, "{border}")?;
+      }
+      // Center the title in the header
+      let term_width = border.chars().count();
+      let title_width = unicode_width::UnicodeWidthStr::width(title);
+      let center_padding = (term_width.saturating_sub(title_width)) / 2;
       writeln!(
         stdout,
         "{}{}{}",
-        " ".repeat(padding),
-        display_name,
-        " ".repeat(term_width - display_name.len() - padding)
+        " ".repeat(center_padding),
+        title,
+        " ".repeat(
+          term_width
+            .saturating_sub(title_width)
+            .saturating_sub(center_padding)
+        )
+      )?;
+      writeln!(stdout, "{border}")?;
+    }
+    header::Layout::Line => {
+      writeln!(stdout, "{title}")?;
+      if ctx.decoration_config.show_grid {
+        writeln!(stdout, "{border}")?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Settings for showing each input in the terminal, fixed for the whole run.
+struct InputOptions<'a> {
+  language_override: Option<&'a EitherLang<CustomLang, Lang>>,
+  preprocessors: &'a preprocess::Preprocessors,
+  header_path: paths::HeaderPath,
+  /// Regular files are copied to the output as they are
+  passthrough: bool,
+  blame: bool,
+  /// `umber blame`, which fails rather than show a file without history
+  blame_command: bool,
+  chunked: bool,
+  follow: bool,
+}
+
+/// What became of one input.
+enum Shown {
+  Output,
+  /// The input could not be read, which has been reported
+  Failed,
+}
+
+/// The output of `umber diff` or `umber show`.
+fn emit_generated(
+  stdout: &mut impl Write,
+  input: GeneratedInput,
+  hex_dump: Option<&hexdump::HexDump>,
+  line_ranges: &LineSelection,
+  language_override: Option<&EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<()> {
+  if let Some(hex_dump) = hex_dump {
+    hex_dump.write(stdout, &mut &input.bytes[..], &mut state.renderer)?;
+    return Ok(());
+  }
+  let language = language_override
+    .map(clone_either_lang)
+    .or(input.language)
+    .or_else(|| {
+      ctx.detect_language(
+        input.path.as_deref(),
+        &String::from_utf8_lossy(&input.bytes),
+      )
+    });
+  emit_bytes(
+    stdout,
+    input.bytes.into(),
+    None,
+    line_ranges,
+    language,
+    ctx,
+    state,
+  )?;
+  Ok(())
+}
+
+/// `--hex`: a dump of standard input, a URL or a file.
+fn dump_input(
+  stdout: &mut impl Write,
+  spec: &FileSpec,
+  hex_dump: &hexdump::HexDump,
+  state: &mut RenderState,
+  reporter: &Reporter,
+) -> Result<Shown> {
+  let mut reader: Box<dyn Read> = if spec.path == Path::new("-") {
+    Box::new(io::stdin().lock())
+  } else if remote::is_url(&spec.path) {
+    match remote::fetch(&spec.path.to_string_lossy()) {
+      Ok(fetched) => Box::new(io::Cursor::new(fetched.body)),
+      Err(err) => {
+        reporter.file_error(&spec.path, &err);
+        return Ok(Shown::Failed);
+      }
+    }
+  } else {
+    match fs::File::open(&spec.path) {
+      Ok(file) => Box::new(io::BufReader::new(file)),
+      Err(err) => {
+        reporter.file_error(&spec.path, &err);
+        return Ok(Shown::Failed);
+      }
+    }
+  };
+  if let Err(err) = hex_dump.write(stdout, &mut reader, &mut state.renderer) {
+    if err.kind() == io::ErrorKind::BrokenPipe {
+      return Err(err.into());
+    }
+    reporter.file_error(&spec.path, &err);
+    return Ok(Shown::Failed);
+  }
+  Ok(Shown::Output)
+}
+
+/// `--log-prefixes`: standard input or a file, line by line.
+fn emit_log_input(
+  stdout: &mut impl Write,
+  spec: &FileSpec,
+  language_override: Option<&EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+  reporter: &Reporter,
+) -> Result<Shown> {
+  let reader: Box<dyn Read> = if spec.path == Path::new("-") {
+    Box::new(io::stdin().lock())
+  } else {
+    match fs::File::open(&spec.path) {
+      Ok(file) => Box::new(file),
+      Err(err) => {
+        reporter.file_error(&spec.path, &err);
+        return Ok(Shown::Failed);
+      }
+    }
+  };
+  emit_log_lines(
+    stdout,
+    io::BufReader::new(reader),
+    language_override.map(clone_either_lang),
+    ctx,
+    state,
+  )?;
+  Ok(Shown::Output)
+}
+
+/// A file fetched over HTTP.
+fn emit_url(
+  stdout: &mut impl Write,
+  spec: &FileSpec,
+  language_override: Option<&EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+  reporter: &Reporter,
+) -> Result<Shown> {
+  let fetched = match remote::fetch(&spec.path.to_string_lossy()) {
+    Ok(fetched) => fetched,
+    Err(err) => {
+      reporter.file_error(&spec.path, &err);
+      return Ok(Shown::Failed);
+    }
+  };
+  // The URL's path names the language more precisely than most servers'
+  // types; the type helps for paths like `/api/users`.
+  let language = language_override.map(clone_either_lang).or_else(|| {
+    let name = fetched.language()?;
+    ctx
+      .detect_input_language(Some(&spec.path), "")
+      .is_none()
+      .then(|| resolve_language_union(name, ctx.language_set))?
+  });
+  emit_bytes(
+    stdout,
+    fetched.body.into(),
+    Some(&spec.path),
+    &spec.line_ranges,
+    language,
+    ctx,
+    state,
+  )?;
+  Ok(Shown::Output)
+}
+
+/// A file on disk: run through its preprocessor, copied as it is, streamed,
+/// or read whole, whichever its kind, size and the options call for. Files
+/// read whole are added to `follow_targets` with `--follow`.
+fn emit_file(
+  stdout: &mut PaddedWriter<pager::Output>,
+  spec: &FileSpec,
+  options: &InputOptions<'_>,
+  follow_targets: &mut Vec<FollowTarget>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+  reporter: &Reporter,
+) -> Result<Shown> {
+  let metadata = fs::metadata(&spec.path).ok();
+  if metadata.as_ref().is_some_and(|metadata| metadata.is_file())
+    && let Some(preprocessor) = options.preprocessors.find(&spec.path)
+  {
+    match options.preprocessors.run(preprocessor, &spec.path) {
+      Ok(output) => {
+        let language = options
+          .language_override
+          .map(clone_either_lang)
+          .or_else(|| {
+            let name = preprocessor.language.as_deref()?;
+            resolve_language_union(name, ctx.language_set)
+          })
+          .or_else(|| ctx.detect_input_language(None, &String::from_utf8_lossy(&output)));
+        emit_bytes(
+          stdout,
+          output.into(),
+          None,
+          &spec.line_ranges,
+          language,
+          ctx,
+          state,
+        )?;
+        return Ok(Shown::Output);
+      }
+      // Show the file itself rather than nothing.
+      Err(err) => reporter.warning(&tr_args(
+        "warning-preprocess-failed",
+        &[
+          ("path", Arg::Str(&spec.path.display().to_string())),
+          ("error", Arg::Str(&err.to_string())),
+        ],
+      )),
+    }
+  }
+  if metadata.as_ref().is_some_and(|metadata| metadata.is_file())
+    && options.passthrough
+    && spec.line_ranges.is_empty()
+  {
+    return match fs::File::open(&spec.path) {
+      Ok(mut file) => {
+        stdout.flush()?;
+        stdout.get_mut().copy_from(&mut file)?;
+        Ok(Shown::Output)
+      }
+      Err(err) => {
+        reporter.file_error(&spec.path, &err);
+        Ok(Shown::Failed)
+      }
+    };
+  }
+
+  // Huge files are highlighted window by window to keep memory bounded.
+  let chunked = metadata.as_ref().is_some_and(|metadata| {
+    metadata.is_file() && (options.chunked || metadata.len() >= stream::CHUNKED_MIN_BYTES)
+  }) && !options.blame
+    && !options.follow;
+  if chunked {
+    let size = metadata.as_ref().map(|metadata| metadata.len());
+    return emit_chunked_file(stdout, spec, size, options, ctx, state, reporter);
+  }
+
+  if metadata.is_some_and(|metadata| stream::is_streamable(&metadata)) {
+    return match fs::File::open(&spec.path) {
+      Ok(file) => {
+        stream_file(
+          stdout,
+          stream::LineChunks::new(file),
+          Some(&spec.path),
+          &spec.line_ranges,
+          options.language_override.map(clone_either_lang),
+          ctx,
+          state,
+        )?;
+        Ok(Shown::Output)
+      }
+      Err(err) => {
+        reporter.file_error(&spec.path, &err);
+        Ok(Shown::Failed)
+      }
+    };
+  }
+
+  // A followed file is still being written, which a map does not survive.
+  let read = if options.follow {
+    fs::read(&spec.path).map(Contents::from)
+  } else {
+    contents::read(&spec.path)
+  };
+  let buf = match read {
+    Ok(buf) => buf,
+    Err(err) => {
+      reporter.file_error(&spec.path, &err);
+      return Ok(Shown::Failed);
+    }
+  };
+  if options.follow {
+    let language = options
+      .language_override
+      .map(clone_either_lang)
+      .or_else(|| ctx.detect_input_language(Some(&spec.path), &String::from_utf8_lossy(&buf)));
+    follow_targets.push(FollowTarget {
+      name: spec.display_name(options.header_path),
+      file: follow::FollowedFile::new(spec.path.clone(), &buf),
+      language,
+    });
+  }
+  // `umber blame` needs the history; the style component shows files
+  // without any, such as untracked ones, as they are.
+  let blamed = match options.blame.then(|| git::blame(&spec.path)) {
+    Some(Ok(lines)) => Some(lines),
+    Some(Err(err)) if options.blame_command => return Err(err),
+    _ => None,
+  };
+  if let Some(blamed) = blamed {
+    emit_blamed(
+      stdout,
+      &buf,
+      spec,
+      &blamed,
+      options.language_override.map(clone_either_lang),
+      ctx,
+      state,
+    )?;
+  } else {
+    emit_bytes(
+      stdout,
+      buf,
+      Some(&spec.path),
+      &spec.line_ranges,
+      options.language_override.map(clone_either_lang),
+      ctx,
+      state,
+    )?;
+  }
+  Ok(Shown::Output)
+}
+
+/// A file of `size` bytes highlighted a window at a time, or dumped or
+/// summarized as binary.
+fn emit_chunked_file(
+  stdout: &mut impl Write,
+  spec: &FileSpec,
+  size: Option<u64>,
+  options: &InputOptions<'_>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+  reporter: &Reporter,
+) -> Result<Shown> {
+  let opened = fs::File::open(&spec.path).and_then(|file| {
+    let binary = ctx.binary != BinaryMode::AsText && stat::file_looks_binary(&file)?;
+    Ok((file, binary))
+  });
+  match opened {
+    Ok((file, true)) => {
+      emit_binary(
+        stdout,
+        &mut io::BufReader::new(file),
+        size,
+        Some(&spec.path),
+        ctx,
+        state,
+      )?;
+    }
+    Ok((file, false)) => {
+      stream_file(
+        stdout,
+        stream::LineChunks::with_window(contents::reader(file), stream::WINDOW_BYTES),
+        Some(&spec.path),
+        &spec.line_ranges,
+        options.language_override.map(clone_either_lang),
+        ctx,
+        state,
+      )?;
+    }
+    Err(err) => {
+      reporter.file_error(&spec.path, &err);
+      return Ok(Shown::Failed);
+    }
+  }
+  Ok(Shown::Output)
+}
+
+/// Parse arguments with help text translated for the current locale.
+fn parse_localized<T: CommandFactory + FromArgMatches>(
+  args: impl IntoIterator<Item = OsString>,
+  prefix: &str,
+) -> T {
+  let matches = i18n::localize_command(T::command(), prefix).get_matches_from(args);
+  T::from_arg_matches(&matches).unwrap_or_else(|err| err.exit())
+}
+
+/// `--stat`: count files, lines and bytes per language below `paths`.
+/// Returns true if some path could not be read.
+fn write_language_stats(
+  paths: &[PathBuf],
+  color_depth: Option<ColorDepth>,
+  theme: &ResolvedTheme,
+  language_set: &Union<CustomLanguageSet, LanguageSetImpl>,
+  syntax_map: &SyntaxMap,
+  ignored_suffixes: &[String],
+  reporter: &Reporter,
+) -> Result<bool> {
+  let mut had_error = false;
+  let mut files = Vec::new();
+  for path in paths {
+    if let Err(err) = stat::collect_files(path, &mut files) {
+      reporter.file_error(path, &err);
+      had_error = true;
+    }
+  }
+
+  let mut stats = stat::LanguageStats::default();
+  for path in files {
+    let content = match contents::read(&path) {
+      Ok(content) => content,
+      Err(err) => {
+        reporter.file_error(&path, &err);
+        had_error = true;
+        continue;
+      }
+    };
+    if stat::looks_binary(&content) {
+      stats.add(stat::BINARY, &content);
+      continue;
+    }
+    let language = detect_mapped_language(
+      Some(&path),
+      &String::from_utf8_lossy(&content),
+      language_set,
+      syntax_map,
+      ignored_suffixes,
+    );
+    let name = language.as_ref().map_or(stat::PLAIN_TEXT, language_name);
+    stats.add(name, &content);
+  }
+
+  let mut stdout = io::stdout().lock();
+  let mut renderer = color_depth.map(DepthRenderer::new);
+  let color = renderer.as_mut().map(|renderer| (renderer, theme));
+  stat::write_table(&mut stdout, &stats, color)?;
+  Ok(had_error)
+}
+
+/// `--import-bat-config`: write bat's translatable settings to umber's config file.
+fn import_bat_config(reporter: &Reporter) -> Result<()> {
+  let (Some(source), Some(imported)) = (bat::config_path(), bat::load()?) else {
+    return Err(eyre!(tr("error-no-bat-config")));
+  };
+  for option in &imported.skipped {
+    reporter.note(&tr_args("bat-skipped", &[("option", Arg::Str(option))]));
+  }
+  let toml = bat::to_toml(&imported, &source);
+  match config::config_path() {
+    Some(path) if !path.exists() => {
+      if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+      }
+      fs::write(&path, toml)?;
+      let path = path.display().to_string();
+      println!("{}", tr_args("bat-imported", &[("path", Arg::Str(&path))]));
+    }
+    Some(path) => {
+      let path = path.display().to_string();
+      reporter.warning(&tr_args("bat-config-exists", &[("path", Arg::Str(&path))]));
+      print!("{toml}");
+    }
+    None => print!("{toml}"),
+  }
+  Ok(())
+}
+
+fn list_themes() {
+  for theme in syntastica_themes::THEMES {
+    println!("{theme}");
+  }
+}
+
+/// Code shown in each theme by `--list-themes`.
+const THEME_SAMPLE: &str = "\
+// Greet someone by name
+fn greet(name: &str, times: usize) -> String {
+  let greeting = format!(\"Hello, {name}!\\n\");
+  greeting.repeat(times)
+}
+";
+
+fn preview_themes(
+  language_set: &Union<CustomLanguageSet, LanguageSetImpl>,
+  terminal: &TerminalQueries,
+) -> Result<()> {
+  let language = EitherLang::<CustomLang, Lang>::for_name("rust", language_set)?;
+  let highlights = syntastica::Processor::new(language_set).process(THEME_SAMPLE, language)?;
+  let mut renderer = DepthRenderer::new(terminal.color_depth());
+  let mut stdout = io::stdout().lock();
+  for name in syntastica_themes::THEMES {
+    let Some(theme) = syntastica_themes::from_str(name) else {
+      continue;
+    };
+    writeln!(stdout, "Theme: {name}\n")?;
+    write!(
+      stdout,
+      "{}",
+      syntastica::render(&highlights, &mut renderer, theme)
+    )?;
+    writeln!(stdout)?;
+  }
+  Ok(())
+}
+
+fn list_languages() {
+  let mut names: Vec<&str> = LANGUAGE_NAMES
+    .iter()
+    .copied()
+    .chain(CustomLang::ALL.iter().map(|lang| lang.as_ref()))
+    .chain(grammars::all().iter().map(|grammar| grammar.name.as_str()))
+    .collect();
+  names.sort_unstable();
+  names.dedup();
+  for name in names {
+    println!("{name}");
+  }
+}
+
+fn run_cache_command(cli: CacheCli) -> Result<()> {
+  let dir = cache::cache_dir(cli.cache_dir.as_deref())?;
+  let path = dir.display().to_string();
+  let clear = cli.clear || cli.action == Some(CacheAction::Clear);
+  if clear {
+    cache::clear_cache(&dir).map_err(|err| {
+      eyre!(tr_args(
+        "cache-failed-clear",
+        &[
+          ("path", Arg::Str(&path)),
+          ("error", Arg::Str(&err.to_string()))
+        ],
+      ))
+    })?;
+    println!("{}", tr_args("cache-cleared", &[("path", Arg::Str(&path))]));
+  }
+  if cli.size {
+    let usage = cache::cache_usage(&dir).map_err(|err| {
+      eyre!(tr_args(
+        "cache-failed-inspect",
+        &[
+          ("path", Arg::Str(&path)),
+          ("error", Arg::Str(&err.to_string()))
+        ],
+      ))
+    })?;
+    println!(
+      "{}",
+      tr_args(
+        "cache-size",
+        &[
+          ("path", Arg::Str(&path)),
+          ("size", Arg::Str(&format_size(usage.bytes))),
+          ("files", Arg::Num(usage.files)),
+        ],
+      )
+    );
+  }
+  for spec in &cli.grammar {
+    install_grammar(spec, &dir)?;
+  }
+  if cli.action == Some(CacheAction::Build) || !cli.grammar.is_empty() {
+    let count = custom_langs::build_query_cache(&dir).map_err(|err| {
+      eyre!(tr_args(
+        "cache-failed-build",
+        &[
+          ("path", Arg::Str(&path)),
+          ("error", Arg::Str(&err.to_string()))
+        ],
+      ))
+    })?;
+    println!(
+      "{}",
+      tr_args(
+        "cache-built",
+        &[("path", Arg::Str(&path)), ("count", Arg::Num(count as u64))],
+      )
+    );
+  }
+  if cli.action.is_none() && !cli.clear && !cli.size {
+    println!("{}", dir.display());
+  }
+  Ok(())
+}
+
+/// Build and install the grammar `spec` names, with its query preprocessed
+/// into the cache in `cache_dir`.
+fn install_grammar(spec: &str, cache_dir: &Path) -> Result<()> {
+  let grammars_dir = config::config_dir()
+    .ok_or_else(|| eyre!(tr("error-no-config-dir")))?
+    .join("grammars");
+  let installed = GrammarSource::parse(spec).and_then(|source| {
+    let query = source.install(&grammars_dir, cache_dir)?;
+    custom_langs::cache_query(cache_dir, &source.name, &query)?;
+    Ok(source.name)
+  });
+  let name = installed.map_err(|err| {
+    eyre!(tr_args(
+      "cache-failed-grammar",
+      &[
+        ("source", Arg::Str(spec)),
+        ("error", Arg::Str(&format!("{err:#}")))
+      ],
+    ))
+  })?;
+  let path = grammars_dir.join(&name).display().to_string();
+  println!(
+    "{}",
+    tr_args(
+      "cache-grammar-installed",
+      &[("name", Arg::Str(&name)), ("path", Arg::Str(&path))],
+    )
+  );
+  Ok(())
+}
+
+/// Format a byte count with binary units, e.g. `12.3 KiB`.
+fn format_size(bytes: u64) -> String {
+  const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+  if bytes < 1024 {
+    return format!("{bytes} B");
+  }
+  let mut value = bytes as f64;
+  let mut unit = 0;
+  while value >= 1024.0 && unit < UNITS.len() - 1 {
+    value /= 1024.0;
+    unit += 1;
+  }
+  format!("{value:.1} {}", UNITS[unit])
+}
+
+fn write_completions(shell: clap_complete::Shell) -> Result<()> {
+  let mut cmd = Cli::command();
+  clap_complete::generate(shell, &mut cmd, "umber", &mut io::stdout());
+  Ok(())
+}
+
+fn write_man_page() -> Result<()> {
+  let cmd = Cli::command();
+  let man = clap_mangen::Man::new(cmd);
+  man.render(&mut io::stdout())?;
+  Ok(())
+}
+
+fn clone_either_lang(lang: &EitherLang<CustomLang, Lang>) -> EitherLang<CustomLang, Lang> {
+  match lang {
+    EitherLang::Left(custom) => EitherLang::Left(*custom),
+    EitherLang::Right(parser) => EitherLang::Right(*parser),
+  }
+}
+
+/// Render `bytes`, which stay mapped through the steps that leave them
+/// unchanged and are rendered from the map.
+fn emit_bytes(
+  stdout: &mut impl Write,
+  bytes: Contents,
+  path: Option<&Path>,
+  line_ranges: &LineSelection,
+  mut language_override: Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<bool> {
+  let decode = encoding::has_bom(&bytes);
+  let mut bytes = bytes.edit_if(decode, |bytes| encoding::decode(bytes, ctx.show_all));
+  // Nothing past the last selected line is looked at, unless lines to
+  // highlight are counted from the end.
+  if ctx.highlight_lines.reach_from_end() == 0
+    && let Some(len) = line_ranges.needed_len(&bytes)
+  {
+    bytes.truncate(len);
+  }
+  let normalize = ctx.normalize_eol && eol::has_crlf(&bytes);
+  let bytes = bytes.edit_if(normalize, eol::normalize);
+  if ctx.binary != BinaryMode::AsText && stat::looks_binary(&bytes) {
+    emit_binary(
+      stdout,
+      &mut &bytes[..],
+      Some(bytes.len() as u64),
+      path,
+      ctx,
+      state,
+    )?;
+    return Ok(true);
+  }
+  // Every range is rendered on its own so its lines keep their numbers; the
+  // language is detected once, from the first.
+  let mut language: Option<Option<EitherLang<CustomLang, Lang>>> = None;
+  let bytes = strip_ansi_input(bytes, path, &mut language, &mut language_override, ctx);
+  if ctx.use_color
+    && let Some(skip) = ctx.max_highlight_size.check(&bytes)
+  {
+    language = Some(None);
+    state.skipped = Some(skip);
+  }
+  let ctx = &RenderContext {
+    marks: ranges::Marks::for_bytes(ctx.highlight_lines, line_ranges, &bytes),
+    ..*ctx
+  };
+  let runs = line_runs(&bytes, line_ranges);
+  let ctx = &RenderContext {
+    min_line_number_width: runs_line_number_width(&runs),
+    ..*ctx
+  };
+  let last = runs.len() - 1;
+  let mut ended_with_newline = true;
+  for (index, (line_number_start, bytes)) in runs.into_iter().enumerate() {
+    if index > 0 && ctx.snip {
+      write_snip(stdout, ctx, state)?;
+    }
+    let bytes = if ctx.squeeze_blank {
+      Cow::Owned(squeeze_blank_lines_bytes(bytes, ctx.squeeze_limit))
+    } else {
+      Cow::Borrowed(bytes)
+    };
+    let resolve_language = |text: &str| {
+      language
+        .get_or_insert_with(|| {
+          language_override
+            .take()
+            .or_else(|| ctx.detect_input_language(path, text))
+        })
+        .as_ref()
+        .map(clone_either_lang)
+    };
+    if index < last {
+      render_line_chunk(
+        stdout,
+        &bytes,
+        path,
+        line_number_start,
+        resolve_language,
+        ctx,
+        state,
+      )?;
+    } else {
+      ended_with_newline = render_bytes(
+        stdout,
+        bytes,
+        path,
+        line_number_start,
+        resolve_language,
+        ctx,
+        state,
+      )?;
+    }
+  }
+  state.finish_input(ctx.file_name.or(path));
+  Ok(ended_with_newline)
+}
+
+/// `bytes` without escape sequences when `--strip-ansi` asks for it: always,
+/// or with `auto` when the output is colored and the stripped text is in a
+/// known language. That language is kept in `language` for highlighting.
+fn strip_ansi_input(
+  bytes: Contents,
+  path: Option<&Path>,
+  language: &mut Option<Option<EitherLang<CustomLang, Lang>>>,
+  language_override: &mut Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+) -> Contents {
+  if ctx.strip_ansi == StripAnsi::Never || !ansi::has_escapes(&bytes) {
+    return bytes;
+  }
+  let stripped = ansi::strip(&bytes);
+  let strip = match ctx.strip_ansi {
+    StripAnsi::Always => true,
+    _ => {
+      ctx.use_color
+        && language
+          .get_or_insert_with(|| {
+            language_override
+              .take()
+              .or_else(|| ctx.detect_input_language(path, &String::from_utf8_lossy(&stripped)))
+          })
+          .is_some()
+    }
+  };
+  if strip { stripped.into() } else { bytes }
+}
+
+/// Binary input as `--binary` asks: a hex dump or a dimmed notice with the name
+/// and `size` of the input, when known. Never called for `--binary=as-text`.
+fn emit_binary(
+  stdout: &mut impl Write,
+  input: &mut impl Read,
+  size: Option<u64>,
+  path: Option<&Path>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<()> {
+  if ctx.binary == BinaryMode::Hex {
+    ctx.hex_dump().write(stdout, input, &mut state.renderer)?;
+    return Ok(());
+  }
+  let size = size.map_or_else(|| tr("size-unknown"), format_size);
+  let notice = match ctx.file_name.or(path) {
+    Some(name) => tr_args(
+      "binary-file",
+      &[
+        ("name", Arg::Str(&name.display().to_string())),
+        ("size", Arg::Str(&size)),
+      ],
+    ),
+    None => tr_args("binary-input", &[("size", Arg::Str(&size))]),
+  };
+  if ctx.use_color {
+    let dim = decorations::get_dim_style_or_create(ctx.theme);
+    writeln!(stdout, "{}", state.renderer.styled(&notice, dim))?;
+  } else {
+    writeln!(stdout, "{notice}")?;
+  }
+  Ok(())
+}
+
+/// Read and highlight every input for `--format`. The flag is set when an
+/// input could not be read; the others are still returned.
+fn collect_listings<'a>(
+  generated: Option<GeneratedInput>,
+  specs: &'a [FileSpec],
+  language_override: Option<&EitherLang<CustomLang, Lang>>,
+  header_path: paths::HeaderPath,
+  ctx: &RenderContext<'a>,
+  reporter: &Reporter,
+) -> (Vec<Listing<'a>>, bool) {
+  let mut state = RenderState::new(ColorDepth::Truecolor);
+  let mut listings = Vec::new();
+  let mut had_error = false;
+  if let Some(input) = generated {
+    let name = input
+      .path
+      .as_deref()
+      .map_or_else(|| "-".to_string(), |path| path.display().to_string());
+    let language = language_override.map(clone_either_lang).or(input.language);
+    listings.push(highlight_listing(
+      name,
+      input.bytes,
+      input.path.as_deref(),
+      &LineSelection::default(),
+      language,
+      ctx,
+      &mut state,
+    ));
+  }
+  let mut stdin_consumed = false;
+  for spec in specs {
+    let ctx = RenderContext {
+      file_name: spec.name.as_deref(),
+      ..*ctx
+    };
+    let (bytes, path) = if spec.path == Path::new("-") {
+      if stdin_consumed {
+        continue;
+      }
+      stdin_consumed = true;
+      let mut buf = Vec::new();
+      (io::stdin().read_to_end(&mut buf).map(|_| buf), None)
+    } else if remote::is_url(&spec.path) {
+      let fetched = remote::fetch(&spec.path.to_string_lossy());
+      (
+        fetched.map(|fetched| fetched.body),
+        Some(spec.path.as_path()),
+      )
+    } else {
+      (fs::read(&spec.path), Some(spec.path.as_path()))
+    };
+    match bytes {
+      Ok(bytes) => listings.push(highlight_listing(
+        spec.display_name(header_path),
+        bytes,
+        path,
+        &spec.line_ranges,
+        language_override.map(clone_either_lang),
+        &ctx,
+        &mut state,
+      )),
+      Err(err) => {
+        reporter.file_error(&spec.path, &err);
+        had_error = true;
+      }
+    }
+  }
+  (listings, had_error)
+}
+
+fn highlight_listing<'a>(
+  name: String,
+  bytes: Vec<u8>,
+  path: Option<&Path>,
+  line_ranges: &LineSelection,
+  language_override: Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'a>,
+  state: &mut RenderState,
+) -> Listing<'a> {
+  let bytes = encoding::decode(bytes, false);
+  let bytes = if ctx.normalize_eol {
+    eol::normalize(bytes)
+  } else {
+    bytes
+  };
+  // Escape sequences mean nothing in a document or an image.
+  let bytes = match ctx.strip_ansi {
+    StripAnsi::Never => bytes,
+    _ => ansi::strip(&bytes),
+  };
+  let runs = line_runs(&bytes, line_ranges);
+  let language = language_override
+    .or_else(|| ctx.detect_input_language(path, &String::from_utf8_lossy(runs[0].1)));
+  let ctx = ctx.for_language(language.as_ref());
+  let mut lines = Vec::new();
+  for (line_number_start, bytes) in runs {
+    if bytes.is_empty() {
+      continue;
+    }
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    let highlighted = language
+      .as_ref()
+      .map(clone_either_lang)
+      .and_then(|language| highlight_lines(text, language, &ctx, state))
+      .unwrap_or_else(|| {
+        text
+          .split('\n')
+          .map(|line| vec![(line.to_string(), None)])
+          .collect()
+      });
+    lines.extend((line_number_start..).zip(highlighted));
+  }
+  Listing {
+    name,
+    lines,
+    theme: ctx.theme,
+    numbers: ctx.decoration_config.show_numbers,
+  }
+}
+
+/// All listings in `format`.
+fn render_listings(
+  format: OutputFormat,
+  listings: &[Listing<'_>],
+  theme: &ResolvedTheme,
+  image_options: &svg::ImageOptions<'_>,
+) -> Result<Vec<u8>> {
+  let text = match format {
+    OutputFormat::Terminal | OutputFormat::Html => html::render(listings, false, theme),
+    OutputFormat::HtmlFull => html::render(listings, true, theme),
+    OutputFormat::Svg => svg::render(listings, theme, image_options),
+    OutputFormat::Latex => latex::render(listings),
+    OutputFormat::Typst => typst::render(listings),
+    #[cfg(feature = "png")]
+    OutputFormat::Png => return raster::render(listings, theme, image_options),
+    #[cfg(not(feature = "png"))]
+    OutputFormat::Png => return Err(eyre!(tr("error-png-feature"))),
+  };
+  Ok(text.into_bytes())
+}
+
+/// The selected lines of `bytes`, one run per range with the number of its
+/// first line. Never empty: a selection past the end gives one empty run.
+fn line_runs<'a>(bytes: &'a [u8], line_ranges: &LineSelection) -> Vec<(usize, &'a [u8])> {
+  if line_ranges.is_empty() {
+    return vec![(1, bytes)];
+  }
+  let runs = ranges::slice(bytes, line_ranges);
+  if runs.is_empty() {
+    vec![(1, &[][..])]
+  } else {
+    runs
+  }
+}
+
+/// Width of the line numbers in `runs`, so that every range of an input shares
+/// the gutter of the largest.
+fn runs_line_number_width(runs: &[(usize, &[u8])]) -> usize {
+  runs
+    .iter()
+    .map(|(start, bytes)| line_number_width(start + count_lines_bytes(bytes).saturating_sub(1)))
+    .max()
+    .unwrap_or(1)
+}
+
+/// Render a file with a margin holding the commit, author and age of each
+/// line. Blank lines are never squeezed so every line keeps its blame entry.
+fn emit_blamed(
+  stdout: &mut impl Write,
+  bytes: &[u8],
+  spec: &FileSpec,
+  blame: &[git::BlameLine],
+  mut language_override: Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<bool> {
+  let clock = decorations::BlameClock::for_blame(blame, ctx.deterministic);
+  // The margin is added per output line, so every line must stay a single row.
+  let ctx = &RenderContext {
+    wrap: decorations::Wrap::default(),
+    marks: ranges::Marks::for_bytes(ctx.highlight_lines, &spec.line_ranges, bytes),
+    ..*ctx
+  };
+  let author_width = blame
+    .iter()
+    .map(|line| line.author.chars().count())
+    .max()
+    .unwrap_or(0)
+    .min(20);
+  let mut language: Option<Option<EitherLang<CustomLang, Lang>>> = None;
+  let runs = line_runs(bytes, &spec.line_ranges);
+  let ctx = &RenderContext {
+    min_line_number_width: runs_line_number_width(&runs),
+    ..*ctx
+  };
+  let last = runs.len() - 1;
+  let mut highlighted = true;
+  let mut rendered = Vec::new();
+  for (run, (first_line, bytes)) in runs.into_iter().enumerate() {
+    rendered.clear();
+    let resolve_language = |text: &str| {
+      language
+        .get_or_insert_with(|| {
+          language_override
+            .take()
+            .or_else(|| ctx.detect_input_language(Some(&spec.path), text))
+        })
+        .as_ref()
+        .map(clone_either_lang)
+    };
+    if run < last {
+      render_line_chunk(
+        &mut rendered,
+        bytes,
+        Some(&spec.path),
+        first_line,
+        resolve_language,
+        ctx,
+        state,
+      )?;
+    } else {
+      highlighted = render_bytes(
+        &mut rendered,
+        Cow::Borrowed(bytes),
+        Some(&spec.path),
+        first_line,
+        resolve_language,
+        ctx,
+        state,
       )?;
-      writeln!(stdout, "{border}")?;
     }
-
-    if spec.path == Path::new("-") {
-      if stdin_consumed {
-        continue;
-      }
-      stdin_consumed = true;
-      let mut buf = Vec::new();
-      if let Err(err) = stdin.read_to_end(&mut buf) {
-        eprintln!("umber: -: {err}");
-        had_error = true;
-        continue;
+    for (index, line) in rendered.split_inclusive(|byte| *byte == b'\n').enumerate() {
+      let entry = blame.get(first_line - 1 + index);
+      let (margin, age) = decorations::blame_margin(entry, author_width, clock);
+      if ctx.use_color {
+        let margin = decorations::render_blame_margin(&margin, age, &mut state.renderer, ctx.theme);
+        stdout.write_all(margin.as_bytes())?;
+      } else {
+        stdout.write_all(margin.as_bytes())?;
       }
-      emit_bytes(
-        &mut stdout,
-        buf,
-        None,
-        spec.line_range,
-        language_override.as_ref().map(clone_either_lang),
-        &ctx,
-        &mut state,
-      )?;
-      wrote_output = true;
-      continue;
+      stdout.write_all(line)?;
     }
+  }
+  Ok(highlighted)
+}
 
-    match fs::read(&spec.path) {
-      Ok(buf) => {
-        emit_bytes(
-          &mut stdout,
-          buf,
-          Some(&spec.path),
-          spec.line_range,
-          language_override.as_ref().map(clone_either_lang),
-          &ctx,
-          &mut state,
-        )?;
-        wrote_output = true;
-      }
+/// `--bench`: time each phase of showing `files`, rendering into a sink.
+/// Returns true if a file could not be read.
+fn bench_files(
+  files: &[FileSpec],
+  format: bench::BenchFormat,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+  reporter: &Reporter,
+) -> Result<bool> {
+  // Time the colored output whether or not stdout is a terminal.
+  let ctx = &RenderContext {
+    use_color: true,
+    ..*ctx
+  };
+  let mut failed = false;
+  let mut timings = Vec::new();
+  for FileSpec { path, .. } in files {
+    let mut timing = bench::Timings {
+      path: path.display().to_string(),
+      ..bench::Timings::default()
+    };
+    let start = Instant::now();
+    let contents = match contents::read(path) {
+      Ok(contents) => contents,
       Err(err) => {
-        eprintln!("umber: {}: {err}", spec.path.display());
-        had_error = true;
+        reporter.file_error(path, &err);
+        failed = true;
+        continue;
       }
-    }
-  }
+    };
+    let text = String::from_utf8_lossy(&contents).into_owned();
+    timing.bytes = contents.len() as u64;
+    timing.read = start.elapsed();
 
-  stdout.flush()?;
-  if had_error {
-    std::process::exit(1);
+    let start = Instant::now();
+    let language = ctx.detect_input_language(Some(path), &text);
+    timing.detect = start.elapsed();
+    timing.language = language
+      .as_ref()
+      .map(|language| language_name(language).to_string());
+    let ctx = &ctx.for_language(language.as_ref());
+    let events =
+      language.and_then(|language| bench_highlight(&text, language, ctx, state, &mut timing));
+
+    let start = Instant::now();
+    match events {
+      Some(events) => {
+        // A sink cannot fail, and highlight errors were caught collecting the events.
+        let _ = write_highlight_events(
+          &mut io::sink(),
+          &text,
+          &mut events.into_iter(),
+          &mut state.renderer,
+          DecorationsStreamSettings::new(ctx, 1, &[], None),
+          ctx,
+        );
+      }
+      None => io::sink().write_all(plain_text_output(&text, 1, ctx).as_bytes())?,
+    }
+    timing.render = start.elapsed();
+    timings.push(timing);
   }
-  Ok(())
+  bench::write(&mut io::stdout().lock(), &timings, format)?;
+  Ok(failed)
 }
 
-fn write_completions(shell: clap_complete::Shell) -> Result<()> {
-  let mut cmd = Cli::command();
-  clap_complete::generate(shell, &mut cmd, "umber", &mut io::stdout());
-  Ok(())
-}
+/// Compile the queries for `language`, parse `text` and collect its highlight
+/// events, timing each step. `None` when highlighting fails.
+fn bench_highlight(
+  text: &str,
+  language: EitherLang<CustomLang, Lang>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+  timing: &mut bench::Timings,
+) -> Option<Vec<HighlightItem>> {
+  let start = Instant::now();
+  let config = highlight_configuration(language, ctx, &mut state.configs).ok()?;
+  timing.queries = start.elapsed();
 
-fn write_man_page() -> Result<()> {
-  let cmd = Cli::command();
-  let man = clap_mangen::Man::new(cmd);
-  man.render(&mut io::stdout())?;
-  Ok(())
+  let start = Instant::now();
+  let mut events = highlight_events(
+    &mut state.highlighter,
+    config,
+    text,
+    ctx.language_set,
+    ctx.highlight_injections,
+  );
+  // The highlighter parses the whole input before its first event.
+  let first = events.next();
+  timing.parse = start.elapsed();
+
+  let start = Instant::now();
+  let events: Vec<_> = first.into_iter().chain(events).collect();
+  timing.highlight = start.elapsed();
+  events.iter().all(Result::is_ok).then_some(events)
 }
 
-fn clone_either_lang(lang: &EitherLang<CustomLang, Lang>) -> EitherLang<CustomLang, Lang> {
-  match lang {
-    EitherLang::Left(custom) => EitherLang::Left(*custom),
-    EitherLang::Right(parser) => EitherLang::Right(*parser),
+/// Finish a file's last line and draw the rule that ends it in grid style.
+fn close_grid_frame<W: Write>(stdout: &mut PaddedWriter<W>, border: &str) -> io::Result<()> {
+  if !stdout.at_line_start() {
+    writeln!(stdout)?;
   }
+  writeln!(stdout, "{border}")
 }
 
-fn emit_bytes(
+/// Render already sliced and squeezed input. `resolve_language` is only called
+/// when the input is highlighted.
+fn render_bytes(
   stdout: &mut impl Write,
-  bytes: Vec<u8>,
+  bytes: Cow<'_, [u8]>,
   path: Option<&Path>,
-  line_range: Option<LineRange>,
-  language_override: Option<EitherLang<CustomLang, Lang>>,
+  line_number_start: usize,
+  resolve_language: impl FnOnce(&str) -> Option<EitherLang<CustomLang, Lang>>,
   ctx: &RenderContext<'_>,
   state: &mut RenderState,
 ) -> Result<bool> {
-  let bytes = if let Some(range) = line_range {
-    slice_bytes_by_line_range(&bytes, range)
-  } else {
-    bytes
+  let ended_with_newline = bytes.last() == Some(&b'\n') || bytes.is_empty();
+  // Expand tabs up front so highlighting, wrapping and --show-all all see the
+  // same columns.
+  let bytes = match std::str::from_utf8(&bytes) {
+    Ok(text) if ctx.tabs > 0 => match tabs::expand_tabs(text, ctx.tabs, ctx.show_all) {
+      Cow::Owned(expanded) => Cow::Owned(expanded.into_bytes()),
+      Cow::Borrowed(_) => bytes,
+    },
+    _ => bytes,
   };
-  let bytes = if ctx.squeeze_blank {
-    squeeze_blank_lines_bytes(&bytes, ctx.squeeze_limit)
-  } else {
-    bytes
+  let bytes = match std::str::from_utf8(&bytes) {
+    Ok(text) if ctx.max_line_length.is_some() => {
+      match chop::chop_lines(
+        text,
+        ctx.chop_columns(&bytes, line_number_start),
+        chop::marker(ctx.char_style),
+      ) {
+        Cow::Owned(chopped) => Cow::Owned(chopped.into_bytes()),
+        Cow::Borrowed(_) => bytes,
+      }
+    }
+    _ => bytes,
   };
-  let line_number_start = line_range.map(|range| range.start).unwrap_or(1);
-  let ended_with_newline = bytes.last() == Some(&b'\n') || bytes.is_empty();
   let decoration_config = ctx.decoration_config;
   let show_all = ctx.show_all;
   let use_color = ctx.use_color;
+  // Uncolored output is written from the bytes; colored output marks line
+  // endings as it renders.
+  let bytes = if !use_color && ctx.show_line_endings && !show_all {
+    Cow::Owned(eol::mark(&bytes, ctx.char_style))
+  } else {
+    bytes
+  };
 
   // Handle show_all flag for non-color, non-decoration case
   if !use_color && !decoration_config.has_decorations() {
     if show_all {
-      if let Ok(text) = String::from_utf8(bytes.clone()) {
-        let transformed = unprintable::show_unprintable(&text, unprintable::get_char_style());
+      if let Ok(text) = std::str::from_utf8(&bytes) {
+        let transformed = unprintable::show_unprintable(text, ctx.char_style);
         stdout.write_all(transformed.as_bytes())?;
       } else {
         // Invalid UTF-8, write as-is
@@ -511,153 +3314,696 @@ fn emit_bytes(
     } else {
       stdout.write_all(&bytes)?;
     }
-    return Ok(ended_with_newline);
+    return Ok(ended_with_newline);
+  }
+
+  if use_color {
+    // A few invalid bytes should not cost the whole input its colors: they are
+    // shown as U+FFFD and reported once the output is done.
+    let text = match std::str::from_utf8(&bytes) {
+      Ok(text) => Cow::Borrowed(text),
+      Err(_) if !stat::looks_binary(&bytes) => {
+        state.invalid_utf8 = true;
+        String::from_utf8_lossy(&bytes)
+      }
+      Err(_) => {
+        if decoration_config.show_numbers {
+          write_numbered_bytes(stdout, &bytes, line_number_start, ctx.min_line_number_width)?;
+        } else if show_all {
+          // Try to convert what we can, handling invalid UTF-8
+          let text = String::from_utf8_lossy(&bytes);
+          let transformed = unprintable::show_unprintable(&text, ctx.char_style);
+          stdout.write_all(transformed.as_bytes())?;
+        } else {
+          stdout.write_all(&bytes)?;
+        }
+        return Ok(ended_with_newline);
+      }
+    };
+    let language = resolve_language(&text);
+    let ctx = &ctx.for_language(language.as_ref());
+    if language.as_ref().is_some_and(is_diff_language) && !ctx.show_all {
+      let highlights = diff::highlight_hunks(&text, |path, code| {
+        let language = ctx.detect_language(Some(Path::new(path)), code)?;
+        highlight_lines(code, language, ctx, state)
+      });
+      diff::write_diff(
+        stdout,
+        &text,
+        &highlights,
+        ctx.decoration_config.show_numbers,
+        &mut state.renderer,
+        ctx.theme,
+      )?;
+      return Ok(ended_with_newline);
+    }
+    let changes = ctx
+      .decoration_config
+      .show_changes
+      .then(|| state.take_git_changes(path, ctx.diff_base));
+    let all_changes = changes.as_ref().map_or(&[][..], |(_, changes)| changes);
+    // The column is there for the whole input or not at all, whichever part
+    // the changes are in.
+    let ctx = &RenderContext {
+      decoration_config: DecorationConfig {
+        show_changes: !all_changes.is_empty(),
+        ..ctx.decoration_config
+      },
+      ..*ctx
+    };
+    // Changes are indexed from line 1, and this may be a later range.
+    let git_changes = all_changes
+      .get(line_number_start.saturating_sub(1)..)
+      .unwrap_or_default();
+    let written = write_rendered_text(
+      stdout,
+      &text,
+      language,
+      line_number_start,
+      git_changes,
+      ctx,
+      state,
+    );
+    state.git_changes = changes;
+    written?;
+    return Ok(ended_with_newline);
+  }
+
+  if decoration_config.show_numbers {
+    if show_all {
+      // Use number_plain_text when show_all is enabled
+      if let Ok(text) = std::str::from_utf8(&bytes) {
+        let numbered = number_plain_text(text, line_number_start, ctx);
+        stdout.write_all(numbered.as_bytes())?;
+      } else {
+        write_numbered_bytes(stdout, &bytes, line_number_start, ctx.min_line_number_width)?;
+      }
+    } else {
+      write_numbered_bytes(stdout, &bytes, line_number_start, ctx.min_line_number_width)?;
+    }
+  } else if show_all {
+    // Handle show_all for non-color case with decorations
+    if let Ok(text) = std::str::from_utf8(&bytes) {
+      let transformed = unprintable::show_unprintable(text, ctx.char_style);
+      stdout.write_all(transformed.as_bytes())?;
+    } else {
+      stdout.write_all(&bytes)?;
+    }
+  } else {
+    stdout.write_all(&bytes)?;
+  }
+  Ok(ended_with_newline)
+}
+
+/// Render stdin. A pipe or terminal is highlighted as its lines arrive, so
+/// the output of a long-running command shows up as it is written and memory
+/// stays bounded; a redirected file, or UTF-16 input, is read whole.
+fn emit_stdin(
+  stdout: &mut impl Write,
+  input: &mut impl Read,
+  line_ranges: &LineSelection,
+  language_override: Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<()> {
+  let redirected = stream::stdin_metadata().is_some_and(|metadata| metadata.is_file());
+  let head = stream::read_head(input, stat::SNIFF_BYTES)?;
+  if redirected || encoding::has_utf16_bom(&head) {
+    let mut bytes = head;
+    input.read_to_end(&mut bytes)?;
+    emit_bytes(
+      stdout,
+      bytes.into(),
+      None,
+      line_ranges,
+      language_override,
+      ctx,
+      state,
+    )?;
+    return Ok(());
+  }
+  if ctx.binary != BinaryMode::AsText && stat::looks_binary(&head) {
+    // A pipe may never end, so it is not drained to tell its size.
+    emit_binary(stdout, &mut head.chain(input), None, None, ctx, state)?;
+    return Ok(());
+  }
+  stream_file(
+    stdout,
+    stream::LineChunks::new(head.chain(input)),
+    None,
+    line_ranges,
+    language_override,
+    ctx,
+    state,
+  )
+}
+
+/// Render a pipe or other non-regular file as its lines arrive instead of waiting for EOF.
+/// The language is detected once, from the path and the first lines.
+fn stream_file(
+  stdout: &mut impl Write,
+  mut chunks: stream::LineChunks<impl Read>,
+  path: Option<&Path>,
+  line_ranges: &LineSelection,
+  mut language_override: Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<()> {
+  let mut filter = stream::LineFilter::new(
+    line_ranges.clone(),
+    ctx.squeeze_blank.then_some(ctx.squeeze_limit),
+  );
+  let mut language: Option<Option<EitherLang<CustomLang, Lang>>> = None;
+  let mut runs = Vec::new();
+  // Runs also break at chunk boundaries, so only a jump in numbering is a gap.
+  let mut next_line = None;
+  let mut first_chunk = true;
+  // Set after the first piece of a line too long to read whole.
+  let mut split_line = false;
+  // Only a regular file can be read again: for its changes, and to size the
+  // gutter for its last line. Other inputs get one wide enough for most.
+  let file_path = path.filter(|path| fs::metadata(path).is_ok_and(|metadata| metadata.is_file()));
+  let min_line_number_width = if ctx.decoration_config.show_numbers {
+    file_path
+      .and_then(|path| stream::count_lines(fs::File::open(path).ok()?).ok())
+      .map_or(stream::LINE_NUMBER_WIDTH, line_number_width)
+  } else {
+    0
+  };
+
+  let mut done = false;
+  while !done {
+    runs.clear();
+    match chunks.next_chunk()? {
+      Some(chunk) => {
+        // Only the start of the input can have a byte order mark.
+        let chunk = if first_chunk && !ctx.show_all {
+          encoding::strip_utf8_bom(chunk)
+        } else {
+          chunk
+        };
+        first_chunk = false;
+        let chunk = if ctx.normalize_eol {
+          eol::normalize(chunk)
+        } else {
+          chunk
+        };
+        let chunk = strip_ansi_input(
+          chunk.into(),
+          path,
+          &mut language,
+          &mut language_override,
+          ctx,
+        );
+        filter.push(&chunk, &mut runs);
+        done = filter.is_done();
+      }
+      None => {
+        filter.finish(&mut runs);
+        done = true;
+      }
+    }
+    let ctx = &RenderContext {
+      marks: ranges::Marks::new(ctx.highlight_lines, line_ranges, filter.total()),
+      min_line_number_width,
+      ..*ctx
+    };
+    for (line_number_start, lines) in &runs {
+      if ctx.snip && next_line.is_some_and(|next| next != *line_number_start) {
+        write_snip(stdout, ctx, state)?;
+      }
+      next_line = Some(line_number_start + lines.iter().filter(|&&byte| byte == b'\n').count());
+      if std::mem::take(&mut split_line) {
+        stdout.write_all(b"\n")?;
+      }
+      split_line = lines.last() != Some(&b'\n');
+      // Only the lines at hand are left plain; later ones may be fine.
+      let skip = ctx
+        .use_color
+        .then(|| ctx.max_highlight_size.check(lines))
+        .flatten();
+      state.skipped = state.skipped.or(skip);
+      render_line_chunk(
+        stdout,
+        lines,
+        file_path,
+        *line_number_start,
+        |text| {
+          if skip.is_some() {
+            return None;
+          }
+          language
+            .get_or_insert_with(|| {
+              language_override
+                .take()
+                .or_else(|| ctx.detect_input_language(path, text))
+            })
+            .as_ref()
+            .map(clone_either_lang)
+        },
+        ctx,
+        state,
+      )?;
+    }
+  }
+  state.finish_input(ctx.file_name.or(path));
+  Ok(())
+}
+
+/// Render complete lines that are part of a longer input and flush them.
+fn render_line_chunk(
+  stdout: &mut impl Write,
+  lines: &[u8],
+  path: Option<&Path>,
+  line_number_start: usize,
+  resolve_language: impl FnOnce(&str) -> Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<()> {
+  // The highlighter treats a trailing newline as the start of another (empty)
+  // line, which would show up as a stray gutter between chunks.
+  let strip_newline = ctx.use_color && lines.last() == Some(&b'\n');
+  let body = if strip_newline {
+    &lines[..lines.len() - 1]
+  } else {
+    lines
+  };
+  render_bytes(
+    stdout,
+    Cow::Borrowed(body),
+    path,
+    line_number_start,
+    resolve_language,
+    ctx,
+    state,
+  )?;
+  if strip_newline {
+    stdout.write_all(b"\n")?;
+  }
+  stdout.flush()?;
+  Ok(())
+}
+
+/// Render multiplexed log lines as they arrive: the tool prefix dimmed and the
+/// message highlighted when it is JSON or a `--language` was given.
+fn emit_log_lines(
+  stdout: &mut impl Write,
+  mut reader: io::BufReader<impl Read>,
+  language_override: Option<EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<()> {
+  let json = resolve_language_union("json", ctx.language_set);
+  let mut line = Vec::new();
+  let mut rendered = String::new();
+  loop {
+    line.clear();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+      break;
+    }
+    if ctx.use_color {
+      let text = String::from_utf8_lossy(&line);
+      let body = text.trim_end_matches(['\n', '\r']);
+      let (prefix, message) = logs::split_prefix(body);
+      rendered.clear();
+      if !prefix.is_empty() {
+        rendered.push_str(&decorations::render_dimmed(
+          prefix,
+          &mut state.renderer,
+          ctx.theme,
+        ));
+      }
+      let language = match &language_override {
+        Some(language) => Some(clone_either_lang(language)),
+        None if logs::is_json(message) => json.as_ref().map(clone_either_lang),
+        None => None,
+      };
+      let ctx = &ctx.for_language(language.as_ref());
+      let highlighted =
+        language.and_then(|language| highlight_lines(message, language, ctx, state));
+      match highlighted {
+        Some(lines) => {
+          for (piece, style_key) in lines.iter().flatten() {
+            decorations::render_content_piece(
+              &mut rendered,
+              piece,
+              *style_key,
+              None,
+              &mut state.renderer,
+              ctx.theme,
+            );
+          }
+        }
+        None => decorations::render_content_piece(
+          &mut rendered,
+          message,
+          None,
+          None,
+          &mut state.renderer,
+          ctx.theme,
+        ),
+      }
+      rendered.push_str(&text[body.len()..]);
+      stdout.write_all(rendered.as_bytes())?;
+    } else {
+      stdout.write_all(&line)?;
+    }
+    // Flush once everything available has been written, not after every line.
+    if reader.buffer().is_empty() {
+      stdout.flush()?;
+    }
+  }
+  stdout.flush()?;
+  Ok(())
+}
+
+/// A file watched by `--follow`, with the language detected from its initial content.
+struct FollowTarget {
+  /// Name shown in the header
+  name: String,
+  file: follow::FollowedFile,
+  language: Option<EitherLang<CustomLang, Lang>>,
+}
+
+/// Re-evaluates what was decided about the terminal at startup while
+/// `--follow` runs, so a session left open for hours picks up a switch
+/// between dark and light mode, a new terminal background and a resized
+/// window.
+struct TerminalWatch<'a> {
+  theme_name: &'a str,
+  per_language: &'a HashMap<String, String>,
+  auto_themes: AutoThemes<'a>,
+  adjustments: ThemeAdjustments,
+  terminal: TerminalQueries,
+  /// Whether a theme is `auto` or adjusted for the terminal background
+  depends_on_terminal: bool,
+  mode: DarkLightMode,
+  background: Option<Color>,
+  checked: Instant,
+  /// Columns taken by `--padding`, left out of the wrap width
+  padding: usize,
+  width: usize,
+}
+
+impl<'a> TerminalWatch<'a> {
+  fn new(
+    theme_name: &'a str,
+    per_language: &'a HashMap<String, String>,
+    auto_themes: AutoThemes<'a>,
+    adjustments: ThemeAdjustments,
+    terminal: TerminalQueries,
+    padding: usize,
+  ) -> Self {
+    let depends_on_terminal = follows_desktop_mode(theme_name)
+      || per_language.values().any(|name| follows_desktop_mode(name))
+      || adjustments.contrast != Contrast::Normal;
+    Self {
+      theme_name,
+      per_language,
+      auto_themes,
+      adjustments,
+      terminal,
+      depends_on_terminal,
+      mode: detect_mode(&terminal),
+      background: depends_on_terminal
+        .then(|| terminal.background_color())
+        .flatten(),
+      checked: Instant::now(),
+      padding,
+      width: terminal.width().saturating_sub(padding),
+    }
+  }
+
+  /// New themes when the desktop mode or the terminal background changed
+  /// since the last check. Checks at most every `follow::RECHECK_INTERVAL`,
+  /// asking the terminal for its colors again, and never when the themes do
+  /// not depend on either.
+  fn poll_themes(&mut self) -> Option<(ResolvedTheme, HashMap<String, ResolvedTheme>)> {
+    if !self.terminal.allows_mode_detection()
+      || self.checked.elapsed() < follow::RECHECK_INTERVAL
+      || !self.depends_on_terminal
+    {
+      return None;
+    }
+    self.checked = Instant::now();
+    self.terminal.refresh_colors();
+    let mode = detect_mode(&self.terminal);
+    let background = self.terminal.background_color();
+    if mode == self.mode && background == self.background {
+      return None;
+    }
+    self.mode = mode;
+    self.background = background;
+    // Errors were reported at startup; a theme file broken since then keeps
+    // the themes in use.
+    let language_themes = self
+      .per_language
+      .iter()
+      .map(|(language, name)| Ok((language.to_ascii_lowercase(), self.resolve(name)?)))
+      .collect::<Result<_>>()
+      .ok()?;
+    Some((self.resolve(self.theme_name).ok()?, language_themes))
+  }
+
+  /// The wrap width when the terminal was resized since the last call. The
+  /// size is cheap to read, so every round of `--follow` looks at it rather
+  /// than waiting for SIGWINCH.
+  fn poll_width(&mut self) -> Option<usize> {
+    let width = self.terminal.width().saturating_sub(self.padding);
+    (width != self.width).then(|| {
+      self.width = width;
+      width
+    })
+  }
+
+  fn resolve(&self, name: &str) -> Result<ResolvedTheme> {
+    let theme = resolve_theme(name, self.auto_themes, &self.terminal)?;
+    Ok(adjust_theme(theme, self.adjustments, &self.terminal))
+  }
+}
+
+/// Print lines appended to `targets` until interrupted. With several files, a
+/// `==> name <==` header in a per-file color marks each switch between files.
+fn follow_files(
+  stdout: &mut impl Write,
+  mut targets: Vec<FollowTarget>,
+  ctx: &RenderContext<'_>,
+  mut watch: TerminalWatch<'_>,
+  state: &mut RenderState,
+  reporter: &Reporter,
+) -> Result<bool> {
+  let show_headers = targets.len() > 1;
+  let changes = follow::ChangeEvents::new(targets.iter().map(|target| target.file.path.as_path()));
+  let mut last_shown = None;
+  let mut had_error = false;
+  // Themes re-resolved after a dark/light switch and the width of a resized
+  // terminal replace the ones in `ctx`.
+  let mut refreshed: Option<(ResolvedTheme, HashMap<String, ResolvedTheme>)> = None;
+  let mut wrap = ctx.wrap;
+
+  while !targets.is_empty() {
+    if let Some(update) = watch.poll_themes() {
+      refreshed = Some(update);
+    }
+    if let Some(width) = watch.poll_width() {
+      wrap.width = width;
+    }
+    let (theme, language_themes) = match &refreshed {
+      Some((theme, language_themes)) => (theme, language_themes),
+      None => (ctx.theme, ctx.language_themes),
+    };
+    let ctx = &RenderContext {
+      theme,
+      language_themes,
+      wrap,
+      ..*ctx
+    };
+    let mut any_output = false;
+    let mut index = 0;
+    while index < targets.len() {
+      let target = &mut targets[index];
+      let (line_number_start, lines) = match target.file.poll() {
+        Ok(Some(appended)) => appended,
+        Ok(None) => {
+          index += 1;
+          continue;
+        }
+        Err(err) => {
+          reporter.file_error(&target.file.path, &err);
+          had_error = true;
+          targets.remove(index);
+          continue;
+        }
+      };
+      if let Some(restart) = target.file.take_restart() {
+        let key = match restart {
+          follow::Restart::Truncated => "note-follow-truncated",
+          follow::Restart::Replaced => "note-follow-replaced",
+        };
+        reporter.note(&tr_args(key, &[("path", Arg::Str(&target.name))]));
+      }
+      if show_headers && last_shown != Some(index) {
+        let name = &target.name;
+        let header = if ctx.use_color {
+          decorations::follow_header(name, index, &mut state.renderer, ctx.theme)
+        } else {
+          format!("==> {name} <==")
+        };
+        writeln!(stdout, "\n{header}")?;
+        last_shown = Some(index);
+      }
+      let language = target.language.as_ref().map(clone_either_lang);
+      render_line_chunk(
+        stdout,
+        &lines,
+        None,
+        line_number_start,
+        |_| language,
+        ctx,
+        state,
+      )?;
+      any_output = true;
+      index += 1;
+    }
+    if !any_output {
+      changes.wait(follow::RECHECK_INTERVAL);
+    }
   }
+  Ok(had_error)
+}
 
-  // Fetch git changes if needed (only for actual file paths, not stdin)
-  let git_changes = if decoration_config.show_changes {
-    // Only check git for real file paths (not stdin "-")
-    if let Some(p) = path {
-      if p != Path::new("-") {
-        // Convert to absolute path for git detection
-        let abs_path = std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
-        git::get_git_line_changes(&abs_path).unwrap_or_default()
-      } else {
-        Vec::new()
-      }
-    } else {
-      Vec::new()
-    }
-  } else {
-    Vec::new()
+/// The ranges of `path` changed in the git working tree, or since `base`,
+/// each widened by `context` lines. `None` when `path` is not in a
+/// repository, so it is shown whole; empty when it has no changes.
+fn changed_lines(path: &Path, context: usize, base: Option<&str>) -> Result<Option<LineSelection>> {
+  if path == Path::new("-") {
+    return Ok(None);
+  }
+  let abs_path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+  let Some(hunks) = git::changed_hunks(&abs_path, base)? else {
+    return Ok(None);
   };
+  // Widened by hand rather than with `--context`, whose shading would only
+  // repeat what the change markers already show.
+  let ranges = hunks.into_iter().map(|(first, last)| RangeSpec {
+    start: ranges::LineIndex::FromStart(first.saturating_sub(context).max(1)),
+    end: ranges::LineIndex::FromStart(last.saturating_add(context)),
+  });
+  Ok(Some(LineSelection::new(ranges)))
+}
 
-  if use_color {
-    match String::from_utf8(bytes) {
-      Ok(text) => {
-        let language = language_override.or_else(|| detect_language(path, &text, ctx.language_set));
-        write_rendered_text(
-          stdout,
-          &text,
-          language,
-          line_number_start,
-          &git_changes,
-          ctx,
-          state,
-        )?;
-        return Ok(ended_with_newline);
-      }
-      Err(err) => {
-        let bytes = err.into_bytes();
-        if decoration_config.show_numbers {
-          write_numbered_bytes(stdout, &bytes, line_number_start)?;
-        } else if show_all {
-          // Try to convert what we can, handling invalid UTF-8
-          let text = String::from_utf8_lossy(&bytes);
-          let transformed = unprintable::show_unprintable(&text, unprintable::get_char_style());
-          stdout.write_all(transformed.as_bytes())?;
-        } else {
-          stdout.write_all(&bytes)?;
-        }
-        return Ok(ended_with_newline);
-      }
-    }
+/// Write the `--diff` separator between two ranges that are not adjacent.
+fn write_snip(
+  stdout: &mut impl Write,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Result<()> {
+  let snip = format!("{:─^width$}", " 8< ", width = ctx.wrap.width.max(8));
+  if ctx.use_color {
+    let snip = decorations::render_dimmed(&snip, &mut state.renderer, ctx.theme);
+    writeln!(stdout, "{snip}")?;
+  } else {
+    writeln!(stdout, "{snip}")?;
   }
+  Ok(())
+}
 
-  if decoration_config.show_numbers {
-    if show_all {
-      // Use number_plain_text when show_all is enabled
-      if let Ok(text) = String::from_utf8(bytes.clone()) {
-        let numbered = number_plain_text(&text, line_number_start, show_all);
-        stdout.write_all(numbered.as_bytes())?;
-      } else {
-        write_numbered_bytes(stdout, &bytes, line_number_start)?;
-      }
-    } else {
-      write_numbered_bytes(stdout, &bytes, line_number_start)?;
-    }
-  } else if show_all {
-    // Handle show_all for non-color case with decorations
-    if let Ok(text) = String::from_utf8(bytes.clone()) {
-      let transformed = unprintable::show_unprintable(&text, unprintable::get_char_style());
-      stdout.write_all(transformed.as_bytes())?;
-    } else {
-      stdout.write_all(&bytes)?;
+/// Fetch git changes for a real file path (not stdin "-"), against the index
+/// or the `--diff-base` revision.
+fn fetch_git_changes(path: Option<&Path>, base: Option<&str>) -> Vec<Option<git::LineStatus>> {
+  match path {
+    Some(p) if p != Path::new("-") => {
+      // Convert to absolute path for git detection
+      let abs_path = std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+      git::get_git_line_changes(&abs_path, base).unwrap_or_default()
     }
-  } else {
-    stdout.write_all(&bytes)?;
+    _ => Vec::new(),
   }
-  Ok(ended_with_newline)
 }
 
 fn detect_language(
   path: Option<&Path>,
   content: &str,
   language_set: &Union<CustomLanguageSet, LanguageSetImpl>,
+  ignored_suffixes: &[String],
 ) -> Option<EitherLang<CustomLang, Lang>> {
-  let name = detect_language_name(path, content)?;
-  resolve_language_union(name.to_ascii_lowercase(), language_set)
+  let name = detect_language_name(path, content, ignored_suffixes)?;
+  resolve_language_union(name, language_set)
 }
 
-fn resolve_language_union(
-  name: impl AsRef<str>,
+/// Language from a matching `[syntax-map]` pattern, or else the detected one.
+/// A pattern naming an unknown language is ignored.
+fn detect_mapped_language(
+  path: Option<&Path>,
+  content: &str,
   language_set: &Union<CustomLanguageSet, LanguageSetImpl>,
+  syntax_map: &SyntaxMap,
+  ignored_suffixes: &[String],
 ) -> Option<EitherLang<CustomLang, Lang>> {
-  let name = name.as_ref().trim();
-  let normalized = name.to_ascii_lowercase();
-
-  // First check if it's a custom language (HCL or Terraform)
-  if let Ok(custom_lang) =
-    <CustomLang as SupportedLanguage<'_, _>>::for_name(&normalized, language_set)
-  {
-    return Some(EitherLang::Left(custom_lang));
-  }
-
-  // Then try the syntastica parsers with aliases
-  let name = match normalized.as_str() {
-    "xml" | "xhtml" | "svg" | "plist" => "html",
-    _ => normalized.as_str(),
-  };
-
-  // Try as a normal language
-  if let Ok(lang) = <Lang as SupportedLanguage<'_, _>>::for_name(name, language_set) {
-    return Some(EitherLang::Right(lang));
-  }
-
-  // Try as an injection language
-  if let Some(lang) = <Lang as SupportedLanguage<'_, _>>::for_injection(name, language_set) {
-    return Some(EitherLang::Right(lang));
-  }
+  path
+    .and_then(|path| syntax_map.lookup(path))
+    .and_then(|name| resolve_language_union(name, language_set))
+    .or_else(|| detect_language(path, content, language_set, ignored_suffixes))
+}
 
-  // Try with canonical names
-  if let Some(canonical) = LANGUAGE_NAMES
-    .iter()
-    .copied()
-    .find(|candidate| candidate.eq_ignore_ascii_case(name))
-    && let Ok(lang) = <Lang as SupportedLanguage<'_, _>>::for_name(canonical, language_set)
-  {
-    return Some(EitherLang::Right(lang));
+/// The values for the placeholders of the header above `spec`. The language
+/// is detected from the name alone, since the file has not been read yet.
+fn header_fields(
+  spec: &FileSpec,
+  format: &header::HeaderFormat,
+  mode: paths::HeaderPath,
+  language_override: Option<&EitherLang<CustomLang, Lang>>,
+  ctx: &RenderContext<'_>,
+) -> header::Fields {
+  let path = spec.display_name(mode);
+  let basename = Path::new(&path)
+    .file_name()
+    .map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned());
+  let metadata = (spec.path != Path::new("-"))
+    .then(|| fs::metadata(&spec.path).ok())
+    .flatten();
+  let lang = format.uses("lang").then(|| {
+    match language_override {
+      Some(language) => Some(language_name(language).to_string()),
+      None => ctx
+        .detect_input_language(Some(&spec.path), "")
+        .map(|language| language_name(&language).to_string()),
+    }
+    .unwrap_or_else(|| "text".to_string())
+  });
+  let branch = format
+    .uses("branch")
+    .then(|| {
+      let path = std::fs::canonicalize(&spec.path).ok()?;
+      git::branch(&path)
+    })
+    .flatten();
+  // Only regular files are read ahead; stdin can be read only once.
+  let eol = (format.uses("eol") || ctx.show_line_endings)
+    .then(|| {
+      metadata.as_ref().filter(|metadata| metadata.is_file())?;
+      contents::read(&spec.path)
+        .ok()
+        .map(|bytes| eol::Counts::of(&bytes))
+    })
+    .flatten();
+  header::Fields {
+    path,
+    basename,
+    lang,
+    size: metadata.as_ref().map(|metadata| metadata.len()),
+    // The file time would make --deterministic output change on every touch.
+    mtime: metadata
+      .filter(|_| !ctx.deterministic)
+      .and_then(|metadata| metadata.modified().ok()),
+    branch,
+    eol,
   }
-
-  None
 }
 
-fn detect_language_name(path: Option<&Path>, content: &str) -> Option<&'static str> {
-  // Use the new palate API which handles all detection internally
-  let file_type = if let Some(path) = path {
-    palate::try_detect(path, content)?
-  } else {
-    // No path, try to detect from content only
-    // palate requires a path, so use a dummy path
-    palate::try_detect("", content)?
-  };
-
-  // Convert FileType to language name string
-  // FileType::Text means no specific language detected
-  match file_type {
-    palate::FileType::Text => None,
-    other => Some(Box::leak(other.to_string().into_boxed_str())),
-  }
+/// Patches and unified diffs get the dedicated diff renderer instead of plain highlighting.
+fn is_diff_language(language: &EitherLang<CustomLang, Lang>) -> bool {
+  language_name(language) == "diff"
 }
 
 #[derive(Debug)]
@@ -677,22 +4023,12 @@ fn write_rendered_text(
   text: &str,
   language: Option<EitherLang<CustomLang, Lang>>,
   line_number_start: usize,
-  git_changes: &[Option<git::LineChange>],
+  git_changes: &[Option<git::LineStatus>],
   ctx: &RenderContext<'_>,
   state: &mut RenderState,
 ) -> Result<()> {
-  let decoration_config = ctx.decoration_config;
-  let show_all = ctx.show_all;
-
   let Some(language) = language else {
-    let out = if decoration_config.show_numbers {
-      number_plain_text(text, line_number_start, show_all)
-    } else if show_all {
-      unprintable::show_unprintable(text, unprintable::get_char_style())
-    } else {
-      text.to_string()
-    };
-    stdout.write_all(out.as_bytes())?;
+    stdout.write_all(plain_text_output(text, line_number_start, ctx).as_bytes())?;
     return Ok(());
   };
 
@@ -707,144 +4043,202 @@ fn write_rendered_text(
   ) {
     Ok(()) => Ok(()),
     Err(StreamHighlightError::Highlight) => {
-      let out = if decoration_config.show_numbers {
-        number_plain_text(text, line_number_start, show_all)
-      } else if show_all {
-        unprintable::show_unprintable(text, unprintable::get_char_style())
-      } else {
-        text.to_string()
-      };
-      stdout.write_all(out.as_bytes())?;
+      stdout.write_all(plain_text_output(text, line_number_start, ctx).as_bytes())?;
       Ok(())
     }
     Err(StreamHighlightError::Io(err)) => Err(err.into()),
   }
 }
 
+/// `text` as shown without highlighting: numbered, with unprintable characters
+/// or line endings marked, as the context asks.
+fn plain_text_output(text: &str, line_number_start: usize, ctx: &RenderContext<'_>) -> String {
+  let marked;
+  let text = if ctx.show_line_endings && !ctx.show_all {
+    marked = String::from_utf8_lossy(&eol::mark(text.as_bytes(), ctx.char_style)).into_owned();
+    &marked
+  } else {
+    text
+  };
+  if ctx.decoration_config.show_numbers {
+    number_plain_text(text, line_number_start, ctx)
+  } else if ctx.show_all {
+    unprintable::show_unprintable(text, ctx.char_style)
+  } else {
+    text.to_string()
+  }
+}
+
 fn write_highlighted_text_stream(
   stdout: &mut impl Write,
   text: &str,
   language: EitherLang<CustomLang, Lang>,
   line_number_start: usize,
-  git_changes: &[Option<git::LineChange>],
+  git_changes: &[Option<git::LineStatus>],
   ctx: &RenderContext<'_>,
   state: &mut RenderState,
 ) -> std::result::Result<(), StreamHighlightError> {
   let language_set = ctx.language_set;
-  let decoration_config = ctx.decoration_config;
-  let theme = ctx.theme;
   let show_all = ctx.show_all;
-  let highlight_locals = ctx.highlight_locals;
   let highlight_injections = ctx.highlight_injections;
-
-  let highlight_config = if highlight_injections {
-    language_set
-      .get_language(language)
-      .map_err(|_| StreamHighlightError::Highlight)?
-  } else if highlight_locals {
-    match language {
-      EitherLang::Right(lang) => get_locals_config(&mut state.locals_configs, lang)?,
-      EitherLang::Left(custom) => language_set
-        .get_language(EitherLang::Left(custom))
-        .map_err(|_| StreamHighlightError::Highlight)?,
-    }
+  // Markers need to know which lines ended in CRLF; the CRs themselves are
+  // dropped so that none reaches the terminal in the middle of a line.
+  let (text, crlf_lines) = if show_all || ctx.show_line_endings {
+    let (text, crlf_lines) = eol::split_crlf(text);
+    (text, Some(crlf_lines))
   } else {
-    match language {
-      EitherLang::Right(lang) => {
-        get_highlights_only_config(&mut state.highlights_only_configs, lang)?
-      }
-      EitherLang::Left(custom) => language_set
-        .get_language(EitherLang::Left(custom))
-        .map_err(|_| StreamHighlightError::Highlight)?,
-    }
+    (Cow::Borrowed(text), None)
   };
+  let text = text.as_ref();
+  let crlf_lines = crlf_lines.as_deref();
 
-  let iter = state
-    .highlighter
-    .highlight(
-      highlight_config,
-      text.as_bytes(),
-      None,
-      |lang_name: &str| {
-        if !highlight_injections {
-          return None;
-        }
-
-        let lang_name = lang_name.to_ascii_lowercase();
-        EitherLang::<CustomLang, Lang>::for_name(&lang_name, language_set)
-          .ok()
-          .or_else(|| EitherLang::<CustomLang, Lang>::for_injection(&lang_name, language_set))
-          .or_else(|| {
-            lang_name.rsplit_once('/').and_then(|(_, name)| {
-              EitherLang::<CustomLang, Lang>::for_name(name, language_set)
-                .ok()
-                .or_else(|| EitherLang::<CustomLang, Lang>::for_injection(name, language_set))
-            })
-          })
-          .and_then(|lang| language_set.get_language(lang).ok())
-      },
-    )
-    .map_err(|_| StreamHighlightError::Highlight)?;
+  let highlight_config = highlight_configuration(language, ctx, &mut state.configs)?;
 
-  if decoration_config.has_decorations() {
-    write_highlight_iter_with_decorations(
+  let highlighter = &mut state.highlighter;
+  let renderer = &mut state.renderer;
+  let mut write = |events: &mut dyn Iterator<Item = HighlightItem>| {
+    write_highlight_events(
       stdout,
       text,
-      iter,
-      &mut state.renderer,
-      DecorationsStreamSettings {
-        decoration_config,
-        line_number_start,
-        git_changes,
-        theme,
-        show_all,
+      events,
+      renderer,
+      DecorationsStreamSettings::new(ctx, line_number_start, git_changes, crlf_lines),
+      ctx,
+    )
+  };
+
+  if text.len() >= pipeline::MIN_PIPELINE_BYTES {
+    // Parse on a worker thread while this one renders and writes.
+    pipeline::pipelined(
+      move || {
+        highlight_events(
+          highlighter,
+          highlight_config,
+          text,
+          language_set,
+          highlight_injections,
+        )
       },
+      write,
     )
   } else {
-    write_highlight_iter_plain(stdout, text, iter, &mut state.renderer, theme, show_all)
+    write(&mut highlight_events(
+      highlighter,
+      highlight_config,
+      text,
+      language_set,
+      highlight_injections,
+    ))
   }
 }
 
-fn current_style_key(style_stack: &[usize]) -> Option<&'static str> {
-  style_stack
-    .last()
-    .and_then(|idx| THEME_KEYS.get(*idx).copied())
-    .and_then(|key| (key != "none").then_some(key))
+/// Write `text` styled by its highlight `events`, with the decorations the
+/// context asks for.
+fn write_highlight_events(
+  stdout: &mut impl Write,
+  text: &str,
+  events: &mut dyn Iterator<Item = HighlightItem>,
+  renderer: &mut DepthRenderer,
+  settings: DecorationsStreamSettings<'_>,
+  ctx: &RenderContext<'_>,
+) -> std::result::Result<(), StreamHighlightError> {
+  if settings.decoration_config.has_decorations() {
+    write_highlight_iter_with_decorations(stdout, text, events, renderer, settings)
+  } else {
+    write_highlight_iter_plain(
+      stdout,
+      text,
+      events,
+      renderer,
+      settings.line_number_start,
+      settings.crlf_lines,
+      ctx,
+    )
+  }
 }
 
-fn get_highlights_only_config(
-  configs: &mut HashMap<Lang, HighlightConfiguration>,
-  lang: Lang,
-) -> std::result::Result<&HighlightConfiguration, StreamHighlightError> {
-  use std::collections::hash_map::Entry;
+/// Configurations umber builds itself for syntastica's languages, for the
+/// style components that leave out some queries, and for languages whose
+/// queries the user overrides.
+#[derive(Default)]
+struct LangConfigs {
+  highlights_only: HashMap<Lang, HighlightConfiguration>,
+  locals: HashMap<Lang, HighlightConfiguration>,
+  injections: HashMap<Lang, HighlightConfiguration>,
+}
 
-  match configs.entry(lang) {
-    Entry::Occupied(entry) => Ok(entry.into_mut()),
-    Entry::Vacant(entry) => {
-      let mut conf =
-        HighlightConfiguration::new(lang.get(), lang.as_ref(), lang.highlights_query(), "", "")
-          .map_err(|_| StreamHighlightError::Highlight)?;
-      conf.configure(THEME_KEYS);
-      Ok(entry.insert(conf))
+/// Pick the highlight configuration matching the enabled style components.
+fn highlight_configuration<'r>(
+  language: EitherLang<CustomLang, Lang>,
+  ctx: &RenderContext<'r>,
+  configs: &'r mut LangConfigs,
+) -> std::result::Result<&'r HighlightConfiguration, StreamHighlightError> {
+  let language_set = ctx.language_set;
+  match language {
+    // syntastica's own configuration has every query, unless overridden.
+    EitherLang::Right(lang)
+      if ctx.highlight_injections && queries::has_overrides(lang.as_ref()) =>
+    {
+      get_lang_config(&mut configs.injections, lang, true, true)
     }
+    EitherLang::Right(lang) if !ctx.highlight_injections && ctx.highlight_locals => {
+      get_lang_config(&mut configs.locals, lang, true, false)
+    }
+    EitherLang::Right(lang) if !ctx.highlight_injections => {
+      get_lang_config(&mut configs.highlights_only, lang, false, false)
+    }
+    language => language_set
+      .get_language(language)
+      .map_err(|_| StreamHighlightError::Highlight),
   }
 }
 
-fn get_locals_config(
+/// Highlight `text` without injections and split the result into lines of
+/// `(text, style key)` pieces. Returns `None` when highlighting fails.
+fn highlight_lines(
+  text: &str,
+  language: EitherLang<CustomLang, Lang>,
+  ctx: &RenderContext<'_>,
+  state: &mut RenderState,
+) -> Option<Vec<diff::HighlightedLine>> {
+  let highlight_config = highlight_configuration(language, ctx, &mut state.configs).ok()?;
+  let events = highlight_events(
+    &mut state.highlighter,
+    highlight_config,
+    text,
+    ctx.language_set,
+    false,
+  );
+  highlight::split_lines(text, events)
+}
+
+/// `lang` with its highlights query and, if asked for, its locals and
+/// injections queries, each as overridden in the user's queries directory.
+fn get_lang_config(
   configs: &mut HashMap<Lang, HighlightConfiguration>,
   lang: Lang,
+  locals: bool,
+  injections: bool,
 ) -> std::result::Result<&HighlightConfiguration, StreamHighlightError> {
   use std::collections::hash_map::Entry;
 
   match configs.entry(lang) {
     Entry::Occupied(entry) => Ok(entry.into_mut()),
     Entry::Vacant(entry) => {
+      let name = lang.as_ref();
+      let query = |kind, wanted: bool, builtin: &'static str| {
+        if wanted {
+          queries::apply(name, kind, builtin)
+        } else {
+          Cow::Borrowed("")
+        }
+      };
       let mut conf = HighlightConfiguration::new(
         lang.get(),
-        lang.as_ref(),
-        lang.highlights_query(),
-        "",
-        lang.locals_query(),
+        name,
+        &query(QueryKind::Highlights, true, lang.highlights_query()),
+        &query(QueryKind::Injections, injections, lang.injections_query()),
+        &query(QueryKind::Locals, locals, lang.locals_query()),
       )
       .map_err(|_| StreamHighlightError::Highlight)?;
       conf.configure(THEME_KEYS);
@@ -866,15 +4260,19 @@ fn write_highlight_iter_plain(
   stdout: &mut impl Write,
   text: &str,
   iter: impl Iterator<Item = std::result::Result<HighlightEvent, syntastica_highlight::Error>>,
-  renderer: &mut TerminalRenderer,
-  theme: &ResolvedTheme,
-  show_all: bool,
+  renderer: &mut DepthRenderer,
+  line_number_start: usize,
+  crlf_lines: Option<&[bool]>,
+  ctx: &RenderContext<'_>,
 ) -> std::result::Result<(), StreamHighlightError> {
+  let theme = ctx.theme;
+  let show_all = ctx.show_all;
+  let backgrounds = LineBackgrounds::new(ctx.marks, ctx.highlight_color, ctx.zebra, theme);
   let mut out = StreamBuffer::new(stdout);
   out.push(renderer.head().as_ref())?;
   out.flush()?;
 
-  let char_style = unprintable::get_char_style();
+  let char_style = ctx.char_style;
   let lf_marker = if matches!(char_style, unprintable::CharStyle::Unicode) {
     "␊"
   } else {
@@ -884,6 +4282,12 @@ fn write_highlight_iter_plain(
   let mut style_stack = Vec::new();
   let mut line_has_content = false;
   let mut flushed_visible_output = false;
+  let mut line_index = 0usize;
+  // Columns written on a line with a background, to fill the rest of the row.
+  let mut line_width = 0usize;
+  // Reused across pieces to avoid per-span allocations on large files.
+  let mut piece = String::new();
+  let mut transformed = String::new();
 
   for event in iter {
     let event = event.map_err(|_| StreamHighlightError::Highlight)?;
@@ -903,16 +4307,35 @@ fn write_highlight_iter_plain(
           }
 
           let style_key = current_style_key(&style_stack);
+          let background = backgrounds.get(line_number_start + line_index, line_index);
 
-          if show_all {
-            let transformed = unprintable::show_unprintable(line, char_style);
+          let line = if show_all {
+            transformed.clear();
+            unprintable::push_unprintable(&mut transformed, line, char_style);
+            transformed.as_str()
+          } else {
+            line
+          };
+          if let Some(bg) = background {
+            line_width += decorations::display_width(line, line_width);
+            piece.clear();
+            decorations::render_content_piece(
+              &mut piece,
+              line,
+              style_key,
+              Some(bg),
+              renderer,
+              theme,
+            );
+            out.push(&piece)?;
+          } else if show_all {
             if let Some(key) = style_key
               && let Some(style_obj) = theme.get(key)
             {
-              let rendered = renderer.styled(transformed.as_str(), *style_obj);
+              let rendered = renderer.styled(line, *style_obj);
               out.push(rendered.as_ref())?;
             } else {
-              out.push(&transformed)?;
+              out.push(line)?;
             }
           } else {
             let escaped = renderer.escape(line);
@@ -930,8 +4353,22 @@ fn write_highlight_iter_plain(
 
           let newline_after = lines.peek().is_some() || ends_with_newline;
           if newline_after {
-            if show_all && line_has_content {
-              out.push(lf_marker)?;
+            if (show_all && line_has_content) || ctx.show_line_endings {
+              let crlf = crlf_lines.is_some_and(|lines| lines.get(line_index) == Some(&true));
+              let marker = eol::marker(crlf, char_style);
+              out.push(marker)?;
+              line_width += decorations::display_width(marker, line_width);
+            }
+            if let Some(bg) = background {
+              piece.clear();
+              decorations::push_fill(
+                &mut piece,
+                ctx.wrap.width.saturating_sub(line_width),
+                bg,
+                renderer,
+                theme,
+              );
+              out.push(&piece)?;
             }
             out.push(renderer.newline().as_ref())?;
             if !flushed_visible_output {
@@ -941,6 +4378,8 @@ fn write_highlight_iter_plain(
               out.flush_if_at_least(STREAM_OUTPUT_FLUSH_BYTES)?;
             }
             line_has_content = false;
+            line_width = 0;
+            line_index += 1;
           }
         }
       }
@@ -960,7 +4399,7 @@ fn write_highlight_iter_with_decorations(
   stdout: &mut impl Write,
   text: &str,
   iter: impl Iterator<Item = std::result::Result<HighlightEvent, syntastica_highlight::Error>>,
-  renderer: &mut TerminalRenderer,
+  renderer: &mut DepthRenderer,
   settings: DecorationsStreamSettings<'_>,
 ) -> std::result::Result<(), StreamHighlightError> {
   let decoration_config = settings.decoration_config;
@@ -968,28 +4407,33 @@ fn write_highlight_iter_with_decorations(
   let git_changes = settings.git_changes;
   let theme = settings.theme;
   let show_all = settings.show_all;
+  let backgrounds = LineBackgrounds::new(
+    settings.marks,
+    settings.highlight_color,
+    settings.zebra,
+    theme,
+  );
 
   // Only show git margin if there are actual changes
-  let has_git_changes = git_changes.iter().any(|c| c.is_some());
-  let effective_config = if has_git_changes {
-    decoration_config
-  } else {
+  let effective_config = if git_changes.is_empty() {
     DecorationConfig {
       show_changes: false,
       ..decoration_config
     }
+  } else {
+    decoration_config
   };
 
   // Match Processor output: number of highlight lines is newlines + 1.
   let line_count = highlight_line_count(text);
   let last_line_no = line_number_start.saturating_add(line_count.saturating_sub(1));
-  let width = line_number_width(last_line_no);
+  let width = line_number_width(last_line_no).max(settings.min_line_number_width);
 
   let mut out = StreamBuffer::new(stdout);
   out.push(renderer.head().as_ref())?;
   out.flush()?;
 
-  let char_style = unprintable::get_char_style();
+  let char_style = settings.char_style;
   let lf_marker = if matches!(char_style, unprintable::CharStyle::Unicode) {
     "␊"
   } else {
@@ -1000,7 +4444,9 @@ fn write_highlight_iter_with_decorations(
   let mut line_no = line_number_start;
   let mut line_index = 0usize;
   let mut line_has_content = false;
-  let mut line_content: Vec<(Cow<'_, str>, Option<&'static str>)> = Vec::new();
+  // Reused for every line to avoid per-line allocations on large files.
+  let mut line_content = decorations::LineContent::default();
+  let mut rendered = String::new();
   let mut flushed_visible_output = false;
 
   for event in iter {
@@ -1021,29 +4467,39 @@ fn write_highlight_iter_with_decorations(
           }
 
           let style_key = current_style_key(&style_stack);
-          let piece = if show_all {
-            Cow::Owned(unprintable::show_unprintable(line, char_style))
+          if show_all {
+            line_content.push_with(style_key, |buf| {
+              unprintable::push_unprintable(buf, line, char_style)
+            });
           } else {
-            Cow::Borrowed(line)
-          };
-          line_content.push((piece, style_key));
+            line_content.push(line, style_key);
+          }
 
           let newline_after = lines.peek().is_some() || ends_with_newline;
           if newline_after {
-            let line_change = git_changes.get(line_index).copied().flatten();
-            let rendered = decorations::render_decorated_line(
-              &line_content,
+            let line = decorations::LineDecoration {
               line_no,
+              line_change: git_changes.get(line_index).copied().flatten(),
+              background: backgrounds.get(line_no, line_index),
+            };
+            rendered.clear();
+            decorations::render_decorated_line(
+              &mut rendered,
+              &line_content,
+              &line,
               &effective_config,
-              line_change,
               renderer,
               theme,
               width,
+              settings.wrap,
             );
             out.push(&rendered)?;
 
-            if show_all && line_has_content {
-              out.push(lf_marker)?;
+            if (show_all && line_has_content) || settings.show_line_endings {
+              let crlf = settings
+                .crlf_lines
+                .is_some_and(|lines| lines.get(line_index) == Some(&true));
+              out.push(eol::marker(crlf, char_style))?;
             }
 
             out.push(renderer.newline().as_ref())?;
@@ -1065,15 +4521,21 @@ fn write_highlight_iter_with_decorations(
   }
 
   // Flush final line (even if empty) to match existing decoration behavior.
-  let line_change = git_changes.get(line_index).copied().flatten();
-  let rendered = decorations::render_decorated_line(
-    &line_content,
+  let line = decorations::LineDecoration {
     line_no,
+    line_change: git_changes.get(line_index).copied().flatten(),
+    background: backgrounds.get(line_no, line_index),
+  };
+  rendered.clear();
+  decorations::render_decorated_line(
+    &mut rendered,
+    &line_content,
+    &line,
     &effective_config,
-    line_change,
     renderer,
     theme,
     width,
+    settings.wrap,
   );
   out.push(&rendered)?;
   if show_all && line_has_content {
@@ -1085,51 +4547,203 @@ fn write_highlight_iter_with_decorations(
   Ok(())
 }
 
-fn resolve_theme(theme: &str) -> ResolvedTheme {
+/// Backgrounds of content lines: marked lines stand out, otherwise odd rows
+/// get the zebra stripe.
+struct LineBackgrounds<'a> {
+  marks: ranges::Marks<'a>,
+  highlighted: Color,
+  selected: Color,
+  zebra: Option<Color>,
+}
+
+impl<'a> LineBackgrounds<'a> {
+  fn new(
+    marks: ranges::Marks<'a>,
+    highlight_color: Option<Color>,
+    zebra: bool,
+    theme: &ResolvedTheme,
+  ) -> Self {
+    let selected = decorations::emphasis_background(theme);
+    Self {
+      marks,
+      highlighted: highlight_color.unwrap_or(selected),
+      selected,
+      zebra: zebra.then(|| decorations::zebra_background(theme)),
+    }
+  }
+
+  fn get(&self, line_no: usize, line_index: usize) -> Option<Color> {
+    match self.marks.get(line_no) {
+      Some(ranges::Mark::Highlighted) => Some(self.highlighted),
+      Some(ranges::Mark::Selected) => Some(self.selected),
+      None => self.zebra.filter(|_| line_index % 2 == 1),
+    }
+  }
+}
+
+/// The themes `auto` chooses between, from `--theme-light`/`--theme-dark` or
+/// the config file; `None` means the built-in default.
+#[derive(Debug, Clone, Copy, Default)]
+struct AutoThemes<'a> {
+  light: Option<&'a str>,
+  dark: Option<&'a str>,
+}
+
+impl AutoThemes<'_> {
+  fn light(&self) -> Result<ResolvedTheme> {
+    Ok(
+      self
+        .light
+        .map(named_theme)
+        .transpose()?
+        .flatten()
+        .unwrap_or_else(syntastica_themes::catppuccin::latte),
+    )
+  }
+
+  fn dark(&self) -> Result<ResolvedTheme> {
+    Ok(
+      self
+        .dark
+        .map(named_theme)
+        .transpose()?
+        .flatten()
+        .unwrap_or_else(syntastica_themes::catppuccin::mocha),
+    )
+  }
+}
+
+/// A built-in theme, or one from a theme file; `None` for an unknown name.
+fn named_theme(name: &str) -> Result<Option<ResolvedTheme>> {
+  if let Some(theme) = highlight::builtin_theme(name) {
+    return Ok(Some(theme));
+  }
+  custom_themes::find(name)
+    .map(|path| custom_themes::load(&path))
+    .transpose()
+}
+
+fn resolve_theme(
+  theme: &str,
+  auto: AutoThemes,
+  terminal: &TerminalQueries,
+) -> Result<ResolvedTheme> {
   let theme_name = theme.trim();
+  if let Some(path) = custom_themes::find(theme_name) {
+    return custom_themes::load(&path);
+  }
   let theme_key = theme_name.split(':').next().unwrap_or("auto");
 
   match theme_key {
-    "" | "auto" => resolve_auto_theme(),
-    "dark" => syntastica_themes::catppuccin::mocha(),
-    "light" => syntastica_themes::catppuccin::latte(),
+    "" | "auto" => resolve_auto_theme(auto, terminal),
+    "dark" => auto.dark(),
+    "light" => auto.light(),
+    "ansi" => Ok(color_depth::ansi_theme()),
     _ => {
       if let Some(theme) = syntastica_themes::from_str(theme_key) {
-        return theme;
+        return Ok(theme);
       }
-      resolve_auto_theme()
+      resolve_auto_theme(auto, terminal)
     }
   }
 }
 
-fn resolve_auto_theme() -> ResolvedTheme {
+fn resolve_auto_theme(auto: AutoThemes, terminal: &TerminalQueries) -> Result<ResolvedTheme> {
+  match detect_mode(terminal) {
+    DarkLightMode::Light => auto.light(),
+    DarkLightMode::Dark | DarkLightMode::Unspecified => auto.dark(),
+  }
+}
+
+/// Changes made to every resolved theme.
+#[derive(Debug, Clone, Copy)]
+struct ThemeAdjustments {
+  /// `--contrast`
+  contrast: Contrast,
+  /// `--italic-text`, `--no-bold` and `--no-underline`
+  styles: StyleFilter,
+}
+
+/// `theme` with its attributes filtered and its colors adjusted for
+/// `--contrast`, against its own background or else the terminal's.
+fn adjust_theme(
+  theme: ResolvedTheme,
+  adjustments: ThemeAdjustments,
+  terminal: &TerminalQueries,
+) -> ResolvedTheme {
+  let theme = adjustments.styles.apply_theme(theme);
+  if adjustments.contrast == Contrast::Normal {
+    return theme;
+  }
+  let background = decorations::theme_background(&theme)
+    .or_else(|| terminal.background_color())
+    .unwrap_or(Color::new(30, 30, 46));
+  adjustments.contrast.apply(theme, background)
+}
+
+/// The desktop's dark/light preference, or else the terminal background's;
+/// unspecified when neither can be asked.
+fn detect_mode(terminal: &TerminalQueries) -> DarkLightMode {
+  if !terminal.allows_mode_detection() {
+    return DarkLightMode::Unspecified;
+  }
   match dark_light::detect() {
-    Ok(DarkLightMode::Light) => syntastica_themes::catppuccin::latte(),
-    Ok(DarkLightMode::Dark) => syntastica_themes::catppuccin::mocha(),
-    Ok(DarkLightMode::Unspecified) => syntastica_themes::catppuccin::mocha(),
-    Err(_) => syntastica_themes::catppuccin::mocha(),
+    Ok(mode @ (DarkLightMode::Dark | DarkLightMode::Light)) => mode,
+    _ => match terminal.background_is_light() {
+      Some(true) => DarkLightMode::Light,
+      Some(false) => DarkLightMode::Dark,
+      None => DarkLightMode::Unspecified,
+    },
+  }
+}
+
+/// True if `theme` is resolved from the desktop mode: `auto`, or an unknown
+/// name that falls back to it.
+fn follows_desktop_mode(theme: &str) -> bool {
+  if custom_themes::find(theme.trim()).is_some() {
+    return false;
+  }
+  match theme.trim().split(':').next().unwrap_or("auto") {
+    "" | "auto" => true,
+    "dark" | "light" | "ansi" => false,
+    name => syntastica_themes::from_str(name).is_none(),
   }
 }
 
-fn number_plain_text(text: &str, line_number_start: usize, show_all: bool) -> String {
+fn number_plain_text(text: &str, line_number_start: usize, ctx: &RenderContext<'_>) -> String {
   let line_count = count_lines_bytes(text.as_bytes());
   if line_count == 0 {
     return String::new();
   }
 
+  let (show_all, char_style, wrap) = (ctx.show_all, ctx.char_style, ctx.wrap);
   let last_line_no = line_number_start.saturating_add(line_count.saturating_sub(1));
-  let width = line_number_width(last_line_no);
+  let width = line_number_width(last_line_no).max(ctx.min_line_number_width);
   let mut out = String::new();
   let mut line_no = line_number_start;
+  // Colors already in the text are turned off around the gutter.
+  let mut sgr = ansi::SgrState::default();
 
   for chunk in text.split_inclusive('\n') {
-    let _ = write!(out, "{:>width$}  ", line_no, width = width);
+    let _ = write!(out, "{:>width$}  {}", line_no, sgr.resume(), width = width);
     let content = if show_all {
-      unprintable::show_unprintable(chunk, unprintable::get_char_style())
+      unprintable::show_unprintable(chunk, char_style)
     } else {
       chunk.to_string()
     };
-    out.push_str(&content);
+    let gutter = width + 2;
+    let body = content.trim_end_matches(['\n', '\r']);
+    let mut start = 0;
+    for at in decorations::wrap_points(body, wrap.width.saturating_sub(gutter), gutter, wrap.mode) {
+      out.push_str(&content[start..at]);
+      sgr.update(&content[start..at]);
+      let _ = write!(out, "{}\n{:gutter$}{}", sgr.suspend(), "", sgr.resume());
+      start = at;
+    }
+    out.push_str(&body[start..]);
+    sgr.update(&body[start..]);
+    out.push_str(sgr.suspend());
+    out.push_str(&content[body.len()..]);
     line_no += 1;
   }
   out
@@ -1139,6 +4753,7 @@ fn write_numbered_bytes(
   stdout: &mut impl Write,
   bytes: &[u8],
   line_number_start: usize,
+  min_width: usize,
 ) -> Result<()> {
   let line_count = count_lines_bytes(bytes);
   if line_count == 0 {
@@ -1146,7 +4761,7 @@ fn write_numbered_bytes(
   }
 
   let last_line_no = line_number_start.saturating_add(line_count.saturating_sub(1));
-  let width = line_number_width(last_line_no);
+  let width = line_number_width(last_line_no).max(min_width);
   let mut line_no = line_number_start;
   write_prefix(stdout, line_no, width)?;
   for (index, byte) in bytes.iter().enumerate() {
@@ -1182,22 +4797,45 @@ fn line_number_width(line_count: usize) -> usize {
 }
 
 /// Parse style components from the --style flag.
-/// Supports: "numbers", "changes", "headers", "rich"
-fn parse_style_components(style: Option<&str>) -> StyleConfig {
+/// Supports: "numbers", "changes", "headers", "grid", "blame", "full" (the
+/// first four),
+/// "rich", "plain"
+///
+/// Precedence, from lowest to highest: components from `--style` (or a
+/// per-language `style` in the config file, which `for_language` merges on
+/// top), then the "plain" component, then `--plain`. Plain drops every
+/// decoration but keeps "rich", which only affects highlighting.
+fn parse_style_components(style: Option<&str>, plain: bool) -> StyleConfig {
   let mut config = StyleConfig {
     highlight_locals: true,
     ..StyleConfig::default()
   };
+  let mut plain = plain;
   for raw in style.unwrap_or_default().split(',') {
     let token = raw.trim();
     match token {
       "numbers" => config.decoration_config.show_numbers = true,
       "changes" => config.decoration_config.show_changes = true,
       "headers" => config.decoration_config.show_headers = true,
+      "grid" => config.decoration_config.show_grid = true,
+      "blame" => config.decoration_config.show_blame = true,
+      "full" => {
+        config.decoration_config = DecorationConfig {
+          show_numbers: true,
+          show_changes: true,
+          show_headers: true,
+          show_grid: true,
+          ..config.decoration_config
+        }
+      }
       "rich" => config.highlight_injections = true,
+      "plain" => plain = true,
       _ => {}
     }
   }
+  if plain {
+    config.decoration_config = DecorationConfig::default();
+  }
 
   if config.highlight_injections {
     config.highlight_locals = true;
@@ -1206,14 +4844,6 @@ fn parse_style_components(style: Option<&str>) -> StyleConfig {
   config
 }
 
-fn display_name_for_spec(spec: &FileSpec) -> String {
-  if spec.path == Path::new("-") {
-    "-".to_string()
-  } else {
-    spec.path.to_string_lossy().to_string()
-  }
-}
-
 fn squeeze_blank_lines_bytes(bytes: &[u8], limit: usize) -> Vec<u8> {
   if bytes.is_empty() {
     return Vec::new();
@@ -1260,116 +4890,47 @@ fn squeeze_blank_lines_bytes(bytes: &[u8], limit: usize) -> Vec<u8> {
   out
 }
 
-fn parse_file_spec(path: PathBuf, default_range: Option<LineRange>) -> Result<FileSpec> {
+fn parse_file_spec(
+  path: PathBuf,
+  default_ranges: &LineSelection,
+  context: usize,
+) -> Result<FileSpec> {
   let raw = path.to_string_lossy();
-  if let Some((path_part, line_range)) = parse_line_range_suffix(&raw)? {
+  if let Some((path_part, line_ranges)) = parse_line_range_suffix(&raw)? {
     let parsed_path = PathBuf::from(path_part);
     return Ok(FileSpec {
       path: parsed_path,
-      line_range: Some(line_range),
+      line_ranges: LineSelection::new(line_ranges).with_context(context),
+      name: None,
     });
   }
   Ok(FileSpec {
     path,
-    line_range: default_range,
+    line_ranges: default_ranges.clone(),
+    name: None,
   })
 }
 
-fn parse_line_range_suffix(raw: &str) -> Result<Option<(String, LineRange)>> {
+fn parse_line_range_suffix(raw: &str) -> Result<Option<(String, Vec<RangeSpec>)>> {
   let (path_part, range_part) = match raw.rsplit_once("#L").or_else(|| raw.rsplit_once("#l")) {
     Some(parts) => parts,
     None => return Ok(None),
   };
   if path_part.is_empty() {
-    return Err(eyre!("missing file path before line range"));
+    return Err(eyre!(tr("error-range-missing-path")));
   }
   if range_part.is_empty() {
-    return Err(eyre!("missing line range after #L"));
+    return Err(eyre!(tr("error-range-missing-range")));
   }
-  let line_range = parse_line_range(range_part).ok_or_else(|| {
-    eyre!(
-      "invalid line range '#L{range_part}' (expected #L<start>-<end>, #L<start>:<end>, #L<start>,<end>, or #L<start>)"
-    )
+  let line_ranges = ranges::parse(range_part).ok_or_else(|| {
+    eyre!(tr_args(
+      "error-range-suffix",
+      &[("range", Arg::Str(range_part))]
+    ))
   })?;
-  Ok(Some((path_part.to_string(), line_range)))
-}
-
-fn parse_line_range_arg(raw: &str) -> Result<LineRange> {
-  parse_line_range(raw).ok_or_else(|| {
-    eyre!("invalid line range '{raw}' (expected start-end, start:end, start,end, or start)")
-  })
-}
-
-fn parse_line_range(raw: &str) -> Option<LineRange> {
-  let raw = raw.trim();
-  let raw = raw
-    .strip_prefix('L')
-    .or_else(|| raw.strip_prefix('l'))
-    .unwrap_or(raw);
-  if raw.is_empty() {
-    return None;
-  }
-  let (start_raw, end_raw) = match split_line_range(raw) {
-    Some(parts) => parts,
-    None => {
-      let line = raw.parse::<usize>().ok()?;
-      if line == 0 {
-        return None;
-      }
-      return Some(LineRange {
-        start: line,
-        end: line,
-      });
-    }
-  };
-  if start_raw.is_empty() || end_raw.is_empty() {
-    return None;
-  }
-  let start_raw = start_raw.trim();
-  let end_raw = end_raw.trim();
-  let start = start_raw.parse::<usize>().ok()?;
-  let end_raw = end_raw
-    .strip_prefix('L')
-    .or_else(|| end_raw.strip_prefix('l'))
-    .unwrap_or(end_raw);
-  let end = end_raw.parse::<usize>().ok()?;
-  if start == 0 || end == 0 || end < start {
-    return None;
-  }
-  Some(LineRange { start, end })
-}
-
-fn split_line_range(raw: &str) -> Option<(&str, &str)> {
-  for separator in ['-', ':', ','] {
-    if let Some(parts) = raw.split_once(separator) {
-      return Some(parts);
-    }
-  }
-  None
+  Ok(Some((path_part.to_string(), line_ranges)))
 }
 
-fn slice_bytes_by_line_range(bytes: &[u8], range: LineRange) -> Vec<u8> {
-  if bytes.is_empty() {
-    return Vec::new();
-  }
-  let mut out = Vec::new();
-  let mut line_no = 1usize;
-  let mut start = 0usize;
-  for (index, byte) in bytes.iter().enumerate() {
-    if *byte == b'\n' {
-      let line_end = index + 1;
-      if line_no >= range.start && line_no <= range.end {
-        out.extend_from_slice(&bytes[start..line_end]);
-      }
-      line_no += 1;
-      if line_no > range.end {
-        return out;
-      }
-      start = line_end;
-    }
-  }
-  if start < bytes.len() && line_no >= range.start && line_no <= range.end {
-    out.extend_from_slice(&bytes[start..]);
-  }
-  out
+fn parse_line_range_arg(raw: &str) -> Result<Vec<RangeSpec>> {
+  ranges::parse(raw).ok_or_else(|| eyre!(tr_args("error-range", &[("range", Arg::Str(raw))])))
 }
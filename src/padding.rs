@@ -0,0 +1,180 @@
+//! Blank margins around the rendered output.
+//! Wraps the output writer so every rendering path gets the same padding.
+
+use std::io::{self, Write};
+
+/// Number of blank columns/lines to insert on each side of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Padding {
+  pub top: usize,
+  pub right: usize,
+  pub bottom: usize,
+  pub left: usize,
+}
+
+impl Padding {
+  /// Build padding from a uniform value with optional per-side overrides.
+  pub fn from_args(
+    all: Option<usize>,
+    top: Option<usize>,
+    right: Option<usize>,
+    bottom: Option<usize>,
+    left: Option<usize>,
+  ) -> Self {
+    let all = all.unwrap_or(0);
+    Self {
+      top: top.unwrap_or(all),
+      right: right.unwrap_or(all),
+      bottom: bottom.unwrap_or(all),
+      left: left.unwrap_or(all),
+    }
+  }
+
+  /// Total number of columns taken by horizontal padding.
+  pub fn horizontal(&self) -> usize {
+    self.left + self.right
+  }
+}
+
+/// Writer that inserts left/right padding around every line and blank lines
+/// above and below the whole output.
+pub struct PaddedWriter<W: Write> {
+  inner: W,
+  padding: Padding,
+  at_line_start: bool,
+  started: bool,
+}
+
+impl<W: Write> PaddedWriter<W> {
+  pub fn new(inner: W, padding: Padding) -> Self {
+    Self {
+      inner,
+      padding,
+      at_line_start: true,
+      started: false,
+    }
+  }
+
+  /// Write the top padding. Called lazily before the first byte of output.
+  fn begin(&mut self) -> io::Result<()> {
+    if !self.started {
+      self.started = true;
+      for _ in 0..self.padding.top {
+        self.inner.write_all(b"\n")?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Terminate the last line if needed and write the bottom padding.
+  pub fn finish(&mut self) -> io::Result<()> {
+    if !self.started || self.padding.bottom == 0 {
+      return self.inner.flush();
+    }
+    if !self.at_line_start {
+      self.write_all(b"\n")?;
+    }
+    for _ in 0..self.padding.bottom {
+      self.inner.write_all(b"\n")?;
+    }
+    self.inner.flush()
+  }
+
+  /// True when nothing has been written since the last newline.
+  pub fn at_line_start(&self) -> bool {
+    self.at_line_start
+  }
+
+  /// The wrapped writer, for output that must bypass padding.
+  pub fn get_mut(&mut self) -> &mut W {
+    &mut self.inner
+  }
+
+  /// Return the wrapped writer.
+  pub fn into_inner(self) -> W {
+    self.inner
+  }
+
+  fn write_spaces(&mut self, count: usize) -> io::Result<()> {
+    const SPACES: &[u8] = b"                                ";
+    let mut remaining = count;
+    while remaining > 0 {
+      let chunk = remaining.min(SPACES.len());
+      self.inner.write_all(&SPACES[..chunk])?;
+      remaining -= chunk;
+    }
+    Ok(())
+  }
+}
+
+impl<W: Write> Write for PaddedWriter<W> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if buf.is_empty() {
+      return Ok(0);
+    }
+    self.begin()?;
+    if self.padding.horizontal() == 0 {
+      self.inner.write_all(buf)?;
+      self.at_line_start = buf.last() == Some(&b'\n');
+      return Ok(buf.len());
+    }
+
+    for segment in buf.split_inclusive(|byte| *byte == b'\n') {
+      if self.at_line_start {
+        self.write_spaces(self.padding.left)?;
+        self.at_line_start = false;
+      }
+      match segment.split_last() {
+        Some((&b'\n', content)) => {
+          self.inner.write_all(content)?;
+          self.write_spaces(self.padding.right)?;
+          self.inner.write_all(b"\n")?;
+          self.at_line_start = true;
+        }
+        _ => self.inner.write_all(segment)?,
+      }
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn render(padding: Padding, chunks: &[&str]) -> String {
+    let mut out = Vec::new();
+    {
+      let mut writer = PaddedWriter::new(&mut out, padding);
+      for chunk in chunks {
+        writer.write_all(chunk.as_bytes()).unwrap();
+      }
+      writer.finish().unwrap();
+    }
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn test_no_padding_is_passthrough() {
+    let result = render(Padding::default(), &["a\nb"]);
+    assert_eq!(result, "a\nb");
+  }
+
+  #[test]
+  fn test_horizontal_padding_across_chunks() {
+    let padding = Padding::from_args(None, None, Some(1), None, Some(2));
+    let result = render(padding, &["ab", "c\nd", "\n"]);
+    assert_eq!(result, "  abc \n  d \n");
+  }
+
+  #[test]
+  fn test_vertical_padding() {
+    let padding = Padding::from_args(Some(1), None, Some(0), None, Some(0));
+    let result = render(padding, &["x"]);
+    assert_eq!(result, "\nx\n\n");
+  }
+}
@@ -0,0 +1,274 @@
+//! Pager support.
+//! Spawns a pager command and routes rendered output into its stdin.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use eyre::{Result, eyre};
+
+/// Read buffer for plain copies on platforms without kernel-side copying.
+#[cfg(not(target_os = "linux"))]
+const COPY_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// Pager used when none is configured: keeps colors (-R), exits right away
+/// when the output fits on one screen (-F) and leaves it on screen (-X).
+pub const DEFAULT_PAGER: &str = "less -RFX";
+
+/// A pager command split into program and arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PagerCommand {
+  pub program: String,
+  pub args: Vec<String>,
+}
+
+impl PagerCommand {
+  /// Parse a pager command line using shell-like word splitting.
+  /// Returns `None` for an empty command.
+  pub fn parse(raw: &str) -> Result<Option<Self>> {
+    let mut words = split_command(raw)?.into_iter();
+    let Some(program) = words.next() else {
+      return Ok(None);
+    };
+    let mut args: Vec<String> = words.collect();
+    // less needs -R to pass colors through
+    if is_program(&program, "less") && args.is_empty() {
+      args.push("-R".to_string());
+    }
+    Ok(Some(Self { program, args }))
+  }
+
+  /// True if the pager would run umber itself, which would recurse forever.
+  pub fn is_recursive(&self) -> bool {
+    is_program(&self.program, "umber")
+  }
+
+  /// True for the built-in default, which may be missing on the system.
+  pub fn is_default(&self) -> bool {
+    Self::parse(DEFAULT_PAGER).ok().flatten().as_ref() == Some(self)
+  }
+}
+
+/// Pick the pager from `--pager`, `UMBER_PAGER` or `PAGER`, falling back to
+/// [`DEFAULT_PAGER`]. Returns `None` when the chosen command is empty.
+pub fn configured_pager(cli_pager: Option<&str>) -> Result<Option<PagerCommand>> {
+  let raw = pager_command_line(
+    cli_pager,
+    std::env::var("UMBER_PAGER").ok(),
+    std::env::var("PAGER").ok(),
+  );
+  PagerCommand::parse(&raw)
+}
+
+fn pager_command_line(
+  cli_pager: Option<&str>,
+  umber_pager: Option<String>,
+  pager: Option<String>,
+) -> String {
+  cli_pager
+    .map(str::to_string)
+    .or(umber_pager)
+    .or(pager)
+    .unwrap_or_else(|| DEFAULT_PAGER.to_string())
+}
+
+fn is_program(program: &str, name: &str) -> bool {
+  Path::new(program)
+    .file_stem()
+    .and_then(OsStr::to_str)
+    .is_some_and(|stem| stem.eq_ignore_ascii_case(name))
+}
+
+/// Split a command line into words, honoring single quotes, double quotes,
+/// and backslash escapes like a POSIX shell (without expansions).
+pub fn split_command(raw: &str) -> Result<Vec<String>> {
+  let mut words = Vec::new();
+  let mut current = String::new();
+  let mut in_word = false;
+  let mut chars = raw.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '\'' => {
+        in_word = true;
+        loop {
+          match chars.next() {
+            Some('\'') => break,
+            Some(c) => current.push(c),
+            None => return Err(eyre!("unterminated single quote: {raw}")),
+          }
+        }
+      }
+      '"' => {
+        in_word = true;
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+              Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+              Some(c) => {
+                current.push('\\');
+                current.push(c);
+              }
+              None => return Err(eyre!("unterminated double quote: {raw}")),
+            },
+            Some(c) => current.push(c),
+            None => return Err(eyre!("unterminated double quote: {raw}")),
+          }
+        }
+      }
+      '\\' => {
+        in_word = true;
+        if let Some(c) = chars.next() {
+          current.push(c);
+        }
+      }
+      c if c.is_whitespace() => {
+        if in_word {
+          words.push(std::mem::take(&mut current));
+          in_word = false;
+        }
+      }
+      c => {
+        in_word = true;
+        current.push(c);
+      }
+    }
+  }
+  if in_word {
+    words.push(current);
+  }
+  Ok(words)
+}
+
+/// Destination for rendered output: the terminal directly or a pager process.
+pub enum Output {
+  Stdout(io::StdoutLock<'static>),
+  Pager { child: Child, stdin: ChildStdin },
+}
+
+impl Output {
+  pub fn stdout() -> Self {
+    Self::Stdout(io::stdout().lock())
+  }
+
+  /// Spawn the pager with its stdin connected to a pipe.
+  pub fn pager(command: &PagerCommand) -> io::Result<Self> {
+    let mut child = Command::new(&command.program)
+      .args(&command.args)
+      .stdin(Stdio::piped())
+      .spawn()?;
+    let stdin = child
+      .stdin
+      .take()
+      .ok_or_else(|| io::Error::other("pager stdin unavailable"))?;
+    Ok(Self::Pager { child, stdin })
+  }
+
+  /// Copy a file to the output unchanged. Anything written before must be flushed.
+  ///
+  /// On Linux `io::copy` from a file to stdout or a pipe uses `copy_file_range`,
+  /// `splice` or `sendfile`, so the data never passes through userspace;
+  /// elsewhere it is copied in large blocks.
+  pub fn copy_from(&mut self, file: &mut File) -> io::Result<u64> {
+    #[cfg(target_os = "linux")]
+    let mut reader = file;
+    #[cfg(not(target_os = "linux"))]
+    let mut reader = io::BufReader::with_capacity(COPY_BUFFER_BYTES, file);
+    match self {
+      Self::Stdout(stdout) => io::copy(&mut reader, stdout),
+      Self::Pager { stdin, .. } => io::copy(&mut reader, stdin),
+    }
+  }
+
+  /// Close the pager's input and wait for the user to quit it.
+  pub fn finish(self) -> io::Result<()> {
+    match self {
+      Self::Stdout(mut stdout) => stdout.flush(),
+      Self::Pager { mut child, stdin } => {
+        drop(stdin);
+        child.wait()?;
+        Ok(())
+      }
+    }
+  }
+}
+
+impl Write for Output {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    match self {
+      Self::Stdout(stdout) => stdout.write(buf),
+      Self::Pager { stdin, .. } => stdin.write(buf),
+    }
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    match self {
+      Self::Stdout(stdout) => stdout.flush(),
+      Self::Pager { stdin, .. } => stdin.flush(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_split_command_quotes() {
+    let words = split_command(r#"less -R --prompt 'a b' "c \"d\"" e\ f"#).unwrap();
+    assert_eq!(
+      words,
+      vec!["less", "-R", "--prompt", "a b", "c \"d\"", "e f"]
+    );
+  }
+
+  #[test]
+  fn test_split_command_unterminated() {
+    assert!(split_command("less 'oops").is_err());
+  }
+
+  #[test]
+  fn test_less_gets_raw_control_chars() {
+    let pager = PagerCommand::parse("less").unwrap().unwrap();
+    assert_eq!(pager.args, vec!["-R"]);
+    let pager = PagerCommand::parse("/usr/bin/less -X").unwrap().unwrap();
+    assert_eq!(pager.args, vec!["-X"]);
+  }
+
+  #[test]
+  fn test_pager_precedence() {
+    let env = |value: &str| Some(value.to_string());
+    assert_eq!(
+      pager_command_line(Some("ov"), env("more"), env("most")),
+      "ov"
+    );
+    assert_eq!(pager_command_line(None, env(""), env("most")), "");
+    assert_eq!(pager_command_line(None, None, env("most")), "most");
+    assert_eq!(pager_command_line(None, None, None), DEFAULT_PAGER);
+    assert!(
+      PagerCommand::parse(DEFAULT_PAGER)
+        .unwrap()
+        .unwrap()
+        .is_default()
+    );
+  }
+
+  #[test]
+  fn test_recursive_pager() {
+    assert!(
+      PagerCommand::parse("umber -p")
+        .unwrap()
+        .unwrap()
+        .is_recursive()
+    );
+    assert!(
+      !PagerCommand::parse("ov -F")
+        .unwrap()
+        .unwrap()
+        .is_recursive()
+    );
+  }
+}
@@ -0,0 +1,97 @@
+//! How file paths are written in headers (`--header-path`).
+
+use std::path::{Component, Path, PathBuf};
+
+use clap::ValueEnum;
+
+use crate::git;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeaderPath {
+  /// Relative to the current directory for files below it, otherwise as given
+  #[default]
+  Auto,
+  /// Relative to the current directory, using `..` when needed
+  Relative,
+  /// Absolute path
+  Absolute,
+  /// Relative to the root of the git repository containing the file
+  GitRelative,
+}
+
+/// The path to show for `path`. Falls back to the path as given when it
+/// cannot be resolved (e.g. stdin or a file outside any repository).
+pub fn display_path(path: &Path, mode: HeaderPath) -> String {
+  let given = || path.display().to_string();
+  if path == Path::new("-") || crate::remote::is_url(path) {
+    return given();
+  }
+  let (Ok(absolute), Ok(cwd)) = (std::path::absolute(path), std::env::current_dir()) else {
+    return given();
+  };
+  let shown = match mode {
+    HeaderPath::Auto => match absolute.strip_prefix(&cwd) {
+      Ok(relative) => relative.to_path_buf(),
+      Err(_) => return given(),
+    },
+    HeaderPath::Relative => relative_to(&absolute, &cwd),
+    HeaderPath::Absolute => absolute,
+    HeaderPath::GitRelative => match git::repository_root(&absolute) {
+      Some(root) => relative_to(&absolute, &root),
+      None => return given(),
+    },
+  };
+  shown.display().to_string()
+}
+
+/// `path` relative to `base`, both absolute, going up with `..` as needed.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+  let path: Vec<Component> = normalized(path);
+  let base: Vec<Component> = normalized(base);
+  let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+  let mut relative = PathBuf::new();
+  for _ in common..base.len() {
+    relative.push("..");
+  }
+  for component in &path[common..] {
+    relative.push(component);
+  }
+  relative
+}
+
+/// Components with `.` dropped and `..` applied.
+fn normalized(path: &Path) -> Vec<Component<'_>> {
+  let mut components = Vec::new();
+  for component in path.components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir if matches!(components.last(), Some(Component::Normal(_))) => {
+        components.pop();
+      }
+      component => components.push(component),
+    }
+  }
+  components
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_relative_to() {
+    let base = Path::new("/home/me/project");
+    assert_eq!(
+      relative_to(Path::new("/home/me/project/src/main.rs"), base),
+      PathBuf::from("src/main.rs")
+    );
+    assert_eq!(
+      relative_to(Path::new("/home/me/other/./notes.md"), base),
+      PathBuf::from("../other/notes.md")
+    );
+    assert_eq!(
+      relative_to(Path::new("/home/me/project/src/../README.md"), base),
+      PathBuf::from("README.md")
+    );
+  }
+}
@@ -0,0 +1,63 @@
+//! Overlaps tree-sitter parsing with rendering for large inputs.
+//! Highlight events are produced on a worker thread and handed to the writer
+//! in batches through a bounded channel, so parsing runs ahead of terminal or
+//! pipe IO without buffering the whole event stream.
+
+use std::sync::mpsc;
+use std::thread;
+
+/// Inputs smaller than this are highlighted on the calling thread; spawning a
+/// worker costs more than it saves.
+pub const MIN_PIPELINE_BYTES: usize = 256 * 1024;
+
+/// Items sent per channel message.
+const BATCH_LEN: usize = 1024;
+
+/// Batches the producer may run ahead of the consumer.
+const CHANNEL_BOUND: usize = 16;
+
+/// Run the iterator built by `produce` on a worker thread and feed its items
+/// to `consume` in order. The worker stops early once `consume` returns.
+pub fn pipelined<T, I, R>(
+  produce: impl FnOnce() -> I + Send,
+  consume: impl FnOnce(&mut dyn Iterator<Item = T>) -> R,
+) -> R
+where
+  I: Iterator<Item = T>,
+  T: Send,
+{
+  thread::scope(|scope| {
+    let (sender, receiver) = mpsc::sync_channel::<Vec<T>>(CHANNEL_BOUND);
+    scope.spawn(move || {
+      let mut batch = Vec::with_capacity(BATCH_LEN);
+      for item in produce() {
+        batch.push(item);
+        if batch.len() == BATCH_LEN {
+          let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_LEN));
+          if sender.send(full).is_err() {
+            // The consumer is gone; nobody wants the rest.
+            return;
+          }
+        }
+      }
+      if !batch.is_empty() {
+        let _ = sender.send(batch);
+      }
+    });
+    consume(&mut receiver.into_iter().flatten())
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pipelined_preserves_order_and_stops_early() {
+    let all: Vec<usize> = pipelined(|| 0..BATCH_LEN * 3 + 7, |items| items.collect());
+    assert_eq!(all, (0..BATCH_LEN * 3 + 7).collect::<Vec<_>>());
+
+    let first: Vec<usize> = pipelined(|| 0.., |items| items.take(5).collect());
+    assert_eq!(first, vec![0, 1, 2, 3, 4]);
+  }
+}
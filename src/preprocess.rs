@@ -0,0 +1,185 @@
+//! LESSOPEN-style preprocessors.
+//! External commands configured per file pattern (`[preprocess]` in the config
+//! file) turn files such as PDFs or SQLite databases into text, which is then
+//! detected and highlighted like any other input. Output is cached until the
+//! file changes.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsString;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use eyre::{Result, eyre};
+
+use crate::config::PreprocessConfig;
+use crate::pager::split_command;
+use crate::syntax_map;
+
+/// A command to run on files matching a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preprocessor {
+  pattern: String,
+  command: String,
+  /// Language of the output, when it should not be detected
+  pub language: Option<String>,
+}
+
+/// The configured preprocessors, most specific pattern first.
+#[derive(Debug, Default)]
+pub struct Preprocessors {
+  entries: Vec<Preprocessor>,
+  cache_dir: Option<PathBuf>,
+}
+
+impl Preprocessors {
+  /// `cache_dir` is where output is cached; `None` disables caching.
+  pub fn new(config: &HashMap<String, PreprocessConfig>, cache_dir: Option<PathBuf>) -> Self {
+    let mut entries: Vec<Preprocessor> = config
+      .iter()
+      .map(|(pattern, entry)| {
+        let (command, language) = match entry {
+          PreprocessConfig::Command(command) => (command.clone(), None),
+          PreprocessConfig::Full { command, language } => (command.clone(), language.clone()),
+        };
+        Preprocessor {
+          pattern: pattern.clone(),
+          command,
+          language,
+        }
+      })
+      .collect();
+    entries.sort_by(|a, b| {
+      b.pattern
+        .len()
+        .cmp(&a.pattern.len())
+        .then_with(|| a.pattern.cmp(&b.pattern))
+    });
+    Self { entries, cache_dir }
+  }
+
+  /// The preprocessor for `path`, if one matches.
+  pub fn find(&self, path: &Path) -> Option<&Preprocessor> {
+    self
+      .entries
+      .iter()
+      .find(|entry| syntax_map::matches(&entry.pattern, path))
+  }
+
+  /// Run `preprocessor` on `path`, reusing cached output while the file is unchanged.
+  pub fn run(&self, preprocessor: &Preprocessor, path: &Path) -> Result<Vec<u8>> {
+    let cached = self
+      .cache_dir
+      .as_ref()
+      .and_then(|dir| Some(dir.join(cache_key(&preprocessor.command, path)?)));
+    if let Some(output) = cached.as_ref().and_then(|file| fs::read(file).ok()) {
+      return Ok(output);
+    }
+
+    let args = command_line(&preprocessor.command, path)?;
+    let (program, args) = args
+      .split_first()
+      .ok_or_else(|| eyre!("empty preprocessor command for '{}'", preprocessor.pattern))?;
+    let output = Command::new(program)
+      .args(args)
+      .output()
+      .map_err(|err| eyre!("{}: {err}", program.to_string_lossy()))?;
+    if !output.status.success() {
+      let stderr = String::from_utf8_lossy(&output.stderr);
+      let program = program.to_string_lossy();
+      return Err(match stderr.trim() {
+        "" => eyre!("{program} exited with {}", output.status),
+        stderr => eyre!("{program} exited with {}: {stderr}", output.status),
+      });
+    }
+
+    // A cache that can't be written only costs the next run some time.
+    if let Some(file) = cached
+      && let Some(dir) = file.parent()
+      && fs::create_dir_all(dir).is_ok()
+    {
+      let _ = fs::write(file, &output.stdout);
+    }
+    Ok(output.stdout)
+  }
+}
+
+/// Split `command` into words and substitute `{}` with the path. Without a
+/// `{}` the path is appended as the last argument.
+fn command_line(command: &str, path: &Path) -> Result<Vec<OsString>> {
+  let words = split_command(command)?;
+  let mut substituted = false;
+  let mut args: Vec<OsString> = words
+    .into_iter()
+    .map(|word| {
+      if word.contains("{}") {
+        substituted = true;
+        OsString::from(word.replace("{}", &path.to_string_lossy()))
+      } else {
+        OsString::from(word)
+      }
+    })
+    .collect();
+  if !substituted {
+    args.push(path.as_os_str().to_owned());
+  }
+  Ok(args)
+}
+
+/// Cache file name for the output of `command` on the current version of `path`.
+fn cache_key(command: &str, path: &Path) -> Option<PathBuf> {
+  let metadata = fs::metadata(path).ok()?;
+  let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+  let mut hasher = DefaultHasher::new();
+  command.hash(&mut hasher);
+  fs::canonicalize(path).ok()?.hash(&mut hasher);
+  metadata.len().hash(&mut hasher);
+  modified.hash(&mut hasher);
+  Some(Path::new("preprocess").join(format!("{:016x}", hasher.finish())))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_command_line_substitutes_path() {
+    let path = Path::new("docs/My File.pdf");
+    assert_eq!(
+      command_line("pdftotext -layout {} -", path).unwrap(),
+      ["pdftotext", "-layout", "docs/My File.pdf", "-"].map(OsString::from)
+    );
+    assert_eq!(
+      command_line("plutil -p", path).unwrap(),
+      ["plutil", "-p", "docs/My File.pdf"].map(OsString::from)
+    );
+  }
+
+  #[test]
+  fn test_find_prefers_specific_patterns() {
+    let config = HashMap::from([
+      (
+        "*.db".to_string(),
+        PreprocessConfig::Command("sqlite3 {} .dump".to_string()),
+      ),
+      (
+        "cache.db".to_string(),
+        PreprocessConfig::Full {
+          command: "true".to_string(),
+          language: Some("sql".to_string()),
+        },
+      ),
+    ]);
+    let preprocessors = Preprocessors::new(&config, None);
+    let found = preprocessors.find(Path::new("data/cache.db")).unwrap();
+    assert_eq!(found.language.as_deref(), Some("sql"));
+    assert_eq!(
+      preprocessors.find(Path::new("other.db")).unwrap().command,
+      "sqlite3 {} .dump"
+    );
+    assert!(preprocessors.find(Path::new("main.rs")).is_none());
+  }
+}
@@ -0,0 +1,203 @@
+//! `Umber`, a builder in the manner of bat's `PrettyPrinter` for embedding
+//! umber in other programs: add inputs, pick a theme and options, and print.
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use eyre::{Result, WrapErr, eyre};
+use syntastica_parsers_git::LanguageSetImpl;
+
+use crate::color_depth::ColorDepth;
+use crate::custom_langs::CustomLanguageSet;
+use crate::detect::detect_language_name;
+use crate::highlight::{
+  self, Format, Languages, Options, builtin_theme, highlighted_lines, plain_lines,
+  resolve_language_union,
+};
+
+/// Something to print.
+enum Input<'a> {
+  File(PathBuf),
+  Bytes(&'a [u8]),
+  Reader(Box<dyn Read + 'a>),
+}
+
+impl<'a> Input<'a> {
+  /// The file name, if any, and the contents.
+  fn read(self) -> Result<(Option<String>, Cow<'a, [u8]>)> {
+    Ok(match self {
+      Input::File(path) => {
+        let bytes =
+          fs::read(&path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        (Some(path.display().to_string()), Cow::Owned(bytes))
+      }
+      Input::Bytes(bytes) => (None, Cow::Borrowed(bytes)),
+      Input::Reader(mut reader) => {
+        let mut bytes = Vec::new();
+        reader
+          .read_to_end(&mut bytes)
+          .wrap_err("Failed to read input")?;
+        (None, Cow::Owned(bytes))
+      }
+    })
+  }
+}
+
+/// Highlights files, bytes and readers with the grammars and themes of the
+/// `umber` command and writes them out one after another.
+///
+/// ```no_run
+/// umber::Umber::new()
+///   .input_file("src/main.rs")
+///   .theme("catppuccin::latte")
+///   .line_numbers(true)
+///   .print()?;
+/// # Ok::<(), eyre::Report>(())
+/// ```
+pub struct Umber<'a> {
+  inputs: Vec<Input<'a>>,
+  language: Option<String>,
+  theme: String,
+  options: Options,
+}
+
+impl Default for Umber<'_> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<'a> Umber<'a> {
+  /// No inputs yet, terminal output in `catppuccin::mocha`, the command's
+  /// dark theme.
+  pub fn new() -> Self {
+    Self {
+      inputs: Vec::new(),
+      language: None,
+      theme: "catppuccin::mocha".to_string(),
+      options: Options::default(),
+    }
+  }
+
+  /// Add the file at `path`; its name helps detect the language.
+  pub fn input_file(&mut self, path: impl AsRef<Path>) -> &mut Self {
+    self.inputs.push(Input::File(path.as_ref().to_path_buf()));
+    self
+  }
+
+  /// Add several files.
+  pub fn input_files(&mut self, paths: impl IntoIterator<Item = impl AsRef<Path>>) -> &mut Self {
+    for path in paths {
+      self.input_file(path);
+    }
+    self
+  }
+
+  /// Add text held in memory.
+  pub fn input_bytes(&mut self, bytes: &'a [u8]) -> &mut Self {
+    self.inputs.push(Input::Bytes(bytes));
+    self
+  }
+
+  /// Add whatever `reader` yields, read when printing.
+  pub fn input_from_reader(&mut self, reader: impl Read + 'a) -> &mut Self {
+    self.inputs.push(Input::Reader(Box::new(reader)));
+    self
+  }
+
+  /// Highlight every input as `name`, such as `rust` or `xml`. Otherwise the
+  /// language of each is detected, and one in no known language is printed
+  /// without highlighting.
+  pub fn language(&mut self, name: &str) -> &mut Self {
+    self.language = Some(name.to_string());
+    self
+  }
+
+  /// One of the built-in themes: `ansi` or one of syntastica's, such as
+  /// `gruvbox::dark`.
+  pub fn theme(&mut self, name: &str) -> &mut Self {
+    self.theme = name.to_string();
+    self
+  }
+
+  /// Number the lines of each input.
+  pub fn line_numbers(&mut self, yes: bool) -> &mut Self {
+    self.options.line_numbers = yes;
+    self
+  }
+
+  /// Terminal escapes, an HTML fragment or an HTML page with all inputs.
+  pub fn format(&mut self, format: Format) -> &mut Self {
+    self.options.format = format;
+    self
+  }
+
+  /// The palette for terminal output.
+  pub fn color_depth(&mut self, depth: ColorDepth) -> &mut Self {
+    self.options.color_depth = depth;
+    self
+  }
+
+  /// Print the inputs to standard output. They are consumed, so the builder
+  /// can be given new ones and reused.
+  pub fn print(&mut self) -> Result<()> {
+    self.print_to(&mut io::stdout().lock())
+  }
+
+  /// Print the inputs to `out`.
+  pub fn print_to(&mut self, out: &mut impl Write) -> Result<()> {
+    let theme =
+      builtin_theme(&self.theme).ok_or_else(|| eyre!("unknown theme '{}'", self.theme))?;
+    let languages = Languages::new(CustomLanguageSet::new(None), LanguageSetImpl::new());
+    let language = match &self.language {
+      Some(name) => Some(
+        resolve_language_union(name, &languages)
+          .ok_or_else(|| eyre!("unknown language '{name}'"))?,
+      ),
+      None => None,
+    };
+    let mut sources = Vec::new();
+    for input in self.inputs.drain(..) {
+      let (name, bytes) = input.read()?;
+      let text = String::from_utf8_lossy(&bytes);
+      let language = language.or_else(|| {
+        let detected = detect_language_name(name.as_deref().map(Path::new), &text, &[])?;
+        resolve_language_union(detected, &languages)
+      });
+      let lines = match language {
+        Some(language) => highlighted_lines(&text, language, &languages)?,
+        None => plain_lines(&text),
+      };
+      sources.push((name.unwrap_or_default(), lines));
+    }
+    out.write_all(highlight::render(sources, &theme, &self.options).as_bytes())?;
+    out.flush()?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_print_to() {
+    let mut out = Vec::new();
+    Umber::new()
+      .input_bytes(b"one\n\ntwo\n")
+      .input_from_reader(&b"three"[..])
+      .theme("ansi")
+      .print_to(&mut out)
+      .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "one\n\ntwo\nthree\n");
+
+    let err = Umber::new()
+      .input_bytes(b"")
+      .theme("no-such-theme")
+      .print_to(&mut Vec::new())
+      .unwrap_err();
+    assert_eq!(err.to_string(), "unknown theme 'no-such-theme'");
+  }
+}
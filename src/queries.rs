@@ -0,0 +1,107 @@
+//! Highlight query overrides from `~/.config/umber/queries/<lang>/`, the
+//! layout nvim-treesitter uses. A `highlights.scm`, `locals.scm` or
+//! `injections.scm` there replaces the query umber ships for the language;
+//! one starting with a `; extends` line is added after it instead.
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::OnceCell;
+
+/// The queries a language can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryKind {
+  Highlights,
+  Locals,
+  Injections,
+}
+
+impl QueryKind {
+  const ALL: [Self; 3] = [Self::Highlights, Self::Locals, Self::Injections];
+
+  fn file_name(self) -> &'static str {
+    match self {
+      Self::Highlights => "highlights.scm",
+      Self::Locals => "locals.scm",
+      Self::Injections => "injections.scm",
+    }
+  }
+
+  /// `query` rewritten for syntastica's capture names and predicates.
+  fn process(self, query: &str) -> String {
+    match self {
+      Self::Highlights => syntastica_query_preprocessor::process_highlights("", true, query),
+      Self::Locals => syntastica_query_preprocessor::process_locals("", true, query),
+      Self::Injections => syntastica_query_preprocessor::process_injections("", true, query),
+    }
+  }
+}
+
+static DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Look for overrides in `dir`. Only the first call has an effect.
+pub fn register(dir: &Path) {
+  DIR.get_or_init(|| dir.to_path_buf());
+}
+
+fn override_path(language: &str, kind: QueryKind) -> Option<PathBuf> {
+  let path = DIR.get()?.join(language).join(kind.file_name());
+  path.is_file().then_some(path)
+}
+
+/// Whether any query of `language` is overridden.
+pub fn has_overrides(language: &str) -> bool {
+  QueryKind::ALL
+    .into_iter()
+    .any(|kind| override_path(language, kind).is_some())
+}
+
+/// The `kind` query of `language`: `builtin`, already preprocessed, unless
+/// the user overrides or extends it. An unreadable override is ignored.
+pub fn apply<'a>(language: &str, kind: QueryKind, builtin: &'a str) -> Cow<'a, str> {
+  let user = override_path(language, kind).and_then(|path| fs::read_to_string(path).ok());
+  match user {
+    Some(user) => Cow::Owned(merge(builtin, &user, kind)),
+    None => Cow::Borrowed(builtin),
+  }
+}
+
+fn merge(builtin: &str, user: &str, kind: QueryKind) -> String {
+  let user_query = kind.process(user);
+  if extends(user) {
+    format!("{builtin}\n{user_query}")
+  } else {
+    user_query
+  }
+}
+
+/// Whether the query's first line is the `; extends` modeline.
+fn extends(query: &str) -> bool {
+  query
+    .lines()
+    .next()
+    .is_some_and(|line| line.trim_start_matches(';').trim() == "extends")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_merge() {
+    let builtin = "(comment) @comment";
+    let replaced = merge(builtin, "(string) @string\n", QueryKind::Highlights);
+    assert!(!replaced.contains(builtin));
+    assert!(replaced.contains("(string) @string"));
+    let extended = merge(
+      builtin,
+      ";; extends\n((identifier) @keyword (#eq? @keyword \"self\"))\n",
+      QueryKind::Highlights,
+    );
+    assert!(extended.starts_with("(comment) @comment\n"));
+    assert!(extended.contains("@keyword"));
+    assert!(extends("; extends\n"));
+    assert!(!extends("; extends nothing\n(string) @string"));
+  }
+}
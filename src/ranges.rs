@@ -0,0 +1,434 @@
+//! Line selections from `--lines`, `#L` fragments and `--highlight-line`.
+//! A selection is a list of ranges whose bounds count from the start of the
+//! input or, with a leading `-`, from its end (`-20:` is the last 20 lines).
+//! Once the number of lines is known, overlapping and adjacent ranges are
+//! merged so every line is shown once, in file order. `--context` widens every
+//! range; the lines it adds are shown without emphasis.
+
+/// A line number as written: from the start (1 is the first line) or from the
+/// end (1 is the last line).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineIndex {
+  FromStart(usize),
+  FromEnd(usize),
+}
+
+impl LineIndex {
+  /// The 1-based line number in an input of `total` lines; 0 when the index
+  /// reaches back before the first line.
+  fn resolve(self, total: usize) -> usize {
+    match self {
+      LineIndex::FromStart(line) => line,
+      LineIndex::FromEnd(back) => (total + 1).saturating_sub(back),
+    }
+  }
+}
+
+/// Inclusive range of lines as written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeSpec {
+  pub start: LineIndex,
+  pub end: LineIndex,
+}
+
+impl RangeSpec {
+  /// The range with `lines` more lines on each side.
+  fn widen(self, lines: usize) -> RangeSpec {
+    let start = match self.start {
+      LineIndex::FromStart(start) => LineIndex::FromStart(start.saturating_sub(lines).max(1)),
+      LineIndex::FromEnd(back) => LineIndex::FromEnd(back.saturating_add(lines)),
+    };
+    let end = match self.end {
+      LineIndex::FromStart(end) => LineIndex::FromStart(end.saturating_add(lines)),
+      LineIndex::FromEnd(back) => LineIndex::FromEnd(back.saturating_sub(lines).max(1)),
+    };
+    RangeSpec { start, end }
+  }
+
+  fn contains(self, line: usize, total: Option<usize>) -> bool {
+    let after_start = match self.start {
+      LineIndex::FromStart(start) => line >= start,
+      LineIndex::FromEnd(back) => total.is_some_and(|total| line + back > total),
+    };
+    let before_end = match self.end {
+      LineIndex::FromStart(end) => line <= end,
+      LineIndex::FromEnd(back) => total.is_none_or(|total| line + back <= total + 1),
+    };
+    after_start && before_end
+  }
+}
+
+/// The ranges given for one input. Empty selects the whole input.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineSelection {
+  ranges: Vec<RangeSpec>,
+  /// Lines of `--context` shown around each range
+  context: usize,
+}
+
+impl LineSelection {
+  pub fn new(ranges: impl IntoIterator<Item = RangeSpec>) -> Self {
+    Self {
+      ranges: ranges.into_iter().collect(),
+      context: 0,
+    }
+  }
+
+  /// Show `lines` more lines around every range, clamped to the input.
+  pub fn with_context(self, lines: usize) -> Self {
+    Self {
+      context: lines,
+      ..self
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.ranges.is_empty()
+  }
+
+  /// The ranges with their context.
+  fn shown(&self) -> impl Iterator<Item = RangeSpec> + '_ {
+    self.ranges.iter().map(|range| range.widen(self.context))
+  }
+
+  /// How many lines from the end the selection reaches back, 0 when every
+  /// bound counts from the start. Streaming input holds back this many lines
+  /// until the end is known.
+  pub fn reach_from_end(&self) -> usize {
+    self
+      .ranges
+      .iter()
+      .copied()
+      .chain(self.shown())
+      .flat_map(|range| [range.start, range.end])
+      .map(|index| match index {
+        LineIndex::FromStart(_) => 0,
+        LineIndex::FromEnd(back) => back,
+      })
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// The last selected line, when it is known without reading to the end.
+  pub fn last_line(&self) -> Option<usize> {
+    if self.is_empty() || self.reach_from_end() > 0 {
+      return None;
+    }
+    self.shown().map(|range| range.end.resolve(0)).max()
+  }
+
+  /// Length of the start of `bytes` that holds every selected line, when the
+  /// last one is known without reading to the end; the rest need not be read.
+  pub fn needed_len(&self, bytes: &[u8]) -> Option<usize> {
+    let last = self.last_line()?;
+    Some(split_lines(bytes).take(last).map(<[u8]>::len).sum())
+  }
+
+  /// True if `line` is selected. `total` is the number of lines in the input;
+  /// `None` means it is not known yet but is more than `reach_from_end` lines
+  /// past `line`.
+  pub fn contains(&self, line: usize, total: Option<usize>) -> bool {
+    self.is_empty() || self.shown().any(|range| range.contains(line, total))
+  }
+
+  /// Sorted, merged line ranges for an input of `total` lines.
+  fn resolve(&self, total: usize) -> Vec<LineRange> {
+    let mut ranges: Vec<LineRange> = self
+      .shown()
+      .map(|range| LineRange {
+        start: range.start.resolve(total).max(1),
+        end: range.end.resolve(total).min(total),
+      })
+      .filter(|range| range.start <= range.end)
+      .collect();
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<LineRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+      match merged.last_mut() {
+        Some(last) if range.start <= last.end.saturating_add(1) => {
+          last.end = last.end.max(range.end);
+        }
+        _ => merged.push(range),
+      }
+    }
+    merged
+  }
+}
+
+/// What a line is marked with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mark {
+  /// Picked with `--highlight-line`
+  Highlighted,
+  /// Selected with `--lines` and shown within its `--context`
+  Selected,
+}
+
+/// The marked lines of one input.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Marks<'a> {
+  highlighted: &'a [RangeSpec],
+  selected: &'a [RangeSpec],
+  /// Lines in the input, `None` while it is still being read
+  total: Option<usize>,
+}
+
+impl<'a> Marks<'a> {
+  /// `total` is the number of lines in the input, as for
+  /// [`LineSelection::contains`]. The selection is only marked when context
+  /// was added around it.
+  pub fn new(
+    highlighted: &'a LineSelection,
+    selection: &'a LineSelection,
+    total: Option<usize>,
+  ) -> Self {
+    let selected: &[RangeSpec] = if selection.context > 0 {
+      &selection.ranges
+    } else {
+      &[]
+    };
+    Self {
+      highlighted: &highlighted.ranges,
+      selected,
+      total,
+    }
+  }
+
+  /// Marks for an input read into `bytes`.
+  pub fn for_bytes(
+    highlighted: &'a LineSelection,
+    selection: &'a LineSelection,
+    bytes: &[u8],
+  ) -> Self {
+    let mut marks = Self::new(highlighted, selection, None);
+    if !marks.is_empty() {
+      marks.total = Some(count_lines(bytes));
+    }
+    marks
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.highlighted.is_empty() && self.selected.is_empty()
+  }
+
+  pub fn get(&self, line: usize) -> Option<Mark> {
+    let marked = |ranges: &[RangeSpec]| ranges.iter().any(|range| range.contains(line, self.total));
+    if marked(self.highlighted) {
+      Some(Mark::Highlighted)
+    } else if marked(self.selected) {
+      Some(Mark::Selected)
+    } else {
+      None
+    }
+  }
+}
+
+/// Inclusive range of 1-based line numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct LineRange {
+  start: usize,
+  end: usize,
+}
+
+/// Parse a selection: one range, or several separated by commas
+/// (`1-10,40-60`). A comma always separates: `10,20` is lines 10 and 20.
+pub fn parse(raw: &str) -> Option<Vec<RangeSpec>> {
+  raw.trim().split(',').map(parse_range).collect()
+}
+
+/// One range: `start-end`, `start:end` or a single line, each
+/// number optionally prefixed with `L`. Negative numbers count from the end
+/// and either bound may be left out (`-20:`, `:10`).
+fn parse_range(raw: &str) -> Option<RangeSpec> {
+  let (start, rest) = take_index(raw.trim())?;
+  if rest.is_empty() {
+    let line = start?;
+    return Some(RangeSpec {
+      start: line,
+      end: line,
+    });
+  }
+  let rest = rest.strip_prefix(['-', ':'])?;
+  let (end, rest) = take_index(rest.trim_start())?;
+  if !rest.trim().is_empty() || (start.is_none() && end.is_none()) {
+    return None;
+  }
+  let range = RangeSpec {
+    start: start.unwrap_or(LineIndex::FromStart(1)),
+    end: end.unwrap_or(LineIndex::FromEnd(1)),
+  };
+  let ordered = match (range.start, range.end) {
+    (LineIndex::FromStart(start), LineIndex::FromStart(end)) => start <= end,
+    (LineIndex::FromEnd(start), LineIndex::FromEnd(end)) => start >= end,
+    _ => true,
+  };
+  ordered.then_some(range)
+}
+
+/// Read an optional line index from the front of `raw`. Fails on a line 0.
+fn take_index(raw: &str) -> Option<(Option<LineIndex>, &str)> {
+  let raw = raw
+    .strip_prefix('L')
+    .or_else(|| raw.strip_prefix('l'))
+    .unwrap_or(raw);
+  let (from_end, digits) = match raw.strip_prefix('-') {
+    Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => (true, rest),
+    _ => (false, raw),
+  };
+  let len = digits
+    .find(|c: char| !c.is_ascii_digit())
+    .unwrap_or(digits.len());
+  if len == 0 {
+    return Some((None, raw));
+  }
+  let line = digits[..len]
+    .parse::<usize>()
+    .ok()
+    .filter(|line| *line > 0)?;
+  let index = if from_end {
+    LineIndex::FromEnd(line)
+  } else {
+    LineIndex::FromStart(line)
+  };
+  Some((Some(index), &digits[len..]))
+}
+
+/// The selected lines of `bytes`, one run per merged range that has any, each
+/// with the number of its first line. The runs borrow from `bytes`.
+pub fn slice<'a>(bytes: &'a [u8], selection: &LineSelection) -> Vec<(usize, &'a [u8])> {
+  // Where each line starts, and where the last one ends.
+  let offsets: Vec<usize> = std::iter::once(0)
+    .chain(split_lines(bytes).scan(0, |end, line| {
+      *end += line.len();
+      Some(*end)
+    }))
+    .collect();
+  selection
+    .resolve(offsets.len() - 1)
+    .into_iter()
+    .map(|range| {
+      (
+        range.start,
+        &bytes[offsets[range.start - 1]..offsets[range.end]],
+      )
+    })
+    .collect()
+}
+
+/// Lines in `bytes`, counting a final line without a newline.
+fn count_lines(bytes: &[u8]) -> usize {
+  split_lines(bytes).count()
+}
+
+fn split_lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+  bytes.split_inclusive(|byte| *byte == b'\n')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lines(start: usize, end: usize) -> RangeSpec {
+    RangeSpec {
+      start: LineIndex::FromStart(start),
+      end: LineIndex::FromStart(end),
+    }
+  }
+
+  #[test]
+  fn test_parse_single_and_multiple_ranges() {
+    assert_eq!(parse("10-20"), Some(vec![lines(10, 20)]));
+    assert_eq!(parse("L10:L20"), Some(vec![lines(10, 20)]));
+    assert_eq!(parse("7"), Some(vec![lines(7, 7)]));
+    assert_eq!(
+      parse("1-10,40:60,75"),
+      Some(vec![lines(1, 10), lines(40, 60), lines(75, 75)])
+    );
+    assert_eq!(parse("1-10,"), None);
+    assert_eq!(parse("20-10"), None);
+    assert_eq!(parse("0"), None);
+  }
+
+  #[test]
+  fn test_parse_commas_separate_lines() {
+    assert_eq!(parse("10,20"), Some(vec![lines(10, 10), lines(20, 20)]));
+    assert_eq!(
+      parse("10,20,30"),
+      Some(vec![lines(10, 10), lines(20, 20), lines(30, 30)])
+    );
+    assert_eq!(parse("20,10"), Some(vec![lines(20, 20), lines(10, 10)]));
+  }
+
+  #[test]
+  fn test_parse_ranges_from_end() {
+    let from_end = LineIndex::FromEnd;
+    assert_eq!(
+      parse("-20:"),
+      Some(vec![RangeSpec {
+        start: from_end(20),
+        end: from_end(1)
+      }])
+    );
+    assert_eq!(
+      parse("L-5"),
+      Some(vec![RangeSpec {
+        start: from_end(5),
+        end: from_end(5)
+      }])
+    );
+    assert_eq!(
+      parse("3--2"),
+      Some(vec![RangeSpec {
+        start: LineIndex::FromStart(3),
+        end: from_end(2)
+      }])
+    );
+    assert_eq!(parse("-2:-5"), None);
+    assert_eq!(parse(":"), None);
+  }
+
+  #[test]
+  fn test_slice_merges_ranges() {
+    let text = b"a\nb\nc\nd\ne\nf\ng";
+    let selection = LineSelection::new([lines(5, 6), lines(1, 2), lines(2, 3)]);
+    assert_eq!(
+      slice(text, &selection),
+      vec![(1, &b"a\nb\nc\n"[..]), (5, &b"e\nf\n"[..])]
+    );
+    let selection = LineSelection::new(parse("1,-2:").unwrap());
+    assert_eq!(
+      slice(text, &selection),
+      vec![(1, &b"a\n"[..]), (6, &b"f\ng"[..])]
+    );
+    let selection = LineSelection::new(parse("-20:,40").unwrap());
+    assert_eq!(slice(b"a\nb\n", &selection), vec![(1, &b"a\nb\n"[..])]);
+  }
+
+  #[test]
+  fn test_needed_len() {
+    let text = b"a\nbb\nc\nd";
+    assert_eq!(LineSelection::new([lines(1, 2)]).needed_len(text), Some(5));
+    assert_eq!(LineSelection::new([lines(3, 9)]).needed_len(text), Some(8));
+    let selection = LineSelection::new(parse("1,-1").unwrap());
+    assert_eq!(selection.needed_len(text), None);
+    assert_eq!(LineSelection::new([]).needed_len(text), None);
+  }
+
+  #[test]
+  fn test_context_widens_ranges_and_marks_lines() {
+    let text = b"1\n2\n3\n4\n5\n6\n7\n8\n";
+    let selection = LineSelection::new(parse("2,-1").unwrap()).with_context(1);
+    assert_eq!(
+      slice(text, &selection),
+      vec![(1, &b"1\n2\n3\n"[..]), (7, &b"7\n8\n"[..])]
+    );
+    let highlighted = LineSelection::new(parse("5").unwrap());
+    let marks = Marks::for_bytes(&highlighted, &selection, text);
+    assert_eq!(marks.get(2), Some(Mark::Selected));
+    assert_eq!(marks.get(8), Some(Mark::Selected));
+    assert_eq!(marks.get(5), Some(Mark::Highlighted));
+    assert_eq!(marks.get(3), None);
+    let selection = LineSelection::new(parse("2").unwrap());
+    assert!(Marks::new(&LineSelection::default(), &selection, None).is_empty());
+  }
+}
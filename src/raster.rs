@@ -0,0 +1,324 @@
+//! `--format png`: the highlighted code rasterized with an embedded DejaVu
+//! Sans Mono, for chat tools and sites that take neither terminal colors
+//! nor HTML. The layout follows `--format svg`.
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use eyre::{Result, bail};
+use syntastica::style::{Color, Style};
+use syntastica::theme::ResolvedTheme;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::listing::{Listing, image_colors, rows};
+use crate::svg::ImageOptions;
+
+const REGULAR: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
+const BOLD: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono-Bold.ttf");
+const ITALIC: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono-Oblique.ttf");
+const BOLD_ITALIC: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono-BoldOblique.ttf");
+
+/// Images are drawn at twice the size of the SVG so they stay sharp on
+/// high-density screens.
+const SCALE: f32 = 2.0;
+const FONT_SIZE: f32 = 14.0 * SCALE;
+const LINE_HEIGHT: f32 = FONT_SIZE * 1.5;
+/// Height of the title bar drawn with `--window-chrome`.
+const CHROME_HEIGHT: f32 = 36.0 * SCALE;
+/// Colors of the close, minimize and zoom buttons in the title bar.
+const BUTTON_COLORS: [Color; 3] = [
+  Color::new(0xff, 0x5f, 0x56),
+  Color::new(0xff, 0xbd, 0x2e),
+  Color::new(0x27, 0xc9, 0x3f),
+];
+/// Larger images take too much memory to draw and most viewers refuse them.
+const MAX_PIXELS: u64 = 100_000_000;
+
+struct Fonts {
+  regular: FontRef<'static>,
+  bold: FontRef<'static>,
+  italic: FontRef<'static>,
+  bold_italic: FontRef<'static>,
+}
+
+impl Fonts {
+  fn new() -> Result<Self> {
+    Ok(Self {
+      regular: FontRef::try_from_slice(REGULAR)?,
+      bold: FontRef::try_from_slice(BOLD)?,
+      italic: FontRef::try_from_slice(ITALIC)?,
+      bold_italic: FontRef::try_from_slice(BOLD_ITALIC)?,
+    })
+  }
+
+  /// The face for `style` that has a glyph for `c`, if any does.
+  fn for_char(&self, c: char, style: Option<Style>) -> &FontRef<'static> {
+    let (bold, italic) = style.map_or((false, false), |style| (style.bold(), style.italic()));
+    let font = match (bold, italic) {
+      (false, false) => &self.regular,
+      (true, false) => &self.bold,
+      (false, true) => &self.italic,
+      (true, true) => &self.bold_italic,
+    };
+    if font.glyph_id(c).0 == 0 {
+      &self.regular
+    } else {
+      font
+    }
+  }
+}
+
+/// An RGB image being drawn.
+struct Canvas {
+  width: u32,
+  height: u32,
+  pixels: Vec<u8>,
+}
+
+impl Canvas {
+  fn new(width: u32, height: u32, background: Color) -> Self {
+    let pixels = [background.red, background.green, background.blue]
+      .into_iter()
+      .cycle()
+      .take(width as usize * height as usize * 3)
+      .collect();
+    Self {
+      width,
+      height,
+      pixels,
+    }
+  }
+
+  /// Mix `color` into the pixel at `x`, `y` by `coverage`, from 0 to 1.
+  fn blend(&mut self, x: i32, y: i32, color: Color, coverage: f32) {
+    if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+      return;
+    }
+    let coverage = coverage.clamp(0.0, 1.0);
+    let offset = (y as usize * self.width as usize + x as usize) * 3;
+    for (pixel, channel) in
+      self.pixels[offset..offset + 3]
+        .iter_mut()
+        .zip([color.red, color.green, color.blue])
+    {
+      let mixed = f32::from(*pixel) + (f32::from(channel) - f32::from(*pixel)) * coverage;
+      *pixel = mixed.round() as u8;
+    }
+  }
+
+  fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+    for py in y.round() as i32..(y + height).round() as i32 {
+      for px in x.round() as i32..(x + width).round() as i32 {
+        self.blend(px, py, color, 1.0);
+      }
+    }
+  }
+
+  /// An antialiased disc.
+  fn fill_circle(&mut self, cx: f32, cy: f32, radius: f32, color: Color) {
+    for py in (cy - radius).floor() as i32..=(cy + radius).ceil() as i32 {
+      for px in (cx - radius).floor() as i32..=(cx + radius).ceil() as i32 {
+        let distance = ((px as f32 + 0.5 - cx).powi(2) + (py as f32 + 0.5 - cy).powi(2)).sqrt();
+        self.blend(px, py, color, radius + 0.5 - distance);
+      }
+    }
+  }
+
+  fn draw_char(&mut self, font: &FontRef<'_>, c: char, x: f32, baseline: f32, color: Color) {
+    let glyph = font
+      .glyph_id(c)
+      .with_scale_and_position(PxScale::from(FONT_SIZE), point(x, baseline));
+    if let Some(outlined) = font.outline_glyph(glyph) {
+      let bounds = outlined.px_bounds();
+      outlined.draw(|gx, gy, coverage| {
+        self.blend(
+          bounds.min.x as i32 + gx as i32,
+          bounds.min.y as i32 + gy as i32,
+          color,
+          coverage,
+        );
+      });
+    }
+  }
+
+  fn encode(&self) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, self.width, self.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&self.pixels)?;
+    writer.finish()?;
+    Ok(out)
+  }
+}
+
+/// `foreground` over `background` at `alpha`.
+fn mix(foreground: Color, background: Color, alpha: f32) -> Color {
+  let channel =
+    |fg: u8, bg: u8| (f32::from(bg) + (f32::from(fg) - f32::from(bg)) * alpha).round() as u8;
+  Color::new(
+    channel(foreground.red, background.red),
+    channel(foreground.green, background.green),
+    channel(foreground.blue, background.blue),
+  )
+}
+
+pub fn render(
+  listings: &[Listing<'_>],
+  theme: &ResolvedTheme,
+  options: &ImageOptions<'_>,
+) -> Result<Vec<u8>> {
+  let fonts = Fonts::new()?;
+  let scaled = fonts.regular.as_scaled(PxScale::from(FONT_SIZE));
+  let cell_width = scaled.h_advance(fonts.regular.glyph_id('M'));
+  // Centers the glyphs' full height in each line.
+  let baseline_offset =
+    (LINE_HEIGHT - (scaled.ascent() - scaled.descent())) / 2.0 + scaled.ascent();
+  let (foreground, background) = image_colors(theme);
+
+  let rows = rows(listings);
+  let columns = rows
+    .iter()
+    .map(|row| row.iter().map(|(text, _)| text.width()).sum::<usize>())
+    .max()
+    .unwrap_or(0);
+  let padding = options.padding;
+  let title = match listings {
+    [listing] if options.window_chrome => Some(listing.name.as_str()),
+    _ => None,
+  };
+  let top = if options.window_chrome {
+    CHROME_HEIGHT
+  } else {
+    0.0
+  };
+  // Room for the title centered between the buttons and the same space on the
+  // other side.
+  let title_width = title.map_or(0.0, |title| {
+    80.0 * SCALE * 2.0 + title.width() as f32 * cell_width
+  });
+  let width = ((padding.left + columns + padding.right) as f32 * cell_width)
+    .max(title_width)
+    .ceil() as u32;
+  let height =
+    (top + (padding.top + rows.len() + padding.bottom) as f32 * LINE_HEIGHT).ceil() as u32;
+  if u64::from(width) * u64::from(height) > MAX_PIXELS {
+    bail!(
+      "The image would be {}x{} pixels; select fewer lines with --lines",
+      width,
+      height
+    );
+  }
+
+  let mut canvas = Canvas::new(width, height, background);
+  if options.window_chrome {
+    for (index, color) in BUTTON_COLORS.into_iter().enumerate() {
+      let cx = (20.0 + index as f32 * 20.0) * SCALE;
+      canvas.fill_circle(cx, CHROME_HEIGHT / 2.0, 6.0 * SCALE, color);
+    }
+    if let Some(title) = title {
+      let color = mix(foreground, background, 0.7);
+      let mut x = (width as f32 - title.width() as f32 * cell_width) / 2.0;
+      let baseline = (CHROME_HEIGHT - (scaled.ascent() - scaled.descent())) / 2.0 + scaled.ascent();
+      for c in title.chars() {
+        canvas.draw_char(fonts.for_char(c, None), c, x, baseline, color);
+        x += c.width().unwrap_or(0) as f32 * cell_width;
+      }
+    }
+  }
+
+  for (index, row) in rows.iter().enumerate() {
+    let y = top + (padding.top + index) as f32 * LINE_HEIGHT;
+    let baseline = y + baseline_offset;
+    let mut column = padding.left;
+    for (text, style) in row {
+      let start = column as f32 * cell_width;
+      let length = text.width() as f32 * cell_width;
+      if let Some(bg) = style.and_then(|style| style.bg()) {
+        canvas.fill_rect(start, y, length, LINE_HEIGHT, bg);
+      }
+      let color = style.map_or(foreground, |style| style.color());
+      for c in text.chars() {
+        let Some(columns) = c.width().filter(|columns| *columns > 0) else {
+          continue;
+        };
+        let x = column as f32 * cell_width;
+        canvas.draw_char(fonts.for_char(c, *style), c, x, baseline, color);
+        column += columns;
+      }
+      if style.is_some_and(|style| style.underline()) {
+        canvas.fill_rect(start, baseline + 2.0 * SCALE, length, SCALE, color);
+      }
+      if style.is_some_and(|style| style.strikethrough()) {
+        canvas.fill_rect(
+          start,
+          baseline - scaled.ascent() * 0.3,
+          length,
+          SCALE,
+          color,
+        );
+      }
+    }
+  }
+  canvas.encode()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::padding::Padding;
+  use std::collections::BTreeMap;
+
+  #[test]
+  fn test_render() {
+    let background = Color::new(0x1e, 0x1e, 0x2e);
+    let mut styles = BTreeMap::new();
+    styles.insert(
+      "_normal".to_string(),
+      Style::new(
+        Color::new(0xcd, 0xd6, 0xf4),
+        Some(background),
+        false,
+        false,
+        false,
+        false,
+      ),
+    );
+    let theme = ResolvedTheme::new(styles);
+    let listings = [Listing {
+      name: "main.rs".to_string(),
+      lines: vec![(1, vec![("fn main() {}".to_string(), None)])],
+      theme: &theme,
+      numbers: true,
+    }];
+    let options = ImageOptions {
+      font_family: "",
+      padding: Padding {
+        top: 1,
+        right: 2,
+        bottom: 1,
+        left: 2,
+      },
+      window_chrome: false,
+    };
+    let image = render(&listings, &theme, &options).unwrap();
+
+    let decoder = png::Decoder::new(image.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    // "1 " and 12 columns of code, plus 4 of padding; 3 lines with padding.
+    let fonts = Fonts::new().unwrap();
+    let cell_width = fonts
+      .regular
+      .as_scaled(PxScale::from(FONT_SIZE))
+      .h_advance(fonts.regular.glyph_id('M'));
+    assert_eq!(info.width, (18.0 * cell_width).ceil() as u32);
+    assert_eq!(info.height, (3.0 * LINE_HEIGHT).ceil() as u32);
+    assert_eq!(
+      pixels[..3],
+      [background.red, background.green, background.blue]
+    );
+    // Some pixels are drawn in the text color.
+    assert!(pixels.chunks(3).any(|pixel| pixel == [0xcd, 0xd6, 0xf4]));
+  }
+}
@@ -0,0 +1,128 @@
+//! `http://` and `https://` arguments: the body is fetched and shown like a
+//! file, named by the URL so its extension picks the language. Fetching needs
+//! the `url` cargo feature; without it such arguments are an error.
+
+use std::io;
+use std::path::Path;
+#[cfg(feature = "url")]
+use std::time::Duration;
+
+/// Largest body that is read; longer ones are an error rather than cut off.
+#[cfg(feature = "url")]
+pub const MAX_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Time allowed for the whole request, from connecting to the end of the body.
+#[cfg(feature = "url")]
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// True for an argument naming an `http://` or `https://` URL.
+pub fn is_url(path: &Path) -> bool {
+  path
+    .to_str()
+    .is_some_and(|path| path.starts_with("https://") || path.starts_with("http://"))
+}
+
+/// A fetched body with its `Content-Type`, if the server sent one.
+pub struct Fetched {
+  pub body: Vec<u8>,
+  pub content_type: Option<String>,
+}
+
+impl Fetched {
+  /// The language the `Content-Type` names, if any.
+  pub fn language(&self) -> Option<&'static str> {
+    content_type_language(self.content_type.as_deref()?)
+  }
+}
+
+/// GET `url`, failing on error statuses, bodies over `MAX_BYTES` and requests
+/// that take longer than `TIMEOUT`.
+#[cfg(feature = "url")]
+pub fn fetch(url: &str) -> io::Result<Fetched> {
+  let agent: ureq::Agent = ureq::Agent::config_builder()
+    .timeout_global(Some(TIMEOUT))
+    .user_agent(concat!("umber/", env!("CARGO_PKG_VERSION")))
+    .build()
+    .into();
+  let mut response = agent.get(url).call().map_err(io::Error::other)?;
+  let content_type = response
+    .headers()
+    .get("content-type")
+    .and_then(|value| value.to_str().ok())
+    .map(str::to_string);
+  let body = response
+    .body_mut()
+    .with_config()
+    .limit(MAX_BYTES)
+    .read_to_vec()
+    .map_err(io::Error::other)?;
+  Ok(Fetched { body, content_type })
+}
+
+#[cfg(not(feature = "url"))]
+pub fn fetch(_url: &str) -> io::Result<Fetched> {
+  Err(io::Error::new(
+    io::ErrorKind::Unsupported,
+    "umber was built without the `url` feature",
+  ))
+}
+
+/// Language for a `Content-Type` that names one, for URLs whose path does not.
+/// Generic types such as `text/plain` and `application/octet-stream` name none.
+fn content_type_language(content_type: &str) -> Option<&'static str> {
+  let mime = content_type
+    .split(';')
+    .next()
+    .unwrap_or_default()
+    .trim()
+    .to_ascii_lowercase();
+  let subtype = mime
+    .split_once('/')
+    .map_or(mime.as_str(), |(_, subtype)| subtype);
+  // Structured syntax suffixes: `application/ld+json`, `image/svg+xml`.
+  let subtype = match subtype.rsplit_once('+') {
+    Some((_, suffix)) => suffix,
+    None => subtype.strip_prefix("x-").unwrap_or(subtype),
+  };
+  Some(match subtype {
+    "json" => "json",
+    "javascript" | "ecmascript" => "javascript",
+    "typescript" => "typescript",
+    "html" | "xhtml" => "html",
+    "css" => "css",
+    "xml" => "xml",
+    "yaml" => "yaml",
+    "toml" => "toml",
+    "markdown" => "markdown",
+    "sh" | "shellscript" => "bash",
+    "python" => "python",
+    "rust" => "rust",
+    "diff" | "patch" => "diff",
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_is_url() {
+    assert!(is_url(Path::new("https://example.com/main.rs")));
+    assert!(is_url(Path::new("http://localhost:8080/")));
+    assert!(!is_url(Path::new("https.rs")));
+    assert!(!is_url(Path::new("ftp://example.com/file")));
+  }
+
+  #[test]
+  fn test_content_type_language() {
+    assert_eq!(
+      content_type_language("application/json; charset=utf-8"),
+      Some("json")
+    );
+    assert_eq!(content_type_language("application/ld+json"), Some("json"));
+    assert_eq!(content_type_language("text/x-python"), Some("python"));
+    assert_eq!(content_type_language("Text/HTML"), Some("html"));
+    assert_eq!(content_type_language("text/plain; charset=utf-8"), None);
+  }
+}
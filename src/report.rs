@@ -0,0 +1,269 @@
+//! Error and warning reporting on stderr.
+//! Produces colored human-readable messages with hints, or one JSON object per
+//! line with `--error-format json` for tooling.
+
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use crossterm::style::Stylize;
+use serde::Serialize;
+
+use crate::i18n::{Arg, tr, tr_args};
+
+/// How diagnostics are written to stderr.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+  /// Colored, human-readable messages
+  #[default]
+  Human,
+  /// One JSON object per line
+  Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+  Error,
+  Warning,
+  Note,
+}
+
+#[derive(Serialize)]
+struct Diagnostic<'a> {
+  level: Level,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  path: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  kind: Option<&'static str>,
+  message: &'a str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  hint: Option<String>,
+}
+
+/// Writes diagnostics to stderr in the configured format.
+#[derive(Clone, Copy, Debug)]
+pub struct Reporter {
+  format: ErrorFormat,
+  color: bool,
+}
+
+impl Default for Reporter {
+  fn default() -> Self {
+    Self::new(ErrorFormat::Human, io::stderr().is_terminal())
+  }
+}
+
+impl Reporter {
+  pub fn new(format: ErrorFormat, color: bool) -> Self {
+    Self { format, color }
+  }
+
+  /// Report a failure to read an input file, with a hint when one applies.
+  pub fn file_error(&self, path: &Path, err: &io::Error) {
+    let hint = file_error_hint(path, err);
+    self.emit(Diagnostic {
+      level: Level::Error,
+      path: Some(path.display().to_string()),
+      kind: Some(io_error_kind(err)),
+      message: &err.to_string(),
+      hint,
+    });
+  }
+
+  /// Report a general error.
+  pub fn error(&self, message: &str) {
+    self.emit(Diagnostic {
+      level: Level::Error,
+      path: None,
+      kind: None,
+      message,
+      hint: None,
+    });
+  }
+
+  /// Report an error concerning a specific input path.
+  pub fn error_at(&self, path: &Path, message: &str) {
+    self.emit(Diagnostic {
+      level: Level::Error,
+      path: Some(path.display().to_string()),
+      kind: None,
+      message,
+      hint: None,
+    });
+  }
+
+  /// Report a non-fatal warning.
+  pub fn warning(&self, message: &str) {
+    self.emit(Diagnostic {
+      level: Level::Warning,
+      path: None,
+      kind: None,
+      message,
+      hint: None,
+    });
+  }
+
+  /// Report an informational message.
+  pub fn note(&self, message: &str) {
+    self.emit(Diagnostic {
+      level: Level::Note,
+      path: None,
+      kind: None,
+      message,
+      hint: None,
+    });
+  }
+
+  fn emit(&self, diagnostic: Diagnostic<'_>) {
+    let line = match self.format {
+      ErrorFormat::Json => match serde_json::to_string(&diagnostic) {
+        Ok(json) => json,
+        Err(_) => return,
+      },
+      ErrorFormat::Human => self.human(&diagnostic),
+    };
+    // Nothing sensible can be done if stderr itself is gone.
+    let _ = writeln!(io::stderr().lock(), "{line}");
+  }
+
+  fn human(&self, diagnostic: &Diagnostic<'_>) -> String {
+    let label = tr(match diagnostic.level {
+      Level::Error => "label-error",
+      Level::Warning => "label-warning",
+      Level::Note => "label-note",
+    });
+    let label = match (self.color, diagnostic.level) {
+      (false, _) => label,
+      (true, Level::Error) => label.red().bold().to_string(),
+      (true, Level::Warning) => label.yellow().bold().to_string(),
+      (true, Level::Note) => label.bold().to_string(),
+    };
+    let mut out = match &diagnostic.path {
+      Some(path) => format!("umber: {label}: {path}: {}", diagnostic.message),
+      None => format!("umber: {label}: {}", diagnostic.message),
+    };
+    if let Some(hint) = &diagnostic.hint {
+      let prefix = tr("label-hint");
+      let prefix = if self.color {
+        prefix.cyan().to_string()
+      } else {
+        prefix
+      };
+      out.push_str(&format!("\n  {prefix}: {hint}"));
+    }
+    out
+  }
+}
+
+fn io_error_kind(err: &io::Error) -> &'static str {
+  match err.kind() {
+    io::ErrorKind::NotFound => "not_found",
+    io::ErrorKind::PermissionDenied => "permission_denied",
+    io::ErrorKind::IsADirectory => "is_a_directory",
+    io::ErrorKind::InvalidData => "invalid_data",
+    _ => "io",
+  }
+}
+
+fn file_error_hint(path: &Path, err: &io::Error) -> Option<String> {
+  match err.kind() {
+    io::ErrorKind::NotFound => {
+      nearest_sibling(path).map(|name| tr_args("hint-did-you-mean", &[("name", Arg::Str(&name))]))
+    }
+    io::ErrorKind::PermissionDenied => Some(tr_args(
+      "hint-permissions",
+      &[("path", Arg::Str(&path.display().to_string()))],
+    )),
+    io::ErrorKind::IsADirectory => Some(tr_args(
+      "hint-directory",
+      &[("path", Arg::Str(&path.display().to_string()))],
+    )),
+    _ => None,
+  }
+}
+
+/// Find the entry in the same directory whose name is closest to the missing one.
+fn nearest_sibling(path: &Path) -> Option<String> {
+  let name = path.file_name()?.to_str()?;
+  let parent = match path.parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent,
+    _ => Path::new("."),
+  };
+  let candidates = fs::read_dir(parent)
+    .ok()?
+    .filter_map(|entry| entry.ok()?.file_name().into_string().ok());
+  let suggestion = closest_name(name, candidates)?;
+  if parent == Path::new(".") && path.parent().is_none_or(|p| p.as_os_str().is_empty()) {
+    Some(suggestion)
+  } else {
+    Some(parent.join(suggestion).display().to_string())
+  }
+}
+
+/// Pick the candidate with the smallest edit distance, if it is close enough to be a typo.
+pub fn closest_name(name: &str, candidates: impl Iterator<Item = String>) -> Option<String> {
+  let max_distance = (name.chars().count() / 3).max(2);
+  candidates
+    .map(|candidate| (edit_distance(name, &candidate), candidate))
+    .filter(|(distance, _)| *distance <= max_distance)
+    .min_by(|(a, a_name), (b, b_name)| a.cmp(b).then_with(|| a_name.cmp(b_name)))
+    .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+  let mut current = vec![0; b.len() + 1];
+  for (i, ca) in a.chars().enumerate() {
+    current[0] = i + 1;
+    for (j, cb) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(ca != *cb);
+      current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+    }
+    std::mem::swap(&mut previous, &mut current);
+  }
+  previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_edit_distance() {
+    assert_eq!(edit_distance("main.rs", "main.rs"), 0);
+    assert_eq!(edit_distance("mian.rs", "main.rs"), 2);
+    assert_eq!(edit_distance("", "abc"), 3);
+  }
+
+  #[test]
+  fn test_closest_name() {
+    let candidates = ["main.rs", "lib.rs", "Cargo.toml"].map(String::from);
+    assert_eq!(
+      closest_name("mainn.rs", candidates.clone().into_iter()),
+      Some("main.rs".to_string())
+    );
+    assert_eq!(closest_name("unrelated.py", candidates.into_iter()), None);
+  }
+
+  #[test]
+  fn test_human_format_without_color() {
+    let reporter = Reporter::new(ErrorFormat::Human, false);
+    let diagnostic = Diagnostic {
+      level: Level::Error,
+      path: Some("a.rs".to_string()),
+      kind: Some("not_found"),
+      message: "No such file",
+      hint: Some("did you mean 'b.rs'?".to_string()),
+    };
+    let expected = format!(
+      "umber: {}: a.rs: No such file\n  {}: did you mean 'b.rs'?",
+      tr("label-error"),
+      tr("label-hint")
+    );
+    assert_eq!(reporter.human(&diagnostic), expected);
+  }
+}
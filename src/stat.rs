@@ -0,0 +1,228 @@
+//! `--stat`: files, lines and bytes per detected language.
+//! A quick overview of a source tree using the same detection as highlighting.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use syntastica::renderer::Renderer;
+use syntastica::theme::ResolvedTheme;
+
+use crate::color_depth::DepthRenderer;
+use crate::decorations;
+
+/// Name used for files whose language could not be detected.
+pub const PLAIN_TEXT: &str = "text";
+
+/// Name used for files that look binary.
+pub const BINARY: &str = "binary";
+
+/// Counts for one language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+  pub files: u64,
+  pub lines: u64,
+  pub bytes: u64,
+}
+
+impl Totals {
+  fn add(&mut self, other: Totals) {
+    self.files += other.files;
+    self.lines += other.lines;
+    self.bytes += other.bytes;
+  }
+}
+
+/// Totals keyed by language name.
+#[derive(Debug, Default)]
+pub struct LanguageStats {
+  by_language: HashMap<String, Totals>,
+}
+
+impl LanguageStats {
+  /// Count one file of `language`.
+  pub fn add(&mut self, language: &str, content: &[u8]) {
+    let totals = Totals {
+      files: 1,
+      lines: count_lines(content),
+      bytes: content.len() as u64,
+    };
+    self
+      .by_language
+      .entry(language.to_string())
+      .or_default()
+      .add(totals);
+  }
+
+  /// Languages ordered by line count, largest first.
+  pub fn rows(&self) -> Vec<(&str, Totals)> {
+    let mut rows: Vec<(&str, Totals)> = self
+      .by_language
+      .iter()
+      .map(|(language, totals)| (language.as_str(), *totals))
+      .collect();
+    rows.sort_by(|(a_name, a), (b_name, b)| b.lines.cmp(&a.lines).then_with(|| a_name.cmp(b_name)));
+    rows
+  }
+
+  pub fn total(&self) -> Totals {
+    let mut total = Totals::default();
+    for totals in self.by_language.values() {
+      total.add(*totals);
+    }
+    total
+  }
+}
+
+/// Lines in `content`, counting a final line without a newline.
+fn count_lines(content: &[u8]) -> u64 {
+  let newlines = content.iter().filter(|byte| **byte == b'\n').count() as u64;
+  newlines + u64::from(content.last().is_some_and(|byte| *byte != b'\n'))
+}
+
+/// Bytes at the start of an input that decide whether it looks binary.
+pub const SNIFF_BYTES: usize = 8000;
+
+/// True when the start of `content` contains a NUL byte, like git's heuristic,
+/// or when more than 30% of it is not valid UTF-8. Stray Latin-1 bytes in a
+/// text file do not count; random data is mostly invalid.
+pub fn looks_binary(content: &[u8]) -> bool {
+  let head = &content[..content.len().min(SNIFF_BYTES)];
+  if head.contains(&0) {
+    return true;
+  }
+  let mut invalid = 0;
+  let mut rest = head;
+  while let Err(err) = std::str::from_utf8(rest) {
+    // A character cut off at the end of the sniffed bytes is not an error.
+    let Some(len) = err.error_len() else {
+      break;
+    };
+    invalid += len;
+    rest = &rest[err.valid_up_to() + len..];
+  }
+  invalid * 10 > head.len() * 3
+}
+
+/// `looks_binary` for the start of `file`, which is rewound afterwards.
+pub fn file_looks_binary(mut file: &fs::File) -> io::Result<bool> {
+  let mut head = Vec::with_capacity(SNIFF_BYTES);
+  file.take(SNIFF_BYTES as u64).read_to_end(&mut head)?;
+  file.rewind()?;
+  Ok(looks_binary(&head))
+}
+
+/// Add the regular files at or below `path` to `files`. Hidden entries inside
+/// directories (such as `.git`) are skipped; a hidden path given directly is not.
+pub fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+  if !fs::metadata(path)?.is_dir() {
+    files.push(path.to_path_buf());
+    return Ok(());
+  }
+  let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+    .filter_map(|entry| entry.ok())
+    .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+    .map(|entry| entry.path())
+    .collect();
+  entries.sort();
+  for entry in entries {
+    let file_type = fs::symlink_metadata(&entry)?.file_type();
+    if file_type.is_dir() {
+      collect_files(&entry, files)?;
+    } else if file_type.is_file() {
+      files.push(entry);
+    }
+  }
+  Ok(())
+}
+
+/// Print the statistics as a table. With a renderer, language names are
+/// colored with the theme's accent colors.
+pub fn write_table(
+  out: &mut impl Write,
+  stats: &LanguageStats,
+  mut color: Option<(&mut DepthRenderer, &ResolvedTheme)>,
+) -> io::Result<()> {
+  let rows = stats.rows();
+  let total = stats.total();
+  let name_width = rows
+    .iter()
+    .map(|(language, _)| language.len())
+    .max()
+    .unwrap_or(0)
+    .max("Language".len());
+
+  writeln!(
+    out,
+    "{:<name_width$}  {:>7}  {:>9}  {:>10}",
+    "Language", "Files", "Lines", "Bytes"
+  )?;
+  let rule = "─".repeat(name_width + 34);
+  writeln!(out, "{rule}")?;
+  for (index, (language, totals)) in rows.iter().enumerate() {
+    let name = format!("{language:<name_width$}");
+    let name = match color.as_mut() {
+      Some((renderer, theme)) => {
+        let escaped = renderer.escape(&name);
+        renderer
+          .styled(&escaped, decorations::accent_style(index, theme))
+          .into_owned()
+      }
+      None => name,
+    };
+    writeln!(
+      out,
+      "{name}  {:>7}  {:>9}  {:>10}",
+      totals.files,
+      totals.lines,
+      crate::format_size(totals.bytes)
+    )?;
+  }
+  writeln!(out, "{rule}")?;
+  writeln!(
+    out,
+    "{:<name_width$}  {:>7}  {:>9}  {:>10}",
+    "Total",
+    total.files,
+    total.lines,
+    crate::format_size(total.bytes)
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rows_sorted_by_lines() {
+    let mut stats = LanguageStats::default();
+    stats.add("rust", b"fn main() {}\n");
+    stats.add("toml", b"[package]\nname = \"x\"\nversion = \"1\"");
+    stats.add("rust", b"\n");
+    let rows = stats.rows();
+    assert_eq!(rows[0].0, "toml");
+    assert_eq!(
+      rows[0].1,
+      Totals {
+        files: 1,
+        lines: 3,
+        bytes: 34
+      }
+    );
+    assert_eq!(rows[1].0, "rust");
+    assert_eq!(rows[1].1.files, 2);
+    assert_eq!(rows[1].1.lines, 2);
+    assert_eq!(stats.total().files, 3);
+  }
+
+  #[test]
+  fn test_looks_binary() {
+    assert!(!looks_binary(b"fn main() {}\n"));
+    assert!(looks_binary(b"PK\x03\x04\0\0"));
+    // One Latin-1 byte in a line of text, and a character cut off at the end.
+    assert!(!looks_binary(b"caf\xe9 au lait, s'il vous pla\xc3"));
+    assert!(!looks_binary(b"caf\xe9\n"));
+    assert!(looks_binary(b"\x89PNG\xff\xfe\xfd\xfc"));
+  }
+}
@@ -0,0 +1,469 @@
+//! Incremental input for pipes, FIFOs, other non-regular files and files too
+//! large to hold in memory.
+//! Hands on complete lines as soon as they arrive instead of waiting for EOF,
+//! keeping `--lines` and `--squeeze-blank` state across chunks.
+
+use std::collections::VecDeque;
+use std::fs::{self, Metadata};
+use std::io::{self, Read};
+
+use crate::ranges::LineSelection;
+
+const READ_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Regular files at least this large are highlighted in windows instead of
+/// being read into memory at once.
+pub const CHUNKED_MIN_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Amount of a file parsed and rendered at a time in chunked mode. Windows
+/// end at a top-level boundary where one can be found, but constructs spanning
+/// a window boundary, such as long block comments, may still be highlighted
+/// incorrectly.
+pub const WINDOW_BYTES: usize = 4 * 1024 * 1024;
+
+/// Width line numbers are padded to when the length of the input is not
+/// known up front, as with `cat -n`, so the gutter does not widen as lines
+/// arrive.
+pub const LINE_NUMBER_WIDTH: usize = 6;
+
+/// True for inputs that may never reach EOF on their own, such as named pipes,
+/// process substitution (`<(cmd)`), sockets and character devices.
+pub fn is_streamable(metadata: &Metadata) -> bool {
+  let file_type = metadata.file_type();
+  !file_type.is_file() && !file_type.is_dir()
+}
+
+/// What stdin is connected to, to tell a redirected file, which is read
+/// whole like any other file, from a pipe or a terminal. `None` where that
+/// cannot be told.
+#[cfg(unix)]
+pub fn stdin_metadata() -> Option<Metadata> {
+  use std::os::fd::AsFd;
+  let fd = io::stdin().as_fd().try_clone_to_owned().ok()?;
+  fs::File::from(fd).metadata().ok()
+}
+
+#[cfg(not(unix))]
+pub fn stdin_metadata() -> Option<Metadata> {
+  None
+}
+
+/// Up to `limit` bytes from a single read of `reader`: whatever a pipe has
+/// ready, so its start can be sniffed without waiting for more. Empty at EOF.
+pub fn read_head(reader: &mut impl Read, limit: usize) -> io::Result<Vec<u8>> {
+  let mut head = vec![0; limit];
+  loop {
+    match reader.read(&mut head) {
+      Ok(read) => {
+        head.truncate(read);
+        return Ok(head);
+      }
+      Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+      Err(err) => return Err(err),
+    }
+  }
+}
+
+/// Number of lines in `reader`, counting an unterminated last one.
+pub fn count_lines(mut reader: impl Read) -> io::Result<usize> {
+  let mut buf = vec![0; READ_BUFFER_BYTES];
+  let mut lines = 0;
+  let mut last = b'\n';
+  loop {
+    let read = match reader.read(&mut buf) {
+      Ok(0) => break,
+      Ok(read) => read,
+      Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+      Err(err) => return Err(err),
+    };
+    lines += buf[..read].iter().filter(|byte| **byte == b'\n').count();
+    last = buf[read - 1];
+  }
+  Ok(if last == b'\n' { lines } else { lines + 1 })
+}
+
+/// Splits a reader into runs of complete lines as data becomes available.
+/// A line longer than the read buffer is handed on in pieces of that size
+/// rather than held whole, so input without newlines still streams.
+pub struct LineChunks<R> {
+  reader: R,
+  pending: Vec<u8>,
+  buf: Vec<u8>,
+  eof: bool,
+  /// End chunks at a top-level boundary rather than at any line
+  top_level: bool,
+}
+
+impl<R: Read> LineChunks<R> {
+  pub fn new(reader: R) -> Self {
+    Self {
+      reader,
+      pending: Vec::new(),
+      buf: vec![0; READ_BUFFER_BYTES],
+      eof: false,
+      top_level: false,
+    }
+  }
+
+  /// Read up to `window_bytes` at a time. Chunks end where a blank line is
+  /// followed by an unindented one, such as between two functions, so that
+  /// each is parsed with what it belongs to; input without such places, or
+  /// with none in two windows, is split at a line boundary, or within a line
+  /// longer than a window.
+  pub fn with_window(reader: R, window_bytes: usize) -> Self {
+    Self {
+      buf: vec![0; window_bytes],
+      top_level: true,
+      ..Self::new(reader)
+    }
+  }
+
+  /// The complete lines read so far, or the unterminated last line once the
+  /// input ends. Returns `None` when everything has been handed out.
+  pub fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+    loop {
+      if self.eof {
+        return Ok((!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending)));
+      }
+      let read = match self.reader.read(&mut self.buf) {
+        Ok(read) => read,
+        Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+        Err(err) => return Err(err),
+      };
+      if read == 0 {
+        self.eof = true;
+        continue;
+      }
+      self.pending.extend_from_slice(&self.buf[..read]);
+      if let Some(end) = self.chunk_end() {
+        let rest = self.pending.split_off(end);
+        return Ok(Some(std::mem::replace(&mut self.pending, rest)));
+      }
+    }
+  }
+
+  /// Where the chunk read so far can end, or `None` to read on.
+  fn chunk_end(&self) -> Option<usize> {
+    if self.top_level {
+      if let Some(end) = top_level_boundary(&self.pending) {
+        return Some(end);
+      }
+      if self.pending.len() < 2 * self.buf.len() {
+        return None;
+      }
+    }
+    match self.pending.iter().rposition(|byte| *byte == b'\n') {
+      Some(last_newline) => Some(last_newline + 1),
+      None => {
+        (self.pending.len() >= self.buf.len()).then(|| char_boundary(&self.pending, self.buf.len()))
+      }
+    }
+  }
+}
+
+/// `at`, moved back to the start of the UTF-8 character it falls in, if any.
+fn char_boundary(bytes: &[u8], at: usize) -> usize {
+  (at.saturating_sub(3)..=at)
+    .rev()
+    .find(|&index| index > 0 && bytes.get(index).is_none_or(|byte| byte & 0xc0 != 0x80))
+    .unwrap_or(at)
+}
+
+/// Start of the last line in `bytes` that follows a blank line and begins in
+/// the first column with something other than a closing bracket: most likely
+/// a new top-level item, and a place where no construct is left open.
+fn top_level_boundary(bytes: &[u8]) -> Option<usize> {
+  let mut end = bytes.len();
+  while let Some(newline) = bytes[..end].iter().rposition(|byte| *byte == b'\n') {
+    let before = &bytes[..newline];
+    let blank_before = before
+      .strip_suffix(b"\r")
+      .unwrap_or(before)
+      .ends_with(b"\n");
+    let starts_item = bytes
+      .get(newline + 1)
+      .is_some_and(|byte| !byte.is_ascii_whitespace() && !matches!(byte, b'}' | b')' | b']'));
+    if blank_before && starts_item {
+      return Some(newline + 1);
+    }
+    end = newline;
+  }
+  None
+}
+
+/// Applies line ranges and blank-line squeezing to consecutive chunks.
+/// Ranges counted from the end hold back that many lines until the input ends.
+pub struct LineFilter {
+  selection: LineSelection,
+  /// Known end of the selection, to stop reading early
+  last_line: Option<usize>,
+  squeeze_limit: Option<usize>,
+  input_line: usize,
+  /// The last lines read, not yet known to be outside the ranges counted from
+  /// the end
+  held: VecDeque<Vec<u8>>,
+  hold: usize,
+  /// Set once the input ended and the held back lines were handed on
+  finished: bool,
+  blank_run: usize,
+  /// Last line that was selected, to tell where a new run starts
+  last_selected: usize,
+  /// First line of the run being shown
+  run_start: usize,
+  /// Lines shown since `run_start`
+  output_lines: usize,
+  /// Set when the last chunk ended within a line, which the next one goes on
+  partial: bool,
+}
+
+impl LineFilter {
+  pub fn new(selection: LineSelection, squeeze_limit: Option<usize>) -> Self {
+    let hold = selection.reach_from_end();
+    Self {
+      last_line: selection.last_line(),
+      selection,
+      squeeze_limit,
+      input_line: 0,
+      held: VecDeque::with_capacity(hold),
+      hold,
+      finished: false,
+      blank_run: 0,
+      last_selected: 0,
+      run_start: 1,
+      output_lines: 0,
+      partial: false,
+    }
+  }
+
+  /// Line number to display for the next line that passes the filter.
+  fn next_line_number(&self) -> usize {
+    self.run_start + self.output_lines
+  }
+
+  /// True once the input is past the end of the last range.
+  pub fn is_done(&self) -> bool {
+    self.last_line.is_some_and(|end| self.input_line >= end)
+  }
+
+  /// Append the lines of `chunk` that should be shown to `out`, one run per
+  /// stretch of consecutive selected lines with the number of its first line.
+  pub fn push(&mut self, chunk: &[u8], out: &mut Vec<(usize, Vec<u8>)>) {
+    let mut run_open = false;
+    for line in chunk.split_inclusive(|byte| *byte == b'\n') {
+      let continued = std::mem::replace(&mut self.partial, !line.ends_with(b"\n"));
+      if !continued {
+        self.input_line += 1;
+      }
+      if self.last_line.is_some_and(|end| self.input_line > end) {
+        return;
+      }
+      if self.hold == 0 {
+        if continued {
+          self.select_rest(self.input_line, line, out, &mut run_open);
+        } else {
+          self.select(self.input_line, line, None, out, &mut run_open);
+        }
+        continue;
+      }
+      if continued && let Some(held) = self.held.back_mut() {
+        held.extend_from_slice(line);
+        continue;
+      }
+      self.held.push_back(line.to_vec());
+      if self.held.len() > self.hold
+        && let Some(line) = self.held.pop_front()
+      {
+        let line_no = self.input_line - self.hold;
+        self.select(line_no, &line, None, out, &mut run_open);
+      }
+    }
+  }
+
+  /// Append the held back lines that should be shown, once the input ended.
+  pub fn finish(&mut self, out: &mut Vec<(usize, Vec<u8>)>) {
+    let total = self.input_line;
+    self.finished = true;
+    let first = total - self.held.len() + 1;
+    let mut run_open = false;
+    for (offset, line) in std::mem::take(&mut self.held).into_iter().enumerate() {
+      self.select(first + offset, &line, Some(total), out, &mut run_open);
+    }
+  }
+
+  /// Lines in the input, once it ended.
+  pub fn total(&self) -> Option<usize> {
+    self.finished.then_some(self.input_line)
+  }
+
+  fn select(
+    &mut self,
+    line_no: usize,
+    line: &[u8],
+    total: Option<usize>,
+    out: &mut Vec<(usize, Vec<u8>)>,
+    run_open: &mut bool,
+  ) {
+    if !self.selection.contains(line_no, total) {
+      return;
+    }
+    if line_no != self.last_selected + 1 {
+      self.run_start = line_no;
+      self.output_lines = 0;
+      self.blank_run = 0;
+      *run_open = false;
+    }
+    self.last_selected = line_no;
+    if let Some(limit) = self.squeeze_limit {
+      let content = line.strip_suffix(b"\n").unwrap_or(line);
+      let content = content.strip_suffix(b"\r").unwrap_or(content);
+      if content.is_empty() {
+        self.blank_run += 1;
+        if self.blank_run > limit {
+          return;
+        }
+      } else {
+        self.blank_run = 0;
+      }
+    }
+    if !*run_open {
+      out.push((self.next_line_number(), Vec::new()));
+      *run_open = true;
+    }
+    if let Some((_, run)) = out.last_mut() {
+      run.extend_from_slice(line);
+    }
+    self.output_lines += 1;
+  }
+
+  /// Append the rest of a line split across chunks, under the number its
+  /// start was shown with.
+  fn select_rest(
+    &mut self,
+    line_no: usize,
+    rest: &[u8],
+    out: &mut Vec<(usize, Vec<u8>)>,
+    run_open: &mut bool,
+  ) {
+    if self.last_selected == line_no {
+      out.push((self.next_line_number() - 1, rest.to_vec()));
+      *run_open = true;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ranges;
+
+  /// A reader that returns the given pieces one `read` call at a time.
+  struct Pieces(Vec<&'static [u8]>);
+
+  impl Read for Pieces {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      if self.0.is_empty() {
+        return Ok(0);
+      }
+      let piece = self.0.remove(0);
+      buf[..piece.len()].copy_from_slice(piece);
+      Ok(piece.len())
+    }
+  }
+
+  #[test]
+  fn test_line_chunks_hold_back_partial_lines() {
+    let mut chunks = LineChunks::new(Pieces(vec![b"one\ntw", b"o\nthree"]));
+    assert_eq!(chunks.next_chunk().unwrap(), Some(b"one\n".to_vec()));
+    assert_eq!(chunks.next_chunk().unwrap(), Some(b"two\n".to_vec()));
+    assert_eq!(chunks.next_chunk().unwrap(), Some(b"three".to_vec()));
+    assert_eq!(chunks.next_chunk().unwrap(), None);
+  }
+
+  #[test]
+  fn test_line_chunks_split_long_lines() {
+    let mut chunks = LineChunks::with_window(Pieces(vec![b"abcd", b"efgh", b"ij\nk"]), 4);
+    assert_eq!(chunks.next_chunk().unwrap(), Some(b"abcd".to_vec()));
+    assert_eq!(chunks.next_chunk().unwrap(), Some(b"efghij\n".to_vec()));
+    assert_eq!(chunks.next_chunk().unwrap(), Some(b"k".to_vec()));
+    assert_eq!(char_boundary("aé".as_bytes(), 2), 1);
+
+    let mut filter = LineFilter::new(LineSelection::default(), None);
+    let mut out = Vec::new();
+    filter.push(b"abcd", &mut out);
+    filter.push(b"efghij\n", &mut out);
+    filter.push(b"k", &mut out);
+    assert_eq!(
+      out,
+      vec![
+        (1, b"abcd".to_vec()),
+        (1, b"efghij\n".to_vec()),
+        (2, b"k".to_vec())
+      ]
+    );
+    assert_eq!(filter.input_line, 2);
+  }
+
+  #[test]
+  fn test_count_lines() {
+    assert_eq!(count_lines(Pieces(vec![])).unwrap(), 0);
+    assert_eq!(count_lines(Pieces(vec![b"one\ntw", b"o\n"])).unwrap(), 2);
+    assert_eq!(count_lines(Pieces(vec![b"one\n", b"two"])).unwrap(), 2);
+  }
+
+  #[test]
+  fn test_windows_end_at_top_level_boundaries() {
+    assert_eq!(top_level_boundary(b"fn a() {\n\n  x\n}\n\nfn b"), Some(17));
+    assert_eq!(top_level_boundary(b"a\r\n\r\nb\n"), Some(5));
+    assert_eq!(top_level_boundary(b"f {\n\n}\n"), None);
+    assert_eq!(top_level_boundary(b"one\ntwo\n"), None);
+
+    let source: &'static [u8] = b"fn a() {\n\n  x\n}\n\nfn b() {}\n";
+    let mut chunks = LineChunks::with_window(Pieces(vec![&source[..22], &source[22..]]), 32);
+    assert_eq!(chunks.next_chunk().unwrap(), Some(source[..17].to_vec()));
+    assert_eq!(chunks.next_chunk().unwrap(), Some(source[17..].to_vec()));
+    assert_eq!(chunks.next_chunk().unwrap(), None);
+  }
+
+  #[test]
+  fn test_line_filter_across_chunks() {
+    let selection = LineSelection::new(ranges::parse("2-6").unwrap());
+    let mut filter = LineFilter::new(selection, Some(1));
+    let mut out = Vec::new();
+    filter.push(b"a\nb\n\n", &mut out);
+    assert_eq!(filter.next_line_number(), 4);
+    filter.push(b"\n\nc\nd\n", &mut out);
+    assert_eq!(out, vec![(2, b"b\n\n".to_vec()), (4, b"c\n".to_vec())]);
+    assert!(filter.is_done());
+  }
+
+  #[test]
+  fn test_line_filter_splits_runs_per_range() {
+    let selection = LineSelection::new(ranges::parse("1,3-4").unwrap());
+    let mut filter = LineFilter::new(selection, None);
+    let mut out = Vec::new();
+    filter.push(b"a\nb\nc\n", &mut out);
+    filter.push(b"d\ne\n", &mut out);
+    assert_eq!(
+      out,
+      vec![
+        (1, b"a\n".to_vec()),
+        (3, b"c\n".to_vec()),
+        (4, b"d\n".to_vec())
+      ]
+    );
+  }
+
+  #[test]
+  fn test_line_filter_holds_back_lines_counted_from_end() {
+    let selection = LineSelection::new(ranges::parse("1,-2:").unwrap());
+    let mut filter = LineFilter::new(selection, None);
+    let mut out = Vec::new();
+    filter.push(b"a\nb\nc\nd\n", &mut out);
+    assert_eq!(out, vec![(1, b"a\n".to_vec())]);
+    out.clear();
+    filter.push(b"e\n", &mut out);
+    assert!(out.is_empty());
+    filter.finish(&mut out);
+    assert_eq!(out, vec![(4, b"d\ne\n".to_vec())]);
+  }
+}
@@ -0,0 +1,80 @@
+//! `--italic-text`, `--no-bold` and `--no-underline`: attributes removed from
+//! theme styles, for terminals that render them poorly or substitute reverse
+//! video.
+
+use clap::ValueEnum;
+use syntastica::style::Style;
+use syntastica::theme::ResolvedTheme;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ItalicText {
+  /// Keep the theme's italics
+  #[default]
+  Always,
+  /// Render italic text upright
+  Never,
+}
+
+/// Which attributes theme styles may keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyleFilter {
+  pub italic: bool,
+  pub bold: bool,
+  pub underline: bool,
+}
+
+impl Default for StyleFilter {
+  fn default() -> Self {
+    Self {
+      italic: true,
+      bold: true,
+      underline: true,
+    }
+  }
+}
+
+impl StyleFilter {
+  pub fn apply(self, style: Style) -> Style {
+    Style::new(
+      style.color(),
+      style.bg(),
+      style.underline() && self.underline,
+      style.strikethrough(),
+      style.italic() && self.italic,
+      style.bold() && self.bold,
+    )
+  }
+
+  /// `theme` with every style filtered.
+  pub fn apply_theme(self, theme: ResolvedTheme) -> ResolvedTheme {
+    if self == Self::default() {
+      return theme;
+    }
+    let styles = theme
+      .into_inner()
+      .into_iter()
+      .map(|(key, style)| (key, self.apply(style)))
+      .collect();
+    ResolvedTheme::new(styles)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use syntastica::style::Color;
+
+  #[test]
+  fn test_apply() {
+    let style = Style::new(Color::new(1, 2, 3), None, true, true, true, true);
+    let filter = StyleFilter {
+      italic: false,
+      bold: true,
+      underline: false,
+    };
+    let filtered = filter.apply(style);
+    assert!(!filtered.italic() && !filtered.underline());
+    assert!(filtered.bold() && filtered.strikethrough());
+    assert_eq!(filtered.color(), style.color());
+  }
+}
@@ -0,0 +1,259 @@
+//! `--format svg`: a standalone image of the highlighted code, in the theme's
+//! colors on its background, optionally framed as a window with a title bar.
+//! Text stays text, so the image is small, sharp at any size and searchable.
+
+use syntastica::style::{Color, Style};
+use syntastica::theme::ResolvedTheme;
+use unicode_width::UnicodeWidthStr;
+
+use crate::listing::{Listing, image_colors, rows};
+use crate::padding::Padding;
+
+/// Font used when `--font-family` is not given, as a CSS `font-family`.
+pub const DEFAULT_FONT_FAMILY: &str =
+  "ui-monospace, SFMono-Regular, Menlo, Consolas, 'DejaVu Sans Mono', monospace";
+
+const FONT_SIZE: f64 = 14.0;
+/// Advance of one column: monospace fonts are close to 0.6em wide.
+const CHAR_WIDTH: f64 = FONT_SIZE * 0.6;
+const LINE_HEIGHT: f64 = FONT_SIZE * 1.5;
+/// Height of the title bar drawn with `--window-chrome`.
+const CHROME_HEIGHT: f64 = 36.0;
+/// Size of the file name in the title bar.
+const TITLE_FONT_SIZE: f64 = 13.0;
+/// Colors of the close, minimize and zoom buttons in the title bar.
+const BUTTON_COLORS: [&str; 3] = ["#ff5f56", "#ffbd2e", "#27c93f"];
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageOptions<'a> {
+  /// CSS `font-family` of the code.
+  pub font_family: &'a str,
+  /// Margin around the code, in columns and lines.
+  pub padding: Padding,
+  /// Draw a title bar with window buttons and the file name.
+  pub window_chrome: bool,
+}
+
+fn hex(color: Color) -> String {
+  format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+}
+
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// `<tspan>` attributes drawing text in `style`.
+fn style_attributes(style: Style) -> String {
+  let mut attributes = format!(" fill=\"{}\"", hex(style.color()));
+  if style.bold() {
+    attributes.push_str(" font-weight=\"bold\"");
+  }
+  if style.italic() {
+    attributes.push_str(" font-style=\"italic\"");
+  }
+  let decorations: Vec<&str> = [
+    (style.underline(), "underline"),
+    (style.strikethrough(), "line-through"),
+  ]
+  .into_iter()
+  .filter_map(|(set, name)| set.then_some(name))
+  .collect();
+  if !decorations.is_empty() {
+    attributes.push_str(&format!(" text-decoration=\"{}\"", decorations.join(" ")));
+  }
+  attributes
+}
+
+pub fn render(
+  listings: &[Listing<'_>],
+  theme: &ResolvedTheme,
+  options: &ImageOptions<'_>,
+) -> String {
+  let (foreground, background) = image_colors(theme);
+
+  let rows = rows(listings);
+  let columns = rows
+    .iter()
+    .map(|row| row.iter().map(|(text, _)| text.width()).sum::<usize>())
+    .max()
+    .unwrap_or(0);
+  let padding = options.padding;
+  let top = if options.window_chrome {
+    CHROME_HEIGHT
+  } else {
+    0.0
+  };
+  let title = match listings {
+    [listing] if options.window_chrome => Some(listing.name.as_str()),
+    _ => None,
+  };
+  // Room for the title centered between the buttons and the same space on the
+  // other side.
+  let title_width = title.map_or(0.0, |title| {
+    80.0 * 2.0 + title.width() as f64 * TITLE_FONT_SIZE * 0.6
+  });
+  let width = ((padding.left + columns + padding.right) as f64 * CHAR_WIDTH).max(title_width);
+  let height = top + (padding.top + rows.len() + padding.bottom) as f64 * LINE_HEIGHT;
+
+  let mut out = format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.1}\" height=\"{height:.1}\" \
+     viewBox=\"0 0 {width:.1} {height:.1}\">\n\
+     <rect width=\"100%\" height=\"100%\" rx=\"8\" fill=\"{}\"/>\n",
+    hex(background)
+  );
+  if options.window_chrome {
+    for (index, color) in BUTTON_COLORS.iter().enumerate() {
+      out.push_str(&format!(
+        "<circle cx=\"{}\" cy=\"{}\" r=\"6\" fill=\"{color}\"/>\n",
+        20 + index * 20,
+        CHROME_HEIGHT / 2.0
+      ));
+    }
+    if let Some(title) = title {
+      out.push_str(&format!(
+        "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"middle\" \
+         font-family=\"sans-serif\" font-size=\"{TITLE_FONT_SIZE}\" fill=\"{}\" fill-opacity=\"0.7\">{}</text>\n",
+        width / 2.0,
+        CHROME_HEIGHT / 2.0,
+        hex(foreground),
+        escape(title)
+      ));
+    }
+  }
+
+  out.push_str(&format!(
+    "<g font-family=\"{}\" font-size=\"{FONT_SIZE}\" fill=\"{}\">\n",
+    escape(options.font_family),
+    hex(foreground)
+  ));
+  for (index, row) in rows.iter().enumerate() {
+    if row.is_empty() {
+      continue;
+    }
+    let y = top + (padding.top + index) as f64 * LINE_HEIGHT;
+    let x = padding.left as f64 * CHAR_WIDTH;
+    // Backgrounds of single pieces go under the text.
+    let mut column = 0;
+    for (text, style) in row {
+      let columns = text.width();
+      if let Some(bg) = style.and_then(|style| style.bg()) {
+        out.push_str(&format!(
+          "<rect x=\"{:.1}\" y=\"{y:.1}\" width=\"{:.1}\" height=\"{LINE_HEIGHT:.1}\" fill=\"{}\"/>\n",
+          x + column as f64 * CHAR_WIDTH,
+          columns as f64 * CHAR_WIDTH,
+          hex(bg)
+        ));
+      }
+      column += columns;
+    }
+    out.push_str(&format!(
+      "<text x=\"{x:.1}\" y=\"{:.1}\" xml:space=\"preserve\">",
+      y + LINE_HEIGHT * 0.72
+    ));
+    for (text, style) in row {
+      match style {
+        Some(style) => out.push_str(&format!(
+          "<tspan{}>{}</tspan>",
+          style_attributes(*style),
+          escape(text)
+        )),
+        None => out.push_str(&escape(text)),
+      }
+    }
+    out.push_str("</text>\n");
+  }
+  out.push_str("</g>\n</svg>\n");
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::BTreeMap;
+
+  const OPTIONS: ImageOptions<'static> = ImageOptions {
+    font_family: DEFAULT_FONT_FAMILY,
+    padding: Padding {
+      top: 1,
+      right: 2,
+      bottom: 1,
+      left: 2,
+    },
+    window_chrome: false,
+  };
+
+  fn theme() -> ResolvedTheme {
+    let mut styles = BTreeMap::new();
+    styles.insert(
+      "_normal".to_string(),
+      Style::new(
+        Color::new(0xcd, 0xd6, 0xf4),
+        Some(Color::new(0x1e, 0x1e, 0x2e)),
+        false,
+        false,
+        false,
+        false,
+      ),
+    );
+    styles.insert(
+      "comment".to_string(),
+      Style::new(
+        Color::new(0x6c, 0x70, 0x86),
+        None,
+        false,
+        false,
+        true,
+        false,
+      ),
+    );
+    ResolvedTheme::new(styles)
+  }
+
+  fn listing(theme: &ResolvedTheme) -> Listing<'_> {
+    Listing {
+      name: "a<b>.rs".to_string(),
+      lines: vec![
+        (1, vec![("// hi".to_string(), Some("comment"))]),
+        (2, vec![("\tx & y".to_string(), None)]),
+      ],
+      theme,
+      numbers: false,
+    }
+  }
+
+  #[test]
+  fn test_render() {
+    let theme = theme();
+    let svg = render(&[listing(&theme)], &theme, &OPTIONS);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains("fill=\"#1e1e2e\""));
+    assert!(svg.contains("<tspan fill=\"#6c7086\" font-style=\"italic\">// hi</tspan>"));
+    assert!(svg.contains(">        x &amp; y</text>"));
+    // 13 columns of content plus 4 of padding, 2 lines plus 2 of padding.
+    assert!(svg.contains(&format!("width=\"{:.1}\"", 17.0 * CHAR_WIDTH)));
+    assert!(svg.contains(&format!("height=\"{:.1}\"", 4.0 * LINE_HEIGHT)));
+    assert!(!svg.contains("<circle"));
+  }
+
+  #[test]
+  fn test_render_window_chrome() {
+    let theme = theme();
+    let options = ImageOptions {
+      window_chrome: true,
+      ..OPTIONS
+    };
+    let svg = render(&[listing(&theme)], &theme, &options);
+    assert_eq!(svg.matches("<circle").count(), 3);
+    assert!(svg.contains(">a&lt;b&gt;.rs</text>"));
+  }
+}
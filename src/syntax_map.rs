@@ -0,0 +1,102 @@
+//! File name patterns mapped to languages, from `[syntax-map]` in the config file.
+//! Patterns without a `/` match the file name; patterns with one match the
+//! whole path. `*` matches any run of characters and `?` a single character.
+
+use std::path::Path;
+
+#[derive(Debug, Default)]
+pub struct SyntaxMap {
+  /// (pattern, language), most specific pattern first
+  entries: Vec<(String, String)>,
+}
+
+impl SyntaxMap {
+  pub fn new(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+    let mut entries: Vec<(String, String)> = entries.into_iter().collect();
+    // Longer patterns are more specific: `Cargo.lock` wins over `*.lock`.
+    entries.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    Self { entries }
+  }
+
+  /// The language mapped to `path`, if any pattern matches.
+  pub fn lookup(&self, path: &Path) -> Option<&str> {
+    self
+      .entries
+      .iter()
+      .find(|(pattern, _)| matches(pattern, path))
+      .map(|(_, language)| language.as_str())
+  }
+}
+
+/// True if `path` matches `pattern`: its file name, or the whole path when the
+/// pattern contains a `/`.
+pub fn matches(pattern: &str, path: &Path) -> bool {
+  if pattern.contains('/') {
+    glob_match(pattern, &path.to_string_lossy())
+  } else {
+    path
+      .file_name()
+      .is_some_and(|name| glob_match(pattern, &name.to_string_lossy()))
+  }
+}
+
+/// Match `text` against a pattern with `*` and `?` wildcards.
+fn glob_match(pattern: &str, text: &str) -> bool {
+  let pattern: Vec<char> = pattern.chars().collect();
+  let text: Vec<char> = text.chars().collect();
+  let (mut p, mut t) = (0, 0);
+  // Position after the last `*` and the text position it was tried at.
+  let mut backtrack = None;
+  while t < text.len() {
+    match pattern.get(p) {
+      Some('*') => {
+        p += 1;
+        backtrack = Some((p, t));
+      }
+      Some(c) if *c == '?' || *c == text[t] => {
+        p += 1;
+        t += 1;
+      }
+      _ => match backtrack {
+        Some((star_p, star_t)) => {
+          p = star_p;
+          t = star_t + 1;
+          backtrack = Some((star_p, star_t + 1));
+        }
+        None => return false,
+      },
+    }
+  }
+  pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_glob_match() {
+    assert!(glob_match("*.conf", "nginx.conf"));
+    assert!(glob_match("Dockerfile.*", "Dockerfile.dev"));
+    assert!(glob_match("?akefile", "Makefile"));
+    assert!(glob_match("*/.ssh/config", "/home/me/.ssh/config"));
+    assert!(!glob_match("*.conf", "nginx.conf.bak"));
+    assert!(!glob_match("a*b", "ac"));
+  }
+
+  #[test]
+  fn test_lookup_prefers_specific_patterns() {
+    let map = SyntaxMap::new([
+      ("*.lock".to_string(), "yaml".to_string()),
+      ("Cargo.lock".to_string(), "toml".to_string()),
+      ("*/.ssh/config".to_string(), "ssh_config".to_string()),
+    ]);
+    assert_eq!(map.lookup(Path::new("Cargo.lock")), Some("toml"));
+    assert_eq!(map.lookup(Path::new("web/yarn.lock")), Some("yaml"));
+    assert_eq!(
+      map.lookup(Path::new("/home/me/.ssh/config")),
+      Some("ssh_config")
+    );
+    assert_eq!(map.lookup(Path::new("main.rs")), None);
+  }
+}
@@ -0,0 +1,56 @@
+//! `--tabs N`: expand tabs to spaces before rendering.
+//! Tab stops are counted from the start of each line's content, so the
+//! gutter in front of it does not shift them the way a terminal would.
+
+use std::borrow::Cow;
+
+use unicode_width::UnicodeWidthChar;
+
+/// Replace each tab with spaces up to the next multiple of `width` columns.
+/// With `keep_tab`, the tab itself fills the first column of its expansion so
+/// `--show-all` can still mark it. Text without tabs is returned as is.
+pub fn expand_tabs(text: &str, width: usize, keep_tab: bool) -> Cow<'_, str> {
+  if width == 0 || !text.contains('\t') {
+    return Cow::Borrowed(text);
+  }
+  let mut out = String::with_capacity(text.len() + text.len() / 4);
+  let mut column = 0;
+  for c in text.chars() {
+    match c {
+      '\t' => {
+        let len = width - column % width;
+        if keep_tab {
+          out.push('\t');
+          out.extend(std::iter::repeat_n(' ', len - 1));
+        } else {
+          out.extend(std::iter::repeat_n(' ', len));
+        }
+        column += len;
+      }
+      '\n' => {
+        out.push(c);
+        column = 0;
+      }
+      c => {
+        out.push(c);
+        column += c.width().unwrap_or(0);
+      }
+    }
+  }
+  Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_expand_tabs_to_stops() {
+    assert_eq!(expand_tabs("\tx\n", 4, false), "    x\n");
+    assert_eq!(expand_tabs("ab\tc\td", 4, false), "ab  c   d");
+    assert_eq!(expand_tabs("ab\nc\td", 4, false), "ab\nc   d");
+    assert_eq!(expand_tabs("日\tx", 4, false), "日  x");
+    assert_eq!(expand_tabs("a\tb", 4, true), "a\t  b");
+    assert!(matches!(expand_tabs("no tabs", 4, false), Cow::Borrowed(_)));
+  }
+}
@@ -0,0 +1,227 @@
+//! Terminal and environment detection.
+//! All queries about the attached terminal go through here so that
+//! `--deterministic` can replace them with fixed values.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use syntastica::style::Color;
+
+use crate::color_depth::{self, ColorDepth};
+use crate::unprintable::{self, CharStyle};
+
+/// Width used when the terminal size is unknown or queries are disabled.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Settings controlling how the terminal is interrogated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalQueries {
+  /// Never query the terminal or the environment; use fixed defaults instead.
+  pub deterministic: bool,
+  /// Explicit terminal width, overriding detection.
+  pub width: Option<usize>,
+  /// `--color-depth`; `Auto` is detected from the environment.
+  pub color_depth: ColorDepth,
+}
+
+impl TerminalQueries {
+  /// Current terminal width in columns.
+  pub fn width(&self) -> usize {
+    if let Some(width) = self.width {
+      return width;
+    }
+    if self.deterministic {
+      return DEFAULT_WIDTH;
+    }
+    crossterm::terminal::size()
+      .map(|(w, _)| w as usize)
+      .unwrap_or(DEFAULT_WIDTH)
+  }
+
+  /// Colors the terminal supports, truecolor when deterministic.
+  pub fn color_depth(&self) -> ColorDepth {
+    match self.color_depth {
+      ColorDepth::Auto if self.deterministic => ColorDepth::Truecolor,
+      ColorDepth::Auto => color_depth::detect(
+        std::env::var("COLORTERM").ok().as_deref(),
+        std::env::var("TERM").ok().as_deref(),
+      ),
+      depth => depth,
+    }
+  }
+
+  /// Character style for `--show-all`, based on the locale unless deterministic.
+  pub fn char_style(&self) -> CharStyle {
+    if self.deterministic {
+      CharStyle::Unicode
+    } else {
+      unprintable::get_char_style()
+    }
+  }
+
+  /// Whether the desktop dark/light preference may be queried.
+  pub fn allows_mode_detection(&self) -> bool {
+    !self.deterministic
+  }
+
+  /// Whether the terminal's background is light, from its answers to OSC 10
+  /// and OSC 11. `None` when deterministic or when the terminal doesn't say.
+  pub fn background_is_light(&self) -> Option<bool> {
+    let (foreground, background) = self.colors()?;
+    Some(is_light(foreground, background))
+  }
+
+  /// The terminal's background color, from its answer to OSC 11.
+  pub fn background_color(&self) -> Option<Color> {
+    let (_, [r, g, b]) = self.colors()?;
+    let channel = |value: f64| (value * 255.0).round() as u8;
+    Some(Color::new(channel(r), channel(g), channel(b)))
+  }
+
+  /// Forget the terminal's colors, so that the next question asks it again,
+  /// as long `--follow` sessions do in case its color scheme changed.
+  pub fn refresh_colors(&self) {
+    *ANSWER.lock().unwrap_or_else(PoisonError::into_inner) = None;
+  }
+
+  /// The foreground, if known, and background colors of the terminal. The
+  /// terminal is asked once, until `refresh_colors`.
+  fn colors(&self) -> Option<TerminalColors> {
+    if self.deterministic {
+      return None;
+    }
+    *ANSWER
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .get_or_insert_with(|| {
+        let reply = query_colors()?;
+        Some((
+          parse_color_reply(&reply, 10),
+          parse_color_reply(&reply, 11)?,
+        ))
+      })
+  }
+}
+
+/// The foreground, if the terminal gave it, and the background, with each
+/// channel between 0 and 1.
+type TerminalColors = (Option<[f64; 3]>, [f64; 3]);
+
+/// The terminal's last answer to the color query; `None` until it is asked.
+static ANSWER: Mutex<Option<Option<TerminalColors>>> = Mutex::new(None);
+
+/// How long to wait for the terminal to answer a color query.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Ask the terminal for its foreground and background colors. A device
+/// attributes request follows, which every terminal answers, so terminals
+/// that ignore OSC 10/11 don't cost the whole timeout.
+#[cfg(unix)]
+fn query_colors() -> Option<String> {
+  use std::io::{Read, Write};
+  use std::os::fd::AsRawFd;
+  use std::time::Instant;
+
+  if std::env::var("TERM").is_ok_and(|term| term == "dumb") {
+    return None;
+  }
+  let mut tty = std::fs::OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open("/dev/tty")
+    .ok()?;
+  crossterm::terminal::enable_raw_mode().ok()?;
+  let mut reply = Vec::new();
+  let asked = tty
+    .write_all(b"\x1b]10;?\x1b\\\x1b]11;?\x1b\\\x1b[c")
+    .and_then(|()| tty.flush());
+  if asked.is_ok() {
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut buf = [0u8; 256];
+    while !answered_attributes(&reply) {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      let mut pollfd = libc::pollfd {
+        fd: tty.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+      };
+      // SAFETY: `pollfd` is a single valid entry that outlives the call.
+      let ready = unsafe { libc::poll(&mut pollfd, 1, remaining.as_millis() as libc::c_int) };
+      if ready <= 0 {
+        break;
+      }
+      match tty.read(&mut buf) {
+        Ok(read) if read > 0 => reply.extend_from_slice(&buf[..read]),
+        _ => break,
+      }
+    }
+  }
+  let _ = crossterm::terminal::disable_raw_mode();
+  Some(String::from_utf8_lossy(&reply).into_owned())
+}
+
+#[cfg(not(unix))]
+fn query_colors() -> Option<String> {
+  None
+}
+
+/// True once the reply holds the answer to the device attributes request.
+fn answered_attributes(reply: &[u8]) -> bool {
+  reply
+    .windows(3)
+    .position(|window| window == b"\x1b[?")
+    .is_some_and(|start| reply[start..].contains(&b'c'))
+}
+
+/// The color in an `OSC <code> ; rgb:RRRR/GGGG/BBBB` answer, with each
+/// channel scaled to 0..=1. Channels may have 1 to 4 hex digits.
+fn parse_color_reply(reply: &str, code: u8) -> Option<[f64; 3]> {
+  let start = reply.find(&format!("\x1b]{code};rgb:"))?;
+  let body = &reply[start + format!("\x1b]{code};rgb:").len()..];
+  let end = body.find(['\x07', '\x1b']).unwrap_or(body.len());
+  let mut channels = body[..end].split('/').map(|hex| {
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    (1..=4)
+      .contains(&hex.len())
+      .then(|| f64::from(value) / f64::from((1u32 << (4 * hex.len())) - 1))
+  });
+  let color = [channels.next()??, channels.next()??, channels.next()??];
+  channels.next().is_none().then_some(color)
+}
+
+/// Relative luminance of an sRGB color, ignoring gamma.
+fn luminance([r, g, b]: [f64; 3]) -> f64 {
+  0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// A background is light when it is brighter than the text, or brighter than
+/// mid-gray when the text color is unknown.
+fn is_light(foreground: Option<[f64; 3]>, background: [f64; 3]) -> bool {
+  let threshold = foreground.map_or(0.5, luminance);
+  luminance(background) > threshold
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_color_reply() {
+    let reply = "\x1b]10;rgb:cdcd/d6d6/f4f4\x1b\\\x1b]11;rgb:1e/1e/2e\x07\x1b[?62;22c";
+    assert_eq!(
+      parse_color_reply(reply, 11),
+      Some([30.0 / 255.0, 30.0 / 255.0, 46.0 / 255.0])
+    );
+    assert_eq!(parse_color_reply(reply, 10).map(|c| c[0] > 0.8), Some(true));
+    assert_eq!(parse_color_reply("\x1b]11;rgb:zz/00/00\x07", 11), None);
+    assert!(answered_attributes(reply.as_bytes()));
+    assert!(!answered_attributes(b"\x1b]11;rgb:1e/1e/2e\x07"));
+  }
+
+  #[test]
+  fn test_is_light() {
+    assert!(is_light(None, [0.9, 0.9, 0.9]));
+    assert!(!is_light(None, [0.1, 0.1, 0.2]));
+    assert!(is_light(Some([0.0, 0.0, 0.0]), [0.4, 0.4, 0.4]));
+  }
+}
@@ -0,0 +1,462 @@
+//! TextMate/Sublime `.tmTheme` files. Their rules style TextMate scopes, so
+//! each theme key is looked up through the TextMate scope closest to it.
+
+use std::collections::BTreeMap;
+
+use eyre::{Result, bail, eyre};
+use syntastica::style::{Color, Style};
+use syntastica::theme::{ResolvedTheme, THEME_KEYS};
+
+/// TextMate scopes standing in for theme keys. Keys missing from the theme
+/// are left out, so later entries fall back to their parents.
+const SCOPES: &[(&str, &str)] = &[
+  ("attribute", "entity.other.attribute-name"),
+  ("boolean", "constant.language.boolean"),
+  ("character", "constant.character"),
+  ("character.special", "constant.character.escape"),
+  ("comment", "comment"),
+  ("comment.documentation", "comment.block.documentation"),
+  ("conditional", "keyword.control.conditional"),
+  ("constant", "variable.other.constant"),
+  ("constant.builtin", "constant.language"),
+  ("constant.macro", "constant.other.placeholder"),
+  ("constructor", "entity.name.function.constructor"),
+  ("diff.delta", "markup.changed"),
+  ("diff.minus", "markup.deleted"),
+  ("diff.plus", "markup.inserted"),
+  ("error", "invalid"),
+  ("exception", "keyword.control.exception"),
+  ("field", "variable.other.member"),
+  ("float", "constant.numeric.float"),
+  ("function", "entity.name.function"),
+  ("function.builtin", "support.function"),
+  ("function.call", "entity.name.function"),
+  ("function.macro", "entity.name.function.macro"),
+  ("function.method", "entity.name.function.method"),
+  ("function.method.call", "entity.name.function.method"),
+  ("include", "keyword.control.import"),
+  ("keyword", "keyword"),
+  ("keyword.conditional", "keyword.control.conditional"),
+  ("keyword.coroutine", "keyword.control.flow"),
+  ("keyword.directive", "keyword.control.directive"),
+  ("keyword.exception", "keyword.control.exception"),
+  ("keyword.function", "storage.type.function"),
+  ("keyword.import", "keyword.control.import"),
+  ("keyword.modifier", "storage.modifier"),
+  ("keyword.operator", "keyword.operator.word"),
+  ("keyword.repeat", "keyword.control.loop"),
+  ("keyword.return", "keyword.control.flow.return"),
+  ("keyword.type", "storage.type"),
+  ("label", "entity.name.label"),
+  ("markup.heading", "markup.heading"),
+  ("markup.italic", "markup.italic"),
+  ("markup.link", "markup.underline.link"),
+  ("markup.link.url", "markup.underline.link"),
+  ("markup.list", "markup.list"),
+  ("markup.quote", "markup.quote"),
+  ("markup.raw", "markup.raw"),
+  ("markup.strikethrough", "markup.strikethrough"),
+  ("markup.strong", "markup.bold"),
+  ("method", "entity.name.function.method"),
+  ("module", "entity.name.namespace"),
+  ("namespace", "entity.name.namespace"),
+  ("number", "constant.numeric"),
+  ("number.float", "constant.numeric.float"),
+  ("operator", "keyword.operator"),
+  ("parameter", "variable.parameter"),
+  ("preproc", "meta.preprocessor"),
+  ("property", "variable.other.property"),
+  ("punctuation", "punctuation"),
+  ("punctuation.bracket", "punctuation.section"),
+  ("punctuation.delimiter", "punctuation.separator"),
+  (
+    "punctuation.special",
+    "punctuation.definition.template-expression",
+  ),
+  ("repeat", "keyword.control.loop"),
+  ("string", "string"),
+  ("string.escape", "constant.character.escape"),
+  ("string.regex", "string.regexp"),
+  ("string.regexp", "string.regexp"),
+  ("string.special", "string.other"),
+  ("string.special.symbol", "constant.other.symbol"),
+  ("string.special.url", "markup.underline.link"),
+  ("tag", "entity.name.tag"),
+  ("tag.attribute", "entity.other.attribute-name"),
+  ("tag.delimiter", "punctuation.definition.tag"),
+  ("type", "entity.name.type"),
+  ("type.builtin", "support.type"),
+  ("type.definition", "entity.name.type"),
+  ("type.qualifier", "storage.modifier"),
+  ("variable", "variable"),
+  ("variable.builtin", "variable.language"),
+  ("variable.member", "variable.other.member"),
+  ("variable.parameter", "variable.parameter"),
+];
+
+/// One rule of the theme: the scopes it applies to and what it sets.
+struct Rule {
+  selectors: Vec<String>,
+  foreground: Option<Color>,
+  background: Option<Color>,
+  font_style: Option<String>,
+}
+
+pub fn parse(raw: &str) -> Result<ResolvedTheme> {
+  let root = Plist::parse(raw)?;
+  let Some(Plist::Array(entries)) = root.get("settings") else {
+    bail!("Expected a 'settings' array");
+  };
+
+  let mut foreground = None;
+  let mut background = None;
+  let mut rules = Vec::new();
+  for entry in entries {
+    let Some(settings) = entry.get("settings") else {
+      continue;
+    };
+    let string = |name: &str| match settings.get(name) {
+      Some(Plist::String(value)) => Some(value.as_str()),
+      _ => None,
+    };
+    match entry.get("scope") {
+      // The entry without a scope holds the editor's default colors.
+      None => {
+        foreground = string("foreground").and_then(|value| parse_color(value, None));
+        background = string("background").and_then(|value| parse_color(value, None));
+      }
+      Some(Plist::String(scope)) => rules.push(Rule {
+        selectors: scope
+          .split(',')
+          .filter_map(|selector| {
+            // Only the innermost scope of a selector counts, and exclusions
+            // (`a - b`) are ignored.
+            let selector = selector.split(" -").next()?;
+            selector.split_whitespace().last().map(str::to_string)
+          })
+          .collect(),
+        foreground: string("foreground").and_then(|value| parse_color(value, background)),
+        background: string("background").and_then(|value| parse_color(value, background)),
+        font_style: string("fontStyle").map(str::to_string),
+      }),
+      Some(_) => {}
+    }
+  }
+  let foreground = foreground.ok_or_else(|| eyre!("Missing the default foreground color"))?;
+
+  let mut styles = BTreeMap::new();
+  styles.insert(
+    "_normal".to_string(),
+    Style::new(foreground, background, false, false, false, false),
+  );
+  for &(key, scope) in SCOPES {
+    if !THEME_KEYS.contains(&key) {
+      continue;
+    }
+    if let Some(rule) = best_rule(&rules, scope) {
+      let font_style = rule.font_style.as_deref().unwrap_or("");
+      let has = |name: &str| font_style.split_whitespace().any(|style| style == name);
+      styles.insert(
+        key.to_string(),
+        Style::new(
+          rule.foreground.unwrap_or(foreground),
+          rule.background,
+          has("underline"),
+          has("strikethrough"),
+          has("italic"),
+          has("bold"),
+        ),
+      );
+    }
+  }
+  Ok(ResolvedTheme::new(styles))
+}
+
+/// The rule with the most specific selector matching `scope`; the later rule
+/// wins a tie, as in TextMate.
+fn best_rule<'a>(rules: &'a [Rule], scope: &str) -> Option<&'a Rule> {
+  rules
+    .iter()
+    .filter_map(|rule| {
+      rule
+        .selectors
+        .iter()
+        .filter(|selector| {
+          scope
+            .strip_prefix(selector.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+        .map(|selector| selector.split('.').count())
+        .max()
+        .map(|specificity| (specificity, rule))
+    })
+    .max_by_key(|(specificity, _)| *specificity)
+    .map(|(_, rule)| rule)
+}
+
+/// A `#rrggbb` or `#rrggbbaa` color. Translucent colors are blended onto
+/// `background` when there is one.
+fn parse_color(value: &str, background: Option<Color>) -> Option<Color> {
+  let hex = value.trim().strip_prefix('#')?;
+  if !hex.is_ascii() || !matches!(hex.len(), 6 | 8) {
+    return None;
+  }
+  let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+  let color = Color::new(channel(0)?, channel(2)?, channel(4)?);
+  let (Some(alpha), Some(background)) =
+    ((hex.len() == 8).then(|| channel(6)).flatten(), background)
+  else {
+    return Some(color);
+  };
+  let blend = |fg: u8, bg: u8| {
+    ((u16::from(fg) * u16::from(alpha) + u16::from(bg) * (255 - u16::from(alpha))) / 255) as u8
+  };
+  Some(Color::new(
+    blend(color.red, background.red),
+    blend(color.green, background.green),
+    blend(color.blue, background.blue),
+  ))
+}
+
+/// The parts of an XML property list a theme uses.
+#[derive(Debug, PartialEq)]
+enum Plist {
+  String(String),
+  Array(Vec<Plist>),
+  Dict(Vec<(String, Plist)>),
+  /// Numbers, booleans, dates and data, which themes don't need
+  Other,
+}
+
+impl Plist {
+  /// The value under `key` of a dictionary.
+  fn get(&self, key: &str) -> Option<&Plist> {
+    match self {
+      Plist::Dict(entries) => entries
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value),
+      _ => None,
+    }
+  }
+
+  /// The value inside the `<plist>` element of a document.
+  fn parse(raw: &str) -> Result<Plist> {
+    let mut tokens = Tokens { rest: raw };
+    loop {
+      match tokens.next()? {
+        Some(Token::Open("plist")) => return Self::parse_value(&mut tokens),
+        Some(_) => continue,
+        None => bail!("Not a property list"),
+      }
+    }
+  }
+
+  fn parse_value(tokens: &mut Tokens) -> Result<Plist> {
+    match tokens.next()? {
+      Some(Token::Open(name)) => match name {
+        "string" => {
+          let text = tokens.text();
+          tokens.expect_close("string")?;
+          Ok(Plist::String(unescape(text)))
+        }
+        "array" => {
+          let mut items = Vec::new();
+          while !tokens.peek_close("array") {
+            items.push(Self::parse_value(tokens)?);
+          }
+          tokens.expect_close("array")?;
+          Ok(Plist::Array(items))
+        }
+        "dict" => {
+          let mut entries = Vec::new();
+          while !tokens.peek_close("dict") {
+            match tokens.next()? {
+              Some(Token::Open("key")) => {
+                let key = unescape(tokens.text());
+                tokens.expect_close("key")?;
+                entries.push((key, Self::parse_value(tokens)?));
+              }
+              _ => bail!("Expected a <key> in a <dict>"),
+            }
+          }
+          tokens.expect_close("dict")?;
+          Ok(Plist::Dict(entries))
+        }
+        other => {
+          tokens.text();
+          tokens.expect_close(other)?;
+          Ok(Plist::Other)
+        }
+      },
+      Some(Token::Empty(name)) => Ok(match name {
+        "string" => Plist::String(String::new()),
+        "array" => Plist::Array(Vec::new()),
+        "dict" => Plist::Dict(Vec::new()),
+        _ => Plist::Other,
+      }),
+      Some(Token::Close(name)) => bail!("Unexpected </{}>", name),
+      None => bail!("Unexpected end of file"),
+    }
+  }
+}
+
+enum Token<'a> {
+  Open(&'a str),
+  Close(&'a str),
+  /// A self-closing element such as `<true/>`
+  Empty(&'a str),
+}
+
+/// XML tags, skipping declarations, comments and the text between tags.
+struct Tokens<'a> {
+  rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+  fn next(&mut self) -> Result<Option<Token<'a>>> {
+    loop {
+      let Some(start) = self.rest.find('<') else {
+        return Ok(None);
+      };
+      let rest = &self.rest[start..];
+      if let Some(comment) = rest.strip_prefix("<!--") {
+        let end = comment
+          .find("-->")
+          .ok_or_else(|| eyre!("Unclosed comment"))?;
+        self.rest = &comment[end + 3..];
+        continue;
+      }
+      let end = rest.find('>').ok_or_else(|| eyre!("Unclosed tag"))?;
+      let tag = &rest[1..end];
+      self.rest = &rest[end + 1..];
+      if tag.starts_with(['?', '!']) {
+        continue;
+      }
+      let name = |tag: &'a str| tag.split_whitespace().next().unwrap_or("");
+      return Ok(Some(if let Some(tag) = tag.strip_prefix('/') {
+        Token::Close(name(tag))
+      } else if let Some(tag) = tag.strip_suffix('/') {
+        Token::Empty(name(tag))
+      } else {
+        Token::Open(name(tag))
+      }));
+    }
+  }
+
+  /// The text up to the next tag.
+  fn text(&mut self) -> &'a str {
+    let end = self.rest.find('<').unwrap_or(self.rest.len());
+    let (text, rest) = self.rest.split_at(end);
+    self.rest = rest;
+    text
+  }
+
+  fn peek_close(&self, name: &str) -> bool {
+    self
+      .rest
+      .trim_start()
+      .strip_prefix("</")
+      .and_then(|rest| rest.strip_prefix(name))
+      .is_some_and(|rest| rest.trim_start().starts_with('>'))
+  }
+
+  fn expect_close(&mut self, expected: &str) -> Result<()> {
+    match self.next()? {
+      Some(Token::Close(name)) if name == expected => Ok(()),
+      _ => Err(eyre!("Expected </{}>", expected)),
+    }
+  }
+}
+
+fn unescape(text: &str) -> String {
+  text
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&apos;", "'")
+    .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const THEME: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>name</key>
+  <string>Test &amp; Co</string>
+  <key>settings</key>
+  <array>
+    <dict>
+      <key>settings</key>
+      <dict>
+        <key>background</key>
+        <string>#272822</string>
+        <key>foreground</key>
+        <string>#F8F8F2</string>
+      </dict>
+    </dict>
+    <!-- comments are skipped -->
+    <dict>
+      <key>scope</key>
+      <string>comment, punctuation.definition.comment</string>
+      <key>settings</key>
+      <dict>
+        <key>foreground</key>
+        <string>#75715E</string>
+        <key>fontStyle</key>
+        <string>italic</string>
+      </dict>
+    </dict>
+    <dict>
+      <key>scope</key>
+      <string>entity.other.attribute-name</string>
+      <key>settings</key>
+      <dict>
+        <key>fontStyle</key>
+        <string>bold</string>
+        <key>foreground</key>
+        <string>#A6E22E80</string>
+      </dict>
+    </dict>
+  </array>
+  <key>semanticClass</key>
+  <string>theme.dark.test</string>
+  <key>isDark</key>
+  <true/>
+</dict>
+</plist>
+"##;
+
+  #[test]
+  fn test_parse_tmtheme() {
+    let theme = parse(THEME).unwrap();
+    let comment = theme.find_style("comment").unwrap();
+    assert_eq!(comment.color(), Color::new(0x75, 0x71, 0x5e));
+    assert!(comment.italic());
+    let attribute = theme.find_style("attribute").unwrap();
+    assert!(attribute.bold());
+    // Half-transparent #A6E22E over #272822
+    assert_eq!(attribute.color(), Color::new(0x66, 0x85, 0x28));
+    let normal = theme.get("_normal").unwrap();
+    assert_eq!(normal.bg(), Some(Color::new(0x27, 0x28, 0x22)));
+  }
+
+  #[test]
+  fn test_best_rule() {
+    let rule = |selector: &str| Rule {
+      selectors: vec![selector.to_string()],
+      foreground: None,
+      background: None,
+      font_style: Some(selector.to_string()),
+    };
+    let rules = [rule("keyword"), rule("keyword.control"), rule("keywords")];
+    let best = |scope| best_rule(&rules, scope).and_then(|rule| rule.font_style.as_deref());
+    assert_eq!(best("keyword.control.import"), Some("keyword.control"));
+    assert_eq!(best("keyword.operator"), Some("keyword"));
+    assert_eq!(best("string"), None);
+  }
+}
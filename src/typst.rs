@@ -0,0 +1,155 @@
+//! `--format typst`: highlighted listings for Typst documents. Each theme key
+//! in use gets a function, e.g. `umber-comment`, that sets `raw` text in its
+//! color and attributes, so a document can restyle them by redefining it.
+
+use syntastica::style::{Color, Style};
+
+use crate::decorations::get_dim_style_or_create;
+use crate::listing::{Listing, expand_tabs};
+
+/// `key` as a function name: `function.builtin` is `umber-function-builtin`.
+fn function_name(key: &str) -> String {
+  format!("umber-{}", key.replace(['.', '_'], "-"))
+}
+
+fn rgb(color: Color) -> String {
+  format!(
+    "rgb(\"#{:02x}{:02x}{:02x}\")",
+    color.red, color.green, color.blue
+  )
+}
+
+/// `text` as a `raw` element, which keeps its spaces.
+fn raw(text: &str) -> String {
+  let mut quoted = String::with_capacity(text.len() + 2);
+  for c in text.chars() {
+    match c {
+      '\\' => quoted.push_str("\\\\"),
+      '"' => quoted.push_str("\\\""),
+      c => quoted.push(c),
+    }
+  }
+  format!("raw(\"{quoted}\")")
+}
+
+/// A function setting its body in `style`.
+fn define(out: &mut String, name: &str, style: Style) {
+  let mut arguments = vec![format!("fill: {}", rgb(style.color()))];
+  if style.bold() {
+    arguments.push("weight: \"bold\"".to_string());
+  }
+  if style.italic() {
+    arguments.push("style: \"italic\"".to_string());
+  }
+  let mut body = format!("text({}, body)", arguments.join(", "));
+  if style.underline() {
+    body = format!("underline({body})");
+  }
+  if style.strikethrough() {
+    body = format!("strike({body})");
+  }
+  out.push_str(&format!("#let {name}(body) = {body}\n"));
+}
+
+fn listing(out: &mut String, listing: &Listing<'_>) {
+  let theme = listing.theme;
+  if listing.numbers {
+    define(out, "umber-line-number", get_dim_style_or_create(theme));
+  }
+  for key in listing.style_keys() {
+    if let Some(style) = theme.find_style(key) {
+      define(out, &function_name(key), style);
+    }
+  }
+
+  let normal = theme.get("_normal");
+  match normal.and_then(|normal| normal.bg()) {
+    Some(bg) => out.push_str(&format!(
+      "#block(fill: {}, inset: 8pt, radius: 4pt)[\n",
+      rgb(bg)
+    )),
+    None => out.push_str("#block[\n"),
+  }
+  if let Some(normal) = normal {
+    out.push_str(&format!("#set text(fill: {})\n", rgb(normal.color())));
+  }
+  let width = listing.number_width();
+  for (index, (line_no, pieces)) in listing.lines.iter().enumerate() {
+    if listing.numbers {
+      out.push_str(&format!(
+        "#umber-line-number({})",
+        raw(&format!("{line_no:>width$} "))
+      ));
+    }
+    let pieces: Vec<_> = expand_tabs(pieces)
+      .into_iter()
+      .filter(|(text, _)| !text.is_empty())
+      .collect();
+    if pieces.is_empty() && !listing.numbers {
+      // A line break needs something on the line before it.
+      out.push_str(&format!("#{}", raw(" ")));
+    }
+    for (text, style_key) in pieces {
+      match style_key.filter(|key| theme.find_style(key).is_some()) {
+        Some(key) => out.push_str(&format!("#{}({})", function_name(key), raw(&text))),
+        None => out.push_str(&format!("#{}", raw(&text))),
+      }
+    }
+    if index + 1 < listing.lines.len() {
+      out.push_str(" \\");
+    }
+    out.push('\n');
+  }
+  out.push_str("]\n");
+}
+
+pub fn render(listings: &[Listing<'_>]) -> String {
+  let mut out = String::from("// Highlighted by umber\n");
+  for item in listings {
+    listing(&mut out, item);
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::BTreeMap;
+  use syntastica::theme::ResolvedTheme;
+
+  #[test]
+  fn test_render() {
+    let mut styles = BTreeMap::new();
+    styles.insert(
+      "comment".to_string(),
+      Style::new(
+        Color::new(0x6c, 0x70, 0x86),
+        None,
+        false,
+        false,
+        true,
+        false,
+      ),
+    );
+    let theme = ResolvedTheme::new(styles);
+    let listings = [Listing {
+      name: "main.rs".to_string(),
+      lines: vec![
+        (9, vec![("// \"hi\"".to_string(), Some("comment"))]),
+        (10, Vec::new()),
+        (11, vec![("a\\b".to_string(), None)]),
+      ],
+      theme: &theme,
+      numbers: true,
+    }];
+    let typ = render(&listings);
+    assert!(typ.contains(
+      "#let umber-comment(body) = text(fill: rgb(\"#6c7086\"), style: \"italic\", body)\n"
+    ));
+    assert!(
+      typ.contains("#umber-line-number(raw(\" 9 \"))#umber-comment(raw(\"// \\\"hi\\\"\")) \\\n")
+    );
+    assert!(typ.contains("#umber-line-number(raw(\"10 \")) \\\n"));
+    assert!(typ.contains("#raw(\"a\\\\b\")\n]\n"));
+  }
+}
@@ -41,6 +41,12 @@ pub fn get_char_style() -> CharStyle {
 /// A new string with unprintable characters replaced by their visual representations
 pub fn show_unprintable(text: &str, style: CharStyle) -> String {
   let mut result = String::with_capacity(text.len() * 2);
+  push_unprintable(&mut result, text, style);
+  result
+}
+
+/// Like [`show_unprintable`], but appends to an existing buffer.
+pub fn push_unprintable(result: &mut String, text: &str, style: CharStyle) {
   for c in text.chars() {
     match c {
       ' ' => result.push('·'),
@@ -119,7 +125,6 @@ pub fn show_unprintable(text: &str, style: CharStyle) -> String {
       _ => result.push(c),
     }
   }
-  result
 }
 
 #[cfg(test)]
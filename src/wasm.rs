@@ -0,0 +1,26 @@
+//! The WebAssembly entry point, for web playgrounds and docs sites that want
+//! exactly the command's grammars and themes. Built for
+//! `wasm32-unknown-unknown` with the `wasm` feature and no default features,
+//! so nothing reaches for git, a subprocess or a terminal.
+
+use wasm_bindgen::prelude::*;
+
+use crate::highlight::{Format, Options, highlight};
+
+/// `source` highlighted as `language` in `theme` and rendered as a `<pre>`
+/// block styled inline, as `--format html` writes it. Throws an `Error` for an
+/// unknown language or theme.
+#[wasm_bindgen]
+pub fn highlight_to_html(
+  source: &str,
+  language: &str,
+  theme: &str,
+  line_numbers: bool,
+) -> Result<String, JsError> {
+  let options = Options {
+    format: Format::Html,
+    line_numbers,
+    ..Options::default()
+  };
+  highlight(source, language, theme, &options).map_err(|err| JsError::new(&format!("{err:#}")))
+}